@@ -0,0 +1,9 @@
+#!/bin/bash
+# Regenerate the checked-in include/prontodb.h from src/lib/ffi.rs.
+# Needs the cbindgen CLI: cargo install cbindgen
+
+set -e
+
+cd "$(dirname "$0")/.."
+cbindgen --config cbindgen.toml --output include/prontodb.h src/lib/ffi.rs
+echo "Wrote include/prontodb.h"
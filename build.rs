@@ -0,0 +1,21 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // Only the `grpc` feature needs generated code (and `protoc` on the PATH).
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/prontodb.proto").expect("compile proto/prontodb.proto");
+    // `ffi` generates the C header into OUT_DIR; bin/header.sh refreshes the
+    // checked-in include/prontodb.h with the same cbindgen.toml.
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/lib/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        let config = cbindgen::Config::from_file("cbindgen.toml").expect("read cbindgen.toml");
+        let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src("src/lib/ffi.rs")
+            .generate()
+            .expect("generate prontodb.h")
+            .write_to_file(std::path::Path::new(&out_dir).join("prontodb.h"));
+    }
+}
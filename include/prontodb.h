@@ -0,0 +1,70 @@
+#ifndef PRONTODB_H
+#define PRONTODB_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * An open database; create with `pronto_open`, release with `pronto_close`.
+ */
+typedef struct ProntoHandle ProntoHandle;
+
+/**
+ * Open (creating if needed) the database at `path`; null on failure.
+ *
+ * # Safety
+ * `path` must be null or a valid NUL-terminated string.
+ */
+ProntoHandle *pronto_open(const char *path);
+
+/**
+ * Close a handle from `pronto_open`; null is ignored.
+ *
+ * # Safety
+ * `handle` must come from `pronto_open` and not be used afterwards.
+ */
+void pronto_close(ProntoHandle *handle);
+
+/**
+ * Store `value` at `address`; `ttl_secs` 0 means no expiry.
+ *
+ * # Safety
+ * `handle` must be live; `address` and `value` valid NUL-terminated strings.
+ */
+int pronto_set(ProntoHandle *handle, const char *address, const char *value, uint64_t ttl_secs);
+
+/**
+ * Read `address` into `*out` (free it with `pronto_free`); 2 when missing, leaving `*out` null.
+ *
+ * # Safety
+ * `handle` must be live, `address` a valid NUL-terminated string and `out` writable.
+ */
+int pronto_get(ProntoHandle *handle, const char *address, char **out);
+
+/**
+ * Delete `address`; 2 when there was nothing to delete.
+ *
+ * # Safety
+ * `handle` must be live and `address` a valid NUL-terminated string.
+ */
+int pronto_delete(ProntoHandle *handle, const char *address);
+
+/**
+ * Message of the handle's last failure, or null; valid until the next failure or `pronto_close`.
+ *
+ * # Safety
+ * `handle` must be null or live.
+ */
+const char *pronto_last_error(const ProntoHandle *handle);
+
+/**
+ * Free a string returned by `pronto_get`; null is ignored.
+ *
+ * # Safety
+ * `value` must come from this library and not be freed twice.
+ */
+void pronto_free(char *value);
+
+#endif  /* PRONTODB_H */
@@ -1,4 +1,11 @@
 //! Root library module exposing the `lib` namespace from `src/lib/`.
+//!
+//! Programs embedding ProntoDB start from `ProntoClient` (or
+//! `AsyncProntoClient` with the `async` feature).
 
 #[path = "lib/mod.rs"]
 pub mod lib;
+
+#[cfg(feature = "async")]
+pub use lib::api::AsyncProntoClient;
+pub use lib::api::ProntoClient;
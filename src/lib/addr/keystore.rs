@@ -0,0 +1,277 @@
+use std::fmt;
+
+/// Delimiter separating project, namespace, and key segments.
+pub const DEFAULT_NS_DELIM: &str = ".";
+
+/// Project/namespace used when an address omits them.
+pub const DEFAULT_NAME: &str = "default";
+
+/// Separator between a key and its optional context suffix.
+const CONTEXT_SEPARATOR: &str = "__";
+
+#[derive(Debug)]
+pub struct AddressError {
+    message: String,
+}
+
+impl AddressError {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Project and namespace filled in when an address omits them.
+///
+/// `default.default` unless a cursor configures its own `default_project`
+/// / `default_namespace`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AddressDefaults {
+    pub project: String,
+    pub namespace: String,
+}
+
+impl AddressDefaults {
+    pub fn new(project: Option<&str>, namespace: Option<&str>) -> Self {
+        Self {
+            project: project.unwrap_or(DEFAULT_NAME).to_string(),
+            namespace: namespace.unwrap_or(DEFAULT_NAME).to_string(),
+        }
+    }
+}
+
+impl Default for AddressDefaults {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+/// Which segments of a long address form the key; a per-database setting.
+///
+/// Addresses of one to three segments read the same under every layout;
+/// the layout only decides what a fourth segment and beyond mean.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AddressLayout {
+    /// `project.namespace.key`; more segments are an error.
+    #[default]
+    Fixed,
+    /// `project.namespace.key.with.dots`: everything after the namespace is the key.
+    DottedKeys,
+}
+
+impl AddressLayout {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fixed => "fixed",
+            Self::DottedKeys => "dotted-keys",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, AddressError> {
+        match name {
+            "fixed" => Ok(Self::Fixed),
+            "dotted-keys" => Ok(Self::DottedKeys),
+            other => Err(AddressError::new(format!(
+                "unknown address layout '{}' (expected fixed|dotted-keys)",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for AddressLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Fully qualified location of a value inside a keystore.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Address {
+    pub project: String,
+    pub namespace: String,
+    pub key: String,
+    pub context: Option<String>,
+}
+
+impl Address {
+    /// Parse `key`, `namespace.key`, or `project.namespace.key`, each with an optional `__context`.
+    ///
+    /// A segment holding the delimiter is written in double quotes
+    /// (`app.config."my.dotted.key"`) or with `\` escapes (`my\.dotted\.key`);
+    /// a quoted or escaped `__` is part of the key, not a context.
+    pub fn parse(path: &str) -> Result<Self, AddressError> {
+        Self::parse_with_delim(path, DEFAULT_NS_DELIM)
+    }
+
+    pub fn parse_with_delim(path: &str, delim: &str) -> Result<Self, AddressError> {
+        Self::parse_from_parts(path, delim, &AddressDefaults::default())
+    }
+
+    /// Like `parse`, but missing segments come from `defaults` (`debug` -> `app.cfg.debug`).
+    pub fn parse_with_defaults(
+        path: &str,
+        defaults: &AddressDefaults,
+    ) -> Result<Self, AddressError> {
+        Self::parse_from_parts(path, DEFAULT_NS_DELIM, defaults)
+    }
+
+    pub fn parse_from_parts(
+        path: &str,
+        delim: &str,
+        defaults: &AddressDefaults,
+    ) -> Result<Self, AddressError> {
+        Self::parse_in_layout(path, delim, defaults, AddressLayout::Fixed)
+    }
+
+    /// Like `parse_from_parts`, reading segments past the third as `layout` says.
+    pub fn parse_in_layout(
+        path: &str,
+        delim: &str,
+        defaults: &AddressDefaults,
+        layout: AddressLayout,
+    ) -> Result<Self, AddressError> {
+        if delim.is_empty() {
+            return Err(AddressError::new("namespace delimiter cannot be empty"));
+        }
+
+        let (base, context) = split_context(path);
+        let mut parts = split_segments(base, delim).ok_or_else(|| {
+            AddressError::new(format!(
+                "unclosed quote or trailing '\\' in address: '{}'",
+                path
+            ))
+        })?;
+        if parts.iter().any(String::is_empty) {
+            return Err(AddressError::new(format!("invalid address: '{}'", path)));
+        }
+        if layout == AddressLayout::DottedKeys && parts.len() > 3 {
+            let key = parts.split_off(2).join(delim);
+            parts.push(key);
+        }
+
+        let (project, namespace, key) = match parts.as_slice() {
+            [key] => (defaults.project.as_str(), defaults.namespace.as_str(), key),
+            [namespace, key] => (defaults.project.as_str(), namespace.as_str(), key),
+            [project, namespace, key] => (project.as_str(), namespace.as_str(), key),
+            _ => {
+                return Err(AddressError::new(format!(
+                    "too many segments in address: '{}' (quote a key that holds '{}')",
+                    path, delim
+                )))
+            }
+        };
+
+        Ok(Self::new(project, namespace, key, context))
+    }
+
+    pub fn new<P, N, K>(project: P, namespace: N, key: K, context: Option<String>) -> Self
+    where
+        P: Into<String>,
+        N: Into<String>,
+        K: Into<String>,
+    {
+        Self {
+            project: project.into(),
+            namespace: namespace.into(),
+            key: key.into(),
+            context,
+        }
+    }
+
+    /// The key segment with its `__context` suffix, as shown by namespace listings.
+    pub fn key_path(&self) -> String {
+        match &self.context {
+            Some(context) => format!("{}{}{}", self.key, CONTEXT_SEPARATOR, context),
+            None => self.key.clone(),
+        }
+    }
+
+    /// Render the address back into its canonical path form.
+    pub fn to_path(&self, delim: &str) -> String {
+        let base = format!(
+            "{}{}{}{}{}",
+            self.project, delim, self.namespace, delim, self.key
+        );
+        match &self.context {
+            Some(context) => format!("{}{}{}", base, CONTEXT_SEPARATOR, context),
+            None => base,
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_path(DEFAULT_NS_DELIM))
+    }
+}
+
+/// Split off the `__context` suffix at the last separator outside quotes and escapes.
+fn split_context(path: &str) -> (&str, Option<String>) {
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut split = None;
+    for (idx, ch) in path.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match ch {
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            _ if !quoted && path[idx..].starts_with(CONTEXT_SEPARATOR) => split = Some(idx),
+            _ => {}
+        }
+    }
+    match split {
+        Some(idx) => {
+            let context = &path[idx + CONTEXT_SEPARATOR.len()..];
+            let context = if context.is_empty() {
+                None
+            } else {
+                Some(context.to_string())
+            };
+            (&path[..idx], context)
+        }
+        None => (path, None),
+    }
+}
+
+/// `path` cut at each `delim` outside `"..."`, with the quotes dropped and `\x` read as `x`;
+/// `None` for an unclosed quote or a trailing `\`.
+fn split_segments(path: &str, delim: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut rest = path;
+    while let Some(ch) = rest.chars().next() {
+        if ch == '\\' {
+            let escaped = rest[1..].chars().next()?;
+            current.push(escaped);
+            rest = &rest[1 + escaped.len_utf8()..];
+        } else if ch == '"' {
+            quoted = !quoted;
+            rest = &rest[1..];
+        } else if !quoted && rest.starts_with(delim) {
+            segments.push(std::mem::take(&mut current));
+            rest = &rest[delim.len()..];
+        } else {
+            current.push(ch);
+            rest = &rest[ch.len_utf8()..];
+        }
+    }
+    if quoted {
+        return None;
+    }
+    segments.push(current);
+    Some(segments)
+}
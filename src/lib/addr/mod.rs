@@ -0,0 +1,12 @@
+//! Keystore addressing (`project.namespace.key__context`).
+//! MODULE_SPEC: orchestrator only; parsing lives in sibling files.
+
+mod keystore;
+mod namespace;
+mod pattern;
+
+pub use keystore::{
+    Address, AddressDefaults, AddressError, AddressLayout, DEFAULT_NAME, DEFAULT_NS_DELIM,
+};
+pub use namespace::{is_reserved_namespace, NamespaceScope, PIPE_CACHE_NAMESPACE, SYSTEM_PROJECT};
+pub use pattern::KeyPattern;
@@ -0,0 +1,83 @@
+use std::fmt;
+
+use super::keystore::{AddressError, DEFAULT_NS_DELIM};
+
+/// Project whose every namespace is reserved for prontodb's own rows.
+///
+/// Current bookkeeping (ACLs, audit log, oplog, hooks, tokens, settings)
+/// lives in `sys_*` SQLite tables beside `kv`, where no address reaches it;
+/// `sys.*` keeps the key space free for bookkeeping stored as keys.
+pub const SYSTEM_PROJECT: &str = "sys";
+
+/// `project.namespace` reserved for the pipe cache, which this tree does not write yet.
+pub const PIPE_CACHE_NAMESPACE: (&str, &str) = ("pipe", "cache");
+
+/// `project.namespace` is reserved (`sys.*` or `pipe.cache`): ordinary writes
+/// and deletes leave it alone.
+pub fn is_reserved_namespace(project: &str, namespace: &str) -> bool {
+    project == SYSTEM_PROJECT || (project, namespace) == PIPE_CACHE_NAMESPACE
+}
+
+/// A `project.namespace` pair, optionally narrowed to keys starting with `prefix`.
+///
+/// Accepts `project.namespace`, `project.namespace.`, `project.namespace.*`,
+/// and `project.namespace.prefix` (a trailing `*` on the prefix is allowed).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NamespaceScope {
+    pub project: String,
+    pub namespace: String,
+    pub prefix: Option<String>,
+}
+
+impl NamespaceScope {
+    pub fn parse(path: &str) -> Result<Self, AddressError> {
+        Self::parse_with_delim(path, DEFAULT_NS_DELIM)
+    }
+
+    pub fn parse_with_delim(path: &str, delim: &str) -> Result<Self, AddressError> {
+        if delim.is_empty() {
+            return Err(AddressError::new("namespace delimiter cannot be empty"));
+        }
+
+        let trimmed = path.strip_suffix('*').unwrap_or(path);
+        let trimmed = trimmed.strip_suffix(delim).unwrap_or(trimmed);
+        let mut parts = trimmed.splitn(3, delim);
+
+        let project = parts.next().unwrap_or_default();
+        let namespace = parts.next().unwrap_or_default();
+        if project.is_empty() || namespace.is_empty() {
+            return Err(AddressError::new(format!(
+                "expected project{}namespace, got '{}'",
+                delim, path
+            )));
+        }
+        let prefix = parts
+            .next()
+            .filter(|prefix| !prefix.is_empty())
+            .map(|prefix| prefix.to_string());
+
+        Ok(Self::new(project, namespace, prefix))
+    }
+
+    pub fn new<P, N>(project: P, namespace: N, prefix: Option<String>) -> Self
+    where
+        P: Into<String>,
+        N: Into<String>,
+    {
+        Self {
+            project: project.into(),
+            namespace: namespace.into(),
+            prefix,
+        }
+    }
+}
+
+impl fmt::Display for NamespaceScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.project, DEFAULT_NS_DELIM, self.namespace)?;
+        if let Some(prefix) = &self.prefix {
+            write!(f, "{}{}*", DEFAULT_NS_DELIM, prefix)?;
+        }
+        Ok(())
+    }
+}
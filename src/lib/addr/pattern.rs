@@ -0,0 +1,77 @@
+use std::fmt;
+
+use super::keystore::{AddressError, DEFAULT_NS_DELIM};
+
+/// Characters with special meaning in SQLite `GLOB` patterns.
+const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+/// A `project.namespace[.key]` selection where any segment may be a glob (`*`, `?`, `[...]`).
+///
+/// `app.*.enabled` matches `enabled` in every namespace of `app`; a missing key
+/// segment matches every key. Globs never cross a delimiter, and keys are
+/// matched without their `__context` suffix.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyPattern {
+    pub project: String,
+    pub namespace: String,
+    pub key: String,
+}
+
+impl KeyPattern {
+    pub fn parse(path: &str) -> Result<Self, AddressError> {
+        Self::parse_with_delim(path, DEFAULT_NS_DELIM)
+    }
+
+    pub fn parse_with_delim(path: &str, delim: &str) -> Result<Self, AddressError> {
+        if delim.is_empty() {
+            return Err(AddressError::new("namespace delimiter cannot be empty"));
+        }
+
+        let parts: Vec<&str> = path.splitn(3, delim).collect();
+        let (project, namespace, key) = match parts.as_slice() {
+            [project, namespace] => (*project, *namespace, "*"),
+            [project, namespace, key] => (*project, *namespace, *key),
+            _ => ("", "", ""),
+        };
+        if project.is_empty() || namespace.is_empty() || key.is_empty() {
+            return Err(AddressError::new(format!(
+                "expected project{}namespace[{}key] pattern, got '{}'",
+                delim, delim, path
+            )));
+        }
+
+        Ok(Self {
+            project: project.to_string(),
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+        })
+    }
+
+    /// True when `path` needs glob matching rather than a plain namespace scope.
+    ///
+    /// A single trailing `*` (`project.ns.prefix*`) is still a prefix scope.
+    pub fn is_pattern(path: &str) -> bool {
+        let body = path.strip_suffix('*').unwrap_or(path);
+        body.contains(GLOB_CHARS)
+    }
+
+    /// True when `path` holds any glob character, a trailing `*` included.
+    pub fn has_glob(path: &str) -> bool {
+        path.contains(GLOB_CHARS)
+    }
+
+    /// Matches may come from more than one project or namespace.
+    pub fn spans_namespaces(&self) -> bool {
+        self.project.contains(GLOB_CHARS) || self.namespace.contains(GLOB_CHARS)
+    }
+}
+
+impl fmt::Display for KeyPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}{}",
+            self.project, DEFAULT_NS_DELIM, self.namespace, DEFAULT_NS_DELIM, self.key
+        )
+    }
+}
@@ -0,0 +1,46 @@
+use crate::lib::addr::{Address, NamespaceScope};
+use crate::lib::core::crud::CrudResult;
+
+use super::sqlite::{KvEntry, SqliteKeystore};
+
+/// The key/value operations the addressing layer needs from a store.
+///
+/// `SqliteKeystore` is the full implementation; `MemoryKeystore` keeps
+/// everything in process for tooling that has no filesystem (such as a
+/// browser build). Expired keys are invisible to every method.
+pub trait KvBackend {
+    fn get(&self, addr: &Address) -> CrudResult<Option<String>>;
+
+    /// Store `value`; `ttl` seconds from now when given, else permanent.
+    fn set(&self, addr: &Address, value: &str, ttl: Option<u64>) -> CrudResult<()>;
+
+    /// Returns whether a live key was removed.
+    fn delete(&self, addr: &Address) -> CrudResult<bool>;
+
+    fn exists(&self, addr: &Address) -> CrudResult<bool>;
+
+    /// Live entries under `scope`, ordered by key then context.
+    fn list(&self, scope: &NamespaceScope) -> CrudResult<Vec<KvEntry>>;
+}
+
+impl KvBackend for SqliteKeystore {
+    fn get(&self, addr: &Address) -> CrudResult<Option<String>> {
+        SqliteKeystore::get(self, addr)
+    }
+
+    fn set(&self, addr: &Address, value: &str, ttl: Option<u64>) -> CrudResult<()> {
+        SqliteKeystore::set(self, addr, value, ttl)
+    }
+
+    fn delete(&self, addr: &Address) -> CrudResult<bool> {
+        SqliteKeystore::delete(self, addr)
+    }
+
+    fn exists(&self, addr: &Address) -> CrudResult<bool> {
+        SqliteKeystore::exists(self, addr)
+    }
+
+    fn list(&self, scope: &NamespaceScope) -> CrudResult<Vec<KvEntry>> {
+        SqliteKeystore::list(self, scope)
+    }
+}
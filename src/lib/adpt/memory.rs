@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::lib::addr::{Address, NamespaceScope};
+use crate::lib::core::crud::CrudResult;
+
+use super::backend::KvBackend;
+use super::sqlite::{Clock, KvEntry, SystemClock};
+
+/// A keystore held entirely in memory, for builds without SQLite or a filesystem.
+///
+/// Addresses, TTLs and scopes behave as in `SqliteKeystore`; namespace
+/// policies (cache settings, ACLs, secrets, encryption) are not modelled,
+/// so any key may carry a TTL. Expired keys are dropped lazily when touched.
+pub struct MemoryKeystore {
+    entries: Mutex<BTreeMap<Address, KvEntry>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for MemoryKeystore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryKeystore {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(BTreeMap::new()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Replace the clock used for every expiry decision (defaults to `SystemClock`).
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Every live entry, in address order (for persisting the store elsewhere).
+    pub fn entries(&self) -> Vec<KvEntry> {
+        let now = self.clock.now();
+        self.lock()
+            .values()
+            .filter(|entry| live(entry, now))
+            .cloned()
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<Address, KvEntry>> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn live_entry(&self, addr: &Address) -> Option<KvEntry> {
+        let now = self.clock.now();
+        let mut entries = self.lock();
+        match entries.get(addr) {
+            Some(entry) if live(entry, now) => Some(entry.clone()),
+            Some(_) => {
+                entries.remove(addr);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+impl KvBackend for MemoryKeystore {
+    fn get(&self, addr: &Address) -> CrudResult<Option<String>> {
+        Ok(self.live_entry(addr).map(|entry| entry.value))
+    }
+
+    fn set(&self, addr: &Address, value: &str, ttl: Option<u64>) -> CrudResult<()> {
+        let now = self.clock.now();
+        let created_at = self.live_entry(addr).map_or(now, |entry| entry.created_at);
+        self.lock().insert(
+            addr.clone(),
+            KvEntry {
+                address: addr.clone(),
+                value: value.to_string(),
+                created_at,
+                updated_at: now,
+                expires_at: ttl.map(|ttl| now + ttl as i64),
+                value_type: None,
+            },
+        );
+        Ok(())
+    }
+
+    fn delete(&self, addr: &Address) -> CrudResult<bool> {
+        let now = self.clock.now();
+        Ok(self
+            .lock()
+            .remove(addr)
+            .is_some_and(|entry| live(&entry, now)))
+    }
+
+    fn exists(&self, addr: &Address) -> CrudResult<bool> {
+        Ok(self.live_entry(addr).is_some())
+    }
+
+    fn list(&self, scope: &NamespaceScope) -> CrudResult<Vec<KvEntry>> {
+        let now = self.clock.now();
+        let prefix = scope.prefix.as_deref().unwrap_or("");
+        Ok(self
+            .lock()
+            .values()
+            .filter(|entry| {
+                entry.address.project == scope.project
+                    && entry.address.namespace == scope.namespace
+                    && entry.address.key.starts_with(prefix)
+                    && live(entry, now)
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+fn live(entry: &KvEntry, now: i64) -> bool {
+    entry.expires_at.is_none_or(|at| at > now)
+}
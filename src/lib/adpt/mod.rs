@@ -1,3 +1,8 @@
 //! Adapter modules bridge CRUD traits to concrete storage backends.
 
+mod backend;
+mod memory;
 pub mod sqlite;
+
+pub use backend::KvBackend;
+pub use memory::MemoryKeystore;
@@ -9,6 +9,7 @@ use crate::lib::core::crud::{
     CrudOutcome, CrudResource, CrudResult, CrudVerb,
 };
 
+use super::toggles::{ensure_capability, CapabilityToggles};
 use super::utils::{SqliteConnectionConfig, SqlitePathResolver};
 
 /// Adapter responsible for SQLite database-level operations (open, backup, restore).
@@ -142,7 +143,13 @@ impl<H: CrudHooks> CrudResource for SqliteBaseAdapter<H> {
         map.allow(CrudObjectKind::Base, CrudVerb::Read);
         map.allow(CrudObjectKind::Base, CrudVerb::Backup);
         map.allow(CrudObjectKind::Base, CrudVerb::Restore);
-        map
+        CapabilityToggles::load(&self.config)
+            .map(|toggles| toggles.apply(map.clone()))
+            .unwrap_or(map)
+    }
+
+    fn ensure_capability(&self, verb: CrudVerb, ctx: &CrudContext) -> CrudResult<()> {
+        ensure_capability(&self.config_from_ctx(ctx), &self.object_kind(), verb)
     }
 
     fn create(&self, ctx: CrudContext) -> CrudResult<CrudOutcome> {
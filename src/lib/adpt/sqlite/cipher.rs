@@ -0,0 +1,381 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+#[cfg(feature = "encryption-aes")]
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+#[cfg(feature = "encryption-aes")]
+use aes_gcm::aead::rand_core::RngCore;
+#[cfg(feature = "encryption-aes")]
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+#[cfg(feature = "encryption-aes")]
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+#[cfg(feature = "encryption-aes")]
+use hub::data_ext::base64::{engine::general_purpose, Engine as _};
+use hub::error_ext::anyhow;
+#[cfg(feature = "encryption-aes")]
+use sha2::{Digest, Sha256};
+
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+
+/// Environment fallback for `--key-file`.
+pub const KEY_FILE_ENV: &str = "PRONTO_KEY_FILE";
+
+/// Stored values of encrypted namespaces: `enc:v1:` + base64(nonce || AES-256-GCM ciphertext),
+/// sealed with the value's `project.namespace.key` as associated data.
+#[cfg(feature = "encryption-aes")]
+const CIPHERTEXT_PREFIX: &str = "enc:v1:";
+#[cfg(feature = "encryption-aes")]
+const PBKDF2_ROUNDS: u32 = 100_000;
+#[cfg(feature = "encryption-aes")]
+const NONCE_LEN: usize = 12;
+
+/// Start of a file written by `seal_file`, followed by the salt, the nonce
+/// prefix and `[last flag][u32 length][ciphertext]` chunks.
+const SEALED_MAGIC: &[u8; 8] = b"PDBSEAL1";
+#[cfg(feature = "encryption-aes")]
+const SEALED_SALT_LEN: usize = 16;
+/// Each chunk's nonce is this prefix, its big-endian counter and its last flag.
+#[cfg(feature = "encryption-aes")]
+const SEALED_NONCE_PREFIX_LEN: usize = 7;
+#[cfg(feature = "encryption-aes")]
+const SEALED_CHUNK_LEN: usize = 1 << 20;
+#[cfg(feature = "encryption-aes")]
+const TAG_LEN: usize = 16;
+
+/// One `sys_encryption` row: the salt a namespace key is derived with, and a check value
+/// that tells a wrong key file apart from a corrupt value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncryptedNamespace {
+    pub project: String,
+    pub namespace: String,
+    /// Base64 PBKDF2 salt, random per namespace.
+    pub salt: String,
+    /// First 16 hex digits of the SHA-256 of the derived key.
+    pub key_check: String,
+}
+
+/// AES-256-GCM key of one namespace, derived from key-file contents with PBKDF2-SHA256.
+///
+/// Needs the `encryption-aes` feature; without it every constructor fails
+/// with `InvalidInput`, so encrypted namespaces are never read as ciphertext.
+pub struct NamespaceCipher {
+    #[cfg(feature = "encryption-aes")]
+    key: [u8; 32],
+}
+
+/// Key-file contents without trailing whitespace (so `echo secret > key` works).
+pub fn read_key_file(path: &Path) -> CrudResult<Vec<u8>> {
+    let verb = CrudVerb::Read;
+    let mut secret = fs::read(path).map_err(|err| {
+        invalid_input(
+            verb,
+            format!("cannot read key file {}: {}", path.display(), err),
+        )
+    })?;
+    while secret.last().is_some_and(u8::is_ascii_whitespace) {
+        secret.pop();
+    }
+    if secret.is_empty() {
+        return Err(invalid_input(
+            verb,
+            format!("key file {} is empty", path.display()),
+        ));
+    }
+    Ok(secret)
+}
+
+#[cfg(feature = "encryption-aes")]
+impl NamespaceCipher {
+    /// A fresh random salt for `derive`, base64-encoded.
+    pub fn new_salt() -> CrudResult<String> {
+        Ok(general_purpose::STANDARD.encode(Aes256Gcm::generate_key(&mut OsRng)))
+    }
+
+    pub fn derive(secret: &[u8], salt: &str) -> CrudResult<Self> {
+        let salt = general_purpose::STANDARD
+            .decode(salt)
+            .map_err(|err| internal(CrudVerb::Read, anyhow::Error::new(err)))?;
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(secret, &salt, PBKDF2_ROUNDS, &mut key);
+        Ok(Self { key })
+    }
+
+    /// `derive` for `namespace`, failing with `PermissionDenied` when `secret` is the wrong key.
+    pub fn unlock(secret: &[u8], namespace: &EncryptedNamespace) -> CrudResult<Self> {
+        let cipher = Self::derive(secret, &namespace.salt)?;
+        if cipher.key_check() != namespace.key_check {
+            return Err(CrudError::permission_denied(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Record,
+                CrudVerb::Read,
+                format!(
+                    "key file does not unlock encrypted namespace {}.{}",
+                    namespace.project, namespace.namespace
+                ),
+            ));
+        }
+        Ok(cipher)
+    }
+
+    pub fn key_check(&self) -> String {
+        let digest = Sha256::digest(self.key);
+        digest[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Seal `plaintext` for `address`; only `decrypt` with the same address opens it.
+    pub fn encrypt(&self, address: &str, plaintext: &str) -> CrudResult<String> {
+        let verb = CrudVerb::Create;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let message = Payload {
+            msg: plaintext.as_bytes(),
+            aad: address.as_bytes(),
+        };
+        let sealed = cipher
+            .encrypt(&nonce, message)
+            .map_err(|_| internal(verb, anyhow::anyhow!("value encryption failed")))?;
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&sealed);
+        Ok(format!(
+            "{}{}",
+            CIPHERTEXT_PREFIX,
+            general_purpose::STANDARD.encode(payload)
+        ))
+    }
+
+    /// Open a value sealed for `address`; a value copied from another address fails as corrupt.
+    pub fn decrypt(&self, address: &str, stored: &str) -> CrudResult<String> {
+        let verb = CrudVerb::Read;
+        let corrupt = || {
+            internal(
+                verb,
+                anyhow::anyhow!("stored value is not valid ciphertext"),
+            )
+        };
+        let payload = stored
+            .strip_prefix(CIPHERTEXT_PREFIX)
+            .and_then(|encoded| general_purpose::STANDARD.decode(encoded).ok())
+            .filter(|payload| payload.len() > NONCE_LEN)
+            .ok_or_else(corrupt)?;
+        let (nonce, sealed) = payload.split_at(NONCE_LEN);
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key));
+        let message = Payload {
+            msg: sealed,
+            aad: address.as_bytes(),
+        };
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), message)
+            .map_err(|_| corrupt())?;
+        String::from_utf8(plaintext).map_err(|_| corrupt())
+    }
+}
+
+/// Whether `path` starts like a file written by `seal_file`.
+pub fn is_sealed(path: &Path) -> io::Result<bool> {
+    let mut magic = [0u8; SEALED_MAGIC.len()];
+    match File::open(path)?.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == SEALED_MAGIC),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Encrypt `source` into `target` with a key derived from `secret` and a fresh salt.
+///
+/// Chunks are sealed with AES-256-GCM under nonces that count them and mark
+/// the last one, so a truncated or reordered file fails to open.
+#[cfg(feature = "encryption-aes")]
+pub fn seal_file(secret: &[u8], source: &Path, target: &Path) -> CrudResult<()> {
+    let verb = CrudVerb::Backup;
+    let io_failed = |err: io::Error| internal(verb, anyhow::Error::new(err));
+    let mut salt = [0u8; SEALED_SALT_LEN];
+    let mut prefix = [0u8; SEALED_NONCE_PREFIX_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut prefix);
+    let cipher = file_cipher(secret, &salt);
+
+    let mut input = BufReader::new(File::open(source).map_err(io_failed)?);
+    let mut out = BufWriter::new(File::create(target).map_err(io_failed)?);
+    out.write_all(SEALED_MAGIC).map_err(io_failed)?;
+    out.write_all(&salt).map_err(io_failed)?;
+    out.write_all(&prefix).map_err(io_failed)?;
+    let mut chunk = vec![0u8; SEALED_CHUNK_LEN];
+    for counter in 0u32.. {
+        let filled = read_full(&mut input, &mut chunk).map_err(io_failed)?;
+        // A full chunk is never the last; a file of whole chunks ends with an empty one.
+        let last = filled < chunk.len();
+        let sealed = cipher
+            .encrypt(
+                Nonce::from_slice(&chunk_nonce(&prefix, counter, last)),
+                &chunk[..filled],
+            )
+            .map_err(|_| internal(verb, anyhow::anyhow!("backup encryption failed")))?;
+        out.write_all(&[u8::from(last)]).map_err(io_failed)?;
+        out.write_all(&(sealed.len() as u32).to_be_bytes())
+            .map_err(io_failed)?;
+        out.write_all(&sealed).map_err(io_failed)?;
+        if last {
+            break;
+        }
+    }
+    out.into_inner()
+        .map_err(|err| io_failed(err.into_error()))?
+        .sync_all()
+        .map_err(io_failed)
+}
+
+/// Decrypt a `seal_file` output into `target`; a wrong `secret` is `PermissionDenied`.
+#[cfg(feature = "encryption-aes")]
+pub fn open_sealed_file(secret: &[u8], source: &Path, target: &Path) -> CrudResult<()> {
+    let verb = CrudVerb::Restore;
+    let io_failed = |err: io::Error| internal(verb, anyhow::Error::new(err));
+    let corrupt = || {
+        invalid_input(
+            verb,
+            format!("{} is damaged or not an encrypted backup", source.display()),
+        )
+    };
+    let mut input = BufReader::new(File::open(source).map_err(io_failed)?);
+    let mut header = [0u8; SEALED_MAGIC.len() + SEALED_SALT_LEN + SEALED_NONCE_PREFIX_LEN];
+    input.read_exact(&mut header).map_err(|_| corrupt())?;
+    let (magic, rest) = header.split_at(SEALED_MAGIC.len());
+    let (salt, prefix) = rest.split_at(SEALED_SALT_LEN);
+    if magic != SEALED_MAGIC {
+        return Err(corrupt());
+    }
+    let cipher = file_cipher(secret, salt);
+
+    let mut out = BufWriter::new(File::create(target).map_err(io_failed)?);
+    for counter in 0u32.. {
+        let mut frame = [0u8; 5];
+        input.read_exact(&mut frame).map_err(|_| corrupt())?;
+        let last = match frame[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(corrupt()),
+        };
+        let len = u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]) as usize;
+        if len > SEALED_CHUNK_LEN + TAG_LEN {
+            return Err(corrupt());
+        }
+        let mut sealed = vec![0u8; len];
+        input.read_exact(&mut sealed).map_err(|_| corrupt())?;
+        let plain = cipher
+            .decrypt(
+                Nonce::from_slice(&chunk_nonce(prefix, counter, last)),
+                sealed.as_slice(),
+            )
+            .map_err(|_| match counter {
+                // The first chunk failing is almost always the wrong key.
+                0 => CrudError::permission_denied(
+                    CrudDomain::Sqlite,
+                    CrudObjectKind::Base,
+                    verb,
+                    format!("key file does not decrypt {}", source.display()),
+                ),
+                _ => corrupt(),
+            })?;
+        out.write_all(&plain).map_err(io_failed)?;
+        if last {
+            break;
+        }
+    }
+    out.flush().map_err(io_failed)
+}
+
+#[cfg(feature = "encryption-aes")]
+fn file_cipher(secret: &[u8], salt: &[u8]) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(secret, salt, PBKDF2_ROUNDS, &mut key);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+#[cfg(feature = "encryption-aes")]
+fn chunk_nonce(prefix: &[u8], counter: u32, last: bool) -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..SEALED_NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[SEALED_NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_LEN - 1] = u8::from(last);
+    nonce
+}
+
+/// Fill `buf` from `input`, stopping early only at end of file.
+#[cfg(feature = "encryption-aes")]
+fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(not(feature = "encryption-aes"))]
+pub fn seal_file(_secret: &[u8], _source: &Path, _target: &Path) -> CrudResult<()> {
+    Err(sealing_missing())
+}
+
+#[cfg(not(feature = "encryption-aes"))]
+pub fn open_sealed_file(_secret: &[u8], _source: &Path, _target: &Path) -> CrudResult<()> {
+    Err(sealing_missing())
+}
+
+#[cfg(not(feature = "encryption-aes"))]
+fn sealing_missing() -> CrudError {
+    invalid_input(
+        CrudVerb::Backup,
+        "encrypted backups need prontodb built with the encryption-aes feature",
+    )
+}
+
+#[cfg(not(feature = "encryption-aes"))]
+impl NamespaceCipher {
+    pub fn new_salt() -> CrudResult<String> {
+        Err(feature_missing())
+    }
+
+    pub fn derive(_secret: &[u8], _salt: &str) -> CrudResult<Self> {
+        Err(feature_missing())
+    }
+
+    pub fn unlock(_secret: &[u8], _namespace: &EncryptedNamespace) -> CrudResult<Self> {
+        Err(feature_missing())
+    }
+
+    pub fn key_check(&self) -> String {
+        String::new()
+    }
+
+    pub fn encrypt(&self, _address: &str, _plaintext: &str) -> CrudResult<String> {
+        Err(feature_missing())
+    }
+
+    pub fn decrypt(&self, _address: &str, _stored: &str) -> CrudResult<String> {
+        Err(feature_missing())
+    }
+}
+
+#[cfg(not(feature = "encryption-aes"))]
+fn feature_missing() -> CrudError {
+    invalid_input(
+        CrudVerb::Read,
+        "encrypted namespaces need prontodb built with the encryption-aes feature",
+    )
+}
+
+fn invalid_input<S: Into<String>>(verb: CrudVerb, message: S) -> CrudError {
+    CrudError::invalid_input(CrudDomain::Sqlite, CrudObjectKind::Record, verb, message)
+}
+
+#[cfg_attr(not(feature = "encryption-aes"), allow(dead_code))]
+fn internal(verb: CrudVerb, source: anyhow::Error) -> CrudError {
+    CrudError::internal(CrudDomain::Sqlite, CrudObjectKind::Record, verb, source)
+}
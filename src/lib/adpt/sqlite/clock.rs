@@ -0,0 +1,45 @@
+use std::env;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment override for the current time (unix seconds), used to replay TTL behaviour.
+pub const PRONTO_NOW_ENV: &str = "PRONTO_NOW";
+
+/// Source of "now" for every TTL decision the keystore makes.
+pub trait Clock: Send + Sync {
+    /// Current time in unix seconds.
+    fn now(&self) -> i64;
+}
+
+/// Wall clock, unless `PRONTO_NOW` holds a unix timestamp.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        if let Some(now) = env::var(PRONTO_NOW_ENV)
+            .ok()
+            .and_then(|raw| raw.trim().parse::<i64>().ok())
+        {
+            return now;
+        }
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default()
+    }
+}
+
+/// Clock pinned to a single instant; handy for deterministic tests.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> i64 {
+        self.0
+    }
+}
+
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
@@ -0,0 +1,4171 @@
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hub::data_ext::serde_json;
+use hub::error_ext::anyhow;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{
+    params, Connection, DatabaseName, OptionalExtension, Row, Transaction, TransactionBehavior,
+};
+
+use crate::lib::addr::{Address, AddressLayout, KeyPattern, NamespaceScope};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+use crate::lib::core::identity::{os_user, owns_path};
+
+use super::cipher::{EncryptedNamespace, NamespaceCipher};
+use super::clock::{system_clock, Clock};
+use super::utils::{SqliteConnectionConfig, SqlitePathResolver};
+
+const SCHEMA_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS kv (
+        project TEXT NOT NULL,
+        namespace TEXT NOT NULL,
+        key TEXT NOT NULL,
+        context TEXT NOT NULL DEFAULT '',
+        value TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL,
+        expires_at INTEGER,
+        value_type TEXT,
+        PRIMARY KEY (project, namespace, key, context)
+    );
+    CREATE TABLE IF NOT EXISTS sys_namespaces (
+        project TEXT NOT NULL,
+        namespace TEXT NOT NULL,
+        default_ttl INTEGER,
+        sliding INTEGER NOT NULL DEFAULT 0,
+        allow_key_ttl INTEGER NOT NULL DEFAULT 0,
+        max_ttl INTEGER,
+        clamp_ttl INTEGER NOT NULL DEFAULT 0,
+        retention_max_age INTEGER,
+        retention_max_keys INTEGER,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (project, namespace)
+    );
+    CREATE TABLE IF NOT EXISTS sys_hooks (
+        project TEXT NOT NULL,
+        namespace TEXT NOT NULL,
+        command TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (project, namespace)
+    );
+    CREATE TABLE IF NOT EXISTS sys_indexes (
+        project TEXT NOT NULL,
+        namespace TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        path TEXT NOT NULL DEFAULT '',
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (project, namespace, kind, path)
+    );
+    CREATE TABLE IF NOT EXISTS sys_acl (
+        user TEXT NOT NULL,
+        project TEXT NOT NULL,
+        namespace TEXT NOT NULL,
+        permission TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (user, project, namespace)
+    );
+    CREATE TABLE IF NOT EXISTS sys_tokens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        token_hash TEXT NOT NULL UNIQUE,
+        project TEXT NOT NULL,
+        namespace TEXT NOT NULL,
+        permission TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS sys_encryption (
+        project TEXT NOT NULL,
+        namespace TEXT NOT NULL,
+        salt TEXT NOT NULL,
+        key_check TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (project, namespace)
+    );
+    CREATE TABLE IF NOT EXISTS sys_secrets (
+        project TEXT NOT NULL,
+        namespace TEXT NOT NULL,
+        key TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        PRIMARY KEY (project, namespace, key)
+    );
+    CREATE TABLE IF NOT EXISTS sys_sync (
+        peer TEXT NOT NULL,
+        direction TEXT NOT NULL,
+        watermark INTEGER NOT NULL,
+        synced_at INTEGER NOT NULL,
+        PRIMARY KEY (peer, direction)
+    );
+    CREATE TABLE IF NOT EXISTS sys_settings (
+        name TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS kv_expires_at ON kv (expires_at) WHERE expires_at IS NOT NULL;
+";
+
+/// `sys_settings` row holding the database's `AddressLayout`.
+const ADDRESS_LAYOUT_SETTING: &str = "address_layout";
+
+/// `sys_settings` row holding the database's `ValidationMode`.
+const VALIDATION_MODE_SETTING: &str = "validation_mode";
+
+/// Columns added after the first release, as `(table, column, declaration)`.
+const ADDED_COLUMNS: [(&str, &str, &str); 3] = [
+    ("kv", "value_type", "TEXT"),
+    ("sys_namespaces", "retention_max_age", "INTEGER"),
+    ("sys_namespaces", "retention_max_keys", "INTEGER"),
+];
+
+/// FTS5 table and triggers, created on first `create_index(.., IndexKind::FullText)`.
+///
+/// `kv_fts` rows share their rowid with `kv`; triggers only index rows of
+/// namespaces registered in `sys_indexes`, so other namespaces pay nothing.
+const FTS_SQL: &str = "
+    CREATE VIRTUAL TABLE IF NOT EXISTS kv_fts USING fts5 (value);
+    CREATE TRIGGER IF NOT EXISTS kv_fts_insert AFTER INSERT ON kv
+    WHEN EXISTS (SELECT 1 FROM sys_indexes
+                 WHERE project = NEW.project AND namespace = NEW.namespace AND kind = 'fts')
+    BEGIN
+        INSERT INTO kv_fts (rowid, value) VALUES (NEW.rowid, NEW.value);
+    END;
+    CREATE TRIGGER IF NOT EXISTS kv_fts_delete AFTER DELETE ON kv
+    BEGIN
+        DELETE FROM kv_fts WHERE rowid = OLD.rowid;
+    END;
+    CREATE TRIGGER IF NOT EXISTS kv_fts_update AFTER UPDATE ON kv
+    BEGIN
+        DELETE FROM kv_fts WHERE rowid = OLD.rowid;
+        INSERT INTO kv_fts (rowid, value)
+        SELECT NEW.rowid, NEW.value
+        WHERE EXISTS (SELECT 1 FROM sys_indexes
+                      WHERE project = NEW.project AND namespace = NEW.namespace AND kind = 'fts');
+    END;
+";
+
+/// Append-only mutation log, created by `enable_audit`; absent means auditing is off.
+const AUDIT_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS sys_audit (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        at INTEGER NOT NULL,
+        user TEXT,
+        command TEXT NOT NULL,
+        address TEXT NOT NULL,
+        old_hash TEXT,
+        new_hash TEXT
+    );
+    CREATE TRIGGER IF NOT EXISTS sys_audit_no_update BEFORE UPDATE ON sys_audit
+    BEGIN
+        SELECT RAISE(ABORT, 'sys_audit is append-only');
+    END;
+    CREATE TRIGGER IF NOT EXISTS sys_audit_no_delete BEFORE DELETE ON sys_audit
+    BEGIN
+        SELECT RAISE(ABORT, 'sys_audit is append-only');
+    END;
+";
+
+/// Change log created by `enable_oplog`; rows are written by the per-connection
+/// triggers of `oplog_triggers`, so they commit or roll back with the mutation.
+const OPLOG_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS sys_oplog (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        op TEXT NOT NULL,
+        address TEXT NOT NULL,
+        value_hash TEXT,
+        at INTEGER NOT NULL,
+        user TEXT
+    );
+    CREATE INDEX IF NOT EXISTS sys_oplog_at ON sys_oplog (at);
+";
+
+/// Precondition checked atomically before a conditional set.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SetCondition {
+    /// Only write when the key is missing (or expired).
+    IfAbsent,
+    /// Only write when the current value equals the expected one (compare-and-swap).
+    IfValue(String),
+}
+
+/// Optional type tag set by `set --type`; untagged values are plain strings.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueType {
+    String,
+    Int,
+    Bool,
+    Json,
+}
+
+impl ValueType {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "string" => Some(ValueType::String),
+            "int" => Some(ValueType::Int),
+            "bool" => Some(ValueType::Bool),
+            "json" => Some(ValueType::Json),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ValueType::String => "string",
+            ValueType::Int => "int",
+            ValueType::Bool => "bool",
+            ValueType::Json => "json",
+        }
+    }
+
+    /// `value` is a valid literal of this type (`42`, `true`/`false`, a JSON document).
+    pub fn accepts(self, value: &str) -> bool {
+        match self {
+            ValueType::String => true,
+            ValueType::Int => value.parse::<i64>().is_ok(),
+            ValueType::Bool => matches!(value, "true" | "false"),
+            ValueType::Json => serde_json::from_str::<serde_json::Value>(value).is_ok(),
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Whether `set` may write to a namespace the database does not know yet.
+///
+/// A namespace is known once it holds a key (live or expired) or was
+/// registered with `create_namespace` (caches and `--create-namespace` are).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ValidationMode {
+    /// Any well-formed address can be written.
+    #[default]
+    Lax,
+    /// Writes to unknown projects/namespaces are refused unless they ask to create it.
+    Strict,
+}
+
+impl ValidationMode {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "lax" => Some(ValidationMode::Lax),
+            "strict" => Some(ValidationMode::Strict),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ValidationMode::Lax => "lax",
+            ValidationMode::Strict => "strict",
+        }
+    }
+}
+
+impl fmt::Display for ValidationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A stored key with its value and timestamps (unix seconds).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KvEntry {
+    pub address: Address,
+    pub value: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub expires_at: Option<i64>,
+    /// `None` for values written without `--type`.
+    pub value_type: Option<ValueType>,
+}
+
+impl KvEntry {
+    /// Seconds left before expiry relative to `now`; `None` for permanent keys.
+    pub fn ttl_remaining(&self, now: i64) -> Option<i64> {
+        self.expires_at.map(|at| (at - now).max(0))
+    }
+}
+
+/// TTL policy of a cache namespace, stored in `sys_namespaces`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CacheSettings {
+    /// Seconds a key lives after being written (or, when `sliding`, after being read).
+    pub ttl_secs: u64,
+    /// Reads push the expiry forward by `ttl_secs` (session-cache semantics).
+    pub sliding: bool,
+    /// Upper bound for explicit `set --ttl` values in this namespace.
+    pub max_ttl_secs: Option<u64>,
+    /// Clamp TTLs above `max_ttl_secs` instead of rejecting them.
+    pub clamp_ttl: bool,
+}
+
+impl CacheSettings {
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl_secs,
+            sliding: false,
+            max_ttl_secs: None,
+            clamp_ttl: false,
+        }
+    }
+
+    pub fn with_sliding(mut self, sliding: bool) -> Self {
+        self.sliding = sliding;
+        self
+    }
+
+    pub fn with_max_ttl(mut self, max_ttl_secs: u64, clamp: bool) -> Self {
+        self.max_ttl_secs = Some(max_ttl_secs);
+        self.clamp_ttl = clamp;
+        self
+    }
+}
+
+/// Trimming rules of a log/metric style namespace, stored in `sys_namespaces`.
+///
+/// The sweeper deletes keys not written for `max_age_secs`, then the least
+/// recently written keys beyond `max_keys`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_age_secs: Option<u64>,
+    pub max_keys: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.max_age_secs.is_none() && self.max_keys.is_none()
+    }
+}
+
+/// One cache namespace as listed by `prontodb-admin --caches`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CacheSummary {
+    pub project: String,
+    pub namespace: String,
+    pub settings: CacheSettings,
+    /// Live (non-expired) keys.
+    pub keys: u64,
+    /// Earliest upcoming `expires_at` (unix seconds), if any key has one.
+    pub next_expiry: Option<i64>,
+}
+
+/// Opt-in secondary index on a namespace, registered in `sys_indexes`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IndexKind {
+    /// FTS5 full-text index over values, queried with `search`.
+    FullText,
+    /// Expression index on a JSON field of the values (`.status`, `.user.id`), used by `scan --where`.
+    JsonPath(String),
+}
+
+impl IndexKind {
+    fn as_parts(&self) -> (&str, &str) {
+        match self {
+            IndexKind::FullText => ("fts", ""),
+            IndexKind::JsonPath(path) => ("json", path),
+        }
+    }
+
+    fn from_parts(kind: &str, path: &str) -> Option<Self> {
+        match kind {
+            "fts" => Some(IndexKind::FullText),
+            "json" => Some(IndexKind::JsonPath(path.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IndexKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexKind::FullText => f.write_str("fts"),
+            IndexKind::JsonPath(path) => write!(f, "json {}", path),
+        }
+    }
+}
+
+/// `scan --where '.path=value'`: keep entries whose JSON value has `value` at `path`.
+///
+/// Numbers and `true`/`false` compare as JSON scalars; anything else as a string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonFilter {
+    pub path: String,
+    pub value: String,
+}
+
+impl JsonFilter {
+    /// Parse `.path=value`; `None` when there is no `=` or the path is empty.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let (path, value) = raw.split_once('=')?;
+        let path = path.trim();
+        if path.is_empty() {
+            return None;
+        }
+        Some(Self {
+            path: path.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+/// One row of `prontodb-admin --indexes`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexInfo {
+    pub project: String,
+    pub namespace: String,
+    pub kind: IndexKind,
+}
+
+/// A full-text match; higher `score` ranks first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    pub entry: KvEntry,
+    pub score: f64,
+    /// Matching fragment with hits wrapped in `[` `]`.
+    pub snippet: String,
+}
+
+/// Column a scan is ordered by; ties fall back to key order.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SortField {
+    #[default]
+    Key,
+    /// Last write time (`updated_at`).
+    Updated,
+    /// Value length in bytes.
+    Size,
+}
+
+/// `scan --sort FIELD [--desc]`, applied in the SQL `ORDER BY`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EntryOrder {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl EntryOrder {
+    /// `ORDER BY` terms; `spans` puts project and namespace ahead of the key.
+    fn order_by(self, spans: bool) -> String {
+        let keyed = |dir: &str| {
+            let columns: &[&str] = if spans {
+                &["project", "namespace", "key", "context"]
+            } else {
+                &["key", "context"]
+            };
+            columns
+                .iter()
+                .map(|column| format!("{} {}", column, dir))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let dir = if self.descending { "DESC" } else { "ASC" };
+        match self.field {
+            SortField::Key => keyed(dir),
+            SortField::Updated => format!("updated_at {}, {}", dir, keyed("ASC")),
+            SortField::Size => format!("length(CAST(value AS BLOB)) {}, {}", dir, keyed("ASC")),
+        }
+    }
+}
+
+/// How a bulk import treats keys that already hold a live value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImportStrategy {
+    /// Replace existing values.
+    Overwrite,
+    /// Keep existing values and skip the imported ones.
+    Skip,
+    /// Abort the whole import if any key already exists.
+    Fail,
+}
+
+/// Outcome of a bulk import.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ImportReport {
+    pub inserted: usize,
+    pub skipped: usize,
+    /// Existing keys under `ImportStrategy::Fail`; when non-zero nothing was written.
+    pub conflicted: usize,
+}
+
+/// Outcome of `SqliteKeystore::sync_to`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncReport {
+    /// Rows inserted or updated in the target.
+    pub copied: usize,
+    /// Changed rows the target already held as they are, or with a newer `updated_at`.
+    pub skipped: usize,
+    /// Newest `updated_at` among the changed rows, where the next sync starts;
+    /// `since` when nothing changed.
+    pub watermark: i64,
+}
+
+/// Expired rows reclaimed from one namespace by a sweep pass.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SweepReport {
+    pub project: String,
+    pub namespace: String,
+    pub removed: usize,
+    /// Removed keys, with a `__context` suffix where one was set.
+    pub keys: Vec<String>,
+}
+
+/// Shell command run (via `sh -c`) after the sweeper removes expired keys from a namespace.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpiryHook {
+    pub project: String,
+    pub namespace: String,
+    pub command: String,
+}
+
+/// What a user may do in a namespace; `Write` includes `Read`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Permission {
+    Read,
+    Write,
+}
+
+impl Permission {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "read" => Some(Permission::Read),
+            "write" => Some(Permission::Write),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Namespace matching every namespace of a project in an ACL rule.
+pub const ACL_ALL_NAMESPACES: &str = "*";
+
+/// One `sys_acl` row: `user` holds `permission` in `project.namespace`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccessRule {
+    pub user: String,
+    pub project: String,
+    /// A namespace, or `*` for the whole project.
+    pub namespace: String,
+    pub permission: Permission,
+}
+
+/// Every ACL rule, loaded once so scans across namespaces can filter rows cheaply.
+///
+/// A namespace without rules (directly or via `project.*`) is open to
+/// everyone. Once it has any rule, only the users it names may touch it, at
+/// the strongest permission granted to them.
+///
+/// Rules are matched against the OS user running the process. A claimed
+/// user (`--user`, `config.user`) replaces it only when that process owns
+/// the database file or is root, who could rewrite the rules anyway.
+#[derive(Clone, Debug, Default)]
+pub struct AccessPolicy {
+    rules: Vec<AccessRule>,
+    /// The token the connection authenticated with; it narrows every rule to its scope.
+    token: Option<ApiToken>,
+    os_user: Option<String>,
+    /// The process owns the database (or is root), so claimed users are honoured.
+    impersonate: bool,
+}
+
+impl AccessPolicy {
+    pub fn allows(
+        &self,
+        user: Option<&str>,
+        project: &str,
+        namespace: &str,
+        need: Permission,
+    ) -> bool {
+        if !self.token_allows(project, namespace, need) {
+            return false;
+        }
+        let user = self.identity(user);
+        let mut restricted = false;
+        let mut granted = None;
+        for rule in &self.rules {
+            let applies = rule.project == project
+                && (rule.namespace == namespace || rule.namespace == ACL_ALL_NAMESPACES);
+            if !applies {
+                continue;
+            }
+            restricted = true;
+            if user == Some(rule.user.as_str()) {
+                granted = granted.max(Some(rule.permission));
+            }
+        }
+        !restricted || granted.is_some_and(|granted| granted >= need)
+    }
+
+    /// `allows` as a result: `PermissionDenied` naming the user, permission and namespace.
+    pub fn check(
+        &self,
+        user: Option<&str>,
+        project: &str,
+        namespace: &str,
+        need: Permission,
+        verb: CrudVerb,
+    ) -> CrudResult<()> {
+        if self.allows(user, project, namespace, need) {
+            return Ok(());
+        }
+        let holder = match &self.token {
+            Some(token) if !self.token_allows(project, namespace, need) => {
+                format!("API token #{}", token.id)
+            }
+            _ => format!("user '{}'", self.identity(user).unwrap_or("(anonymous)")),
+        };
+        Err(CrudError::permission_denied(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Record,
+            verb,
+            format!(
+                "{} has no {} access to {}.{}",
+                holder, need, project, namespace
+            ),
+        ))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.token.is_none()
+    }
+
+    /// Whether a rule names `user` with write access to `project.namespace`
+    /// (a `project.*` rule covers every namespace). Unlike `allows`, an
+    /// unrestricted namespace does not count.
+    pub fn grants_write(&self, user: &str, project: &str, namespace: &str) -> bool {
+        self.rules.iter().any(|rule| {
+            rule.user == user
+                && rule.project == project
+                && (rule.namespace == namespace || rule.namespace == ACL_ALL_NAMESPACES)
+                && rule.permission == Permission::Write
+        })
+    }
+
+    /// Who the rules are checked for when `claimed` asks: see the type docs.
+    pub fn identity<'a>(&'a self, claimed: Option<&'a str>) -> Option<&'a str> {
+        match claimed {
+            Some(user) if self.impersonate => Some(user),
+            _ => self.os_user.as_deref(),
+        }
+    }
+
+    fn token_allows(&self, project: &str, namespace: &str, need: Permission) -> bool {
+        self.token
+            .as_ref()
+            .is_none_or(|token| token.allows(project, namespace, need))
+    }
+}
+
+/// Prefix of raw API tokens, so leaked ones are easy to grep for.
+pub const API_TOKEN_PREFIX: &str = "pdb_";
+
+/// One `sys_tokens` row: a remote-access token scoped to `project.namespace` (or `project.*`).
+///
+/// Only the MD5 of the raw token is stored. Tokens carry 256 random bits,
+/// so the digest cannot be reversed; it just keeps a copied database from
+/// handing out live credentials.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApiToken {
+    pub id: i64,
+    pub project: String,
+    /// A namespace, or `*` for the whole project.
+    pub namespace: String,
+    pub permission: Permission,
+    pub created_at: i64,
+}
+
+impl ApiToken {
+    pub fn allows(&self, project: &str, namespace: &str, need: Permission) -> bool {
+        self.project == project
+            && (self.namespace == namespace || self.namespace == ACL_ALL_NAMESPACES)
+            && self.permission >= need
+    }
+}
+
+/// Key matching every key of a namespace in `sys_secrets`.
+pub const SECRET_ALL_KEYS: &str = "*";
+
+/// Keys (or whole namespaces) marked secret, loaded once so listings can mask values cheaply.
+///
+/// Marks apply to a key in every `__context`.
+#[derive(Clone, Debug, Default)]
+pub struct SecretMarks {
+    marks: Vec<(String, String, String)>,
+}
+
+impl SecretMarks {
+    pub fn is_secret(&self, addr: &Address) -> bool {
+        self.marks.iter().any(|(project, namespace, key)| {
+            *project == addr.project
+                && *namespace == addr.namespace
+                && (key == SECRET_ALL_KEYS || *key == addr.key)
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+}
+
+/// One `sys_audit` row: who changed an address, and the MD5 of its value before and after.
+///
+/// A `None` hash means the key was missing (before a create, after a delete).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub at: i64,
+    pub user: Option<String>,
+    pub command: String,
+    pub address: String,
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+}
+
+/// One `sys_oplog` row: `set` or `del` of an address, with the MD5 of the value written.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OplogEntry {
+    pub seq: i64,
+    pub op: String,
+    pub address: String,
+    /// `None` for `del`.
+    pub value_hash: Option<String>,
+    pub at: i64,
+    pub user: Option<String>,
+}
+
+/// Where `changes_since` starts reading the oplog.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OplogSince {
+    /// Rows after this sequence number.
+    Seq(i64),
+    /// Rows recorded at or after this unix time.
+    Time(i64),
+}
+
+/// Filters for `audit_search`; `None` matches everything, `limit` keeps the newest rows.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuditQuery {
+    pub user: Option<String>,
+    pub command: Option<String>,
+    /// Address prefix such as `app.cfg` or `app.cfg.db_`.
+    pub address_prefix: Option<String>,
+    pub limit: usize,
+}
+
+/// Key/value store persisted in a single SQLite database (`kv` table).
+pub struct SqliteKeystore {
+    conn: Connection,
+    clock: Arc<dyn Clock>,
+    /// Recorded as the `user` of audit rows.
+    actor: Option<String>,
+    /// `sys_audit` exists, so `set`, `del` and `copy` are logged.
+    audited: bool,
+    /// `sys_oplog` exists, so every write to `kv` is logged by temp triggers.
+    oplogged: bool,
+    /// Authenticated from `config.token`; limits every access check to its scope.
+    token: Option<ApiToken>,
+    os_user: Option<String>,
+    /// Whether ACL checks honour a claimed user; see `AccessPolicy`.
+    impersonate: bool,
+}
+
+impl SqliteKeystore {
+    /// Open (creating if needed) the keystore described by `config` and ensure its schema.
+    pub fn open(config: &SqliteConnectionConfig) -> CrudResult<Self> {
+        let verb = CrudVerb::Create;
+        let path = config.database_path();
+        if !config.create_if_missing && !path.exists() {
+            return Err(not_found(
+                verb,
+                format!("database {} does not exist", path.display()),
+            ));
+        }
+        if !config.read_only {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .map_err(|err| internal(verb, anyhow::Error::new(err)))?;
+            }
+        }
+
+        let conn = Connection::open_with_flags(path, SqlitePathResolver::flags_for(config))
+            .map_err(|err| sql_error(verb, err))?;
+        if let Some(timeout) = config.busy_timeout {
+            conn.busy_timeout(timeout)
+                .map_err(|err| sql_error(verb, err))?;
+        }
+
+        if !config.read_only {
+            if config.journal_wal {
+                conn.pragma_update(None, "journal_mode", &"WAL")
+                    .map_err(|err| sql_error(verb, err))?;
+            }
+            conn.execute_batch(SCHEMA_SQL)
+                .map_err(|err| sql_error(verb, err))?;
+            add_missing_columns(&conn)?;
+        }
+        let audited = table_exists(&conn, "sys_audit", verb)?;
+        let oplogged = table_exists(&conn, "sys_oplog", verb)?;
+        let token = authenticate(&conn, config.token.as_deref())?;
+
+        let os_user = os_user();
+        let impersonate = owns_path(path);
+        let actor = match &config.user {
+            Some(user) if impersonate => Some(user.clone()),
+            Some(_) => os_user.clone(),
+            None => None,
+        };
+        let store = Self {
+            conn,
+            clock: system_clock(),
+            actor,
+            audited,
+            oplogged,
+            token,
+            os_user,
+            impersonate,
+        };
+        if oplogged && !config.read_only {
+            store.install_oplog()?;
+        }
+        Ok(store)
+    }
+
+    /// Replace the clock used for every expiry decision (defaults to `SystemClock`).
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        if self.oplogged {
+            // Re-registering `prontodb_now` points oplog timestamps at the new clock.
+            let _ = register_oplog_functions(&self.conn, self.clock.clone());
+        }
+        self
+    }
+
+    /// Re-read which optional logs exist and re-authenticate `config.token`,
+    /// for a connection kept open while another one may have enabled the
+    /// audit log or oplog, or revoked the token.
+    pub fn refresh(&mut self, config: &SqliteConnectionConfig) -> CrudResult<()> {
+        let verb = CrudVerb::Read;
+        self.token = authenticate(&self.conn, config.token.as_deref())?;
+        self.audited = table_exists(&self.conn, "sys_audit", verb)?;
+        let oplogged = table_exists(&self.conn, "sys_oplog", verb)?;
+        if oplogged && !self.oplogged {
+            self.oplogged = true;
+            if !config.read_only {
+                self.install_oplog()?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    /// Write a consistent copy of the whole database to `target` (`VACUUM INTO`); it must not exist.
+    pub fn snapshot(&self, target: &Path) -> CrudResult<()> {
+        let verb = CrudVerb::Backup;
+        self.conn
+            .execute("VACUUM INTO ?1", params![target.to_string_lossy()])
+            .map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Copy the database page by page to `target` with the SQLite online backup API.
+    ///
+    /// The whole copy is one step, so it reads a single point in time while
+    /// other connections keep writing (in WAL mode); a busy or locked source
+    /// is retried until it frees up.
+    pub fn backup_to(&self, target: &Path) -> CrudResult<()> {
+        let verb = CrudVerb::Backup;
+        let mut dest = Connection::open(target).map_err(|err| sql_error(verb, err))?;
+        rusqlite::backup::Backup::new(&self.conn, &mut dest)
+            .and_then(|backup| backup.run_to_completion(-1, Duration::from_millis(50), None))
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Problems `PRAGMA integrity_check` reports; empty when the file is sound.
+    pub fn integrity_check(&self) -> CrudResult<Vec<String>> {
+        let verb = CrudVerb::Read;
+        let mut stmt = self
+            .conn
+            .prepare("PRAGMA integrity_check")
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|err| sql_error(verb, err))?;
+        let lines = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))?;
+        Ok(lines.into_iter().filter(|line| line != "ok").collect())
+    }
+
+    /// Cut a snapshot copy down to an incremental layer over an earlier backup.
+    ///
+    /// Drops kv rows that have expired, and rows untouched since `since` whose
+    /// address is in `known` (the keys of the earlier backup), then vacuums.
+    /// The `sys_*` tables stay whole.
+    pub fn prune_to_changes(&mut self, since: i64, known: &[Address]) -> CrudResult<()> {
+        let verb = CrudVerb::Backup;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|err| sql_error(verb, err))?;
+        tx.execute_batch(
+            "CREATE TEMP TABLE backup_known (
+                 project TEXT NOT NULL,
+                 namespace TEXT NOT NULL,
+                 key TEXT NOT NULL,
+                 context TEXT NOT NULL,
+                 PRIMARY KEY (project, namespace, key, context)
+             )",
+        )
+        .map_err(|err| sql_error(verb, err))?;
+        {
+            let mut insert = tx
+                .prepare("INSERT OR IGNORE INTO backup_known VALUES (?1, ?2, ?3, ?4)")
+                .map_err(|err| sql_error(verb, err))?;
+            for addr in known {
+                insert
+                    .execute(params![
+                        addr.project,
+                        addr.namespace,
+                        addr.key,
+                        context_column(addr)
+                    ])
+                    .map_err(|err| sql_error(verb, err))?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM kv
+             WHERE (expires_at IS NOT NULL AND expires_at <= ?1)
+                OR (updated_at < ?2 AND EXISTS (
+                        SELECT 1 FROM backup_known k
+                        WHERE k.project = kv.project AND k.namespace = kv.namespace
+                          AND k.key = kv.key AND k.context = kv.context))",
+            params![now, since],
+        )
+        .map_err(|err| sql_error(verb, err))?;
+        tx.execute_batch("DROP TABLE backup_known")
+            .map_err(|err| sql_error(verb, err))?;
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        self.conn
+            .execute_batch("VACUUM")
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Lay an incremental snapshot (see `prune_to_changes`) over this database in one transaction.
+    ///
+    /// Tables, indexes and triggers only the layer has are created and JSON
+    /// indexes it lacks are dropped; the `sys_*` tables are replaced by the
+    /// layer's and its newer audit rows appended; its kv rows are upserted
+    /// with their timestamps, `removed` keys deleted and the full-text index
+    /// rebuilt.
+    pub fn apply_layer(&mut self, layer: &Path, removed: &[Address]) -> CrudResult<()> {
+        let verb = CrudVerb::Restore;
+        self.conn
+            .execute(
+                "ATTACH DATABASE ?1 AS layer",
+                params![layer.to_string_lossy()],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let applied = apply_layer(&mut self.conn, removed);
+        let detached = self
+            .conn
+            .execute_batch("DETACH DATABASE layer")
+            .map_err(|err| sql_error(verb, err));
+        applied.and(detached)
+    }
+
+    /// Copy live kv rows updated at or after `since` into the database at `target`, in one
+    /// transaction.
+    ///
+    /// Rows keep their timestamps, expiry and type. A row the target holds
+    /// with a newer `updated_at` is left alone; deletions are not carried.
+    /// Sealed values are copied as they are, so an encrypted namespace the
+    /// target lacks gets this database's `sys_encryption` row, and one
+    /// encrypted differently on either side is refused.
+    pub fn sync_to(&mut self, target: &Path, since: i64) -> CrudResult<SyncReport> {
+        let verb = CrudVerb::Update;
+        let now = self.now();
+        self.conn
+            .execute(
+                "ATTACH DATABASE ?1 AS peer",
+                params![target.to_string_lossy()],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let synced = sync_rows(&mut self.conn, since, now);
+        let detached = self
+            .conn
+            .execute_batch("DETACH DATABASE peer")
+            .map_err(|err| sql_error(verb, err));
+        synced.and_then(|report| detached.map(|()| report))
+    }
+
+    /// Upsert `entries` exactly as given, timestamps and type included, in one transaction.
+    ///
+    /// `encryption` rows this database lacks are registered first, so sealed
+    /// values copied from another database stay readable with its key file.
+    pub fn write_entries(
+        &mut self,
+        entries: &[KvEntry],
+        encryption: &[EncryptedNamespace],
+    ) -> CrudResult<()> {
+        let verb = CrudVerb::Update;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|err| sql_error(verb, err))?;
+        for namespace in encryption {
+            tx.execute(
+                "INSERT INTO sys_encryption (project, namespace, salt, key_check, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (project, namespace) DO NOTHING",
+                params![
+                    namespace.project,
+                    namespace.namespace,
+                    namespace.salt,
+                    namespace.key_check,
+                    now
+                ],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        }
+        for entry in entries {
+            let addr = &entry.address;
+            tx.execute(
+                "INSERT INTO kv (project, namespace, key, context, value, created_at, updated_at,
+                                 expires_at, value_type)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(project, namespace, key, context) DO UPDATE SET
+                     value = excluded.value,
+                     created_at = excluded.created_at,
+                     updated_at = excluded.updated_at,
+                     expires_at = excluded.expires_at,
+                     value_type = excluded.value_type",
+                params![
+                    addr.project,
+                    addr.namespace,
+                    addr.key,
+                    context_column(addr),
+                    entry.value,
+                    entry.created_at,
+                    entry.updated_at,
+                    entry.expires_at,
+                    entry.value_type.map(ValueType::as_str)
+                ],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))
+    }
+
+    /// Where the last `direction` sync with `peer` stopped; `None` before the first.
+    pub fn sync_watermark(&self, peer: &str, direction: &str) -> CrudResult<Option<i64>> {
+        self.conn
+            .query_row(
+                "SELECT watermark FROM sys_sync WHERE peer = ?1 AND direction = ?2",
+                params![peer, direction],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| sql_error(CrudVerb::Read, err))
+    }
+
+    pub fn set_sync_watermark(
+        &self,
+        peer: &str,
+        direction: &str,
+        watermark: i64,
+    ) -> CrudResult<()> {
+        self.conn
+            .execute(
+                "INSERT INTO sys_sync (peer, direction, watermark, synced_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (peer, direction)
+                 DO UPDATE SET watermark = excluded.watermark, synced_at = excluded.synced_at",
+                params![peer, direction, watermark, self.now()],
+            )
+            .map(|_| ())
+            .map_err(|err| sql_error(CrudVerb::Update, err))
+    }
+
+    pub fn set(&self, addr: &Address, value: &str, ttl: Option<u64>) -> CrudResult<()> {
+        let now = self.now();
+        self.audited_write("set", addr, CrudVerb::Create, |conn| {
+            upsert(conn, addr, value, ttl, now)
+        })
+    }
+
+    /// Like `set`, tagging the value with `value_type` after checking it parses as one.
+    ///
+    /// Untyped writes (`set`, `append`, imports) clear the tag again.
+    pub fn set_typed(
+        &mut self,
+        addr: &Address,
+        value: &str,
+        ttl: Option<u64>,
+        value_type: ValueType,
+    ) -> CrudResult<()> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        if !value_type.accepts(value) {
+            return Err(invalid_input(
+                verb,
+                format!("'{}' is not a valid {} value", value, value_type),
+            ));
+        }
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+        let old = select_value(&tx, addr, now)?;
+        upsert(&tx, addr, value, ttl, now)?;
+        tx.execute(
+            "UPDATE kv SET value_type = ?5
+             WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4",
+            params![
+                addr.project,
+                addr.namespace,
+                addr.key,
+                context_column(addr),
+                value_type.as_str()
+            ],
+        )
+        .map_err(|err| sql_error(verb, err))?;
+        if self.audited {
+            record_audit(&tx, self.actor.as_deref(), "set", addr, old.as_deref(), now)?;
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Read a live value; in a sliding cache namespace the read also renews its TTL.
+    pub fn get(&self, addr: &Address) -> CrudResult<Option<String>> {
+        read_value(&self.conn, addr, self.now())
+    }
+
+    /// Like `get`, but returns the whole row (timestamps and expiry included).
+    pub fn get_entry(&self, addr: &Address) -> CrudResult<Option<KvEntry>> {
+        let now = self.now();
+        if read_value(&self.conn, addr, now)?.is_none() {
+            return Ok(None);
+        }
+        select_full_entry(&self.conn, addr)
+    }
+
+    /// Live entries in `scope`, ordered by key then context.
+    pub fn list(&self, scope: &NamespaceScope) -> CrudResult<Vec<KvEntry>> {
+        let mut entries = Vec::new();
+        self.for_each_entry(scope, EntryOrder::default(), |entry| {
+            entries.push(entry);
+            ControlFlow::Continue(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Like `for_each_entry`, but every segment is matched with SQLite `GLOB`.
+    pub fn for_each_match<F>(
+        &self,
+        pattern: &KeyPattern,
+        order: EntryOrder,
+        mut visit: F,
+    ) -> CrudResult<()>
+    where
+        F: FnMut(KvEntry) -> ControlFlow<()>,
+    {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let sql = format!(
+            "SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type
+             FROM kv
+             WHERE project GLOB ?1 AND namespace GLOB ?2 AND key GLOB ?3
+               AND (expires_at IS NULL OR expires_at > ?4)
+             ORDER BY {}",
+            order.order_by(true)
+        );
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![pattern.project, pattern.namespace, pattern.key, now],
+                entry_from_row,
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        for row in rows {
+            let entry = row.map_err(|err| sql_error(verb, err))?;
+            if visit(entry).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Live entries across projects/namespaces (`None` matches all), for export.
+    pub fn entries(
+        &self,
+        project: Option<&str>,
+        namespace: Option<&str>,
+    ) -> CrudResult<Vec<KvEntry>> {
+        let mut entries = Vec::new();
+        self.for_each_in(project, namespace, |entry| {
+            entries.push(entry);
+            ControlFlow::Continue(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Streaming form of `entries`, ordered by project, namespace, key, context.
+    pub fn for_each_in<F>(
+        &self,
+        project: Option<&str>,
+        namespace: Option<&str>,
+        mut visit: F,
+    ) -> CrudResult<()>
+    where
+        F: FnMut(KvEntry) -> ControlFlow<()>,
+    {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type
+                 FROM kv
+                 WHERE (?1 IS NULL OR project = ?1)
+                   AND (?2 IS NULL OR namespace = ?2)
+                   AND (expires_at IS NULL OR expires_at > ?3)
+                 ORDER BY project, namespace, key, context",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(params![project, namespace, now], entry_from_row)
+            .map_err(|err| sql_error(verb, err))?;
+        for row in rows {
+            let entry = row.map_err(|err| sql_error(verb, err))?;
+            if visit(entry).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stream live entries in `scope` to `visit` as SQLite yields them, without buffering.
+    ///
+    /// Returning `ControlFlow::Break` stops the scan early (e.g. on a closed pipe).
+    pub fn for_each_entry<F>(
+        &self,
+        scope: &NamespaceScope,
+        order: EntryOrder,
+        mut visit: F,
+    ) -> CrudResult<()>
+    where
+        F: FnMut(KvEntry) -> ControlFlow<()>,
+    {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let sql = format!(
+            "SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type
+             FROM kv
+             WHERE project = ?1 AND namespace = ?2
+               AND (?3 IS NULL OR substr(key, 1, length(?3)) = ?3)
+               AND (expires_at IS NULL OR expires_at > ?4)
+             ORDER BY {}",
+            order.order_by(false)
+        );
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![scope.project, scope.namespace, scope.prefix, now],
+                entry_from_row,
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        for row in rows {
+            let entry = row.map_err(|err| sql_error(verb, err))?;
+            if visit(entry).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `for_each_entry`, limited to keys in `[from, to)` (byte-wise lexicographic).
+    ///
+    /// Either bound may be open; the range is answered from the primary key index.
+    pub fn for_each_in_range<F>(
+        &self,
+        scope: &NamespaceScope,
+        from: Option<&str>,
+        to: Option<&str>,
+        order: EntryOrder,
+        mut visit: F,
+    ) -> CrudResult<()>
+    where
+        F: FnMut(KvEntry) -> ControlFlow<()>,
+    {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let sql = format!(
+            "SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type
+             FROM kv
+             WHERE project = ?1 AND namespace = ?2
+               AND (?3 IS NULL OR substr(key, 1, length(?3)) = ?3)
+               AND (?4 IS NULL OR key >= ?4)
+               AND (?5 IS NULL OR key < ?5)
+               AND (expires_at IS NULL OR expires_at > ?6)
+             ORDER BY {}",
+            order.order_by(false)
+        );
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![scope.project, scope.namespace, scope.prefix, from, to, now],
+                entry_from_row,
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        for row in rows {
+            let entry = row.map_err(|err| sql_error(verb, err))?;
+            if visit(entry).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Up to `limit` live entries in `scope` that sort after `after`, in key, context order.
+    ///
+    /// Pages are keyed on the primary key rather than an offset, so each page
+    /// costs the same however deep into the namespace it starts.
+    pub fn entries_after(
+        &self,
+        scope: &NamespaceScope,
+        after: Option<&Address>,
+        limit: usize,
+    ) -> CrudResult<Vec<KvEntry>> {
+        let verb = CrudVerb::List;
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type
+                 FROM kv
+                 WHERE project = ?1 AND namespace = ?2
+                   AND (?3 IS NULL OR substr(key, 1, length(?3)) = ?3)
+                   AND (?4 IS NULL OR (key, context) > (?4, ?5))
+                   AND (expires_at IS NULL OR expires_at > ?6)
+                 ORDER BY key, context
+                 LIMIT ?7",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    scope.project,
+                    scope.namespace,
+                    scope.prefix,
+                    after.map(|addr| addr.key.as_str()),
+                    after.and_then(|addr| addr.context.as_deref()).unwrap_or(""),
+                    self.now(),
+                    limit as i64
+                ],
+                entry_from_row,
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Live entries in `scope` from this database and each of `others`, read in one
+    /// query with `others` ATTACHed as `db1`, `db2`, ...
+    ///
+    /// Each entry comes with the index of its database (0 is this one, then
+    /// `others` in order), ordered by key, context and index so the copies of
+    /// a key sit together. Values are returned as stored.
+    pub fn for_each_entry_across<F>(
+        &self,
+        others: &[&Path],
+        scope: &NamespaceScope,
+        visit: F,
+    ) -> CrudResult<()>
+    where
+        F: FnMut(usize, KvEntry) -> ControlFlow<()>,
+    {
+        let verb = CrudVerb::List;
+        let mut attached = 0;
+        let mut result = Ok(());
+        for path in others {
+            let attach = self
+                .conn
+                .execute(
+                    &format!("ATTACH DATABASE ?1 AS db{}", attached + 1),
+                    params![path.to_string_lossy()],
+                )
+                .map_err(|err| sql_error(verb, err));
+            if let Err(error) = attach {
+                result = Err(error);
+                break;
+            }
+            attached += 1;
+        }
+        if result.is_ok() {
+            result = scan_across(&self.conn, attached, scope, self.now(), visit);
+        }
+        for index in 1..=attached {
+            let detached = self
+                .conn
+                .execute_batch(&format!("DETACH DATABASE db{}", index))
+                .map_err(|err| sql_error(verb, err));
+            result = result.and(detached);
+        }
+        result
+    }
+
+    /// Live entries in `scope` whose row was inserted after row `after`, oldest first, each
+    /// with its `rowid`; `last` keeps only the newest rows.
+    ///
+    /// Rewriting a key updates its row in place, so only newly created keys appear.
+    pub fn inserted_after(
+        &self,
+        scope: &NamespaceScope,
+        after: i64,
+        last: Option<usize>,
+    ) -> CrudResult<Vec<(i64, KvEntry)>> {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let limit = last.map_or(-1, |last| i64::try_from(last).unwrap_or(i64::MAX));
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type, rowid
+                 FROM kv
+                 WHERE project = ?1 AND namespace = ?2
+                   AND (?3 IS NULL OR substr(key, 1, length(?3)) = ?3)
+                   AND rowid > ?4
+                   AND (expires_at IS NULL OR expires_at > ?5)
+                 ORDER BY rowid DESC
+                 LIMIT ?6",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    scope.project,
+                    scope.namespace,
+                    scope.prefix,
+                    after,
+                    now,
+                    limit
+                ],
+                |row| Ok((row.get::<_, i64>(9)?, entry_from_row(row)?)),
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let mut entries = rows
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|err| sql_error(verb, err))?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Projects holding at least one live key.
+    pub fn projects(&self) -> CrudResult<Vec<String>> {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT DISTINCT project FROM kv
+                 WHERE expires_at IS NULL OR expires_at > ?1
+                 ORDER BY project",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(params![now], |row| row.get::<_, String>(0))
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Namespaces of `project` holding at least one live key.
+    pub fn namespaces(&self, project: &str) -> CrudResult<Vec<String>> {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT DISTINCT namespace FROM kv
+                 WHERE project = ?1 AND (expires_at IS NULL OR expires_at > ?2)
+                 ORDER BY namespace",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(params![project, now], |row| row.get::<_, String>(0))
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Contexts holding a live value for `addr`'s key (its own context is ignored);
+    /// the context-less value is not listed.
+    pub fn contexts(&self, addr: &Address) -> CrudResult<Vec<String>> {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT context FROM kv
+                 WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context != ''
+                   AND (expires_at IS NULL OR expires_at > ?4)
+                 ORDER BY context",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![addr.project, addr.namespace, addr.key, now],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Current time according to this keystore's clock.
+    pub fn now(&self) -> i64 {
+        self.clock.now()
+    }
+
+    /// Read an entry even if its TTL has passed (debugging aid); never renews a sliding TTL.
+    pub fn get_including_expired(&self, addr: &Address) -> CrudResult<Option<KvEntry>> {
+        select_full_entry(&self.conn, addr)
+    }
+
+    /// Mark `project.namespace` as a cache: writes without an explicit TTL get `settings.ttl_secs`.
+    pub fn create_cache(
+        &self,
+        project: &str,
+        namespace: &str,
+        settings: &CacheSettings,
+    ) -> CrudResult<()> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        if settings.ttl_secs == 0 {
+            return Err(invalid_input(verb, "cache TTL must be at least 1 second"));
+        }
+        if let Some(max_ttl_secs) = settings.max_ttl_secs {
+            if max_ttl_secs < settings.ttl_secs {
+                return Err(invalid_input(
+                    verb,
+                    format!(
+                        "max TTL {}s is below the default TTL {}s",
+                        max_ttl_secs, settings.ttl_secs
+                    ),
+                ));
+            }
+        }
+        self.conn
+            .execute(
+                "INSERT INTO sys_namespaces
+                     (project, namespace, default_ttl, sliding, max_ttl, clamp_ttl, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT(project, namespace) DO UPDATE SET
+                     default_ttl = excluded.default_ttl,
+                     sliding = excluded.sliding,
+                     max_ttl = excluded.max_ttl,
+                     clamp_ttl = excluded.clamp_ttl",
+                params![
+                    project,
+                    namespace,
+                    settings.ttl_secs as i64,
+                    settings.sliding,
+                    settings.max_ttl_secs.map(|secs| secs as i64),
+                    settings.clamp_ttl,
+                    now
+                ],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Every cache namespace with its TTL policy, live key count and nearest expiry.
+    pub fn caches(&self) -> CrudResult<Vec<CacheSummary>> {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT n.project, n.namespace, n.default_ttl, n.sliding, n.max_ttl, n.clamp_ttl,
+                        COUNT(k.key), MIN(k.expires_at)
+                 FROM sys_namespaces n
+                 LEFT JOIN kv k
+                   ON k.project = n.project AND k.namespace = n.namespace
+                  AND (k.expires_at IS NULL OR k.expires_at > ?1)
+                 WHERE n.default_ttl IS NOT NULL
+                 GROUP BY n.project, n.namespace
+                 ORDER BY n.project, n.namespace",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(params![now], |row| {
+                Ok(CacheSummary {
+                    project: row.get(0)?,
+                    namespace: row.get(1)?,
+                    settings: cache_settings_from_row(row, 2)?,
+                    keys: row.get::<_, i64>(6)? as u64,
+                    next_expiry: row.get(7)?,
+                })
+            })
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Opt a namespace in (or back out) of ad-hoc per-key TTLs without making it a cache.
+    pub fn set_key_ttl_allowed(
+        &self,
+        project: &str,
+        namespace: &str,
+        allowed: bool,
+    ) -> CrudResult<()> {
+        let now = self.now();
+        self.conn
+            .execute(
+                "INSERT INTO sys_namespaces (project, namespace, allow_key_ttl, created_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(project, namespace) DO UPDATE SET
+                     allow_key_ttl = excluded.allow_key_ttl",
+                params![project, namespace, allowed, now],
+            )
+            .map_err(|err| sql_error(CrudVerb::Update, err))?;
+        Ok(())
+    }
+
+    /// Set (or, with an empty `policy`, clear) the retention policy of `project.namespace`.
+    pub fn set_retention(
+        &self,
+        project: &str,
+        namespace: &str,
+        policy: &RetentionPolicy,
+    ) -> CrudResult<()> {
+        let verb = CrudVerb::Update;
+        if policy.max_age_secs == Some(0) {
+            return Err(invalid_input(
+                verb,
+                "retention --max-age must be at least 1 second",
+            ));
+        }
+        let now = self.now();
+        self.conn
+            .execute(
+                "INSERT INTO sys_namespaces
+                     (project, namespace, retention_max_age, retention_max_keys, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(project, namespace) DO UPDATE SET
+                     retention_max_age = excluded.retention_max_age,
+                     retention_max_keys = excluded.retention_max_keys",
+                params![
+                    project,
+                    namespace,
+                    policy.max_age_secs.map(|secs| secs as i64),
+                    policy.max_keys.map(|keys| keys as i64),
+                    now
+                ],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Namespaces with a retention policy, as `(project, namespace, policy)`.
+    pub fn retention_policies(&self) -> CrudResult<Vec<(String, String, RetentionPolicy)>> {
+        let verb = CrudVerb::List;
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT project, namespace, retention_max_age, retention_max_keys
+                 FROM sys_namespaces
+                 WHERE retention_max_age IS NOT NULL OR retention_max_keys IS NOT NULL
+                 ORDER BY project, namespace",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], |row| {
+                let policy = RetentionPolicy {
+                    max_age_secs: row.get::<_, Option<i64>>(2)?.map(|secs| secs as u64),
+                    max_keys: row.get::<_, Option<i64>>(3)?.map(|keys| keys as u64),
+                };
+                Ok((row.get(0)?, row.get(1)?, policy))
+            })
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Trim every namespace with a retention policy; one report per namespace that lost keys.
+    pub fn enforce_retention(&self) -> CrudResult<Vec<SweepReport>> {
+        let verb = CrudVerb::Delete;
+        let now = self.now();
+        let mut reports = Vec::new();
+        for (project, namespace, policy) in self.retention_policies()? {
+            let mut keys = Vec::new();
+            if let Some(max_age_secs) = policy.max_age_secs {
+                let cutoff = now.saturating_sub(i64::try_from(max_age_secs).unwrap_or(i64::MAX));
+                let mut stmt = self
+                    .conn
+                    .prepare_cached(
+                        "DELETE FROM kv
+                         WHERE project = ?1 AND namespace = ?2 AND updated_at <= ?3
+                         RETURNING key, context",
+                    )
+                    .map_err(|err| sql_error(verb, err))?;
+                let rows = stmt
+                    .query_map(params![project, namespace, cutoff], removed_key)
+                    .map_err(|err| sql_error(verb, err))?;
+                for row in rows {
+                    keys.push(row.map_err(|err| sql_error(verb, err))?);
+                }
+            }
+            if let Some(max_keys) = policy.max_keys {
+                let mut stmt = self
+                    .conn
+                    .prepare_cached(
+                        "DELETE FROM kv WHERE rowid IN (
+                             SELECT rowid FROM kv
+                             WHERE project = ?1 AND namespace = ?2
+                             ORDER BY updated_at DESC, key DESC, context DESC
+                             LIMIT -1 OFFSET ?3)
+                         RETURNING key, context",
+                    )
+                    .map_err(|err| sql_error(verb, err))?;
+                let rows = stmt
+                    .query_map(
+                        params![
+                            project,
+                            namespace,
+                            i64::try_from(max_keys).unwrap_or(i64::MAX)
+                        ],
+                        removed_key,
+                    )
+                    .map_err(|err| sql_error(verb, err))?;
+                for row in rows {
+                    keys.push(row.map_err(|err| sql_error(verb, err))?);
+                }
+            }
+            if !keys.is_empty() {
+                reports.push(SweepReport {
+                    project,
+                    namespace,
+                    removed: keys.len(),
+                    keys,
+                });
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Whether `set --ttl` is accepted: cache namespaces and opted-in namespaces only.
+    pub fn key_ttl_allowed(&self, project: &str, namespace: &str) -> CrudResult<bool> {
+        let allowed = self
+            .conn
+            .query_row(
+                "SELECT default_ttl IS NOT NULL OR allow_key_ttl FROM sys_namespaces
+                 WHERE project = ?1 AND namespace = ?2",
+                params![project, namespace],
+                |row| row.get::<_, bool>(0),
+            )
+            .optional()
+            .map_err(|err| sql_error(CrudVerb::Read, err))?;
+        Ok(allowed.unwrap_or(false))
+    }
+
+    pub fn cache_settings(
+        &self,
+        project: &str,
+        namespace: &str,
+    ) -> CrudResult<Option<CacheSettings>> {
+        cache_settings(&self.conn, project, namespace)
+    }
+
+    /// Presence check that never materialises the value.
+    pub fn exists(&self, addr: &Address) -> CrudResult<bool> {
+        let now = self.now();
+        let found = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM kv
+                 WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4
+                   AND (expires_at IS NULL OR expires_at > ?5)",
+                params![
+                    addr.project,
+                    addr.namespace,
+                    addr.key,
+                    context_column(addr),
+                    now
+                ],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map_err(|err| sql_error(CrudVerb::Find, err))?;
+        Ok(found.is_some())
+    }
+
+    /// Number of live keys in `scope`, counted in SQL rather than by listing.
+    pub fn count(&self, scope: &NamespaceScope) -> CrudResult<u64> {
+        let now = self.now();
+        let count = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM kv
+                 WHERE project = ?1 AND namespace = ?2
+                   AND (?3 IS NULL OR substr(key, 1, length(?3)) = ?3)
+                   AND (expires_at IS NULL OR expires_at > ?4)",
+                params![scope.project, scope.namespace, scope.prefix, now],
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(|err| sql_error(CrudVerb::Find, err))?;
+        Ok(count as u64)
+    }
+
+    /// Clear the expiry of a live key, keeping its value; returns whether the key was found.
+    pub fn persist(&self, addr: &Address) -> CrudResult<bool> {
+        let now = self.now();
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE kv SET expires_at = NULL, updated_at = ?5
+                 WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4
+                   AND (expires_at IS NULL OR expires_at > ?5)",
+                params![
+                    addr.project,
+                    addr.namespace,
+                    addr.key,
+                    context_column(addr),
+                    now
+                ],
+            )
+            .map_err(|err| sql_error(CrudVerb::Update, err))?;
+        Ok(updated > 0)
+    }
+
+    /// Make a live key expire `ttl_secs` from now, keeping its value; returns whether it was found.
+    pub fn expire(&self, addr: &Address, ttl_secs: u64) -> CrudResult<bool> {
+        let now = self.now();
+        let expires_at = now.saturating_add(i64::try_from(ttl_secs).unwrap_or(i64::MAX));
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE kv SET expires_at = ?6, updated_at = ?5
+                 WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4
+                   AND (expires_at IS NULL OR expires_at > ?5)",
+                params![
+                    addr.project,
+                    addr.namespace,
+                    addr.key,
+                    context_column(addr),
+                    now,
+                    expires_at
+                ],
+            )
+            .map_err(|err| sql_error(CrudVerb::Update, err))?;
+        Ok(updated > 0)
+    }
+
+    /// Remove a key; returns whether a row was deleted.
+    pub fn delete(&self, addr: &Address) -> CrudResult<bool> {
+        let verb = CrudVerb::Delete;
+        let removed = self.audited_write("del", addr, verb, |conn| {
+            conn.execute(
+                "DELETE FROM kv WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4",
+                params![addr.project, addr.namespace, addr.key, context_column(addr)],
+            )
+            .map_err(|err| sql_error(verb, err))
+        })?;
+        Ok(removed > 0)
+    }
+
+    /// Set a value only when `condition` holds, checked and written in one immediate transaction.
+    ///
+    /// A failed precondition returns a `Conflict` error and leaves the key untouched.
+    pub fn set_if(
+        &mut self,
+        addr: &Address,
+        value: &str,
+        condition: &SetCondition,
+    ) -> CrudResult<()> {
+        let verb = CrudVerb::Update;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+
+        let current = select_value(&tx, addr, now)?;
+        match condition {
+            SetCondition::IfAbsent if current.is_some() => {
+                return Err(conflict(verb, format!("{} already exists", addr)));
+            }
+            SetCondition::IfValue(expected) if current.as_deref() != Some(expected.as_str()) => {
+                return Err(conflict(
+                    verb,
+                    format!("{} does not hold the expected value", addr),
+                ));
+            }
+            _ => {}
+        }
+
+        upsert(&tx, addr, value, None, now)?;
+        if self.audited {
+            record_audit(
+                &tx,
+                self.actor.as_deref(),
+                "set",
+                addr,
+                current.as_deref(),
+                now,
+            )?;
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Append `text` to the stored value in one statement, creating the key if missing.
+    ///
+    /// An expired row is treated as missing: it restarts from `text` with no TTL.
+    pub fn append(&self, addr: &Address, text: &str) -> CrudResult<()> {
+        let now = self.now();
+        self.conn
+            .execute(
+                "INSERT INTO kv (project, namespace, key, context, value, created_at, updated_at, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, NULL)
+                 ON CONFLICT(project, namespace, key, context) DO UPDATE SET
+                     value = CASE WHEN kv.expires_at IS NOT NULL AND kv.expires_at <= ?6
+                                  THEN excluded.value
+                                  ELSE kv.value || excluded.value END,
+                     expires_at = CASE WHEN kv.expires_at IS NOT NULL AND kv.expires_at <= ?6
+                                       THEN NULL
+                                       ELSE kv.expires_at END,
+                     updated_at = excluded.updated_at,
+                     value_type = NULL",
+                params![
+                    addr.project,
+                    addr.namespace,
+                    addr.key,
+                    context_column(addr),
+                    text,
+                    now
+                ],
+            )
+            .map_err(|err| sql_error(CrudVerb::Update, err))?;
+        Ok(())
+    }
+
+    /// Move a key to a new address in one transaction, keeping its value, timestamps, and TTL.
+    ///
+    /// When `dst` carries no `__context`, the source context is preserved. An
+    /// existing destination is a `Conflict` unless `overwrite` is set. `reseal`
+    /// turns the stored value into the one written at `dst` (encrypted values
+    /// are bound to their key).
+    pub fn rename<F>(
+        &mut self,
+        src: &Address,
+        dst: &Address,
+        overwrite: bool,
+        reseal: F,
+    ) -> CrudResult<()>
+    where
+        F: FnOnce(String) -> CrudResult<String>,
+    {
+        self.transfer(src, dst, overwrite, Transfer::Move, reseal)
+    }
+
+    /// Copy a single key; the copy is a new row, keeping the TTL only when `keep_ttl` is set.
+    pub fn copy<F>(
+        &mut self,
+        src: &Address,
+        dst: &Address,
+        overwrite: bool,
+        keep_ttl: bool,
+        reseal: F,
+    ) -> CrudResult<()>
+    where
+        F: FnOnce(String) -> CrudResult<String>,
+    {
+        self.transfer(src, dst, overwrite, Transfer::Copy { keep_ttl }, reseal)
+    }
+
+    /// Copy every live key of `src` (optionally limited to its prefix) into `dst` in one transaction.
+    ///
+    /// Existing destination keys are overwritten; returns the number of keys copied.
+    pub fn copy_namespace(
+        &mut self,
+        src: &NamespaceScope,
+        dst: &NamespaceScope,
+        keep_ttl: bool,
+    ) -> CrudResult<usize> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+
+        let audit_targets = if self.audited {
+            copy_targets(&tx, src, dst, now)?
+        } else {
+            Vec::new()
+        };
+        let copied = tx
+            .execute(
+                "INSERT INTO kv (project, namespace, key, context, value, created_at, updated_at, expires_at, value_type)
+                 SELECT ?4, ?5, key, context, value, ?6, ?6,
+                        CASE WHEN ?7 THEN expires_at ELSE NULL END, value_type
+                 FROM kv
+                 WHERE project = ?1 AND namespace = ?2
+                   AND (?3 IS NULL OR substr(key, 1, length(?3)) = ?3)
+                   AND (expires_at IS NULL OR expires_at > ?6)
+                 ON CONFLICT(project, namespace, key, context) DO UPDATE SET
+                     value = excluded.value,
+                     updated_at = excluded.updated_at,
+                     expires_at = excluded.expires_at,
+                     value_type = excluded.value_type",
+                params![
+                    src.project,
+                    src.namespace,
+                    src.prefix,
+                    dst.project,
+                    dst.namespace,
+                    now,
+                    keep_ttl
+                ],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        for (addr, old) in &audit_targets {
+            record_audit(
+                &tx,
+                self.actor.as_deref(),
+                "copy",
+                addr,
+                old.as_deref(),
+                now,
+            )?;
+        }
+
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(copied)
+    }
+
+    fn transfer<F>(
+        &mut self,
+        src: &Address,
+        dst: &Address,
+        overwrite: bool,
+        mode: Transfer,
+        reseal: F,
+    ) -> CrudResult<()>
+    where
+        F: FnOnce(String) -> CrudResult<String>,
+    {
+        let verb = CrudVerb::Update;
+        let now = self.now();
+        let mut dst = dst.clone();
+        if dst.context.is_none() {
+            dst.context = src.context.clone();
+        }
+        if &dst == src {
+            return Ok(());
+        }
+
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+
+        let entry = select_entry(&tx, src, now)?
+            .ok_or_else(|| not_found(verb, format!("{} not found", src)))?;
+        let existing = select_entry(&tx, &dst, now)?;
+        if !overwrite && existing.is_some() {
+            return Err(conflict(verb, format!("{} already exists", dst)));
+        }
+        let value = reseal(entry.value)?;
+
+        let (created_at, expires_at) = match mode {
+            Transfer::Move => (entry.created_at, entry.expires_at),
+            Transfer::Copy { keep_ttl: true } => (now, entry.expires_at),
+            Transfer::Copy { keep_ttl: false } => (now, None),
+        };
+        tx.execute(
+            "INSERT INTO kv (project, namespace, key, context, value, created_at, updated_at, expires_at, value_type)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(project, namespace, key, context) DO UPDATE SET
+                 value = excluded.value,
+                 created_at = excluded.created_at,
+                 updated_at = excluded.updated_at,
+                 expires_at = excluded.expires_at,
+                 value_type = excluded.value_type",
+            params![
+                dst.project,
+                dst.namespace,
+                dst.key,
+                context_column(&dst),
+                value,
+                created_at,
+                now,
+                expires_at,
+                entry.value_type
+            ],
+        )
+        .map_err(|err| sql_error(verb, err))?;
+
+        match mode {
+            Transfer::Move => {
+                tx.execute(
+                    "DELETE FROM kv WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4",
+                    params![src.project, src.namespace, src.key, context_column(src)],
+                )
+                .map_err(|err| sql_error(verb, err))?;
+            }
+            Transfer::Copy { .. } if self.audited => {
+                let old = existing.map(|entry| entry.value);
+                record_audit(
+                    &tx,
+                    self.actor.as_deref(),
+                    "copy",
+                    &dst,
+                    old.as_deref(),
+                    now,
+                )?;
+            }
+            Transfer::Copy { .. } => {}
+        }
+
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Delete expired rows namespace by namespace, at most `batch_size` rows per statement.
+    ///
+    /// Each batch commits on its own so a large sweep never holds the write lock for long.
+    pub fn sweep_expired(&self, batch_size: usize) -> CrudResult<Vec<SweepReport>> {
+        self.purge_expired(None, None, batch_size)
+    }
+
+    /// `sweep_expired` limited to one project, or to one `project.namespace`.
+    pub fn purge_expired(
+        &self,
+        project: Option<&str>,
+        namespace: Option<&str>,
+        batch_size: usize,
+    ) -> CrudResult<Vec<SweepReport>> {
+        let verb = CrudVerb::Delete;
+        let now = self.now();
+        if batch_size == 0 {
+            return Err(invalid_input(verb, "sweep batch size must be at least 1"));
+        }
+
+        let namespaces = {
+            let mut stmt = self
+                .conn
+                .prepare(
+                    "SELECT DISTINCT project, namespace FROM kv
+                     WHERE expires_at IS NOT NULL AND expires_at <= ?1
+                       AND (?2 IS NULL OR project = ?2)
+                       AND (?3 IS NULL OR namespace = ?3)
+                     ORDER BY project, namespace",
+                )
+                .map_err(|err| sql_error(verb, err))?;
+            let rows = stmt
+                .query_map(params![now, project, namespace], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(|err| sql_error(verb, err))?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|err| sql_error(verb, err))?
+        };
+
+        let mut reports = Vec::with_capacity(namespaces.len());
+        for (project, namespace) in namespaces {
+            let mut keys = Vec::new();
+            loop {
+                let mut stmt = self
+                    .conn
+                    .prepare_cached(
+                        "DELETE FROM kv WHERE rowid IN (
+                             SELECT rowid FROM kv
+                             WHERE project = ?1 AND namespace = ?2
+                               AND expires_at IS NOT NULL AND expires_at <= ?3
+                             LIMIT ?4)
+                         RETURNING key, context",
+                    )
+                    .map_err(|err| sql_error(verb, err))?;
+                let rows = stmt
+                    .query_map(
+                        params![project, namespace, now, batch_size as i64],
+                        removed_key,
+                    )
+                    .map_err(|err| sql_error(verb, err))?;
+                let batch = rows
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| sql_error(verb, err))?;
+                let done = batch.len() < batch_size;
+                keys.extend(batch);
+                if done {
+                    break;
+                }
+            }
+            reports.push(SweepReport {
+                project,
+                namespace,
+                removed: keys.len(),
+                keys,
+            });
+        }
+        Ok(reports)
+    }
+
+    /// Register (or replace) the expiry hook of a namespace.
+    pub fn set_expiry_hook(&self, project: &str, namespace: &str, command: &str) -> CrudResult<()> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        if command.trim().is_empty() {
+            return Err(invalid_input(verb, "hook command cannot be empty"));
+        }
+        self.conn
+            .execute(
+                "INSERT INTO sys_hooks (project, namespace, command, created_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(project, namespace) DO UPDATE SET command = excluded.command",
+                params![project, namespace, command, now],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Remove a namespace's expiry hook; returns whether one was registered.
+    pub fn remove_expiry_hook(&self, project: &str, namespace: &str) -> CrudResult<bool> {
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM sys_hooks WHERE project = ?1 AND namespace = ?2",
+                params![project, namespace],
+            )
+            .map_err(|err| sql_error(CrudVerb::Delete, err))?;
+        Ok(removed > 0)
+    }
+
+    /// Give `user` `permission` in `project.namespace` (or `project.*`), replacing earlier grants.
+    pub fn grant_access(
+        &self,
+        user: &str,
+        project: &str,
+        namespace: &str,
+        permission: Permission,
+    ) -> CrudResult<()> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        if user.is_empty() || project.is_empty() || namespace.is_empty() {
+            return Err(invalid_input(
+                verb,
+                "ACL rules need a user, project and namespace",
+            ));
+        }
+        self.conn
+            .execute(
+                "INSERT INTO sys_acl (user, project, namespace, permission, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(user, project, namespace) DO UPDATE SET permission = excluded.permission",
+                params![user, project, namespace, permission.as_str(), now],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Remove `user`'s rule on `project.namespace`; returns whether one existed.
+    pub fn revoke_access(&self, user: &str, project: &str, namespace: &str) -> CrudResult<bool> {
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM sys_acl WHERE user = ?1 AND project = ?2 AND namespace = ?3",
+                params![user, project, namespace],
+            )
+            .map_err(|err| sql_error(CrudVerb::Delete, err))?;
+        Ok(removed > 0)
+    }
+
+    /// Every ACL rule; empty for read-only databases created before ACLs existed.
+    pub fn access_rules(&self) -> CrudResult<Vec<AccessRule>> {
+        let verb = CrudVerb::List;
+        if !table_exists(&self.conn, "sys_acl", verb)? {
+            return Ok(Vec::new());
+        }
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT user, project, namespace, permission FROM sys_acl
+                 ORDER BY project, namespace, user",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .map_err(|err| sql_error(verb, err))?;
+        let mut rules = Vec::new();
+        for row in rows {
+            let (user, project, namespace, permission) = row.map_err(|err| sql_error(verb, err))?;
+            let permission = Permission::parse(&permission).ok_or_else(|| {
+                internal(
+                    verb,
+                    anyhow::anyhow!("unknown ACL permission '{}'", permission),
+                )
+            })?;
+            rules.push(AccessRule {
+                user,
+                project,
+                namespace,
+                permission,
+            });
+        }
+        Ok(rules)
+    }
+
+    pub fn access_policy(&self) -> CrudResult<AccessPolicy> {
+        Ok(AccessPolicy {
+            rules: self.access_rules()?,
+            token: self.token.clone(),
+            os_user: self.os_user.clone(),
+            impersonate: self.impersonate,
+        })
+    }
+
+    /// `PermissionDenied` unless `user` (see `AccessPolicy::identity`) may `need` in `project.namespace`.
+    pub fn check_access(
+        &self,
+        user: Option<&str>,
+        project: &str,
+        namespace: &str,
+        need: Permission,
+        verb: CrudVerb,
+    ) -> CrudResult<()> {
+        self.access_policy()?
+            .check(user, project, namespace, need, verb)
+    }
+
+    /// Issue a token for `project.namespace` (or `project.*`); the raw token is returned only here.
+    pub fn create_token(
+        &self,
+        project: &str,
+        namespace: &str,
+        permission: Permission,
+    ) -> CrudResult<(ApiToken, String)> {
+        let verb = CrudVerb::Create;
+        if project.is_empty() || namespace.is_empty() {
+            return Err(invalid_input(
+                verb,
+                "API tokens need a project and namespace",
+            ));
+        }
+        let mut bytes = [0u8; 32];
+        fs::File::open("/dev/urandom")
+            .and_then(|mut random| random.read_exact(&mut bytes))
+            .map_err(|err| internal(verb, anyhow::Error::new(err)))?;
+        let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let raw = format!("{}{}", API_TOKEN_PREFIX, hex);
+        let now = self.now();
+        self.conn
+            .execute(
+                "INSERT INTO sys_tokens (token_hash, project, namespace, permission, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    value_hash(&raw),
+                    project,
+                    namespace,
+                    permission.as_str(),
+                    now
+                ],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let token = ApiToken {
+            id: self.conn.last_insert_rowid(),
+            project: project.to_string(),
+            namespace: namespace.to_string(),
+            permission,
+            created_at: now,
+        };
+        Ok((token, raw))
+    }
+
+    /// Revoke token `id`; returns whether it existed.
+    pub fn revoke_token(&self, id: i64) -> CrudResult<bool> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM sys_tokens WHERE id = ?1", params![id])
+            .map_err(|err| sql_error(CrudVerb::Delete, err))?;
+        Ok(removed > 0)
+    }
+
+    /// Every issued token (never the raw values), oldest first.
+    pub fn tokens(&self) -> CrudResult<Vec<ApiToken>> {
+        let verb = CrudVerb::List;
+        if !table_exists(&self.conn, "sys_tokens", verb)? {
+            return Ok(Vec::new());
+        }
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, project, namespace, permission, created_at FROM sys_tokens
+                 ORDER BY id",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], token_from_row)
+            .map_err(|err| sql_error(verb, err))?;
+        let mut tokens = Vec::new();
+        for row in rows {
+            tokens.push(row.map_err(|err| sql_error(verb, err))??);
+        }
+        Ok(tokens)
+    }
+
+    /// The token this keystore was opened with (`config.token`), if any.
+    pub fn token(&self) -> Option<&ApiToken> {
+        self.token.as_ref()
+    }
+
+    /// Register `project.namespace`; with `secret` (key-file contents) its values are encrypted.
+    ///
+    /// Only an empty namespace can become encrypted, and only once; anything
+    /// else is a `Conflict`. Registering an existing plain namespace is a no-op.
+    pub fn create_namespace(
+        &mut self,
+        project: &str,
+        namespace: &str,
+        secret: Option<&[u8]>,
+    ) -> CrudResult<()> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        let encryption = match secret {
+            Some(secret) => {
+                let salt = NamespaceCipher::new_salt()?;
+                let key_check = NamespaceCipher::derive(secret, &salt)?.key_check();
+                Some((salt, key_check))
+            }
+            None => None,
+        };
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+        tx.execute(
+            "INSERT INTO sys_namespaces (project, namespace, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(project, namespace) DO NOTHING",
+            params![project, namespace, now],
+        )
+        .map_err(|err| sql_error(verb, err))?;
+
+        if let Some((salt, key_check)) = encryption {
+            let (encrypted, has_keys): (bool, bool) = tx
+                .query_row(
+                    "SELECT
+                         EXISTS (SELECT 1 FROM sys_encryption WHERE project = ?1 AND namespace = ?2),
+                         EXISTS (SELECT 1 FROM kv WHERE project = ?1 AND namespace = ?2)",
+                    params![project, namespace],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .map_err(|err| sql_error(verb, err))?;
+            if encrypted {
+                return Err(conflict(
+                    verb,
+                    format!("{}.{} is already encrypted", project, namespace),
+                ));
+            }
+            if has_keys {
+                return Err(conflict(
+                    verb,
+                    format!(
+                        "{}.{} already holds keys; only an empty namespace can be encrypted",
+                        project, namespace
+                    ),
+                ));
+            }
+            tx.execute(
+                "INSERT INTO sys_encryption (project, namespace, salt, key_check, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![project, namespace, salt, key_check, now],
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// How this database splits addresses of four or more segments.
+    pub fn address_layout(&self) -> CrudResult<AddressLayout> {
+        read_address_layout(&self.conn)
+    }
+
+    /// `address_layout` of the database at `path`, read without creating or migrating it;
+    /// `Fixed` when the file does not exist.
+    pub fn stored_address_layout(path: &Path) -> CrudResult<AddressLayout> {
+        if !path.exists() {
+            return Ok(AddressLayout::Fixed);
+        }
+        let config = SqliteConnectionConfig::new(path).with_read_only(true);
+        let conn = Connection::open_with_flags(path, SqlitePathResolver::flags_for(&config))
+            .map_err(|err| sql_error(CrudVerb::Read, err))?;
+        read_address_layout(&conn)
+    }
+
+    /// Record `layout` for this database; stored keys are untouched (under `Fixed`,
+    /// keys holding the delimiter are addressed quoted).
+    pub fn set_address_layout(&self, layout: AddressLayout) -> CrudResult<()> {
+        write_setting(&self.conn, ADDRESS_LAYOUT_SETTING, layout.as_str())
+    }
+
+    /// Whether `set` may create namespaces here; `Lax` unless recorded otherwise.
+    pub fn validation_mode(&self) -> CrudResult<ValidationMode> {
+        let verb = CrudVerb::Read;
+        match read_setting(&self.conn, VALIDATION_MODE_SETTING)? {
+            Some(name) => ValidationMode::parse(&name)
+                .ok_or_else(|| invalid_input(verb, format!("unknown validation mode '{}'", name))),
+            None => Ok(ValidationMode::Lax),
+        }
+    }
+
+    /// Record `mode` for this database; existing namespaces stay known.
+    pub fn set_validation_mode(&self, mode: ValidationMode) -> CrudResult<()> {
+        write_setting(&self.conn, VALIDATION_MODE_SETTING, mode.as_str())
+    }
+
+    /// `project.namespace` holds a key (even an expired one) or is registered.
+    pub fn namespace_known(&self, project: &str, namespace: &str) -> CrudResult<bool> {
+        let verb = CrudVerb::Find;
+        self.conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM kv WHERE project = ?1 AND namespace = ?2)
+                     OR EXISTS (SELECT 1 FROM sys_namespaces WHERE project = ?1 AND namespace = ?2)",
+                params![project, namespace],
+                |row| row.get(0),
+            )
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Every encrypted namespace; empty for read-only databases created before encryption.
+    pub fn encrypted_namespaces(&self) -> CrudResult<Vec<EncryptedNamespace>> {
+        let verb = CrudVerb::List;
+        if !table_exists(&self.conn, "sys_encryption", verb)? {
+            return Ok(Vec::new());
+        }
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT project, namespace, salt, key_check FROM sys_encryption
+                 ORDER BY project, namespace",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(EncryptedNamespace {
+                    project: row.get(0)?,
+                    namespace: row.get(1)?,
+                    salt: row.get(2)?,
+                    key_check: row.get(3)?,
+                })
+            })
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Mark `project.namespace.key` (or, with `key = None`, the whole namespace) secret.
+    ///
+    /// With `secret = false` the mark is removed; returns whether anything changed.
+    pub fn mark_secret(
+        &self,
+        project: &str,
+        namespace: &str,
+        key: Option<&str>,
+        secret: bool,
+    ) -> CrudResult<bool> {
+        let key = key.unwrap_or(SECRET_ALL_KEYS);
+        let changed = if secret {
+            self.conn.execute(
+                "INSERT INTO sys_secrets (project, namespace, key, created_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(project, namespace, key) DO NOTHING",
+                params![project, namespace, key, self.now()],
+            )
+        } else {
+            self.conn.execute(
+                "DELETE FROM sys_secrets WHERE project = ?1 AND namespace = ?2 AND key = ?3",
+                params![project, namespace, key],
+            )
+        }
+        .map_err(|err| sql_error(CrudVerb::Update, err))?;
+        Ok(changed > 0)
+    }
+
+    /// Every secret mark; empty for read-only databases created before marks existed.
+    pub fn secret_marks(&self) -> CrudResult<SecretMarks> {
+        let verb = CrudVerb::List;
+        if !table_exists(&self.conn, "sys_secrets", verb)? {
+            return Ok(SecretMarks::default());
+        }
+        let mut stmt = self
+            .conn
+            .prepare("SELECT project, namespace, key FROM sys_secrets")
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|err| sql_error(verb, err))?;
+        let marks = rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))?;
+        Ok(SecretMarks { marks })
+    }
+
+    /// Create the audit log; from then on every `set`, `del` and `copy` appends a row.
+    ///
+    /// Returns `false` when it was already enabled.
+    pub fn enable_audit(&mut self) -> CrudResult<bool> {
+        if self.audited {
+            return Ok(false);
+        }
+        self.conn
+            .execute_batch(AUDIT_SQL)
+            .map_err(|err| sql_error(CrudVerb::Create, err))?;
+        self.audited = true;
+        Ok(true)
+    }
+
+    pub fn audit_enabled(&self) -> bool {
+        self.audited
+    }
+
+    /// The newest `limit` audit rows, oldest first.
+    pub fn audit_tail(&self, limit: usize) -> CrudResult<Vec<AuditEntry>> {
+        self.audit_search(&AuditQuery {
+            limit,
+            ..AuditQuery::default()
+        })
+    }
+
+    /// The newest `query.limit` audit rows matching every filter in `query`, oldest first.
+    pub fn audit_search(&self, query: &AuditQuery) -> CrudResult<Vec<AuditEntry>> {
+        let verb = CrudVerb::List;
+        if !self.audited {
+            return Ok(Vec::new());
+        }
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, at, user, command, address, old_hash, new_hash FROM (
+                     SELECT * FROM sys_audit
+                     WHERE (?1 IS NULL OR user = ?1)
+                       AND (?2 IS NULL OR command = ?2)
+                       AND (?3 IS NULL OR substr(address, 1, length(?3)) = ?3)
+                     ORDER BY id DESC
+                     LIMIT ?4
+                 ) ORDER BY id",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    query.user,
+                    query.command,
+                    query.address_prefix,
+                    query.limit as i64
+                ],
+                |row| {
+                    Ok(AuditEntry {
+                        id: row.get(0)?,
+                        at: row.get(1)?,
+                        user: row.get(2)?,
+                        command: row.get(3)?,
+                        address: row.get(4)?,
+                        old_hash: row.get(5)?,
+                        new_hash: row.get(6)?,
+                    })
+                },
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Create the oplog; from then on every insert, update and delete of a key
+    /// appends a row, in the same transaction as the write.
+    ///
+    /// Returns `false` when it was already enabled.
+    pub fn enable_oplog(&mut self) -> CrudResult<bool> {
+        if self.oplogged {
+            return Ok(false);
+        }
+        self.conn
+            .execute_batch(OPLOG_SQL)
+            .map_err(|err| sql_error(CrudVerb::Create, err))?;
+        self.oplogged = true;
+        self.install_oplog()?;
+        Ok(true)
+    }
+
+    pub fn oplog_enabled(&self) -> bool {
+        self.oplogged
+    }
+
+    /// Oplog rows after `since`, oldest first; at most `limit` of them.
+    pub fn changes_since(&self, since: OplogSince, limit: usize) -> CrudResult<Vec<OplogEntry>> {
+        let verb = CrudVerb::List;
+        if !self.oplogged {
+            return Ok(Vec::new());
+        }
+        let (after_seq, from_time) = match since {
+            OplogSince::Seq(seq) => (seq, i64::MIN),
+            OplogSince::Time(at) => (0, at),
+        };
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT seq, op, address, value_hash, at, user FROM sys_oplog
+                 WHERE seq > ?1 AND at >= ?2
+                 ORDER BY seq
+                 LIMIT ?3",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    after_seq,
+                    from_time,
+                    i64::try_from(limit).unwrap_or(i64::MAX)
+                ],
+                |row| {
+                    Ok(OplogEntry {
+                        seq: row.get(0)?,
+                        op: row.get(1)?,
+                        address: row.get(2)?,
+                        value_hash: row.get(3)?,
+                        at: row.get(4)?,
+                        user: row.get(5)?,
+                    })
+                },
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Register the oplog's SQL functions and this connection's triggers on `kv`.
+    ///
+    /// The triggers are TEMP so they can call functions that only exist on
+    /// connections opened by the keystore, and carry `actor` as a literal.
+    fn install_oplog(&self) -> CrudResult<()> {
+        register_oplog_functions(&self.conn, self.clock.clone())?;
+        self.conn
+            .execute_batch(&oplog_triggers(self.actor.as_deref()))
+            .map_err(|err| sql_error(CrudVerb::Create, err))
+    }
+
+    /// Run `write`; with auditing on, inside one transaction that also logs it as `command`.
+    fn audited_write<T, F>(
+        &self,
+        command: &str,
+        addr: &Address,
+        verb: CrudVerb,
+        write: F,
+    ) -> CrudResult<T>
+    where
+        F: FnOnce(&Connection) -> CrudResult<T>,
+    {
+        if !self.audited {
+            return write(&self.conn);
+        }
+        let now = self.now();
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+        let old = select_value(&tx, addr, now)?;
+        let result = write(&tx)?;
+        record_audit(
+            &tx,
+            self.actor.as_deref(),
+            command,
+            addr,
+            old.as_deref(),
+            now,
+        )?;
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(result)
+    }
+
+    pub fn expiry_hooks(&self) -> CrudResult<Vec<ExpiryHook>> {
+        let verb = CrudVerb::List;
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT project, namespace, command FROM sys_hooks ORDER BY project, namespace",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(ExpiryHook {
+                    project: row.get(0)?,
+                    namespace: row.get(1)?,
+                    command: row.get(2)?,
+                })
+            })
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Write every entry inside one transaction; either all land or none do.
+    pub fn set_many(&mut self, entries: &[(Address, String)]) -> CrudResult<usize> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|err| sql_error(verb, err))?;
+        for (addr, value) in entries {
+            upsert(&tx, addr, value, None, now)?;
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(entries.len())
+    }
+
+    /// Delete `addrs` in one immediate transaction; returns how many existed.
+    pub fn delete_many(&mut self, addrs: &[Address]) -> CrudResult<usize> {
+        let verb = CrudVerb::Delete;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+        let mut removed = 0;
+        for addr in addrs {
+            let old = if self.audited {
+                select_value(&tx, addr, now)?
+            } else {
+                None
+            };
+            let deleted = tx
+                .execute(
+                    "DELETE FROM kv WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4",
+                    params![addr.project, addr.namespace, addr.key, context_column(addr)],
+                )
+                .map_err(|err| sql_error(verb, err))?;
+            if self.audited {
+                record_audit(&tx, self.actor.as_deref(), "del", addr, old.as_deref(), now)?;
+            }
+            removed += deleted;
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(removed)
+    }
+
+    /// Load `(address, value, ttl)` rows in one transaction, resolving existing keys by `strategy`.
+    ///
+    /// A `None` TTL falls back to the namespace's cache default, as with `set`.
+    pub fn import_many(
+        &mut self,
+        entries: &[(Address, String, Option<u64>)],
+        strategy: ImportStrategy,
+    ) -> CrudResult<ImportReport> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|err| sql_error(verb, err))?;
+        let mut report = ImportReport::default();
+        for (addr, value, ttl) in entries {
+            let exists = select_value(&tx, addr, now)?.is_some();
+            match (exists, strategy) {
+                (true, ImportStrategy::Skip) => report.skipped += 1,
+                (true, ImportStrategy::Fail) => report.conflicted += 1,
+                _ => {
+                    upsert(&tx, addr, value, *ttl, now)?;
+                    report.inserted += 1;
+                }
+            }
+        }
+        if report.conflicted > 0 {
+            // Dropping the transaction rolls back the rows written so far.
+            report.inserted = 0;
+            return Ok(report);
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(report)
+    }
+
+    /// Register `kind` on a namespace and index its existing keys; `false` if it already existed.
+    pub fn create_index(
+        &mut self,
+        project: &str,
+        namespace: &str,
+        kind: &IndexKind,
+    ) -> CrudResult<bool> {
+        let verb = CrudVerb::Create;
+        let now = self.now();
+        let kind = &canonical_index(kind, verb)?;
+        let (kind_name, path) = kind.as_parts();
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|err| sql_error(verb, err))?;
+        let created = tx
+            .execute(
+                "INSERT OR IGNORE INTO sys_indexes (project, namespace, kind, path, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![project, namespace, kind_name, path, now],
+            )
+            .map_err(|err| sql_error(verb, err))?
+            > 0;
+        if created {
+            match kind {
+                IndexKind::JsonPath(path) => {
+                    tx.execute_batch(&format!(
+                        "CREATE INDEX IF NOT EXISTS {} ON kv ({}) WHERE {}",
+                        json_index_name(project, namespace, path),
+                        json_expr(path),
+                        json_index_scope(project, namespace)
+                    ))
+                    .map_err(|err| sql_error(verb, err))?;
+                }
+                IndexKind::FullText => {
+                    tx.execute_batch(FTS_SQL)
+                        .map_err(|err| sql_error(verb, err))?;
+                    tx.execute(
+                        "INSERT INTO kv_fts (rowid, value)
+                         SELECT rowid, value FROM kv WHERE project = ?1 AND namespace = ?2",
+                        params![project, namespace],
+                    )
+                    .map_err(|err| sql_error(verb, err))?;
+                }
+            }
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(created)
+    }
+
+    /// Remove an index from a namespace; `false` if it was not registered.
+    pub fn drop_index(
+        &mut self,
+        project: &str,
+        namespace: &str,
+        kind: &IndexKind,
+    ) -> CrudResult<bool> {
+        let verb = CrudVerb::Delete;
+        let kind = &canonical_index(kind, verb)?;
+        let (kind_name, path) = kind.as_parts();
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|err| sql_error(verb, err))?;
+        let removed = tx
+            .execute(
+                "DELETE FROM sys_indexes
+                 WHERE project = ?1 AND namespace = ?2 AND kind = ?3 AND path = ?4",
+                params![project, namespace, kind_name, path],
+            )
+            .map_err(|err| sql_error(verb, err))?
+            > 0;
+        if removed {
+            match kind {
+                IndexKind::JsonPath(path) => {
+                    tx.execute_batch(&format!(
+                        "DROP INDEX IF EXISTS {}",
+                        json_index_name(project, namespace, path)
+                    ))
+                    .map_err(|err| sql_error(verb, err))?;
+                }
+                IndexKind::FullText => {
+                    tx.execute(
+                        "DELETE FROM kv_fts
+                         WHERE rowid IN (SELECT rowid FROM kv WHERE project = ?1 AND namespace = ?2)",
+                        params![project, namespace],
+                    )
+                    .map_err(|err| sql_error(verb, err))?;
+                }
+            }
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(removed)
+    }
+
+    /// Every registered index, ordered by namespace.
+    pub fn indexes(&self) -> CrudResult<Vec<IndexInfo>> {
+        let verb = CrudVerb::List;
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT project, namespace, kind, path FROM sys_indexes
+                 ORDER BY project, namespace, kind, path",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .map_err(|err| sql_error(verb, err))?;
+        let mut indexes = Vec::new();
+        for row in rows {
+            let (project, namespace, kind, path) = row.map_err(|err| sql_error(verb, err))?;
+            if let Some(kind) = IndexKind::from_parts(&kind, &path) {
+                indexes.push(IndexInfo {
+                    project,
+                    namespace,
+                    kind,
+                });
+            }
+        }
+        Ok(indexes)
+    }
+
+    /// Stream live entries in `scope` whose JSON value matches `filter`.
+    ///
+    /// The query repeats the expression and namespace terms of a `--json-path`
+    /// index verbatim and names it with `INDEXED BY` when one is registered
+    /// (the planner otherwise prefers the primary key).
+    pub fn for_each_where<F>(
+        &self,
+        scope: &NamespaceScope,
+        filter: &JsonFilter,
+        order: EntryOrder,
+        mut visit: F,
+    ) -> CrudResult<()>
+    where
+        F: FnMut(KvEntry) -> ControlFlow<()>,
+    {
+        let verb = CrudVerb::List;
+        let now = self.now();
+        let path = json_path(&filter.path, verb)?;
+        let indexed: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sys_indexes
+                                WHERE project = ?1 AND namespace = ?2 AND kind = 'json' AND path = ?3)",
+                params![scope.project, scope.namespace, path],
+                |row| row.get(0),
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let indexed_by = if indexed {
+            format!(
+                " INDEXED BY {}",
+                json_index_name(&scope.project, &scope.namespace, &path)
+            )
+        } else {
+            String::new()
+        };
+        let sql = format!(
+            "SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type
+             FROM kv{}
+             WHERE {} AND {} = ?1
+               AND (?2 IS NULL OR substr(key, 1, length(?2)) = ?2)
+               AND (expires_at IS NULL OR expires_at > ?3)
+             ORDER BY {}",
+            indexed_by,
+            json_index_scope(&scope.project, &scope.namespace),
+            json_expr(&path),
+            order.order_by(false)
+        );
+        let mut stmt = self
+            .conn
+            .prepare(&sql)
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![json_scalar(&filter.value), scope.prefix, now],
+                entry_from_row,
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        for row in rows {
+            let entry = row.map_err(|err| sql_error(verb, err))?;
+            if visit(entry).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rank live entries in full-text indexed namespaces against an FTS5 `query` (bm25).
+    pub fn search(
+        &self,
+        query: &str,
+        project: Option<&str>,
+        namespace: Option<&str>,
+        limit: usize,
+    ) -> CrudResult<Vec<SearchHit>> {
+        let verb = CrudVerb::Find;
+        let now = self.now();
+        let has_fts: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'kv_fts')",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        if !has_fts {
+            return Err(invalid_input(
+                verb,
+                "no full-text index; run prontodb-admin --create-index --scope=project.ns --fts",
+            ));
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT kv.project, kv.namespace, kv.key, kv.context, kv.value,
+                        kv.created_at, kv.updated_at, kv.expires_at, kv.value_type,
+                        bm25(kv_fts), snippet(kv_fts, 0, '[', ']', '...', 12)
+                 FROM kv_fts JOIN kv ON kv.rowid = kv_fts.rowid
+                 WHERE kv_fts MATCH ?1
+                   AND (?2 IS NULL OR kv.project = ?2)
+                   AND (?3 IS NULL OR kv.namespace = ?3)
+                   AND (kv.expires_at IS NULL OR kv.expires_at > ?4)
+                 ORDER BY bm25(kv_fts)
+                 LIMIT ?5",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map(
+                params![query, project, namespace, now, limit as i64],
+                |row| {
+                    Ok(SearchHit {
+                        entry: entry_from_row(row)?,
+                        score: -row.get::<_, f64>(9)?,
+                        snippet: row.get(10)?,
+                    })
+                },
+            )
+            .map_err(|err| invalid_input(verb, format!("invalid search query: {}", err)))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| invalid_input(verb, format!("invalid search query: {}", err)))
+    }
+
+    /// Read every address from one consistent snapshot, preserving input order.
+    pub fn get_many(&mut self, addrs: &[Address]) -> CrudResult<Vec<Option<String>>> {
+        let verb = CrudVerb::Read;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|err| sql_error(verb, err))?;
+        let mut values = Vec::with_capacity(addrs.len());
+        for addr in addrs {
+            values.push(read_value(&tx, addr, now)?);
+        }
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(values)
+    }
+
+    /// Atomically add `delta` to an integer value and return the result.
+    ///
+    /// Missing (or expired) keys start from zero; an existing TTL is kept.
+    /// Values tagged with a type other than `int` are rejected.
+    /// Runs under an immediate transaction so concurrent writers serialise.
+    pub fn increment(&mut self, addr: &Address, delta: i64) -> CrudResult<i64> {
+        let verb = CrudVerb::Update;
+        let now = self.now();
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+
+        let next = match select_entry(&tx, addr, now)? {
+            Some(StoredEntry {
+                value_type: Some(value_type),
+                ..
+            }) if value_type != ValueType::Int.as_str() => {
+                return Err(invalid_input(
+                    verb,
+                    format!("value at {} is typed {}, not int", addr, value_type),
+                ));
+            }
+            Some(StoredEntry { value: raw, .. }) => {
+                let current = raw.parse::<i64>().map_err(|_| {
+                    invalid_input(
+                        verb,
+                        format!("value at {} is not an integer: '{}'", addr, raw),
+                    )
+                })?;
+                let next = current.checked_add(delta).ok_or_else(|| {
+                    invalid_input(verb, format!("increment overflows at {}", addr))
+                })?;
+                tx.execute(
+                    "UPDATE kv SET value = ?5, updated_at = ?6
+                     WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4",
+                    params![
+                        addr.project,
+                        addr.namespace,
+                        addr.key,
+                        context_column(addr),
+                        next.to_string(),
+                        now
+                    ],
+                )
+                .map_err(|err| sql_error(verb, err))?;
+                next
+            }
+            None => {
+                upsert(&tx, addr, &delta.to_string(), None, now)?;
+                delta
+            }
+        };
+
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(next)
+    }
+
+    /// Set `path` (`.db.port`) inside the JSON document at `addr` in one transaction.
+    ///
+    /// `value` is stored as JSON when it parses as JSON and as a string otherwise.
+    /// A missing key starts from `{}`; an existing TTL is kept.
+    pub fn json_set(&mut self, addr: &Address, path: &str, value: &str) -> CrudResult<()> {
+        let verb = CrudVerb::Update;
+        let now = self.now();
+        let path = json_path(path, verb)?;
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+
+        let current = select_value(&tx, addr, now)?;
+        let document = json_document(&tx, addr, current.as_deref().unwrap_or("{}"), verb)?;
+        let updated: String = tx
+            .query_row(
+                "SELECT json_set(?1, ?2, CASE WHEN json_valid(?3) THEN json(?3) ELSE ?3 END)",
+                params![document, format!("${}", path), value],
+                |row| row.get(0),
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        if current.is_some() {
+            replace_value(&tx, addr, &updated, now, verb)?;
+        } else {
+            upsert(&tx, addr, &updated, None, now)?;
+        }
+
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(())
+    }
+
+    /// Read `path` from the JSON document at `addr`; `None` when the key or path is missing.
+    ///
+    /// Strings come back unquoted; objects, arrays, numbers, and literals as JSON text.
+    pub fn json_get(&self, addr: &Address, path: &str) -> CrudResult<Option<String>> {
+        let verb = CrudVerb::Read;
+        let path = json_path(path, verb)?;
+        let raw = match select_value(&self.conn, addr, self.now())? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+        let document = json_document(&self.conn, addr, &raw, verb)?;
+        self.conn
+            .query_row(
+                "SELECT CASE json_type(?1, ?2)
+                            WHEN 'true' THEN 'true'
+                            WHEN 'false' THEN 'false'
+                            WHEN 'null' THEN 'null'
+                            ELSE CAST(json_extract(?1, ?2) AS TEXT) END",
+                params![document, format!("${}", path)],
+                |row| row.get(0),
+            )
+            .map_err(|err| sql_error(verb, err))
+    }
+
+    /// Remove `path` from the JSON document at `addr`; `false` when the key or path is missing.
+    pub fn json_delete(&mut self, addr: &Address, path: &str) -> CrudResult<bool> {
+        let verb = CrudVerb::Update;
+        let now = self.now();
+        let path = format!("${}", json_path(path, verb)?);
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|err| sql_error(verb, err))?;
+
+        let raw = match select_value(&tx, addr, now)? {
+            Some(raw) => raw,
+            None => return Ok(false),
+        };
+        let document = json_document(&tx, addr, &raw, verb)?;
+        let updated: Option<String> = tx
+            .query_row(
+                "SELECT CASE WHEN json_type(?1, ?2) IS NULL THEN NULL
+                             ELSE json_remove(?1, ?2) END",
+                params![document, path],
+                |row| row.get(0),
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let updated = match updated {
+            Some(updated) => updated,
+            None => return Ok(false),
+        };
+        replace_value(&tx, addr, &updated, now, verb)?;
+
+        tx.commit().map_err(|err| sql_error(verb, err))?;
+        Ok(true)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Transfer {
+    Move,
+    Copy { keep_ttl: bool },
+}
+
+/// `raw` when it is a JSON document, else an `InvalidInput` naming `addr`.
+fn json_document<'a>(
+    conn: &Connection,
+    addr: &Address,
+    raw: &'a str,
+    verb: CrudVerb,
+) -> CrudResult<&'a str> {
+    let valid: bool = conn
+        .query_row("SELECT json_valid(?1)", params![raw], |row| row.get(0))
+        .map_err(|err| sql_error(verb, err))?;
+    if valid {
+        Ok(raw)
+    } else {
+        Err(invalid_input(
+            verb,
+            format!("value at {} is not a JSON document", addr),
+        ))
+    }
+}
+
+/// Overwrite the value of an existing row, keeping its TTL and creation time.
+fn replace_value(
+    conn: &Connection,
+    addr: &Address,
+    value: &str,
+    now: i64,
+    verb: CrudVerb,
+) -> CrudResult<()> {
+    conn.execute(
+        "UPDATE kv SET value = ?5, updated_at = ?6
+         WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4",
+        params![
+            addr.project,
+            addr.namespace,
+            addr.key,
+            context_column(addr),
+            value,
+            now
+        ],
+    )
+    .map_err(|err| sql_error(verb, err))?;
+    Ok(())
+}
+
+/// Body of `SqliteKeystore::apply_layer`, with the layer attached as `layer`.
+fn apply_layer(conn: &mut Connection, removed: &[Address]) -> CrudResult<()> {
+    let verb = CrudVerb::Restore;
+    let tx = conn.transaction().map_err(|err| sql_error(verb, err))?;
+
+    let objects: Vec<(String, String)> = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT name, sql FROM layer.sqlite_master
+                 WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+                 ORDER BY rowid",
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|err| sql_error(verb, err))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|err| sql_error(verb, err))?
+    };
+    // In rowid order, so a virtual table comes before the shadow tables it creates.
+    for (name, sql) in &objects {
+        let exists: bool = tx
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM main.sqlite_master WHERE name = ?1)",
+                params![name],
+                |row| row.get(0),
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        if !exists {
+            tx.execute_batch(sql).map_err(|err| sql_error(verb, err))?;
+        }
+    }
+    let stale = layer_names(
+        &tx,
+        "SELECT name FROM main.sqlite_master m
+         WHERE type = 'index' AND name LIKE 'kv\\_json\\_%' ESCAPE '\\'
+           AND NOT EXISTS (SELECT 1 FROM layer.sqlite_master l WHERE l.name = m.name)",
+    )?;
+    for index in stale {
+        tx.execute_batch(&format!("DROP INDEX main.{}", index))
+            .map_err(|err| sql_error(verb, err))?;
+    }
+
+    let tables = layer_names(
+        &tx,
+        "SELECT name FROM layer.sqlite_master
+         WHERE type = 'table' AND name LIKE 'sys\\_%' ESCAPE '\\' AND name <> 'sys_audit'",
+    )?;
+    for table in tables {
+        let columns = {
+            let mut stmt = tx
+                .prepare("SELECT name FROM pragma_table_info(?1, 'layer')")
+                .map_err(|err| sql_error(verb, err))?;
+            let rows = stmt
+                .query_map(params![table], |row| row.get::<_, String>(0))
+                .map_err(|err| sql_error(verb, err))?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|err| sql_error(verb, err))?
+                .join(", ")
+        };
+        tx.execute_batch(&format!(
+            "DELETE FROM main.{0};
+             INSERT INTO main.{0} ({1}) SELECT {1} FROM layer.{0};",
+            table, columns
+        ))
+        .map_err(|err| sql_error(verb, err))?;
+    }
+    let audited = !layer_names(
+        &tx,
+        "SELECT name FROM layer.sqlite_master WHERE type = 'table' AND name = 'sys_audit'",
+    )?
+    .is_empty();
+    if audited {
+        tx.execute_batch(
+            "INSERT INTO main.sys_audit
+             SELECT * FROM layer.sys_audit
+             WHERE id > (SELECT IFNULL(MAX(id), 0) FROM main.sys_audit)",
+        )
+        .map_err(|err| sql_error(verb, err))?;
+    }
+
+    tx.execute_batch(
+        "INSERT INTO main.kv (project, namespace, key, context, value, created_at, updated_at, expires_at, value_type)
+         SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type
+         FROM layer.kv WHERE true
+         ON CONFLICT(project, namespace, key, context) DO UPDATE SET
+             value = excluded.value,
+             created_at = excluded.created_at,
+             updated_at = excluded.updated_at,
+             expires_at = excluded.expires_at,
+             value_type = excluded.value_type",
+    )
+    .map_err(|err| sql_error(verb, err))?;
+    for addr in removed {
+        tx.execute(
+            "DELETE FROM main.kv WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4",
+            params![addr.project, addr.namespace, addr.key, context_column(addr)],
+        )
+        .map_err(|err| sql_error(verb, err))?;
+    }
+    if table_exists(&tx, "kv_fts", verb)? {
+        tx.execute_batch(
+            "DELETE FROM main.kv_fts;
+             INSERT INTO main.kv_fts (rowid, value)
+             SELECT rowid, value FROM main.kv
+             WHERE EXISTS (SELECT 1 FROM main.sys_indexes i
+                           WHERE i.project = kv.project AND i.namespace = kv.namespace
+                             AND i.kind = 'fts');",
+        )
+        .map_err(|err| sql_error(verb, err))?;
+    }
+    tx.commit().map_err(|err| sql_error(verb, err))
+}
+
+/// Body of `SqliteKeystore::sync_to`, with the target attached as `peer`.
+fn sync_rows(conn: &mut Connection, since: i64, now: i64) -> CrudResult<SyncReport> {
+    let verb = CrudVerb::Update;
+    let tx = conn.transaction().map_err(|err| sql_error(verb, err))?;
+    tx.execute(
+        "CREATE TEMP TABLE sync_changed AS
+         SELECT * FROM main.kv
+         WHERE updated_at >= ?1 AND (expires_at IS NULL OR expires_at > ?2)",
+        params![since, now],
+    )
+    .map_err(|err| sql_error(verb, err))?;
+
+    let clash: Option<(String, String)> = tx
+        .query_row(
+            "SELECT c.project, c.namespace
+             FROM (SELECT DISTINCT project, namespace FROM temp.sync_changed) c
+             LEFT JOIN main.sys_encryption m USING (project, namespace)
+             LEFT JOIN peer.sys_encryption p USING (project, namespace)
+             WHERE m.salt IS NOT p.salt
+               AND (p.salt IS NOT NULL OR EXISTS (
+                        SELECT 1 FROM peer.kv k
+                        WHERE k.project = c.project AND k.namespace = c.namespace))
+             LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|err| sql_error(verb, err))?;
+    if let Some((project, namespace)) = clash {
+        return Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!(
+                "{}.{} is encrypted differently in the two databases",
+                project, namespace
+            ),
+        ));
+    }
+    tx.execute_batch(
+        "INSERT INTO peer.sys_encryption (project, namespace, salt, key_check, created_at)
+         SELECT project, namespace, salt, key_check, created_at FROM main.sys_encryption m
+         WHERE EXISTS (SELECT 1 FROM temp.sync_changed c
+                       WHERE c.project = m.project AND c.namespace = m.namespace)
+         ON CONFLICT (project, namespace) DO NOTHING",
+    )
+    .map_err(|err| sql_error(verb, err))?;
+
+    let (changed, watermark): (i64, Option<i64>) = tx
+        .query_row(
+            "SELECT COUNT(*), MAX(updated_at) FROM temp.sync_changed",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|err| sql_error(verb, err))?;
+    // Equal timestamps with different values count as changed, so the sending side wins ties.
+    let copied = tx
+        .execute(
+            "INSERT INTO peer.kv AS dest
+                 (project, namespace, key, context, value, created_at, updated_at,
+                  expires_at, value_type)
+             SELECT project, namespace, key, context, value, created_at, updated_at,
+                    expires_at, value_type
+             FROM temp.sync_changed WHERE 1
+             ON CONFLICT (project, namespace, key, context) DO UPDATE SET
+                 value = excluded.value,
+                 created_at = excluded.created_at,
+                 updated_at = excluded.updated_at,
+                 expires_at = excluded.expires_at,
+                 value_type = excluded.value_type
+             WHERE excluded.updated_at > dest.updated_at
+                OR (excluded.updated_at = dest.updated_at
+                    AND (excluded.value IS NOT dest.value
+                         OR excluded.expires_at IS NOT dest.expires_at
+                         OR excluded.value_type IS NOT dest.value_type))",
+            [],
+        )
+        .map_err(|err| sql_error(verb, err))?;
+    tx.execute_batch("DROP TABLE temp.sync_changed")
+        .map_err(|err| sql_error(verb, err))?;
+    tx.commit().map_err(|err| sql_error(verb, err))?;
+    Ok(SyncReport {
+        copied,
+        skipped: changed as usize - copied,
+        watermark: watermark.unwrap_or(since),
+    })
+}
+
+/// The `for_each_entry_across` query over `main.kv` and `db1.kv` to `db<attached>.kv`.
+fn scan_across<F>(
+    conn: &Connection,
+    attached: usize,
+    scope: &NamespaceScope,
+    now: i64,
+    mut visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(usize, KvEntry) -> ControlFlow<()>,
+{
+    let verb = CrudVerb::List;
+    let selects: Vec<String> = (0..=attached)
+        .map(|index| {
+            let schema = match index {
+                0 => "main".to_string(),
+                index => format!("db{}", index),
+            };
+            format!(
+                "SELECT project, namespace, key, context, value, created_at, updated_at,
+                        expires_at, value_type, {} AS db
+                 FROM {}.kv
+                 WHERE project = ?1 AND namespace = ?2
+                   AND (?3 IS NULL OR substr(key, 1, length(?3)) = ?3)
+                   AND (expires_at IS NULL OR expires_at > ?4)",
+                index, schema
+            )
+        })
+        .collect();
+    let sql = format!("{} ORDER BY key, context, db", selects.join(" UNION ALL "));
+    let mut stmt = conn.prepare(&sql).map_err(|err| sql_error(verb, err))?;
+    let rows = stmt
+        .query_map(
+            params![scope.project, scope.namespace, scope.prefix, now],
+            |row| Ok((row.get::<_, i64>(9)? as usize, entry_from_row(row)?)),
+        )
+        .map_err(|err| sql_error(verb, err))?;
+    for row in rows {
+        let (index, entry) = row.map_err(|err| sql_error(verb, err))?;
+        if visit(index, entry).is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn layer_names(conn: &Connection, sql: &str) -> CrudResult<Vec<String>> {
+    let verb = CrudVerb::Restore;
+    let mut stmt = conn.prepare(sql).map_err(|err| sql_error(verb, err))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|err| sql_error(verb, err))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|err| sql_error(verb, err))
+}
+
+fn table_exists(conn: &Connection, name: &str, verb: CrudVerb) -> CrudResult<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        params![name],
+        |row| row.get(0),
+    )
+    .map_err(|err| sql_error(verb, err))
+}
+
+/// The recorded `AddressLayout`; `Fixed` when the database predates `sys_settings` or has none.
+fn read_address_layout(conn: &Connection) -> CrudResult<AddressLayout> {
+    let verb = CrudVerb::Read;
+    match read_setting(conn, ADDRESS_LAYOUT_SETTING)? {
+        Some(name) => {
+            AddressLayout::parse(&name).map_err(|err| invalid_input(verb, err.to_string()))
+        }
+        None => Ok(AddressLayout::Fixed),
+    }
+}
+
+/// The `sys_settings` value of `name`; `None` when unset or the table predates it.
+fn read_setting(conn: &Connection, name: &str) -> CrudResult<Option<String>> {
+    let verb = CrudVerb::Read;
+    if !table_exists(conn, "sys_settings", verb)? {
+        return Ok(None);
+    }
+    conn.query_row(
+        "SELECT value FROM sys_settings WHERE name = ?1",
+        params![name],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|err| sql_error(verb, err))
+}
+
+fn write_setting(conn: &Connection, name: &str, value: &str) -> CrudResult<()> {
+    conn.execute(
+        "INSERT INTO sys_settings (name, value) VALUES (?1, ?2)
+         ON CONFLICT (name) DO UPDATE SET value = excluded.value",
+        params![name, value],
+    )
+    .map_err(|err| sql_error(CrudVerb::Update, err))?;
+    Ok(())
+}
+
+/// The `sys_tokens` row whose hash matches `raw`; `None` for unknown tokens.
+/// The token `raw` names; an unknown or revoked one is `PermissionDenied`.
+fn authenticate(conn: &Connection, raw: Option<&str>) -> CrudResult<Option<ApiToken>> {
+    match raw {
+        Some(raw) => Ok(Some(find_token(conn, raw)?.ok_or_else(|| {
+            CrudError::permission_denied(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Record,
+                CrudVerb::Read,
+                "unknown or revoked API token",
+            )
+        })?)),
+        None => Ok(None),
+    }
+}
+
+fn find_token(conn: &Connection, raw: &str) -> CrudResult<Option<ApiToken>> {
+    let verb = CrudVerb::Read;
+    if !table_exists(conn, "sys_tokens", verb)? {
+        return Ok(None);
+    }
+    conn.query_row(
+        "SELECT id, project, namespace, permission, created_at FROM sys_tokens
+         WHERE token_hash = ?1",
+        params![value_hash(raw)],
+        token_from_row,
+    )
+    .optional()
+    .map_err(|err| sql_error(verb, err))?
+    .transpose()
+}
+
+/// A `sys_tokens` row; the inner error flags a permission this build does not know.
+fn token_from_row(row: &Row<'_>) -> rusqlite::Result<CrudResult<ApiToken>> {
+    let permission: String = row.get(3)?;
+    let token = match Permission::parse(&permission) {
+        Some(permission) => Ok(ApiToken {
+            id: row.get(0)?,
+            project: row.get(1)?,
+            namespace: row.get(2)?,
+            permission,
+            created_at: row.get(4)?,
+        }),
+        None => Err(internal(
+            CrudVerb::Read,
+            anyhow::anyhow!("unknown token permission '{}'", permission),
+        )),
+    };
+    Ok(token)
+}
+
+/// Append an audit row for `addr`, hashing `old` and the value it holds now.
+fn record_audit(
+    conn: &Connection,
+    actor: Option<&str>,
+    command: &str,
+    addr: &Address,
+    old: Option<&str>,
+    now: i64,
+) -> CrudResult<()> {
+    let new = select_value(conn, addr, now)?;
+    conn.execute(
+        "INSERT INTO sys_audit (at, user, command, address, old_hash, new_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            now,
+            actor,
+            command,
+            addr.to_string(),
+            old.map(value_hash),
+            new.as_deref().map(value_hash)
+        ],
+    )
+    .map_err(|err| sql_error(CrudVerb::Create, err))?;
+    Ok(())
+}
+
+fn value_hash(value: &str) -> String {
+    format!("{:x}", md5::compute(value))
+}
+
+/// `prontodb_now()` (the keystore clock) and `prontodb_md5(value)`, used by the oplog triggers.
+fn register_oplog_functions(conn: &Connection, clock: Arc<dyn Clock>) -> CrudResult<()> {
+    let verb = CrudVerb::Create;
+    let flags = FunctionFlags::SQLITE_UTF8;
+    conn.create_scalar_function("prontodb_now", 0, flags, move |_| Ok(clock.now()))
+        .map_err(|err| sql_error(verb, err))?;
+    conn.create_scalar_function(
+        "prontodb_md5",
+        1,
+        flags | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(value_hash(&ctx.get::<String>(0)?)),
+    )
+    .map_err(|err| sql_error(verb, err))
+}
+
+/// TEMP triggers appending to `sys_oplog`. A read that renews a sliding TTL
+/// only moves `expires_at`, so expiry-only updates in sliding namespaces are
+/// not logged unless `updated_at` moves too.
+fn oplog_triggers(actor: Option<&str>) -> String {
+    let user = actor.map_or_else(|| "NULL".to_string(), sql_literal);
+    let address = |row: &str| {
+        format!(
+            "{0}.project || '.' || {0}.namespace || '.' || {0}.key
+             || CASE WHEN {0}.context = '' THEN '' ELSE '__' || {0}.context END",
+            row
+        )
+    };
+    format!(
+        "CREATE TEMP TRIGGER IF NOT EXISTS oplog_insert AFTER INSERT ON main.kv
+         BEGIN
+             INSERT INTO sys_oplog (op, address, value_hash, at, user)
+             VALUES ('set', {new}, prontodb_md5(NEW.value), prontodb_now(), {user});
+         END;
+         CREATE TEMP TRIGGER IF NOT EXISTS oplog_update AFTER UPDATE ON main.kv
+         WHEN NEW.updated_at IS NOT OLD.updated_at OR NEW.value IS NOT OLD.value
+           OR NEW.value_type IS NOT OLD.value_type
+           OR (NEW.expires_at IS NOT OLD.expires_at
+               AND NOT EXISTS (SELECT 1 FROM sys_namespaces
+                               WHERE project = NEW.project AND namespace = NEW.namespace
+                                 AND sliding = 1))
+         BEGIN
+             INSERT INTO sys_oplog (op, address, value_hash, at, user)
+             VALUES ('set', {new}, prontodb_md5(NEW.value), prontodb_now(), {user});
+         END;
+         CREATE TEMP TRIGGER IF NOT EXISTS oplog_delete AFTER DELETE ON main.kv
+         BEGIN
+             INSERT INTO sys_oplog (op, address, value_hash, at, user)
+             VALUES ('del', {old}, NULL, prontodb_now(), {user});
+         END;",
+        new = address("NEW"),
+        old = address("OLD"),
+        user = user
+    )
+}
+
+/// Destination addresses `copy_namespace` is about to write, with their current values.
+fn copy_targets(
+    conn: &Connection,
+    src: &NamespaceScope,
+    dst: &NamespaceScope,
+    now: i64,
+) -> CrudResult<Vec<(Address, Option<String>)>> {
+    let verb = CrudVerb::Create;
+    let mut stmt = conn
+        .prepare(
+            "SELECT key, context FROM kv
+             WHERE project = ?1 AND namespace = ?2
+               AND (?3 IS NULL OR substr(key, 1, length(?3)) = ?3)
+               AND (expires_at IS NULL OR expires_at > ?4)",
+        )
+        .map_err(|err| sql_error(verb, err))?;
+    let rows = stmt
+        .query_map(
+            params![src.project, src.namespace, src.prefix, now],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .map_err(|err| sql_error(verb, err))?;
+    let mut targets = Vec::new();
+    for row in rows {
+        let (key, context) = row.map_err(|err| sql_error(verb, err))?;
+        let context = Some(context).filter(|context| !context.is_empty());
+        let addr = Address::new(dst.project.as_str(), dst.namespace.as_str(), key, context);
+        let old = select_value(conn, &addr, now)?;
+        targets.push((addr, old));
+    }
+    Ok(targets)
+}
+
+/// Add the `ADDED_COLUMNS` an older database lacks.
+fn add_missing_columns(conn: &Connection) -> CrudResult<()> {
+    let verb = CrudVerb::Create;
+    for (table, column, decl) in ADDED_COLUMNS {
+        let present: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+                params![table, column],
+                |row| row.get(0),
+            )
+            .map_err(|err| sql_error(verb, err))?;
+        if !present {
+            conn.execute_batch(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, decl
+            ))
+            .map_err(|err| sql_error(verb, err))?;
+        }
+    }
+    Ok(())
+}
+
+/// A `RETURNING key, context` row as the key name reports show, `key__context` when set.
+fn removed_key(row: &Row<'_>) -> rusqlite::Result<String> {
+    let key: String = row.get(0)?;
+    let context: String = row.get(1)?;
+    Ok(if context.is_empty() {
+        key
+    } else {
+        format!("{}__{}", key, context)
+    })
+}
+
+/// Full row for a live key.
+struct StoredEntry {
+    value: String,
+    created_at: i64,
+    expires_at: Option<i64>,
+    value_type: Option<String>,
+}
+
+fn select_entry(conn: &Connection, addr: &Address, now: i64) -> CrudResult<Option<StoredEntry>> {
+    conn.query_row(
+        "SELECT value, created_at, expires_at, value_type FROM kv
+         WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4
+           AND (expires_at IS NULL OR expires_at > ?5)",
+        params![
+            addr.project,
+            addr.namespace,
+            addr.key,
+            context_column(addr),
+            now
+        ],
+        |row| {
+            Ok(StoredEntry {
+                value: row.get(0)?,
+                created_at: row.get(1)?,
+                expires_at: row.get(2)?,
+                value_type: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|err| sql_error(CrudVerb::Read, err))
+}
+
+/// Write a value; without an explicit `ttl` the namespace's cache TTL (if any) applies.
+fn upsert(
+    conn: &Connection,
+    addr: &Address,
+    value: &str,
+    ttl: Option<u64>,
+    now: i64,
+) -> CrudResult<()> {
+    let ttl = match ttl {
+        Some(ttl) => Some(ttl),
+        None => {
+            cache_settings(conn, &addr.project, &addr.namespace)?.map(|settings| settings.ttl_secs)
+        }
+    };
+    let expires_at = ttl.map(|ttl| now + ttl as i64);
+    conn.execute(
+        "INSERT INTO kv (project, namespace, key, context, value, created_at, updated_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7)
+         ON CONFLICT(project, namespace, key, context) DO UPDATE SET
+             value = excluded.value,
+             updated_at = excluded.updated_at,
+             expires_at = excluded.expires_at,
+             value_type = NULL",
+        params![
+            addr.project,
+            addr.namespace,
+            addr.key,
+            context_column(addr),
+            value,
+            now,
+            expires_at
+        ],
+    )
+    .map_err(|err| sql_error(CrudVerb::Create, err))?;
+    Ok(())
+}
+
+fn cache_settings(
+    conn: &Connection,
+    project: &str,
+    namespace: &str,
+) -> CrudResult<Option<CacheSettings>> {
+    conn.query_row(
+        "SELECT default_ttl, sliding, max_ttl, clamp_ttl FROM sys_namespaces
+         WHERE project = ?1 AND namespace = ?2 AND default_ttl IS NOT NULL",
+        params![project, namespace],
+        |row| cache_settings_from_row(row, 0),
+    )
+    .optional()
+    .map_err(|err| sql_error(CrudVerb::Read, err))
+}
+
+/// Row for `addr` regardless of expiry.
+fn select_full_entry(conn: &Connection, addr: &Address) -> CrudResult<Option<KvEntry>> {
+    conn.query_row(
+        "SELECT project, namespace, key, context, value, created_at, updated_at, expires_at, value_type
+         FROM kv
+         WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4",
+        params![addr.project, addr.namespace, addr.key, context_column(addr)],
+        entry_from_row,
+    )
+    .optional()
+    .map_err(|err| sql_error(CrudVerb::Read, err))
+}
+
+/// Read `project, namespace, key, context, value, created_at, updated_at, expires_at, value_type`.
+fn entry_from_row(row: &Row<'_>) -> rusqlite::Result<KvEntry> {
+    let context: String = row.get(3)?;
+    Ok(KvEntry {
+        address: Address::new(
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            Some(context).filter(|context| !context.is_empty()),
+        ),
+        value: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+        expires_at: row.get(7)?,
+        value_type: row
+            .get::<_, Option<String>>(8)?
+            .as_deref()
+            .and_then(ValueType::parse),
+    })
+}
+
+/// Read `default_ttl, sliding, max_ttl, clamp_ttl` starting at column `first`.
+fn cache_settings_from_row(row: &Row<'_>, first: usize) -> rusqlite::Result<CacheSettings> {
+    Ok(CacheSettings {
+        ttl_secs: row.get::<_, i64>(first)? as u64,
+        sliding: row.get(first + 1)?,
+        max_ttl_secs: row
+            .get::<_, Option<i64>>(first + 2)?
+            .map(|secs| secs as u64),
+        clamp_ttl: row.get(first + 3)?,
+    })
+}
+
+/// `select_value`, except a sliding cache renews the TTL in the same statement.
+///
+/// Keys made permanent with `persist` stay permanent.
+fn read_value(conn: &Connection, addr: &Address, now: i64) -> CrudResult<Option<String>> {
+    let settings = cache_settings(conn, &addr.project, &addr.namespace)?;
+    let ttl_secs = match settings {
+        Some(CacheSettings {
+            ttl_secs,
+            sliding: true,
+            ..
+        }) => ttl_secs,
+        _ => return select_value(conn, addr, now),
+    };
+    // Read-only connections serve sliding caches without extending their TTLs.
+    if conn.is_readonly(DatabaseName::Main).unwrap_or(false) {
+        return select_value(conn, addr, now);
+    }
+
+    conn.query_row(
+        "UPDATE kv SET expires_at = CASE WHEN expires_at IS NULL THEN NULL ELSE ?6 END
+         WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4
+           AND (expires_at IS NULL OR expires_at > ?5)
+         RETURNING value",
+        params![
+            addr.project,
+            addr.namespace,
+            addr.key,
+            context_column(addr),
+            now,
+            now + ttl_secs as i64
+        ],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .map_err(|err| sql_error(CrudVerb::Read, err))
+}
+
+fn select_value(conn: &Connection, addr: &Address, now: i64) -> CrudResult<Option<String>> {
+    conn.query_row(
+        "SELECT value FROM kv
+         WHERE project = ?1 AND namespace = ?2 AND key = ?3 AND context = ?4
+           AND (expires_at IS NULL OR expires_at > ?5)",
+        params![
+            addr.project,
+            addr.namespace,
+            addr.key,
+            context_column(addr),
+            now
+        ],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .map_err(|err| sql_error(CrudVerb::Read, err))
+}
+
+/// Canonical `.a.b` form of a JSON path; segments are limited to `[A-Za-z0-9_]`
+/// because the path is inlined into SQL (index expressions cannot use bound parameters).
+fn json_path(path: &str, verb: CrudVerb) -> CrudResult<String> {
+    let segments: Vec<&str> = path.strip_prefix('.').unwrap_or(path).split('.').collect();
+    let valid = segments.iter().all(|segment| {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+    });
+    if !valid {
+        return Err(invalid_input(
+            verb,
+            format!("invalid JSON path '{}' (expected .field[.field...])", path),
+        ));
+    }
+    Ok(format!(".{}", segments.join(".")))
+}
+
+/// `json_extract(value, '$.a.b')` for a canonical `.a.b` path.
+fn json_expr(path: &str) -> String {
+    format!("json_extract(value, '${}')", path)
+}
+
+/// `kind` with its JSON path (if any) in canonical form, so `status` and `.status` agree.
+fn canonical_index(kind: &IndexKind, verb: CrudVerb) -> CrudResult<IndexKind> {
+    Ok(match kind {
+        IndexKind::JsonPath(path) => IndexKind::JsonPath(json_path(path, verb)?),
+        other => other.clone(),
+    })
+}
+
+/// Partial-index predicate for one namespace; non-JSON values are left out.
+fn json_index_scope(project: &str, namespace: &str) -> String {
+    format!(
+        "project = {} AND namespace = {} AND json_valid(value)",
+        sql_literal(project),
+        sql_literal(namespace)
+    )
+}
+
+fn json_index_name(project: &str, namespace: &str, path: &str) -> String {
+    let digest = md5::compute(format!("{}\0{}\0{}", project, namespace, path));
+    format!("kv_json_{:x}", digest)
+}
+
+fn sql_literal(raw: &str) -> String {
+    format!("'{}'", raw.replace('\'', "''"))
+}
+
+/// Bind a `--where` value with the type `json_extract` would return for it.
+fn json_scalar(raw: &str) -> rusqlite::types::Value {
+    use rusqlite::types::Value;
+    match raw {
+        "true" => Value::Integer(1),
+        "false" => Value::Integer(0),
+        _ => match raw.parse::<i64>() {
+            Ok(number) => Value::Integer(number),
+            Err(_) => match raw.parse::<f64>() {
+                Ok(number) if number.is_finite() => Value::Real(number),
+                _ => Value::Text(raw.to_string()),
+            },
+        },
+    }
+}
+
+fn context_column(addr: &Address) -> &str {
+    addr.context.as_deref().unwrap_or("")
+}
+
+fn sql_error(verb: CrudVerb, err: rusqlite::Error) -> CrudError {
+    internal(verb, anyhow::Error::new(err))
+}
+
+fn invalid_input<S: Into<String>>(verb: CrudVerb, message: S) -> CrudError {
+    CrudError::invalid_input(CrudDomain::Sqlite, CrudObjectKind::Record, verb, message)
+}
+
+fn not_found<S: Into<String>>(verb: CrudVerb, message: S) -> CrudError {
+    CrudError::not_found(CrudDomain::Sqlite, CrudObjectKind::Record, verb, message)
+}
+
+fn conflict<S: Into<String>>(verb: CrudVerb, message: S) -> CrudError {
+    CrudError::conflict(CrudDomain::Sqlite, CrudObjectKind::Record, verb, message)
+}
+
+fn internal(verb: CrudVerb, source: anyhow::Error) -> CrudError {
+    CrudError::internal(CrudDomain::Sqlite, CrudObjectKind::Record, verb, source)
+}
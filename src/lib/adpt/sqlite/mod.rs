@@ -1,11 +1,28 @@
 //! SQLite adapters implementing the core CRUD traits.
 
 mod base;
+mod cipher;
+mod clock;
+mod keystore;
 mod record;
 mod table;
+mod toggles;
 pub mod utils;
 
 pub use base::SqliteBaseAdapter;
+pub use cipher::{
+    is_sealed, open_sealed_file, read_key_file, seal_file, EncryptedNamespace, NamespaceCipher,
+    KEY_FILE_ENV,
+};
+pub use clock::{Clock, FixedClock, SystemClock, PRONTO_NOW_ENV};
+pub use keystore::{
+    AccessPolicy, AccessRule, ApiToken, AuditEntry, AuditQuery, CacheSettings, CacheSummary,
+    EntryOrder, ExpiryHook, ImportReport, ImportStrategy, IndexInfo, IndexKind, JsonFilter,
+    KvEntry, OplogEntry, OplogSince, Permission, RetentionPolicy, SearchHit, SecretMarks,
+    SetCondition, SortField, SqliteKeystore, SweepReport, SyncReport, ValidationMode, ValueType,
+    ACL_ALL_NAMESPACES, API_TOKEN_PREFIX, SECRET_ALL_KEYS,
+};
 pub use record::SqliteRecordAdapter;
 pub use table::SqliteTableAdapter;
+pub use toggles::{set_capability_enabled, CapabilityToggles};
 pub use utils::{SqliteConnectionConfig, SqlitePathResolver};
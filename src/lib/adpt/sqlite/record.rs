@@ -3,6 +3,7 @@ use crate::lib::core::crud::{
     CrudResource, CrudResult, CrudVerb,
 };
 
+use super::toggles::ensure_capability;
 use super::utils::SqliteConnectionConfig;
 
 /// Adapter for row-level operations within a SQLite table.
@@ -46,6 +47,10 @@ impl<H: CrudHooks> CrudResource for SqliteRecordAdapter<H> {
         CapabilityMap::new()
     }
 
+    fn ensure_capability(&self, verb: CrudVerb, _ctx: &CrudContext) -> CrudResult<()> {
+        ensure_capability(&self.config, &self.object_kind(), verb)
+    }
+
     fn create(&self, _ctx: CrudContext) -> CrudResult<CrudOutcome> {
         Err(CrudError::unsupported(
             self.domain(),
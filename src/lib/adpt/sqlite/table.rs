@@ -15,6 +15,7 @@ use crate::lib::core::crud::{
     CrudOutcome, CrudResource, CrudResult, CrudVerb, MetadataValue,
 };
 
+use super::toggles::{ensure_capability, CapabilityToggles};
 use super::utils::{SqliteConnectionConfig, SqlitePathResolver, SqliteRow, SqliteValue};
 
 /// Adapter for SQLite table operations (schema + row group level).
@@ -354,7 +355,13 @@ impl<H: CrudHooks> CrudResource for SqliteTableAdapter<H> {
         map.allow(CrudObjectKind::Table, CrudVerb::Find);
         map.allow(CrudObjectKind::Table, CrudVerb::Backup);
         map.allow(CrudObjectKind::Table, CrudVerb::Restore);
-        map
+        CapabilityToggles::load(&self.config)
+            .map(|toggles| toggles.apply(map.clone()))
+            .unwrap_or(map)
+    }
+
+    fn ensure_capability(&self, verb: CrudVerb, ctx: &CrudContext) -> CrudResult<()> {
+        ensure_capability(&self.config_from_ctx(ctx), &self.object_kind(), verb)
     }
 
     fn create(&self, ctx: CrudContext) -> CrudResult<CrudOutcome> {
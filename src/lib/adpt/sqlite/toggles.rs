@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+
+use hub::error_ext::anyhow;
+use rusqlite::{params, Connection};
+
+use crate::lib::core::crud::{
+    CapabilityMap, CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb,
+};
+
+use super::clock::{Clock, SystemClock};
+use super::utils::{SqliteConnectionConfig, SqlitePathResolver};
+
+const TOGGLES_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS sys_capability_toggles (
+        object TEXT NOT NULL,
+        verb TEXT NOT NULL,
+        updated_at INTEGER NOT NULL,
+        PRIMARY KEY (object, verb)
+    );
+";
+
+/// Adapter verbs switched off with `admin capability disable`, stored in the database they guard.
+///
+/// Every verb an adapter supports is enabled until disabled here. A
+/// database without the table (or not created yet) has no toggles.
+#[derive(Clone, Debug, Default)]
+pub struct CapabilityToggles {
+    disabled: BTreeSet<(CrudObjectKind, CrudVerb)>,
+}
+
+impl CapabilityToggles {
+    pub fn load(config: &SqliteConnectionConfig) -> CrudResult<Self> {
+        let verb = CrudVerb::Read;
+        let path = config.database_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let readonly = config.clone().with_read_only(true);
+        let conn = Connection::open_with_flags(path, SqlitePathResolver::flags_for(&readonly))
+            .map_err(|err| internal(verb, anyhow::Error::new(err)))?;
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master
+                 WHERE type = 'table' AND name = 'sys_capability_toggles')",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|err| internal(verb, anyhow::Error::new(err)))?;
+        if !exists {
+            return Ok(Self::default());
+        }
+
+        let mut stmt = conn
+            .prepare("SELECT object, verb FROM sys_capability_toggles")
+            .map_err(|err| internal(verb, anyhow::Error::new(err)))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|err| internal(verb, anyhow::Error::new(err)))?;
+        let mut disabled = BTreeSet::new();
+        for row in rows {
+            let (object, toggled) = row.map_err(|err| internal(verb, anyhow::Error::new(err)))?;
+            // Rows naming kinds or verbs this build does not know cannot gate anything.
+            if let (Ok(object), Ok(toggled)) = (object.parse::<CrudObjectKind>(), toggled.parse()) {
+                disabled.insert((object, toggled));
+            }
+        }
+        Ok(Self { disabled })
+    }
+
+    pub fn disabled(&self) -> impl Iterator<Item = &(CrudObjectKind, CrudVerb)> {
+        self.disabled.iter()
+    }
+
+    pub fn is_enabled(&self, object: &CrudObjectKind, verb: CrudVerb) -> bool {
+        !self.disabled.contains(&(object.clone(), verb))
+    }
+
+    /// `map` without the disabled verbs.
+    pub fn apply(&self, mut map: CapabilityMap) -> CapabilityMap {
+        for (object, verb) in &self.disabled {
+            map.deny(object, *verb);
+        }
+        map
+    }
+
+    /// `PermissionDenied` when `verb` on `object` is disabled.
+    pub fn check(&self, object: &CrudObjectKind, verb: CrudVerb) -> CrudResult<()> {
+        if self.is_enabled(object, verb) {
+            return Ok(());
+        }
+        Err(CrudError::permission_denied(
+            CrudDomain::Sqlite,
+            object.clone(),
+            verb,
+            format!(
+                "{} {} is disabled (prontodb admin capability enable {} {})",
+                object, verb, object, verb
+            ),
+        ))
+    }
+}
+
+/// Persist a toggle for `verb` on `object`; enabling removes the override.
+pub fn set_capability_enabled(
+    config: &SqliteConnectionConfig,
+    object: &CrudObjectKind,
+    verb: CrudVerb,
+    enabled: bool,
+) -> CrudResult<()> {
+    let write = CrudVerb::Update;
+    let conn = Connection::open_with_flags(
+        config.database_path(),
+        SqlitePathResolver::flags_for(config),
+    )
+    .map_err(|err| internal(write, anyhow::Error::new(err)))?;
+    conn.execute_batch(TOGGLES_SQL)
+        .map_err(|err| internal(write, anyhow::Error::new(err)))?;
+    let result = if enabled {
+        conn.execute(
+            "DELETE FROM sys_capability_toggles WHERE object = ?1 AND verb = ?2",
+            params![object.as_str(), verb.as_str()],
+        )
+    } else {
+        conn.execute(
+            "INSERT INTO sys_capability_toggles (object, verb, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(object, verb) DO UPDATE SET updated_at = excluded.updated_at",
+            params![object.as_str(), verb.as_str(), SystemClock.now()],
+        )
+    };
+    result
+        .map(|_| ())
+        .map_err(|err| internal(write, anyhow::Error::new(err)))
+}
+
+/// The toggle check adapters run before dispatching `verb`.
+///
+/// A file that is not a readable database holds no toggles either, so the
+/// adapter itself reports the failure (and `restore` can still replace it).
+pub(crate) fn ensure_capability(
+    config: &SqliteConnectionConfig,
+    object: &CrudObjectKind,
+    verb: CrudVerb,
+) -> CrudResult<()> {
+    CapabilityToggles::load(config)
+        .unwrap_or_default()
+        .check(object, verb)
+}
+
+fn internal(verb: CrudVerb, source: anyhow::Error) -> CrudError {
+    CrudError::internal(CrudDomain::Sqlite, CrudObjectKind::Base, verb, source)
+}
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use hub::data_ext::base64::{engine::general_purpose, Engine as _};
 use hub::error_ext::anyhow;
@@ -7,14 +8,47 @@ use hub::serde::{Deserialize, Serialize};
 use rusqlite::types::{Value as SqlValue, ValueRef};
 use rusqlite::OpenFlags;
 
+use crate::lib::addr::{AddressDefaults, AddressLayout};
 use crate::lib::core::crud::CrudDomain;
 
+use super::keystore::ValidationMode;
+
 /// Configuration for establishing SQLite connections for adapters.
 #[derive(Clone, Debug)]
 pub struct SqliteConnectionConfig {
     pub database_path: PathBuf,
     pub read_only: bool,
     pub journal_wal: bool,
+    /// Project/namespace for partial addresses (set from the active cursor).
+    pub address_defaults: AddressDefaults,
+    /// How addresses of four or more segments are split; `None` reads the
+    /// layout recorded in the database (`fixed` when none is).
+    pub address_layout: Option<AddressLayout>,
+    /// Organization context every address and scope is placed under: project
+    /// `app` is stored as `<meta>.app` (`--meta`).
+    pub meta_context: Option<String>,
+    /// Whether `set` may create namespaces; `None` uses the database's recorded mode.
+    pub validation_mode: Option<ValidationMode>,
+    /// Let `set` write to namespaces a strict database does not know yet (`set --create`).
+    pub create_namespaces: bool,
+    /// Let writes and deletes reach reserved namespaces (`sys.*`, `pipe.cache`; `--system`).
+    pub system_access: bool,
+    /// User namespace ACLs are checked for when this process owns the database;
+    /// otherwise (and when `None`) they see the OS user. See `AccessPolicy`.
+    pub user: Option<String>,
+    /// Key file unlocking encrypted namespaces.
+    pub key_file: Option<PathBuf>,
+    /// Raw API token of a remote caller; opening fails unless it is valid,
+    /// and access is then limited to the token's scope.
+    pub token: Option<String>,
+    /// How long a connection waits on another writer's lock before failing busy;
+    /// `None` fails at once.
+    pub busy_timeout: Option<Duration>,
+    /// Create the database file when it does not exist; otherwise opening it is `NotFound`.
+    pub create_if_missing: bool,
+    /// Let `api` calls borrow an idle connection to the same database instead of
+    /// opening one each time (see `api::close_idle_connections`).
+    pub reuse_connections: bool,
 }
 
 impl SqliteConnectionConfig {
@@ -23,6 +57,18 @@ impl SqliteConnectionConfig {
             database_path: database_path.as_ref().to_path_buf(),
             read_only: false,
             journal_wal: true,
+            address_defaults: AddressDefaults::default(),
+            address_layout: None,
+            meta_context: None,
+            validation_mode: None,
+            create_namespaces: false,
+            system_access: false,
+            user: None,
+            key_file: None,
+            token: None,
+            busy_timeout: None,
+            create_if_missing: true,
+            reuse_connections: false,
         }
     }
 
@@ -36,6 +82,66 @@ impl SqliteConnectionConfig {
         self
     }
 
+    pub fn with_address_defaults(mut self, defaults: AddressDefaults) -> Self {
+        self.address_defaults = defaults;
+        self
+    }
+
+    pub fn with_address_layout(mut self, layout: Option<AddressLayout>) -> Self {
+        self.address_layout = layout;
+        self
+    }
+
+    pub fn with_meta_context<S: Into<String>>(mut self, meta: Option<S>) -> Self {
+        self.meta_context = meta.map(Into::into);
+        self
+    }
+
+    pub fn with_validation_mode(mut self, mode: Option<ValidationMode>) -> Self {
+        self.validation_mode = mode;
+        self
+    }
+
+    pub fn with_namespace_creation(mut self, create: bool) -> Self {
+        self.create_namespaces = create;
+        self
+    }
+
+    pub fn with_system_access(mut self, system: bool) -> Self {
+        self.system_access = system;
+        self
+    }
+
+    pub fn with_user<S: Into<String>>(mut self, user: Option<S>) -> Self {
+        self.user = user.map(Into::into);
+        self
+    }
+
+    pub fn with_key_file<P: AsRef<Path>>(mut self, key_file: Option<P>) -> Self {
+        self.key_file = key_file.map(|path| path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn with_token<S: Into<String>>(mut self, token: Option<S>) -> Self {
+        self.token = token.map(Into::into);
+        self
+    }
+
+    pub fn with_busy_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.busy_timeout = timeout;
+        self
+    }
+
+    pub fn with_create_if_missing(mut self, create: bool) -> Self {
+        self.create_if_missing = create;
+        self
+    }
+
+    pub fn with_connection_reuse(mut self, reuse: bool) -> Self {
+        self.reuse_connections = reuse;
+        self
+    }
+
     pub fn with_database_path<P: AsRef<Path>>(mut self, path: P) -> Self {
         self.database_path = path.as_ref().to_path_buf();
         self
@@ -68,7 +174,9 @@ impl SqlitePathResolver {
             flags.insert(OpenFlags::SQLITE_OPEN_READ_ONLY);
         } else {
             flags.insert(OpenFlags::SQLITE_OPEN_READ_WRITE);
-            flags.insert(OpenFlags::SQLITE_OPEN_CREATE);
+            if config.create_if_missing {
+                flags.insert(OpenFlags::SQLITE_OPEN_CREATE);
+            }
         }
         flags
     }
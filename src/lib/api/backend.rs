@@ -0,0 +1,86 @@
+use crate::lib::addr::{Address, AddressDefaults, AddressLayout, NamespaceScope, DEFAULT_NS_DELIM};
+use crate::lib::adpt::sqlite::KvEntry;
+use crate::lib::adpt::KvBackend;
+use crate::lib::core::crud::{CrudResult, CrudVerb};
+
+use super::error::{coded_invalid_input, ErrorCode};
+
+/// `ProntoClient`'s string addresses over any `KvBackend`, such as `MemoryKeystore`.
+///
+/// Addresses and scopes parse as they do for the SQLite-backed API (defaults
+/// filled in, `__context` split off, the layout applied) and fail with the
+/// same `ErrorCode`s. Namespace policies (TTL caches, ACLs, secrets,
+/// encryption) live in SQLite and are not applied, so any key may carry a TTL.
+///
+/// ```
+/// use prontodb::lib::adpt::MemoryKeystore;
+/// use prontodb::lib::api::BackendClient;
+///
+/// let client = BackendClient::new(MemoryKeystore::new());
+/// client.set("app.config.port", "8080", None)?;
+/// assert_eq!(client.get("app.config.port")?.as_deref(), Some("8080"));
+/// # Ok::<(), prontodb::lib::core::crud::CrudError>(())
+/// ```
+pub struct BackendClient<B> {
+    backend: B,
+    defaults: AddressDefaults,
+    layout: AddressLayout,
+}
+
+impl<B: KvBackend> BackendClient<B> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            defaults: AddressDefaults::default(),
+            layout: AddressLayout::default(),
+        }
+    }
+
+    /// Project and namespace for addresses that leave them out (`default.default` otherwise).
+    pub fn with_defaults(mut self, defaults: AddressDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: AddressLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The live value, or `None` when the key is missing or expired.
+    pub fn get(&self, address: &str) -> CrudResult<Option<String>> {
+        self.backend.get(&self.parse(address, CrudVerb::Read)?)
+    }
+
+    /// Store `value`, expiring after `ttl_secs` when given.
+    pub fn set(&self, address: &str, value: &str, ttl_secs: Option<u64>) -> CrudResult<()> {
+        let addr = self.parse(address, CrudVerb::Create)?;
+        self.backend.set(&addr, value, ttl_secs)
+    }
+
+    /// Returns whether a live key was removed.
+    pub fn delete(&self, address: &str) -> CrudResult<bool> {
+        self.backend.delete(&self.parse(address, CrudVerb::Delete)?)
+    }
+
+    pub fn exists(&self, address: &str) -> CrudResult<bool> {
+        self.backend.exists(&self.parse(address, CrudVerb::Find)?)
+    }
+
+    /// Live entries under `project.namespace[.prefix]`, ordered by key then context.
+    pub fn entries(&self, scope: &str) -> CrudResult<Vec<KvEntry>> {
+        let verb = CrudVerb::List;
+        let scope = NamespaceScope::parse(scope)
+            .map_err(|err| coded_invalid_input(ErrorCode::InvalidScope, verb, err.to_string()))?;
+        self.backend.list(&scope)
+    }
+
+    fn parse(&self, address: &str, verb: CrudVerb) -> CrudResult<Address> {
+        Address::parse_in_layout(address, DEFAULT_NS_DELIM, &self.defaults, self.layout)
+            .map_err(|err| coded_invalid_input(ErrorCode::InvalidAddress, verb, err.to_string()))
+    }
+}
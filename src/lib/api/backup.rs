@@ -0,0 +1,1351 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hub::data_ext::serde_json;
+use hub::error_ext::anyhow;
+use hub::serde::{Deserialize, Serialize};
+
+use crate::lib::addr::Address;
+use crate::lib::adpt::sqlite::{
+    is_sealed, open_sealed_file, read_key_file, seal_file, FixedClock, ImportReport,
+    ImportStrategy, KvEntry, SqliteConnectionConfig, SqliteKeystore,
+};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+use crate::lib::core::helpers::{format_timestamp, parse_timestamp};
+
+use super::error::{coded_invalid_input, ErrorCode};
+use super::kv::{export_entries, import_values};
+use super::pool::close_idle_connections;
+
+/// Entry holding the database snapshot inside a backup archive.
+const SNAPSHOT_ENTRY: &str = "prontodb.db";
+
+/// Entry holding the `Manifest`, written before the snapshot so it is cheap to read.
+const MANIFEST_ENTRY: &str = "manifest.json";
+
+/// Entry holding the `ScopedEntry` list of a scoped archive, in place of the snapshot.
+const SCOPED_ENTRY: &str = "entries.json";
+
+const ARCHIVE_PREFIX: &str = "prontodb_";
+const ARCHIVE_SUFFIX: &str = ".tar.gz";
+/// Suffix of archives written with `BackupOptions::encrypt`.
+const SEALED_ARCHIVE_SUFFIX: &str = ".tar.gz.enc";
+
+/// Archives of one database per day before `create_backup` refuses (`_n01` to `_n99`).
+const MAX_DAILY_BACKUPS: u32 = 99;
+
+/// A backup archive on disk.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BackupArchive {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// `None` for archives without a manifest (taken before incremental backups), and
+    /// for encrypted ones when listed.
+    pub kind: Option<BackupKind>,
+    pub encrypted: bool,
+}
+
+/// How `create_backup` writes an archive.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BackupOptions {
+    /// Keep only what changed since the newest archive of the database in the directory.
+    pub incremental: bool,
+    /// Encrypt with the key file of the connection (`--key-file` / `PRONTO_KEY_FILE`).
+    pub encrypt: bool,
+    /// Archive only the keys of this `project` or `project.namespace`, for `merge_backup`.
+    pub scope: Option<String>,
+}
+
+/// What `verify_backup` found in an archive that checked out.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BackupVerification {
+    /// `None` for archives without a manifest (taken before incremental backups).
+    pub kind: Option<BackupKind>,
+    pub encrypted: bool,
+    /// Archives checked: the one given and the chain below it.
+    pub archives: usize,
+    /// Live keys the archive restores to, as of when it was taken.
+    pub keys: usize,
+    /// Whether `keys` was checked against a manifest; older archives have none.
+    pub matched_manifest: bool,
+}
+
+/// How one key differs between a backup and the live database.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RestoreChange {
+    /// Only in the backup.
+    Added,
+    /// In both, with a different value, type or expiry.
+    Overwritten,
+    /// Only in the database.
+    Removed,
+}
+
+impl RestoreChange {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RestoreChange::Added => "added",
+            RestoreChange::Overwritten => "overwritten",
+            RestoreChange::Removed => "removed",
+        }
+    }
+}
+
+/// What `preview_restore` found: the keys a restore would change, in address order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RestorePreview {
+    pub changes: Vec<(Address, RestoreChange)>,
+    /// Live keys the backup holds exactly as they are.
+    pub unchanged: usize,
+}
+
+/// Which archives `prune_backups` keeps, per database, on top of the bases kept incrementals need.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BackupRetention {
+    /// The newest archive of each of this many most recent days that have one.
+    pub keep_daily: usize,
+    /// The newest archive of each of this many most recent weeks (Monday to Sunday) that have one.
+    pub keep_weekly: usize,
+}
+
+/// What `prune_backups` did, each list oldest name first.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PruneReport {
+    pub kept: Vec<BackupArchive>,
+    pub removed: Vec<BackupArchive>,
+}
+
+/// Whether an archive holds the whole database, only what changed since the one below it,
+/// or the keys of one project or namespace.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "hub::serde", rename_all = "lowercase")]
+pub enum BackupKind {
+    Full,
+    Incremental,
+    Scoped,
+}
+
+impl BackupKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BackupKind::Full => "full",
+            BackupKind::Incremental => "incremental",
+            BackupKind::Scoped => "scoped",
+        }
+    }
+}
+
+/// What an archive holds, stored in it as `manifest.json`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "hub::serde")]
+struct Manifest {
+    kind: BackupKind,
+    /// Store clock just before the snapshot; the next incremental keeps rows updated since.
+    taken_at: i64,
+    /// File name of the archive an incremental layers over, in the same directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<String>,
+    /// Every live key in the snapshot, so the next incremental can tell deleted and moved keys.
+    keys: Vec<ManifestKey>,
+    /// Keys of the base that are gone (incrementals only).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    removed: Vec<ManifestKey>,
+    /// `project` or `project.namespace` of a scoped archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "hub::serde")]
+struct ManifestKey {
+    project: String,
+    namespace: String,
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    context: Option<String>,
+}
+
+/// One key of a scoped archive, its value in the clear so any database can take it.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "hub::serde")]
+struct ScopedEntry {
+    #[serde(flatten)]
+    address: ManifestKey,
+    value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<i64>,
+}
+
+impl From<&Address> for ManifestKey {
+    fn from(addr: &Address) -> Self {
+        ManifestKey {
+            project: addr.project.clone(),
+            namespace: addr.namespace.clone(),
+            key: addr.key.clone(),
+            context: addr.context.clone(),
+        }
+    }
+}
+
+impl ManifestKey {
+    fn address(&self) -> Address {
+        Address {
+            project: self.project.clone(),
+            namespace: self.namespace.clone(),
+            key: self.key.clone(),
+            context: self.context.clone(),
+        }
+    }
+}
+
+/// `backups/` beside the database file, where `backup` writes and lists by default.
+pub fn default_backup_dir(config: &SqliteConnectionConfig) -> PathBuf {
+    database_dir(config).join("backups")
+}
+
+/// Snapshot the database into `dir/prontodb_<name>_<YYYYMMDD>.tar.gz` (`_n01`, ... for
+/// later backups that day).
+///
+/// The snapshot comes from SQLite (`VACUUM INTO`), so it is consistent while
+/// other processes write; the archive is written in-process and renamed into
+/// place only once complete.
+///
+/// `options.incremental` keeps only kv rows changed since the newest archive
+/// of this database in `dir`, which the new one layers over. A row counts as
+/// changed when its `updated_at` is not older than the base's snapshot, or
+/// its address is not among the base's keys (new and moved keys); keys gone
+/// since are listed for deletion. The `sys_*` tables (settings, ACLs,
+/// tokens, encryption, audit) are carried whole.
+///
+/// `options.encrypt` seals the archive (`.tar.gz.enc`) with a key derived
+/// from the key file; the plaintext is staged beside the database, never in
+/// `dir`.
+///
+/// `options.scope` writes `prontodb_<name>_<scope>_<YYYYMMDD>.tar.gz` with
+/// the keys of that project or namespace the caller may read, values
+/// decrypted, instead of a snapshot; `merge_backup` loads it into any
+/// database. Scoped archives are always whole.
+pub fn create_backup(
+    config: &SqliteConnectionConfig,
+    dir: &Path,
+    options: BackupOptions,
+) -> CrudResult<BackupArchive> {
+    let verb = CrudVerb::Backup;
+    if options.incremental && options.scope.is_some() {
+        return Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            "scoped backups are always whole; drop --incremental",
+        ));
+    }
+    let secret = if options.encrypt {
+        Some(backup_secret(
+            config,
+            verb,
+            "backup --encrypt needs a key; pass --key-file or set PRONTO_KEY_FILE".to_string(),
+        )?)
+    } else {
+        None
+    };
+    let base = if options.incremental {
+        Some(newest_base(config, dir)?)
+    } else {
+        None
+    };
+    write_backup(
+        config,
+        dir,
+        base,
+        options.scope.as_deref(),
+        secret.as_deref(),
+    )
+}
+
+/// The newest archive of this database in `dir` with its manifest, for an incremental.
+fn newest_base(config: &SqliteConnectionConfig, dir: &Path) -> CrudResult<(String, Manifest)> {
+    let verb = CrudVerb::Backup;
+    let prefix = archive_prefix(config);
+    let newest = list_backups(dir)?
+        .into_iter()
+        .filter(|archive| {
+            file_name(&archive.path)
+                .strip_prefix(&prefix)
+                .is_some_and(|rest| rest.starts_with(|ch: char| ch.is_ascii_digit()))
+        })
+        .last()
+        .ok_or_else(|| {
+            CrudError::invalid_input(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Base,
+                verb,
+                format!(
+                    "no earlier backup of {} in {}; take a full backup first",
+                    config.database_path().display(),
+                    dir.display()
+                ),
+            )
+        })?;
+    let plain = plain_archive(config, &newest.path, verb)?;
+    let manifest = read_manifest(&plain.path, verb)?.ok_or_else(|| {
+        CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!(
+                "{} has no manifest to build on; take a full backup first",
+                newest.path.display()
+            ),
+        )
+    })?;
+    Ok((file_name(&newest.path), manifest))
+}
+
+/// Replace the database with the snapshot in `archive`.
+///
+/// Encrypted archives are decrypted beside the database with the key file of
+/// `config`. An incremental archive is restored by unpacking the full backup
+/// at the bottom of its chain and laying each incremental over it in order. The
+/// result is staged next to the database, checked to be a keystore, then
+/// renamed over the database file (dropping its `-wal` and `-shm` files).
+/// Idle pooled connections of this process are closed first; other processes
+/// that already have the database open keep reading the old file until they
+/// reopen it.
+pub fn restore_backup(config: &SqliteConnectionConfig, archive: &Path) -> CrudResult<()> {
+    let verb = CrudVerb::Restore;
+    let target = config.database_path();
+    let staged = sibling(target, ".restore");
+    let layer = sibling(target, ".restore-layer");
+    for leftover in [&staged, &layer] {
+        let _ = fs::remove_file(leftover);
+    }
+
+    let restored = backup_chain(config, archive, verb)
+        .and_then(|chain| stage_chain(config, &chain, &staged, &layer, verb))
+        .and_then(|()| {
+            close_idle_connections(Some(target));
+            for suffix in ["-wal", "-shm"] {
+                match fs::remove_file(sibling(target, suffix)) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                        return Err(io_error(verb, err))
+                    }
+                    _ => {}
+                }
+            }
+            fs::rename(&staged, target).map_err(|err| io_error(verb, err))
+        });
+    let _ = fs::remove_file(&layer);
+    if restored.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    restored
+}
+
+/// Load the keys of a scoped archive into the database, leaving every other key alone.
+///
+/// Values are written through the normal import path, so write ACLs apply
+/// and encrypted namespaces of this database seal them with its own keys.
+/// Keys keep the time they had left when the archive was taken; those that
+/// have expired since are left out. `strategy` decides what happens to keys
+/// the database already holds.
+pub fn merge_backup(
+    config: &SqliteConnectionConfig,
+    archive: &Path,
+    strategy: ImportStrategy,
+) -> CrudResult<ImportReport> {
+    let verb = CrudVerb::Restore;
+    let plain = plain_archive(config, archive, verb)?;
+    match read_manifest(&plain.path, verb)? {
+        Some(manifest) if manifest.kind == BackupKind::Scoped => {}
+        _ => {
+            return Err(CrudError::invalid_input(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Base,
+                verb,
+                format!(
+                    "{} is not a scoped backup; restore it with backup --restore",
+                    archive.display()
+                ),
+            ))
+        }
+    }
+    let entries = read_scoped_entries(&plain.path, archive, verb)?;
+    let now = SqliteKeystore::open(config)?.now();
+    let records: Vec<(String, String, Option<u64>)> = entries
+        .into_iter()
+        .filter_map(|entry| {
+            let ttl = match entry.expires_at {
+                None => None,
+                Some(at) if at > now => Some((at - now) as u64),
+                Some(_) => return None,
+            };
+            Some((entry.address.address().to_string(), entry.value, ttl))
+        })
+        .collect();
+    import_values(config, &records, strategy)
+}
+
+/// Compare the live keys `restore_backup` would leave with those of the database, without
+/// touching it.
+///
+/// The chain is staged beside the database as for a restore and both sides
+/// are read as of now; a missing database counts as empty.
+pub fn preview_restore(
+    config: &SqliteConnectionConfig,
+    archive: &Path,
+) -> CrudResult<RestorePreview> {
+    let verb = CrudVerb::Restore;
+    let target = config.database_path();
+    let staged = sibling(target, ".preview");
+    let layer = sibling(target, ".preview-layer");
+    for leftover in [&staged, &layer] {
+        let _ = fs::remove_file(leftover);
+    }
+
+    let previewed = backup_chain(config, archive, verb)
+        .and_then(|chain| stage_chain(config, &chain, &staged, &layer, verb))
+        .and_then(|()| {
+            let restored = SqliteKeystore::open(
+                &config
+                    .clone()
+                    .with_database_path(&staged)
+                    .with_read_only(true),
+            )?
+            .entries(None, None)?;
+            let live = if target.is_file() {
+                SqliteKeystore::open(config)?.entries(None, None)?
+            } else {
+                Vec::new()
+            };
+            Ok(diff_entries(restored, live))
+        });
+    for leftover in [&staged, &layer] {
+        let _ = fs::remove_file(leftover);
+    }
+    previewed
+}
+
+/// Check that `archive` restores cleanly, without touching the database.
+///
+/// Every archive of its chain is decrypted if sealed and decompressed to the
+/// end, so gzip checksums are checked. The chain is then staged beside the
+/// database as `restore_backup` would, opened read-only, put through
+/// `PRAGMA integrity_check`, and its live keys as of the backup's time
+/// counted against the key list of its manifest. A scoped archive has no
+/// snapshot; its entries are counted against the manifest instead.
+pub fn verify_backup(
+    config: &SqliteConnectionConfig,
+    archive: &Path,
+) -> CrudResult<BackupVerification> {
+    let verb = CrudVerb::Restore;
+    let target = config.database_path();
+    let staged = sibling(target, ".verify");
+    let layer = sibling(target, ".verify-layer");
+    for leftover in [&staged, &layer] {
+        let _ = fs::remove_file(leftover);
+    }
+
+    let verified = backup_chain(config, archive, verb).and_then(|chain| {
+        for link in &chain {
+            check_compression(&link.plain.path, &link.path, verb)?;
+        }
+        if let Some(manifest) = chain.last().and_then(|link| link.manifest.as_ref()) {
+            if manifest.kind == BackupKind::Scoped {
+                return check_scoped(archive, &chain[0], manifest, verb);
+            }
+        }
+        stage_chain(config, &chain, &staged, &layer, verb)?;
+        check_staged(config, archive, &chain, &staged, verb)
+    });
+    for leftover in [&staged, &layer] {
+        let _ = fs::remove_file(leftover);
+    }
+    verified
+}
+
+/// Copy the live database to `target`, or `<name>-<YYYYMMDD-HHMMSS>.<ext>` beside it, with
+/// the SQLite online backup API.
+///
+/// The copy reads one point in time while other processes keep writing, so
+/// it is never torn the way copying the file can be. It is written to
+/// `<target>.partial` and renamed once complete; an existing `target` is
+/// refused.
+pub fn snapshot_database(
+    config: &SqliteConnectionConfig,
+    target: Option<&Path>,
+) -> CrudResult<PathBuf> {
+    let verb = CrudVerb::Backup;
+    let source = config.database_path();
+    if !source.is_file() {
+        return Err(CrudError::not_found(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("no database at {}", source.display()),
+        ));
+    }
+    let store = SqliteKeystore::open(config)?;
+    let target = match target {
+        Some(target) => target.to_path_buf(),
+        None => {
+            let stamp: String = format_timestamp(store.now())
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect();
+            let stem = source
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "prontodb".to_string());
+            let extension = source
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "sqlite".to_string());
+            database_dir(config).join(format!(
+                "{}-{}-{}.{}",
+                stem,
+                &stamp[..8],
+                &stamp[8..14],
+                extension
+            ))
+        }
+    };
+    if target.exists() {
+        return Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("{} already exists", target.display()),
+        ));
+    }
+    let partial = sibling(&target, ".partial");
+    let _ = fs::remove_file(&partial);
+    let copied = store
+        .backup_to(&partial)
+        .and_then(|()| fs::rename(&partial, &target).map_err(|err| io_error(verb, err)));
+    match copied {
+        Ok(()) => Ok(target),
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            Err(err)
+        }
+    }
+}
+
+/// Backup archives in `dir`, oldest name first; a missing directory has none.
+pub fn list_backups(dir: &Path) -> CrudResult<Vec<BackupArchive>> {
+    let verb = CrudVerb::List;
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(io_error(verb, err)),
+    };
+    let mut archives = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| io_error(verb, err))?.path();
+        let name = file_name(&path);
+        if !name.starts_with(ARCHIVE_PREFIX) {
+            continue;
+        }
+        if name.ends_with(SEALED_ARCHIVE_SUFFIX) {
+            archives.push(archive_at(path, None, true, verb)?);
+        } else if name.ends_with(ARCHIVE_SUFFIX) {
+            let kind = read_manifest(&path, verb)
+                .ok()
+                .flatten()
+                .map(|manifest| manifest.kind);
+            archives.push(archive_at(path, kind, false, verb)?);
+        }
+    }
+    archives.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(archives)
+}
+
+/// Delete the archives in `dir` that `policy` does not keep.
+///
+/// Archives are grouped by database and dated by their names
+/// (`prontodb_<name>_<YYYYMMDD>[_nNN]`), the highest `_nNN` being the newest
+/// of a day. Every archive below a kept incremental is kept too, so pruning
+/// never breaks a restore chain; finding those bases in encrypted archives
+/// needs the key file. Files not named like archives are left alone.
+pub fn prune_backups(
+    config: &SqliteConnectionConfig,
+    dir: &Path,
+    policy: BackupRetention,
+) -> CrudResult<PruneReport> {
+    let verb = CrudVerb::Delete;
+    if policy.keep_daily == 0 && policy.keep_weekly == 0 {
+        return Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            "prune would remove every archive; keep at least one daily or weekly",
+        ));
+    }
+
+    let archives = list_backups(dir)?;
+    let mut databases: BTreeMap<String, Vec<(i64, u32, &BackupArchive)>> = BTreeMap::new();
+    let mut kept: HashSet<PathBuf> = HashSet::new();
+    for archive in &archives {
+        match archive_stamp(&archive.path) {
+            Some((database, day, n)) => databases
+                .entry(database)
+                .or_default()
+                .push((day, n, archive)),
+            None => {
+                kept.insert(archive.path.clone());
+            }
+        }
+    }
+    for dated in databases.values_mut() {
+        dated.sort_by(|a, b| (b.0, b.1).cmp(&(a.0, a.1)));
+        // Monday-based week numbers; 1970-01-01 was a Thursday.
+        let periods: [(usize, fn(i64) -> i64); 2] = [
+            (policy.keep_daily, |day| day),
+            (policy.keep_weekly, |day| (day + 3).div_euclid(7)),
+        ];
+        for (keep, period_of) in periods {
+            let mut seen = HashSet::new();
+            for (day, _, archive) in dated.iter() {
+                if seen.len() == keep {
+                    break;
+                }
+                if seen.insert(period_of(*day)) {
+                    kept.insert(archive.path.clone());
+                }
+            }
+        }
+    }
+
+    let mut pending: Vec<&BackupArchive> = archives
+        .iter()
+        .filter(|archive| kept.contains(&archive.path))
+        .collect();
+    while let Some(archive) = pending.pop() {
+        if archive.kind == Some(BackupKind::Full) {
+            continue;
+        }
+        let plain = plain_archive(config, &archive.path, verb)?;
+        let base = match read_manifest(&plain.path, verb)?.and_then(|manifest| manifest.base) {
+            Some(base) => dir.join(base),
+            None => continue,
+        };
+        if let Some(below) = archives.iter().find(|candidate| candidate.path == base) {
+            if kept.insert(base) {
+                pending.push(below);
+            }
+        }
+    }
+
+    let mut report = PruneReport::default();
+    for archive in archives {
+        if kept.contains(&archive.path) {
+            report.kept.push(archive);
+        } else {
+            fs::remove_file(&archive.path).map_err(|err| io_error(verb, err))?;
+            report.removed.push(archive);
+        }
+    }
+    Ok(report)
+}
+
+fn write_backup(
+    config: &SqliteConnectionConfig,
+    dir: &Path,
+    base: Option<(String, Manifest)>,
+    scope: Option<&str>,
+    secret: Option<&[u8]>,
+) -> CrudResult<BackupArchive> {
+    let verb = CrudVerb::Backup;
+    let source = config.database_path();
+    if !source.is_file() {
+        return Err(CrudError::not_found(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("no database at {}", source.display()),
+        ));
+    }
+    let store = SqliteKeystore::open(config)?;
+    fs::create_dir_all(dir).map_err(|err| io_error(verb, err))?;
+    let taken_at = store.now();
+    let path = next_archive_path(config, dir, scope, taken_at, secret.is_some())?;
+    let name = file_name(&path);
+    let work = match secret {
+        Some(_) => database_dir(config),
+        None => dir.to_path_buf(),
+    };
+    let snapshot = work.join(format!(".{}.db.partial", name));
+    let plain = work.join(format!(".{}.tar.partial", name));
+    let partial = dir.join(format!(".{}.partial", name));
+    for leftover in [&snapshot, &plain, &partial] {
+        let _ = fs::remove_file(leftover);
+    }
+
+    let described = match scope {
+        Some(scope) => write_scoped_entries(config, scope, &snapshot, taken_at),
+        None => store
+            .snapshot(&snapshot)
+            .and_then(|()| describe_snapshot(config, &snapshot, taken_at, base)),
+    };
+    let written = described.and_then(|manifest| {
+        write_archive(&plain, &snapshot, &manifest).map_err(|err| io_error(verb, err))?;
+        match secret {
+            Some(secret) => seal_file(secret, &plain, &partial)?,
+            None => fs::rename(&plain, &partial).map_err(|err| io_error(verb, err))?,
+        }
+        fs::rename(&partial, &path).map_err(|err| io_error(verb, err))?;
+        Ok(manifest.kind)
+    });
+    for leftover in [&snapshot, &plain] {
+        let _ = fs::remove_file(leftover);
+    }
+    match written {
+        Ok(kind) => archive_at(path, Some(kind), secret.is_some(), verb),
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            Err(err)
+        }
+    }
+}
+
+/// The manifest for `snapshot`, pruning it to the changes over `base` when given.
+fn describe_snapshot(
+    config: &SqliteConnectionConfig,
+    snapshot: &Path,
+    taken_at: i64,
+    base: Option<(String, Manifest)>,
+) -> CrudResult<Manifest> {
+    let mut store = SqliteKeystore::open(&scratch_config(config, snapshot))?;
+    let live: Vec<Address> = store
+        .entries(None, None)?
+        .into_iter()
+        .map(|entry| entry.address)
+        .collect();
+    let keys = live.iter().map(ManifestKey::from).collect();
+    let (base_name, base) = match base {
+        Some(base) => base,
+        None => {
+            return Ok(Manifest {
+                kind: BackupKind::Full,
+                taken_at,
+                base: None,
+                keys,
+                removed: Vec::new(),
+                scope: None,
+            })
+        }
+    };
+
+    let known: Vec<Address> = base.keys.iter().map(ManifestKey::address).collect();
+    let current: HashSet<&Address> = live.iter().collect();
+    let removed = known
+        .iter()
+        .filter(|addr| !current.contains(addr))
+        .map(ManifestKey::from)
+        .collect();
+    store.prune_to_changes(base.taken_at, &known)?;
+    Ok(Manifest {
+        kind: BackupKind::Incremental,
+        taken_at,
+        base: Some(base_name),
+        keys,
+        removed,
+        scope: None,
+    })
+}
+
+/// Write the entries under `scope` the caller may read to `path`, and the manifest for them.
+fn write_scoped_entries(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    path: &Path,
+    taken_at: i64,
+) -> CrudResult<Manifest> {
+    let verb = CrudVerb::Backup;
+    let exported = export_entries(config, Some(scope))?;
+    let keys = exported
+        .iter()
+        .map(|entry| ManifestKey::from(&entry.address))
+        .collect();
+    let entries: Vec<ScopedEntry> = exported
+        .into_iter()
+        .map(|entry| ScopedEntry {
+            address: ManifestKey::from(&entry.address),
+            value: entry.value,
+            expires_at: entry.expires_at,
+        })
+        .collect();
+    let raw = serde_json::to_vec_pretty(&entries).map_err(|err| io_error(verb, err.into()))?;
+    fs::write(path, raw).map_err(|err| io_error(verb, err))?;
+    Ok(Manifest {
+        kind: BackupKind::Scoped,
+        taken_at,
+        base: None,
+        keys,
+        removed: Vec::new(),
+        scope: Some(scope.to_string()),
+    })
+}
+
+/// An archive readable as tar.gz: the file itself, or a decrypted copy removed on drop.
+struct PlainArchive {
+    path: PathBuf,
+    decrypted: bool,
+}
+
+impl Drop for PlainArchive {
+    fn drop(&mut self) {
+        if self.decrypted {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// `archive` as tar.gz, decrypting it beside the database when it is sealed.
+fn plain_archive(
+    config: &SqliteConnectionConfig,
+    archive: &Path,
+    verb: CrudVerb,
+) -> CrudResult<PlainArchive> {
+    let sealed = is_sealed(archive).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => CrudError::not_found(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("no backup at {}", archive.display()),
+        ),
+        _ => io_error(verb, err),
+    })?;
+    if !sealed {
+        return Ok(PlainArchive {
+            path: archive.to_path_buf(),
+            decrypted: false,
+        });
+    }
+    let secret = backup_secret(
+        config,
+        verb,
+        format!(
+            "{} is encrypted; pass --key-file or set PRONTO_KEY_FILE",
+            archive.display()
+        ),
+    )?;
+    let plain = PlainArchive {
+        path: database_dir(config).join(format!(".{}.plain", file_name(archive))),
+        decrypted: true,
+    };
+    open_sealed_file(&secret, archive, &plain.path)?;
+    Ok(plain)
+}
+
+/// Contents of the key file of `config`; `KEY_REQUIRED` with `missing` when there is none.
+fn backup_secret(
+    config: &SqliteConnectionConfig,
+    verb: CrudVerb,
+    missing: String,
+) -> CrudResult<Vec<u8>> {
+    let key_file = config
+        .key_file
+        .as_deref()
+        .ok_or_else(|| coded_invalid_input(ErrorCode::KeyRequired, verb, missing))?;
+    read_key_file(key_file)
+}
+
+/// One archive of a restore chain.
+struct ChainLink {
+    path: PathBuf,
+    plain: PlainArchive,
+    manifest: Option<Manifest>,
+}
+
+/// `archive` and the archives below it, the full backup first.
+fn backup_chain(
+    config: &SqliteConnectionConfig,
+    archive: &Path,
+    verb: CrudVerb,
+) -> CrudResult<Vec<ChainLink>> {
+    let mut chain: Vec<ChainLink> = Vec::new();
+    let mut next = Some(archive.to_path_buf());
+    while let Some(path) = next.take() {
+        if chain.iter().any(|link| link.path == path) {
+            return Err(not_a_backup(
+                archive,
+                verb,
+                "its chain of base archives loops".to_string(),
+            ));
+        }
+        let plain = plain_archive(config, &path, verb)?;
+        let manifest = read_manifest(&plain.path, verb)?;
+        if let Some(manifest) = manifest.as_ref() {
+            match (manifest.kind, manifest.base.as_deref()) {
+                (BackupKind::Full | BackupKind::Scoped, _) => {}
+                (BackupKind::Incremental, Some(base))
+                    if Path::new(base).file_name().is_some_and(|name| name == base) =>
+                {
+                    next = Some(path.parent().unwrap_or_else(|| Path::new(".")).join(base));
+                }
+                (BackupKind::Incremental, _) => {
+                    return Err(not_a_backup(
+                        &path,
+                        verb,
+                        "incremental without a base archive".to_string(),
+                    ))
+                }
+            }
+        }
+        chain.push(ChainLink {
+            path,
+            plain,
+            manifest,
+        });
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Unpack the full backup of `chain` to `staged` and lay its incrementals over it through `layer`.
+fn stage_chain(
+    config: &SqliteConnectionConfig,
+    chain: &[ChainLink],
+    staged: &Path,
+    layer: &Path,
+    verb: CrudVerb,
+) -> CrudResult<()> {
+    let (full, incrementals) = match chain.split_first() {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+    if let Some(scope) = full
+        .manifest
+        .as_ref()
+        .and_then(|manifest| manifest.scope.as_ref())
+    {
+        return Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!(
+                "{} only holds {}; merge it with backup --merge",
+                full.path.display(),
+                scope
+            ),
+        ));
+    }
+    unpack_snapshot(&full.plain.path, staged, verb)?;
+    check_snapshot(config, staged, verb)?;
+    if incrementals.is_empty() {
+        return Ok(());
+    }
+
+    let mut store = SqliteKeystore::open(&scratch_config(config, staged))?;
+    for link in incrementals {
+        let _ = fs::remove_file(layer);
+        unpack_snapshot(&link.plain.path, layer, verb)?;
+        check_snapshot(config, layer, verb)?;
+        let removed: Vec<Address> = link
+            .manifest
+            .iter()
+            .flat_map(|manifest| &manifest.removed)
+            .map(ManifestKey::address)
+            .collect();
+        store.apply_layer(layer, &removed)?;
+    }
+    Ok(())
+}
+
+/// The changes that turn `live` into `restored`.
+fn diff_entries(restored: Vec<KvEntry>, live: Vec<KvEntry>) -> RestorePreview {
+    let mut live: BTreeMap<Address, KvEntry> = live
+        .into_iter()
+        .map(|entry| (entry.address.clone(), entry))
+        .collect();
+    let mut preview = RestorePreview::default();
+    for entry in restored {
+        match live.remove(&entry.address) {
+            None => preview.changes.push((entry.address, RestoreChange::Added)),
+            Some(current)
+                if current.value != entry.value
+                    || current.value_type != entry.value_type
+                    || current.expires_at != entry.expires_at =>
+            {
+                preview
+                    .changes
+                    .push((entry.address, RestoreChange::Overwritten))
+            }
+            Some(_) => preview.unchanged += 1,
+        }
+    }
+    preview.changes.extend(
+        live.into_keys()
+            .map(|address| (address, RestoreChange::Removed)),
+    );
+    preview.changes.sort_by(|a, b| a.0.cmp(&b.0));
+    preview
+}
+
+/// Integrity-check the staged result of `chain` and count its keys against the top manifest.
+fn check_staged(
+    config: &SqliteConnectionConfig,
+    archive: &Path,
+    chain: &[ChainLink],
+    staged: &Path,
+    verb: CrudVerb,
+) -> CrudResult<BackupVerification> {
+    let manifest = chain.last().and_then(|link| link.manifest.as_ref());
+    let mut store = SqliteKeystore::open(
+        &config
+            .clone()
+            .with_database_path(staged)
+            .with_read_only(true),
+    )?;
+    if let Some(manifest) = manifest {
+        // Keys that expired after the backup still count, as they did in its manifest.
+        store = store.with_clock(Arc::new(FixedClock(manifest.taken_at)));
+    }
+    let problems = store.integrity_check()?;
+    if !problems.is_empty() {
+        return Err(not_a_backup(
+            archive,
+            verb,
+            format!("integrity_check failed: {}", problems.join("; ")),
+        ));
+    }
+    let keys = store.entries(None, None)?.len();
+    if let Some(manifest) = manifest {
+        if keys != manifest.keys.len() {
+            return Err(not_a_backup(
+                archive,
+                verb,
+                format!(
+                    "it restores {} keys but its manifest lists {}",
+                    keys,
+                    manifest.keys.len()
+                ),
+            ));
+        }
+    }
+    Ok(BackupVerification {
+        kind: manifest.map(|manifest| manifest.kind),
+        encrypted: chain.last().is_some_and(|link| link.plain.decrypted),
+        archives: chain.len(),
+        keys,
+        matched_manifest: manifest.is_some(),
+    })
+}
+
+/// Count the entries of a scoped archive against its manifest.
+fn check_scoped(
+    archive: &Path,
+    link: &ChainLink,
+    manifest: &Manifest,
+    verb: CrudVerb,
+) -> CrudResult<BackupVerification> {
+    let keys = read_scoped_entries(&link.plain.path, archive, verb)?.len();
+    if keys != manifest.keys.len() {
+        return Err(not_a_backup(
+            archive,
+            verb,
+            format!(
+                "it holds {} keys but its manifest lists {}",
+                keys,
+                manifest.keys.len()
+            ),
+        ));
+    }
+    Ok(BackupVerification {
+        kind: Some(BackupKind::Scoped),
+        encrypted: link.plain.decrypted,
+        archives: 1,
+        keys,
+        matched_manifest: true,
+    })
+}
+
+/// Decompress `plain` to the end so a truncated or damaged stream fails its gzip checksum.
+fn check_compression(plain: &Path, archive: &Path, verb: CrudVerb) -> CrudResult<()> {
+    let file = File::open(plain).map_err(|err| io_error(verb, err))?;
+    io::copy(&mut GzDecoder::new(file), &mut io::sink())
+        .map(|_| ())
+        .map_err(|err| not_a_backup(archive, verb, err.to_string()))
+}
+
+/// `config` pointed at a private working copy: writable, without WAL or token.
+fn scratch_config(config: &SqliteConnectionConfig, path: &Path) -> SqliteConnectionConfig {
+    config
+        .clone()
+        .with_database_path(path)
+        .with_read_only(false)
+        .with_wal(false)
+        .with_token(None::<String>)
+}
+
+/// The directory holding the database file.
+fn database_dir(config: &SqliteConnectionConfig) -> PathBuf {
+    config
+        .database_path()
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf()
+}
+
+/// `prontodb_<name>_`, the start of every archive name of this database.
+fn archive_prefix(config: &SqliteConnectionConfig) -> String {
+    let database = config
+        .database_path()
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "prontodb".to_string());
+    format!("{}{}_", ARCHIVE_PREFIX, database)
+}
+
+/// `(name, day, n)` of `prontodb_<name>_<YYYYMMDD>[_nNN].tar.gz[.enc]`, the day counted from
+/// the epoch and `n` 0 for the first archive of the day.
+fn archive_stamp(path: &Path) -> Option<(String, i64, u32)> {
+    let name = file_name(path);
+    let stem = name.strip_prefix(ARCHIVE_PREFIX)?;
+    let stem = stem
+        .strip_suffix(SEALED_ARCHIVE_SUFFIX)
+        .or_else(|| stem.strip_suffix(ARCHIVE_SUFFIX))?;
+    let all_digits =
+        |raw: &str, len: usize| raw.len() == len && raw.bytes().all(|b| b.is_ascii_digit());
+    let (dated, n) = match stem.rsplit_once("_n") {
+        Some((dated, n)) if all_digits(n, 2) => (dated, n.parse().ok()?),
+        _ => (stem, 0),
+    };
+    let (database, date) = dated.rsplit_once('_')?;
+    if !all_digits(date, 8) {
+        return None;
+    }
+    let midnight = parse_timestamp(&format!(
+        "{}-{}-{}T00:00:00Z",
+        &date[..4],
+        &date[4..6],
+        &date[6..]
+    ))?;
+    Some((database.to_string(), midnight.div_euclid(86_400), n))
+}
+
+/// The first free `prontodb_<name>[_<scope>]_<YYYYMMDD>[_nNN].tar.gz[.enc]` in `dir`; plain
+/// and encrypted archives share the numbering.
+fn next_archive_path(
+    config: &SqliteConnectionConfig,
+    dir: &Path,
+    scope: Option<&str>,
+    now: i64,
+    encrypted: bool,
+) -> CrudResult<PathBuf> {
+    let date: String = format_timestamp(now)
+        .chars()
+        .take(10)
+        .filter(char::is_ascii_digit)
+        .collect();
+    let base = match scope {
+        Some(scope) => format!("{}{}_{}", archive_prefix(config), scope, date),
+        None => format!("{}{}", archive_prefix(config), date),
+    };
+    let suffix = if encrypted {
+        SEALED_ARCHIVE_SUFFIX
+    } else {
+        ARCHIVE_SUFFIX
+    };
+    (0..=MAX_DAILY_BACKUPS)
+        .map(|n| match n {
+            0 => base.clone(),
+            n => format!("{}_n{:02}", base, n),
+        })
+        .find(|stem| {
+            [ARCHIVE_SUFFIX, SEALED_ARCHIVE_SUFFIX]
+                .iter()
+                .all(|taken| !dir.join(format!("{}{}", stem, taken)).exists())
+        })
+        .map(|stem| dir.join(format!("{}{}", stem, suffix)))
+        .ok_or_else(|| {
+            CrudError::invalid_input(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Base,
+                CrudVerb::Backup,
+                format!(
+                    "{} backups of {} already exist for today",
+                    MAX_DAILY_BACKUPS + 1,
+                    config.database_path().display()
+                ),
+            )
+        })
+}
+
+/// Pack `manifest` and `payload`, the snapshot or the entries of a scoped archive.
+fn write_archive(target: &Path, payload: &Path, manifest: &Manifest) -> io::Result<()> {
+    let manifest = serde_json::to_vec_pretty(manifest)?;
+    let encoder = GzEncoder::new(File::create(target)?, Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, MANIFEST_ENTRY, manifest.as_slice())?;
+    let entry = match manifest.kind {
+        BackupKind::Scoped => SCOPED_ENTRY,
+        BackupKind::Full | BackupKind::Incremental => SNAPSHOT_ENTRY,
+    };
+    archive.append_path_with_name(payload, entry)?;
+    archive.into_inner()?.finish()?.sync_all()
+}
+
+/// Read `archive` entry by entry until `found` returns a value; `None` when none matched.
+fn scan_archive<T, F>(archive: &Path, verb: CrudVerb, mut found: F) -> CrudResult<Option<T>>
+where
+    F: FnMut(&mut tar::Entry<'_, GzDecoder<File>>, &Path) -> CrudResult<Option<T>>,
+{
+    let file = File::open(archive).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => CrudError::not_found(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("no backup at {}", archive.display()),
+        ),
+        _ => io_error(verb, err),
+    })?;
+    let mut tarball = tar::Archive::new(GzDecoder::new(file));
+    for entry in tarball
+        .entries()
+        .map_err(|err| not_a_backup(archive, verb, err.to_string()))?
+    {
+        let mut entry = entry.map_err(|err| not_a_backup(archive, verb, err.to_string()))?;
+        let path = entry
+            .path()
+            .map(|path| path.into_owned())
+            .map_err(|err| not_a_backup(archive, verb, err.to_string()))?;
+        if let Some(value) = found(&mut entry, &path)? {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+/// The manifest of `archive`; `None` for archives written before manifests.
+fn read_manifest(archive: &Path, verb: CrudVerb) -> CrudResult<Option<Manifest>> {
+    let manifest = scan_archive(archive, verb, |entry, path| {
+        if path == Path::new(SNAPSHOT_ENTRY) || path == Path::new(SCOPED_ENTRY) {
+            // The manifest is written first, so past the snapshot there is none.
+            return Ok(Some(None));
+        }
+        if path != Path::new(MANIFEST_ENTRY) {
+            return Ok(None);
+        }
+        let mut raw = String::new();
+        entry
+            .read_to_string(&mut raw)
+            .map_err(|err| not_a_backup(archive, verb, err.to_string()))?;
+        serde_json::from_str(&raw)
+            .map(|manifest| Some(Some(manifest)))
+            .map_err(|err| not_a_backup(archive, verb, format!("bad {}: {}", MANIFEST_ENTRY, err)))
+    })?;
+    Ok(manifest.flatten())
+}
+
+/// The entries of the scoped archive at `plain` (`archive` as named to the caller).
+fn read_scoped_entries(
+    plain: &Path,
+    archive: &Path,
+    verb: CrudVerb,
+) -> CrudResult<Vec<ScopedEntry>> {
+    let entries = scan_archive(plain, verb, |entry, path| {
+        if path != Path::new(SCOPED_ENTRY) {
+            return Ok(None);
+        }
+        serde_json::from_reader(entry)
+            .map(Some)
+            .map_err(|err| not_a_backup(archive, verb, format!("bad {}: {}", SCOPED_ENTRY, err)))
+    })?;
+    entries.ok_or_else(|| not_a_backup(archive, verb, format!("no {} entry", SCOPED_ENTRY)))
+}
+
+/// Extract the snapshot entry of `archive` to `staged`.
+fn unpack_snapshot(archive: &Path, staged: &Path, verb: CrudVerb) -> CrudResult<()> {
+    let unpacked = scan_archive(archive, verb, |entry, path| {
+        if path != Path::new(SNAPSHOT_ENTRY) {
+            return Ok(None);
+        }
+        entry.unpack(staged).map_err(|err| io_error(verb, err))?;
+        Ok(Some(()))
+    })?;
+    unpacked.ok_or_else(|| not_a_backup(archive, verb, format!("no {} entry", SNAPSHOT_ENTRY)))
+}
+
+fn not_a_backup(archive: &Path, verb: CrudVerb, detail: String) -> CrudError {
+    CrudError::invalid_input(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Base,
+        verb,
+        format!("{} is not a prontodb backup: {}", archive.display(), detail),
+    )
+}
+
+/// Open `staged` read-only and read its projects, so a foreign SQLite file is never restored.
+fn check_snapshot(
+    config: &SqliteConnectionConfig,
+    staged: &Path,
+    verb: CrudVerb,
+) -> CrudResult<()> {
+    let staged_config = config
+        .clone()
+        .with_database_path(staged)
+        .with_read_only(true);
+    SqliteKeystore::open(&staged_config)
+        .and_then(|store| store.projects())
+        .map(|_| ())
+        .map_err(|err| {
+            CrudError::invalid_input(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Base,
+                verb,
+                format!("backup does not hold a prontodb keystore: {}", err),
+            )
+        })
+}
+
+fn archive_at(
+    path: PathBuf,
+    kind: Option<BackupKind>,
+    encrypted: bool,
+    verb: CrudVerb,
+) -> CrudResult<BackupArchive> {
+    let size_bytes = fs::metadata(&path)
+        .map_err(|err| io_error(verb, err))?
+        .len();
+    Ok(BackupArchive {
+        path,
+        size_bytes,
+        kind,
+        encrypted,
+    })
+}
+
+/// `path` with `suffix` appended to its file name (`kv.sqlite` → `kv.sqlite-wal`).
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn io_error(verb: CrudVerb, err: io::Error) -> CrudError {
+    CrudError::internal(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Base,
+        verb,
+        anyhow::Error::new(err),
+    )
+}
@@ -0,0 +1,183 @@
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use hub::data_ext::serde_json;
+use hub::serde::de::DeserializeOwned;
+use hub::serde::Serialize;
+
+use crate::lib::adpt::sqlite::{KvEntry, SqliteConnectionConfig};
+use crate::lib::core::crud::{CrudResult, CrudVerb};
+
+use super::error::{coded_invalid_input, ErrorCode};
+use super::options::StorageOptions;
+use super::session::Session;
+use super::stream::EntryIter;
+
+/// Embeddable handle on one database: open a path, then get and set by address.
+///
+/// Nothing here prints or exits; every failure comes back as a `CrudError`
+/// (see `ErrorCode::of` for a stable cause). Calls share one connection
+/// and follow the same ACL, TTL and encryption rules as the CLI.
+///
+/// ```no_run
+/// use prontodb::ProntoClient;
+///
+/// let client = ProntoClient::open("app.sqlite3")?;
+/// client.set("app.config.port", "8080", None)?;
+/// assert_eq!(client.get("app.config.port")?.as_deref(), Some("8080"));
+/// # Ok::<(), prontodb::lib::core::crud::CrudError>(())
+/// ```
+pub struct ProntoClient {
+    session: Session,
+}
+
+impl ProntoClient {
+    /// Open (creating if needed) the database at `path` as the OS user,
+    /// borrowing an idle pooled connection when one is free.
+    pub fn open<P: AsRef<Path>>(path: P) -> CrudResult<Self> {
+        Self::with_config(&SqliteConnectionConfig::new(path).with_connection_reuse(true))
+    }
+
+    /// Open the database `options` pick (path, registry name or cursor).
+    pub fn with_options(options: &StorageOptions) -> CrudResult<Self> {
+        Self::with_config(&options.resolve()?)
+    }
+
+    /// Open with full control over user, key file, read-only mode and address defaults.
+    pub fn with_config(config: &SqliteConnectionConfig) -> CrudResult<Self> {
+        Ok(Self {
+            session: Session::open(config)?,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.session.config().database_path()
+    }
+
+    pub fn config(&self) -> &SqliteConnectionConfig {
+        self.session.config()
+    }
+
+    /// Store `value`, expiring after `ttl_secs` when given (the namespace must be a TTL cache).
+    pub fn set(&self, address: &str, value: &str, ttl_secs: Option<u64>) -> CrudResult<()> {
+        self.session.set(address, value, ttl_secs)
+    }
+
+    /// The live value, or `None` when the key is missing or expired.
+    pub fn get(&self, address: &str) -> CrudResult<Option<String>> {
+        self.session.get(address)
+    }
+
+    /// `value` stored as JSON text; see `set`.
+    pub fn set_json<T: Serialize + ?Sized>(
+        &self,
+        address: &str,
+        value: &T,
+        ttl_secs: Option<u64>,
+    ) -> CrudResult<()> {
+        self.set(address, &encode_json(value)?, ttl_secs)
+    }
+
+    /// The live value parsed from JSON as `T`; `INVALID_VALUE` when it does not parse.
+    pub fn get_json<T: DeserializeOwned>(&self, address: &str) -> CrudResult<Option<T>> {
+        match self.get(address)? {
+            Some(raw) => decode_json(address, &raw).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The live entry with its metadata (TTL, timestamps).
+    pub fn get_entry(&self, address: &str) -> CrudResult<Option<KvEntry>> {
+        self.session.get_entry(address)
+    }
+
+    pub fn exists(&self, address: &str) -> CrudResult<bool> {
+        self.session.exists(address)
+    }
+
+    /// Returns whether a key was removed.
+    pub fn delete(&self, address: &str) -> CrudResult<bool> {
+        self.session.delete(address)
+    }
+
+    /// Reset the TTL of a key in a TTL cache; returns whether the key exists.
+    pub fn expire(&self, address: &str, ttl_secs: u64) -> CrudResult<bool> {
+        self.session.expire(address, ttl_secs)
+    }
+
+    /// Every live entry under `scope` (`project` or `project.namespace`), in key order.
+    pub fn entries(&self, scope: &str) -> CrudResult<Vec<KvEntry>> {
+        let mut entries = Vec::new();
+        self.session.scan(scope, |entry| {
+            entries.push(entry);
+            ControlFlow::Continue(())
+        })?;
+        Ok(entries)
+    }
+
+    /// Stream entries under `scope` without collecting them; return `Break` to stop early.
+    pub fn scan<F>(&self, scope: &str, visit: F) -> CrudResult<()>
+    where
+        F: FnMut(KvEntry) -> ControlFlow<()>,
+    {
+        self.session.scan(scope, visit)
+    }
+
+    /// Entries under `project.namespace[.prefix]` as a lazy iterator, read in batches.
+    ///
+    /// ```no_run
+    /// # let client = prontodb::ProntoClient::open("app.sqlite3")?;
+    /// for entry in client.scan_iter("app.sessions")? {
+    ///     let entry = entry?;
+    ///     println!("{} = {}", entry.address, entry.value);
+    /// }
+    /// # Ok::<(), prontodb::lib::core::crud::CrudError>(())
+    /// ```
+    pub fn scan_iter(&self, scope: &str) -> CrudResult<EntryIter<'_>> {
+        self.session.scan_iter(scope)
+    }
+
+    pub fn projects(&self) -> CrudResult<Vec<String>> {
+        self.session.projects()
+    }
+
+    pub fn namespaces(&self, project: &str) -> CrudResult<Vec<String>> {
+        self.session.namespaces(project)
+    }
+
+    /// The underlying session, e.g. to register an `on_change` listener.
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+}
+
+impl From<Session> for ProntoClient {
+    fn from(session: Session) -> Self {
+        Self { session }
+    }
+}
+
+pub(super) fn encode_json<T: Serialize + ?Sized>(value: &T) -> CrudResult<String> {
+    serde_json::to_string(value).map_err(|err| {
+        coded_invalid_input(
+            ErrorCode::InvalidInput,
+            CrudVerb::Create,
+            format!("value does not serialize as JSON: {}", err),
+        )
+    })
+}
+
+pub(super) fn decode_json<T: DeserializeOwned>(address: &str, raw: &str) -> CrudResult<T> {
+    serde_json::from_str(raw).map_err(|err| {
+        coded_invalid_input(
+            ErrorCode::InvalidValue,
+            CrudVerb::Read,
+            format!(
+                "value at {} is not a valid {}: {}",
+                address,
+                std::any::type_name::<T>(),
+                err
+            ),
+        )
+    })
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use hub::error_ext::anyhow;
+use hub::serde::de::DeserializeOwned;
+use hub::serde::Serialize;
+
+use crate::lib::adpt::sqlite::{KvEntry, SqliteConnectionConfig};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+
+use super::client::{decode_json, encode_json, ProntoClient};
+
+/// `ProntoClient` for async services (feature `async`).
+///
+/// SQLite calls block, so each method runs on tokio's blocking pool
+/// (`spawn_blocking`) and the executor threads never wait on the disk.
+/// Clones share one connection; calls through it are serialized. Must be
+/// used inside a tokio runtime.
+#[derive(Clone)]
+pub struct AsyncProntoClient {
+    client: Arc<Mutex<ProntoClient>>,
+    config: SqliteConnectionConfig,
+}
+
+impl AsyncProntoClient {
+    pub async fn open<P: AsRef<Path>>(path: P) -> CrudResult<Self> {
+        Self::with_config(SqliteConnectionConfig::new(path)).await
+    }
+
+    pub async fn with_config(config: SqliteConnectionConfig) -> CrudResult<Self> {
+        let opened = config.clone();
+        let client = blocking(CrudVerb::Read, move || ProntoClient::with_config(&opened)).await?;
+        Ok(Self {
+            client: Arc::new(Mutex::new(client)),
+            config,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.config.database_path()
+    }
+
+    pub fn config(&self) -> &SqliteConnectionConfig {
+        &self.config
+    }
+
+    /// See `ProntoClient::set`.
+    pub async fn set(&self, address: &str, value: &str, ttl_secs: Option<u64>) -> CrudResult<()> {
+        let (address, value) = (address.to_string(), value.to_string());
+        self.run(CrudVerb::Create, move |client| {
+            client.set(&address, &value, ttl_secs)
+        })
+        .await
+    }
+
+    /// See `ProntoClient::get`.
+    pub async fn get(&self, address: &str) -> CrudResult<Option<String>> {
+        let address = address.to_string();
+        self.run(CrudVerb::Read, move |client| client.get(&address))
+            .await
+    }
+
+    /// See `ProntoClient::set_json`; `value` is serialized before the call is queued.
+    pub async fn set_json<T: Serialize + ?Sized>(
+        &self,
+        address: &str,
+        value: &T,
+        ttl_secs: Option<u64>,
+    ) -> CrudResult<()> {
+        self.set(address, &encode_json(value)?, ttl_secs).await
+    }
+
+    /// See `ProntoClient::get_json`.
+    pub async fn get_json<T: DeserializeOwned>(&self, address: &str) -> CrudResult<Option<T>> {
+        match self.get(address).await? {
+            Some(raw) => decode_json(address, &raw).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// See `ProntoClient::get_entry`.
+    pub async fn get_entry(&self, address: &str) -> CrudResult<Option<KvEntry>> {
+        let address = address.to_string();
+        self.run(CrudVerb::Read, move |client| client.get_entry(&address))
+            .await
+    }
+
+    pub async fn exists(&self, address: &str) -> CrudResult<bool> {
+        let address = address.to_string();
+        self.run(CrudVerb::Find, move |client| client.exists(&address))
+            .await
+    }
+
+    /// See `ProntoClient::delete`.
+    pub async fn delete(&self, address: &str) -> CrudResult<bool> {
+        let address = address.to_string();
+        self.run(CrudVerb::Delete, move |client| client.delete(&address))
+            .await
+    }
+
+    /// See `ProntoClient::expire`.
+    pub async fn expire(&self, address: &str, ttl_secs: u64) -> CrudResult<bool> {
+        let address = address.to_string();
+        self.run(CrudVerb::Update, move |client| {
+            client.expire(&address, ttl_secs)
+        })
+        .await
+    }
+
+    /// See `ProntoClient::entries`.
+    pub async fn entries(&self, scope: &str) -> CrudResult<Vec<KvEntry>> {
+        let scope = scope.to_string();
+        self.run(CrudVerb::List, move |client| client.entries(&scope))
+            .await
+    }
+
+    pub async fn projects(&self) -> CrudResult<Vec<String>> {
+        self.run(CrudVerb::List, |client| client.projects()).await
+    }
+
+    pub async fn namespaces(&self, project: &str) -> CrudResult<Vec<String>> {
+        let project = project.to_string();
+        self.run(CrudVerb::List, move |client| client.namespaces(&project))
+            .await
+    }
+
+    async fn run<T, F>(&self, verb: CrudVerb, call: F) -> CrudResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&ProntoClient) -> CrudResult<T> + Send + 'static,
+    {
+        let client = Arc::clone(&self.client);
+        blocking(verb, move || {
+            // A panic mid-call leaves no half-done transaction behind, so keep going.
+            let client = match client.lock() {
+                Ok(client) => client,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            call(&client)
+        })
+        .await
+    }
+}
+
+impl From<ProntoClient> for AsyncProntoClient {
+    fn from(client: ProntoClient) -> Self {
+        Self {
+            config: client.config().clone(),
+            client: Arc::new(Mutex::new(client)),
+        }
+    }
+}
+
+/// Run `call` on the blocking pool; a panic in it becomes an internal error.
+async fn blocking<T, F>(verb: CrudVerb, call: F) -> CrudResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> CrudResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(call).await.map_err(|err| {
+        CrudError::internal(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            anyhow::anyhow!("blocking task failed: {}", err),
+        )
+    })?
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+
+use hub::error_ext::anyhow;
+use hub::error_ext::thiserror::Error;
+
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudErrorKind, CrudObjectKind, CrudVerb};
+
+/// Stable, machine-readable failure causes (`{"code": "NS_NOT_TTL", ...}` under `--json`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorCode {
+    InvalidAddress,
+    InvalidScope,
+    InvalidPattern,
+    NsNotTtl,
+    TtlExceedsMax,
+    /// A strict database refused a `set` into a namespace it does not know.
+    NsUnknown,
+    /// An encrypted namespace was touched without a key file.
+    KeyRequired,
+    NotFound,
+    /// A named cursor (or its database directory) does not exist.
+    CursorMissing,
+    Conflict,
+    PermissionDenied,
+    InvalidInput,
+    /// A stored value did not parse as the type the caller asked for.
+    InvalidValue,
+    Unsupported,
+    /// SQLite reported the database busy or locked; the call may succeed when retried.
+    StorageBusy,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidAddress => "INVALID_ADDRESS",
+            ErrorCode::InvalidScope => "INVALID_SCOPE",
+            ErrorCode::InvalidPattern => "INVALID_PATTERN",
+            ErrorCode::NsNotTtl => "NS_NOT_TTL",
+            ErrorCode::TtlExceedsMax => "TTL_EXCEEDS_MAX",
+            ErrorCode::NsUnknown => "NS_UNKNOWN",
+            ErrorCode::KeyRequired => "KEY_REQUIRED",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::CursorMissing => "CURSOR_MISSING",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::PermissionDenied => "PERMISSION_DENIED",
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::InvalidValue => "INVALID_VALUE",
+            ErrorCode::Unsupported => "UNSUPPORTED",
+            ErrorCode::StorageBusy => "STORAGE_BUSY",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+
+    /// The code for `error`: the specific cause when one was recorded, else one per `CrudErrorKind`.
+    pub fn of(error: &CrudError) -> Self {
+        if let Some(coded) = error.source().downcast_ref::<CodedError>() {
+            return coded.code;
+        }
+        if let Some(rusqlite::Error::SqliteFailure(failure, _)) =
+            error.source().downcast_ref::<rusqlite::Error>()
+        {
+            if matches!(
+                failure.code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            ) {
+                return ErrorCode::StorageBusy;
+            }
+        }
+        match error.kind {
+            CrudErrorKind::NotFound
+                if error.domain == CrudDomain::Filesystem
+                    && error.object == CrudObjectKind::Alias =>
+            {
+                ErrorCode::CursorMissing
+            }
+            CrudErrorKind::Unsupported => ErrorCode::Unsupported,
+            CrudErrorKind::InvalidInput => ErrorCode::InvalidInput,
+            CrudErrorKind::Conflict => ErrorCode::Conflict,
+            CrudErrorKind::NotFound => ErrorCode::NotFound,
+            CrudErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            CrudErrorKind::Internal => ErrorCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A failed call as a typed cause, for callers that match on it rather than on an `ErrorCode`.
+///
+/// Built from a `CrudError` by its code; causes without a variant of their
+/// own are `Other`. Each variant keeps the error's message.
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum ProntoError {
+    /// A TTL was given for a namespace that is not a TTL cache.
+    #[error("{0}")]
+    NamespaceNotTtl(String),
+    #[error("{0}")]
+    KeyNotFound(String),
+    #[error("{0}")]
+    CursorMissing(String),
+    /// The database was busy or locked; retrying may succeed.
+    #[error("{0}")]
+    StorageBusy(String),
+    /// A conditional write's precondition failed.
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{message}")]
+    Other { code: ErrorCode, message: String },
+}
+
+impl ProntoError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ProntoError::NamespaceNotTtl(_) => ErrorCode::NsNotTtl,
+            ProntoError::KeyNotFound(_) => ErrorCode::NotFound,
+            ProntoError::CursorMissing(_) => ErrorCode::CursorMissing,
+            ProntoError::StorageBusy(_) => ErrorCode::StorageBusy,
+            ProntoError::Conflict(_) => ErrorCode::Conflict,
+            ProntoError::Other { code, .. } => *code,
+        }
+    }
+}
+
+impl From<&CrudError> for ProntoError {
+    fn from(error: &CrudError) -> Self {
+        let message = error.source().to_string();
+        match ErrorCode::of(error) {
+            ErrorCode::NsNotTtl => ProntoError::NamespaceNotTtl(message),
+            ErrorCode::NotFound => ProntoError::KeyNotFound(message),
+            ErrorCode::CursorMissing => ProntoError::CursorMissing(message),
+            ErrorCode::StorageBusy => ProntoError::StorageBusy(message),
+            ErrorCode::Conflict => ProntoError::Conflict(message),
+            code => ProntoError::Other { code, message },
+        }
+    }
+}
+
+impl From<CrudError> for ProntoError {
+    fn from(error: CrudError) -> Self {
+        ProntoError::from(&error)
+    }
+}
+
+/// Error source carrying an `ErrorCode`, recovered later with `ErrorCode::of`.
+#[derive(Debug)]
+struct CodedError {
+    code: ErrorCode,
+    message: String,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CodedError {}
+
+/// `InvalidInput` keystore error tagged with a specific `code`.
+pub(crate) fn coded_invalid_input<S: Into<String>>(
+    code: ErrorCode,
+    verb: CrudVerb,
+    message: S,
+) -> CrudError {
+    CrudError::new(
+        CrudErrorKind::InvalidInput,
+        CrudDomain::Sqlite,
+        CrudObjectKind::Record,
+        verb,
+        anyhow::Error::new(CodedError {
+            code,
+            message: message.into(),
+        }),
+    )
+}
@@ -0,0 +1,1446 @@
+use std::ops::ControlFlow;
+
+use hub::text_ext::regex::Regex;
+
+use crate::lib::addr::{
+    is_reserved_namespace, Address, AddressLayout, KeyPattern, NamespaceScope, DEFAULT_NS_DELIM,
+};
+use crate::lib::adpt::sqlite::{
+    AccessPolicy, CacheSettings, EntryOrder, ExpiryHook, ImportReport, ImportStrategy, JsonFilter,
+    KvEntry, OplogEntry, OplogSince, Permission, RetentionPolicy, SearchHit, SecretMarks,
+    SetCondition, SqliteConnectionConfig, SqliteKeystore, ValidationMode, ValueType,
+};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+
+use super::error::{coded_invalid_input, ErrorCode};
+use super::pool::{open_store, PooledKeystore};
+use super::vault::{scan_decrypted, NamespaceKeys};
+
+pub fn set_value(config: &SqliteConnectionConfig, address: &str, value: &str) -> CrudResult<()> {
+    let verb = CrudVerb::Create;
+    let addr = parse_address(config, address, verb)?;
+    let store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        verb,
+    )?;
+    check_namespace_known(&store, config, &addr, verb)?;
+    let value = NamespaceKeys::load(&store, config)?.seal(&addr, value)?;
+    store.set(&addr, &value, None)
+}
+
+/// Set a value that expires after `ttl_secs`.
+///
+/// Strict by default: the namespace must be a cache (`create-cache`) or have
+/// opted in with `allow-ttl`, otherwise this is an `InvalidInput` error. A
+/// cache's `--max-ttl` either clamps or rejects longer TTLs.
+pub fn set_value_with_ttl(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    value: &str,
+    ttl_secs: u64,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Create;
+    let addr = parse_address(config, address, verb)?;
+    let store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        verb,
+    )?;
+    check_namespace_known(&store, config, &addr, verb)?;
+    let ttl_secs = checked_ttl(&store, &addr, ttl_secs, verb)?;
+    let value = NamespaceKeys::load(&store, config)?.seal(&addr, value)?;
+    store.set(&addr, &value, Some(ttl_secs))
+}
+
+/// Set a value tagged with `value_type` (`set --type`); it must parse as that type.
+///
+/// `ttl_secs` obeys the same namespace rules as `set_value_with_ttl`.
+pub fn set_typed_value(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    value: &str,
+    value_type: ValueType,
+    ttl_secs: Option<u64>,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Create;
+    let addr = parse_address(config, address, verb)?;
+    let mut store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        verb,
+    )?;
+    check_namespace_known(&store, config, &addr, verb)?;
+    NamespaceKeys::load(&store, config)?.refuse(
+        &addr.project,
+        &addr.namespace,
+        "set --type",
+        verb,
+    )?;
+    let ttl_secs = match ttl_secs {
+        Some(ttl_secs) => Some(checked_ttl(&store, &addr, ttl_secs, verb)?),
+        None => None,
+    };
+    store.set_typed(&addr, value, ttl_secs, value_type)
+}
+
+/// Under `ValidationMode::Strict`, refuse to `set` into a namespace the database
+/// does not know unless `config.create_namespaces` allows creating it.
+pub(super) fn check_namespace_known(
+    store: &SqliteKeystore,
+    config: &SqliteConnectionConfig,
+    addr: &Address,
+    verb: CrudVerb,
+) -> CrudResult<()> {
+    if config.create_namespaces {
+        return Ok(());
+    }
+    let mode = match config.validation_mode {
+        Some(mode) => mode,
+        None => store.validation_mode()?,
+    };
+    if mode == ValidationMode::Lax || store.namespace_known(&addr.project, &addr.namespace)? {
+        return Ok(());
+    }
+    Err(coded_invalid_input(
+        ErrorCode::NsUnknown,
+        verb,
+        format!(
+            "namespace {}.{} does not exist (strict validation; set --create to create it)",
+            addr.project, addr.namespace
+        ),
+    ))
+}
+
+/// Apply the namespace TTL policy (opt-in, `--max-ttl` clamp or reject) to `ttl_secs`.
+pub(super) fn checked_ttl(
+    store: &SqliteKeystore,
+    addr: &Address,
+    ttl_secs: u64,
+    verb: CrudVerb,
+) -> CrudResult<u64> {
+    if !store.key_ttl_allowed(&addr.project, &addr.namespace)? {
+        return Err(coded_invalid_input(
+            ErrorCode::NsNotTtl,
+            verb,
+            format!(
+                "namespace {}.{} is not TTL-enabled (use create-cache or allow-ttl)",
+                addr.project, addr.namespace
+            ),
+        ));
+    }
+    let ttl_secs = match store.cache_settings(&addr.project, &addr.namespace)? {
+        Some(CacheSettings {
+            max_ttl_secs: Some(max_ttl_secs),
+            clamp_ttl,
+            ..
+        }) if ttl_secs > max_ttl_secs => {
+            if !clamp_ttl {
+                return Err(coded_invalid_input(
+                    ErrorCode::TtlExceedsMax,
+                    verb,
+                    format!(
+                        "TTL {}s exceeds the {}s maximum of {}.{}",
+                        ttl_secs, max_ttl_secs, addr.project, addr.namespace
+                    ),
+                ));
+            }
+            max_ttl_secs
+        }
+        _ => ttl_secs,
+    };
+    Ok(ttl_secs)
+}
+
+/// Conditional set (`--if-absent` / `--if-value`); a failed precondition is a `Conflict` error.
+pub fn set_value_if(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    value: &str,
+    condition: &SetCondition,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Update;
+    let addr = parse_address(config, address, verb)?;
+    let mut store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        verb,
+    )?;
+    check_namespace_known(&store, config, &addr, verb)?;
+    let mut keys = NamespaceKeys::load(&store, config)?;
+    if let SetCondition::IfValue(_) = condition {
+        keys.refuse(&addr.project, &addr.namespace, "set --if-value", verb)?;
+    }
+    let value = keys.seal(&addr, value)?;
+    store.set_if(&addr, &value, condition)
+}
+
+pub fn get_value(config: &SqliteConnectionConfig, address: &str) -> CrudResult<Option<String>> {
+    let addr = parse_address(config, address, CrudVerb::Read)?;
+    let store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Read,
+        CrudVerb::Read,
+    )?;
+    match store.get(&addr)? {
+        Some(value) => Ok(Some(
+            NamespaceKeys::load(&store, config)?.open(&addr, value)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// `get --include-expired`: expired rows that have not been swept yet are still returned.
+pub fn get_value_including_expired(
+    config: &SqliteConnectionConfig,
+    address: &str,
+) -> CrudResult<Option<String>> {
+    let verb = CrudVerb::Read;
+    let addr = parse_address(config, address, verb)?;
+    let store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Read,
+        verb,
+    )?;
+    match store.get_including_expired(&addr)? {
+        Some(entry) => Ok(Some(
+            NamespaceKeys::load(&store, config)?.open(&addr, entry.value)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// `get` returning the full entry (value plus expiry) for structured output.
+pub fn get_entry(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    include_expired: bool,
+) -> CrudResult<Option<KvEntry>> {
+    let verb = CrudVerb::Read;
+    let addr = parse_address(config, address, verb)?;
+    let store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Read,
+        verb,
+    )?;
+    let entry = if include_expired {
+        store.get_including_expired(&addr)?
+    } else {
+        store.get_entry(&addr)?
+    };
+    match entry {
+        Some(entry) => Ok(Some(
+            NamespaceKeys::load(&store, config)?.open_entry(entry)?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Live entries under `project.namespace[.prefix]`, ordered by key.
+///
+/// Scopes with glob characters (`app.*.enabled`, `app.cfg.db_?`) are matched
+/// per segment instead; see `KeyPattern`.
+pub fn list_entries(config: &SqliteConnectionConfig, scope: &str) -> CrudResult<Vec<KvEntry>> {
+    let mut entries = Vec::new();
+    scan_entries(config, scope, |entry| {
+        entries.push(entry);
+        ControlFlow::Continue(())
+    })?;
+    Ok(entries)
+}
+
+/// Stream live entries under `project.namespace[.prefix]` (or a glob) to `visit`, one row at a time.
+pub fn scan_entries<F>(config: &SqliteConnectionConfig, scope: &str, visit: F) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    scan_entries_sorted(config, scope, EntryOrder::default(), visit)
+}
+
+/// Like `scan_entries`, with rows in `order` (sorted by SQLite, not in memory).
+pub fn scan_entries_sorted<F>(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    order: EntryOrder,
+    visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    scan_store(&open_store(config)?, config, scope, order, visit)
+}
+
+/// `scan_entries_sorted` over an already open `store`.
+pub(super) fn scan_store<F>(
+    store: &SqliteKeystore,
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    order: EntryOrder,
+    visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    let verb = CrudVerb::List;
+    if KeyPattern::is_pattern(scope) {
+        let pattern = parse_pattern(config, scope, verb)?;
+        let policy = store.access_policy()?;
+        return scan_decrypted(store, config, visit, |visit| {
+            store.for_each_match(&pattern, order, readable_only(config, policy, visit))
+        });
+    }
+    let scope = parse_scope(config, scope, verb)?;
+    store.check_access(
+        config.user.as_deref(),
+        &scope.project,
+        &scope.namespace,
+        Permission::Read,
+        verb,
+    )?;
+    scan_decrypted(store, config, visit, |visit| {
+        store.for_each_entry(&scope, order, visit)
+    })
+}
+
+/// Like `list_entries`, keeping only keys (with any `__context` suffix) matched by `regex`.
+pub fn list_entries_matching(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    regex: &str,
+) -> CrudResult<Vec<KvEntry>> {
+    let mut entries = Vec::new();
+    scan_entries_matching(config, scope, regex, EntryOrder::default(), |entry| {
+        entries.push(entry);
+        ControlFlow::Continue(())
+    })?;
+    Ok(entries)
+}
+
+/// Like `scan_entries`, keeping only keys (with any `__context` suffix) matched by `regex`.
+pub fn scan_entries_matching<F>(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    regex: &str,
+    order: EntryOrder,
+    mut visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    let regex = parse_regex(regex, CrudVerb::List)?;
+    scan_entries_sorted(config, scope, order, |entry| {
+        if regex.is_match(&entry.address.key_path()) {
+            visit(entry)
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+}
+
+/// Entries in `project.namespace[.prefix]` with keys in `[from, to)`, ordered by key.
+pub fn list_entries_in_range(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> CrudResult<Vec<KvEntry>> {
+    let mut entries = Vec::new();
+    scan_entries_in_range(config, scope, from, to, EntryOrder::default(), |entry| {
+        entries.push(entry);
+        ControlFlow::Continue(())
+    })?;
+    Ok(entries)
+}
+
+/// Stream entries whose key lies in `[from, to)` to `visit`; either bound may be open.
+///
+/// The comparison happens in SQLite, so a window such as `--from 2025-01 --to 2025-02`
+/// over time-prefixed keys reads only the matching rows.
+pub fn scan_entries_in_range<F>(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+    order: EntryOrder,
+    visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    let verb = CrudVerb::List;
+    let scope = parse_scope(config, scope, verb)?;
+    let store = open_authorized(
+        config,
+        &scope.project,
+        &scope.namespace,
+        Permission::Read,
+        verb,
+    )?;
+    scan_decrypted(&store, config, visit, |visit| {
+        store.for_each_in_range(&scope, from, to, order, visit)
+    })
+}
+
+/// Entries under `project.namespace[.prefix]` created after row `after`, in insert order.
+///
+/// Each comes with the row id to pass as the next `after`; `last` keeps only the
+/// newest. Rewritten keys keep their row and do not reappear, which is what `tail` follows.
+pub fn entries_inserted_after(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    after: i64,
+    last: Option<usize>,
+) -> CrudResult<Vec<(i64, KvEntry)>> {
+    let verb = CrudVerb::List;
+    let scope = parse_scope(config, scope, verb)?;
+    let store = open_authorized(
+        config,
+        &scope.project,
+        &scope.namespace,
+        Permission::Read,
+        verb,
+    )?;
+    let mut keys = NamespaceKeys::load(&store, config)?;
+    store
+        .inserted_after(&scope, after, last)?
+        .into_iter()
+        .map(|(row, entry)| Ok((row, keys.open_entry(entry)?)))
+        .collect()
+}
+
+/// Entries in `scope` whose JSON value has `value` at `path`, from a `.path=value` filter.
+pub fn list_entries_where(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    filter: &str,
+) -> CrudResult<Vec<KvEntry>> {
+    let mut entries = Vec::new();
+    scan_entries_where(config, scope, filter, EntryOrder::default(), |entry| {
+        entries.push(entry);
+        ControlFlow::Continue(())
+    })?;
+    Ok(entries)
+}
+
+/// Stream entries matching a `.path=value` filter to `visit`; non-JSON values never match.
+///
+/// Uses the namespace's `--json-path` index when one exists.
+pub fn scan_entries_where<F>(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    filter: &str,
+    order: EntryOrder,
+    visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    let verb = CrudVerb::List;
+    let parsed = parse_scope(config, scope, verb)?;
+    let filter = JsonFilter::parse(filter).ok_or_else(|| {
+        coded_invalid_input(
+            ErrorCode::InvalidPattern,
+            verb,
+            format!("invalid filter '{}' (expected .path=value)", filter),
+        )
+    })?;
+    let store = open_authorized(
+        config,
+        &parsed.project,
+        &parsed.namespace,
+        Permission::Read,
+        verb,
+    )?;
+    NamespaceKeys::load(&store, config)?.refuse(
+        &parsed.project,
+        &parsed.namespace,
+        "scan --where",
+        verb,
+    )?;
+    store.for_each_where(&parsed, &filter, order, visit)
+}
+
+/// Live entries for export: everything, one `project`, or one `project.namespace`.
+pub fn export_entries(
+    config: &SqliteConnectionConfig,
+    scope: Option<&str>,
+) -> CrudResult<Vec<KvEntry>> {
+    let mut entries = Vec::new();
+    scan_database(config, scope, |entry| {
+        entries.push(entry);
+        ControlFlow::Continue(())
+    })?;
+    Ok(entries)
+}
+
+/// Stream entries whose value contains `pattern` (or matches it, with `regex`) to `visit`.
+///
+/// `scope` is as for `export_entries`; rows are read one at a time, so large
+/// databases are searched without loading them into memory.
+pub fn grep_values<F>(
+    config: &SqliteConnectionConfig,
+    scope: Option<&str>,
+    pattern: &str,
+    regex: bool,
+    mut visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    let regex = if regex {
+        Some(parse_regex(pattern, CrudVerb::Find)?)
+    } else {
+        None
+    };
+    scan_database(config, scope, |entry| {
+        let matched = match &regex {
+            Some(regex) => regex.is_match(&entry.value),
+            None => entry.value.contains(pattern),
+        };
+        if matched {
+            visit(entry)
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+}
+
+/// Rank entries of full-text indexed namespaces against an FTS5 `query`, best first.
+///
+/// `scope` is as for `export_entries`; fails when no namespace has a full-text index.
+pub fn search_values(
+    config: &SqliteConnectionConfig,
+    query: &str,
+    scope: Option<&str>,
+    limit: usize,
+) -> CrudResult<Vec<SearchHit>> {
+    let (project, namespace) = parse_database_scope(config, scope, CrudVerb::Find)?;
+    let store = open_store(config)?;
+    let policy = store.access_policy()?;
+    let keys = NamespaceKeys::load(&store, config)?;
+    let mut hits = store.search(query, project.as_deref(), namespace.as_deref(), limit)?;
+    // Filtered after ranking, so hidden (or encrypted, hence unsearchable)
+    // namespaces can leave fewer than `limit` hits.
+    hits.retain(|hit| {
+        let addr = &hit.entry.address;
+        policy.allows(
+            config.user.as_deref(),
+            &addr.project,
+            &addr.namespace,
+            Permission::Read,
+        ) && !keys.is_encrypted(&addr.project, &addr.namespace)
+    });
+    Ok(hits)
+}
+
+/// Visit live entries in the whole database, one `project`, or one `project.namespace`.
+fn scan_database<F>(
+    config: &SqliteConnectionConfig,
+    scope: Option<&str>,
+    visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    let (project, namespace) = parse_database_scope(config, scope, CrudVerb::List)?;
+    let store = open_store(config)?;
+    let policy = store.access_policy()?;
+    scan_decrypted(&store, config, visit, |visit| {
+        store.for_each_in(
+            project.as_deref(),
+            namespace.as_deref(),
+            readable_only(config, policy, visit),
+        )
+    })
+}
+
+/// Projects holding at least one namespace `config.user` may read.
+pub fn list_projects(config: &SqliteConnectionConfig) -> CrudResult<Vec<String>> {
+    readable_projects(&open_store(config)?, config)
+}
+
+/// Namespaces of `project` that `config.user` may read.
+pub fn list_namespaces(config: &SqliteConnectionConfig, project: &str) -> CrudResult<Vec<String>> {
+    let store = open_store(config)?;
+    readable_namespaces(&store, config, &store.access_policy()?, project)
+}
+
+/// Contexts stored for `address`'s key (its own `__ctx` is ignored), sorted.
+pub fn list_contexts(config: &SqliteConnectionConfig, address: &str) -> CrudResult<Vec<String>> {
+    let verb = CrudVerb::List;
+    let addr = parse_address(config, address, verb)?;
+    open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Read,
+        verb,
+    )?
+    .contexts(&addr)
+}
+
+/// The storage address `address` names under `config`: defaults filled in,
+/// context split off, and the layout applied. Nothing is read or written.
+pub fn resolve_address(config: &SqliteConnectionConfig, address: &str) -> CrudResult<Address> {
+    parse_address(config, address, CrudVerb::Read)
+}
+
+pub fn value_exists(config: &SqliteConnectionConfig, address: &str) -> CrudResult<bool> {
+    let addr = parse_address(config, address, CrudVerb::Find)?;
+    open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Read,
+        CrudVerb::Find,
+    )?
+    .exists(&addr)
+}
+
+/// Turn `project.namespace` into a TTL cache (see `CacheSettings` for sliding and max-TTL).
+pub fn create_cache(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    settings: &CacheSettings,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Create;
+    let scope = parse_namespace(config, scope, verb)?;
+    open_authorized(
+        config,
+        &scope.project,
+        &scope.namespace,
+        Permission::Write,
+        verb,
+    )?
+    .create_cache(&scope.project, &scope.namespace, settings)
+}
+
+/// Allow (or, with `allowed = false`, forbid again) per-key TTLs in a namespace.
+pub fn allow_key_ttl(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    allowed: bool,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Update;
+    let scope = parse_namespace(config, scope, verb)?;
+    open_authorized(
+        config,
+        &scope.project,
+        &scope.namespace,
+        Permission::Write,
+        verb,
+    )?
+    .set_key_ttl_allowed(&scope.project, &scope.namespace, allowed)
+}
+
+/// Give `project.namespace` a retention policy (an empty one clears it); the sweeper trims it.
+///
+/// The sweeper deletes what the policy no longer keeps, so this needs write
+/// access to the namespace, like deleting its keys directly.
+pub fn set_retention(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    policy: &RetentionPolicy,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Update;
+    let scope = parse_namespace(config, scope, verb)?;
+    open_authorized(
+        config,
+        &scope.project,
+        &scope.namespace,
+        Permission::Write,
+        verb,
+    )?
+    .set_retention(&scope.project, &scope.namespace, policy)
+}
+
+pub fn retention_policies(
+    config: &SqliteConnectionConfig,
+) -> CrudResult<Vec<(String, String, RetentionPolicy)>> {
+    open_store(config)?.retention_policies()
+}
+
+/// Mark `project.namespace` or one `project.namespace.key` secret (`secret = false` unmarks).
+///
+/// Listings mask secret values unless asked to reveal them; returns whether
+/// the mark changed.
+pub fn mark_secret(
+    config: &SqliteConnectionConfig,
+    target: &str,
+    secret: bool,
+) -> CrudResult<bool> {
+    let verb = CrudVerb::Update;
+    let scope = parse_scope(config, target, verb)?;
+    open_authorized(
+        config,
+        &scope.project,
+        &scope.namespace,
+        Permission::Write,
+        verb,
+    )?
+    .mark_secret(
+        &scope.project,
+        &scope.namespace,
+        scope.prefix.as_deref(),
+        secret,
+    )
+}
+
+pub fn secret_marks(config: &SqliteConnectionConfig) -> CrudResult<SecretMarks> {
+    open_store(config)?.secret_marks()
+}
+
+/// Run `command` whenever the sweeper removes expired keys from `project.namespace`.
+pub fn set_expiry_hook(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    command: &str,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Create;
+    let scope = parse_namespace(config, scope, verb)?;
+    refuse_token(config, "expiry hooks", verb)?;
+    open_store(config)?.set_expiry_hook(&scope.project, &scope.namespace, command)
+}
+
+pub fn remove_expiry_hook(config: &SqliteConnectionConfig, scope: &str) -> CrudResult<bool> {
+    let verb = CrudVerb::Delete;
+    let scope = parse_namespace(config, scope, verb)?;
+    refuse_token(config, "expiry hooks", verb)?;
+    open_store(config)?.remove_expiry_hook(&scope.project, &scope.namespace)
+}
+
+pub fn expiry_hooks(config: &SqliteConnectionConfig) -> CrudResult<Vec<ExpiryHook>> {
+    open_store(config)?.expiry_hooks()
+}
+
+/// Start recording every write in `sys_oplog`; `false` when it already was.
+pub fn enable_oplog(config: &SqliteConnectionConfig) -> CrudResult<bool> {
+    let verb = CrudVerb::Create;
+    refuse_token(config, "the oplog", verb)?;
+    open_store(config)?.enable_oplog()
+}
+
+/// How the database splits addresses of four or more segments.
+pub fn address_layout(config: &SqliteConnectionConfig) -> CrudResult<AddressLayout> {
+    open_store(config)?.address_layout()
+}
+
+/// Record the database's address layout; configs that leave `address_layout`
+/// unset parse with it from then on.
+pub fn set_address_layout(
+    config: &SqliteConnectionConfig,
+    layout: AddressLayout,
+) -> CrudResult<()> {
+    refuse_token(config, "the address layout", CrudVerb::Update)?;
+    open_store(config)?.set_address_layout(layout)
+}
+
+/// Whether `set` may create namespaces in the database (see `ValidationMode`).
+pub fn validation_mode(config: &SqliteConnectionConfig) -> CrudResult<ValidationMode> {
+    open_store(config)?.validation_mode()
+}
+
+/// Record the database's validation mode; configs that leave `validation_mode`
+/// unset follow it from then on.
+pub fn set_validation_mode(
+    config: &SqliteConnectionConfig,
+    mode: ValidationMode,
+) -> CrudResult<()> {
+    refuse_token(config, "the validation mode", CrudVerb::Update)?;
+    open_store(config)?.set_validation_mode(mode)
+}
+
+/// Oplog rows after `since`, oldest first; `None` when the oplog was never enabled.
+pub fn changes_since(
+    config: &SqliteConnectionConfig,
+    since: OplogSince,
+    limit: usize,
+) -> CrudResult<Option<Vec<OplogEntry>>> {
+    let store = open_store(config)?;
+    if !store.oplog_enabled() {
+        return Ok(None);
+    }
+    store.changes_since(since, limit).map(Some)
+}
+
+/// Count live keys under `project.namespace[.prefix]`.
+pub fn count_keys(config: &SqliteConnectionConfig, scope: &str) -> CrudResult<u64> {
+    let verb = CrudVerb::Find;
+    let scope = parse_scope(config, scope, verb)?;
+    open_authorized(
+        config,
+        &scope.project,
+        &scope.namespace,
+        Permission::Read,
+        verb,
+    )?
+    .count(&scope)
+}
+
+/// Drop the TTL of a key so it becomes permanent; `false` when the key is missing.
+pub fn persist_value(config: &SqliteConnectionConfig, address: &str) -> CrudResult<bool> {
+    let addr = parse_address(config, address, CrudVerb::Update)?;
+    open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        CrudVerb::Update,
+    )?
+    .persist(&addr)
+}
+
+/// Give an existing key a TTL of `ttl_secs` (namespace TTL rules as in `set_value_with_ttl`).
+///
+/// Returns whether the key was found.
+pub fn expire_value(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    ttl_secs: u64,
+) -> CrudResult<bool> {
+    let verb = CrudVerb::Update;
+    let addr = parse_address(config, address, verb)?;
+    let store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        verb,
+    )?;
+    let ttl_secs = checked_ttl(&store, &addr, ttl_secs, verb)?;
+    store.expire(&addr, ttl_secs)
+}
+
+pub fn delete_value(config: &SqliteConnectionConfig, address: &str) -> CrudResult<bool> {
+    let addr = parse_address(config, address, CrudVerb::Delete)?;
+    open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        CrudVerb::Delete,
+    )?
+    .delete(&addr)
+}
+
+/// Live keys (every `__context` included) matched by glob `pattern`, such as
+/// `app.cache.*`, in address order.
+///
+/// `config.user` needs write access to each namespace with a match; one it
+/// lacks refuses the whole pattern instead of being skipped.
+pub fn keys_matching(config: &SqliteConnectionConfig, pattern: &str) -> CrudResult<Vec<Address>> {
+    deletable_matches(&open_store(config)?, config, pattern)
+}
+
+/// Delete every key `keys_matching` returns in one transaction; returns how many were removed.
+pub fn delete_matching(config: &SqliteConnectionConfig, pattern: &str) -> CrudResult<usize> {
+    let mut store = open_store(config)?;
+    let addrs = deletable_matches(&store, config, pattern)?;
+    store.delete_many(&addrs)
+}
+
+fn deletable_matches(
+    store: &SqliteKeystore,
+    config: &SqliteConnectionConfig,
+    pattern: &str,
+) -> CrudResult<Vec<Address>> {
+    let verb = CrudVerb::Delete;
+    let pattern = parse_pattern(config, pattern, verb)?;
+    let mut addrs: Vec<Address> = Vec::new();
+    store.for_each_match(&pattern, EntryOrder::default(), |entry| {
+        addrs.push(entry.address);
+        ControlFlow::Continue(())
+    })?;
+    let policy = store.access_policy()?;
+    let mut namespaces: Vec<(&str, &str)> = addrs
+        .iter()
+        .map(|addr| (addr.project.as_str(), addr.namespace.as_str()))
+        .collect();
+    namespaces.dedup();
+    check_writable(config, Permission::Write, verb)?;
+    for (project, namespace) in namespaces {
+        check_reserved(config, project, namespace, Permission::Write, verb)?;
+        policy.check(
+            config.user.as_deref(),
+            project,
+            namespace,
+            Permission::Write,
+            verb,
+        )?;
+    }
+    Ok(addrs)
+}
+
+/// Append `text` to a value (creating it when missing) without a get/set race.
+pub fn append_value(config: &SqliteConnectionConfig, address: &str, text: &str) -> CrudResult<()> {
+    let addr = parse_address(config, address, CrudVerb::Update)?;
+    let store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        CrudVerb::Update,
+    )?;
+    NamespaceKeys::load(&store, config)?.refuse(
+        &addr.project,
+        &addr.namespace,
+        "append",
+        CrudVerb::Update,
+    )?;
+    store.append(&addr, text)
+}
+
+/// Set a sub-path of the JSON value at `address` (read-modify-write in one transaction).
+pub fn json_set_value(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    path: &str,
+    value: &str,
+) -> CrudResult<()> {
+    let addr = parse_address(config, address, CrudVerb::Update)?;
+    let mut store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        CrudVerb::Update,
+    )?;
+    NamespaceKeys::load(&store, config)?.refuse(
+        &addr.project,
+        &addr.namespace,
+        "jset",
+        CrudVerb::Update,
+    )?;
+    store.json_set(&addr, path, value)
+}
+
+/// Read a sub-path of the JSON value at `address`.
+pub fn json_get_value(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    path: &str,
+) -> CrudResult<Option<String>> {
+    let addr = parse_address(config, address, CrudVerb::Read)?;
+    let store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Read,
+        CrudVerb::Read,
+    )?;
+    NamespaceKeys::load(&store, config)?.refuse(
+        &addr.project,
+        &addr.namespace,
+        "jget",
+        CrudVerb::Read,
+    )?;
+    store.json_get(&addr, path)
+}
+
+/// Remove a sub-path of the JSON value at `address`; `false` when nothing was there.
+pub fn json_delete_value(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    path: &str,
+) -> CrudResult<bool> {
+    let addr = parse_address(config, address, CrudVerb::Update)?;
+    let mut store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        CrudVerb::Update,
+    )?;
+    NamespaceKeys::load(&store, config)?.refuse(
+        &addr.project,
+        &addr.namespace,
+        "jdel",
+        CrudVerb::Update,
+    )?;
+    store.json_delete(&addr, path)
+}
+
+/// Atomically add `delta` (negative to decrement) and return the new value.
+pub fn increment_value(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    delta: i64,
+) -> CrudResult<i64> {
+    let addr = parse_address(config, address, CrudVerb::Update)?;
+    let mut store = open_authorized(
+        config,
+        &addr.project,
+        &addr.namespace,
+        Permission::Write,
+        CrudVerb::Update,
+    )?;
+    NamespaceKeys::load(&store, config)?.refuse(
+        &addr.project,
+        &addr.namespace,
+        "incr/decr",
+        CrudVerb::Update,
+    )?;
+    store.increment(&addr, delta)
+}
+
+/// Atomically move `src` to `dst`, preserving TTL and (by default) context.
+pub fn move_value(
+    config: &SqliteConnectionConfig,
+    src: &str,
+    dst: &str,
+    overwrite: bool,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Update;
+    let src = parse_address(config, src, verb)?;
+    let dst = parse_address(config, dst, verb)?;
+    let mut store = open_authorized_all(
+        config,
+        [(&src, Permission::Write), (&dst, Permission::Write)],
+        verb,
+    )?;
+    refuse_cross_namespace(
+        &store,
+        config,
+        (&src.project, &src.namespace),
+        (&dst.project, &dst.namespace),
+        verb,
+    )?;
+    let mut keys = NamespaceKeys::load(&store, config)?;
+    store.rename(&src, &dst, overwrite, |value| {
+        keys.reseal(&src, &dst, value)
+    })
+}
+
+/// Copy a single key; the destination inherits the source context when it has none.
+pub fn copy_value(
+    config: &SqliteConnectionConfig,
+    src: &str,
+    dst: &str,
+    overwrite: bool,
+    keep_ttl: bool,
+) -> CrudResult<()> {
+    let verb = CrudVerb::Create;
+    let src = parse_address(config, src, verb)?;
+    let dst = parse_address(config, dst, verb)?;
+    let mut store = open_authorized_all(
+        config,
+        [(&src, Permission::Read), (&dst, Permission::Write)],
+        verb,
+    )?;
+    refuse_cross_namespace(
+        &store,
+        config,
+        (&src.project, &src.namespace),
+        (&dst.project, &dst.namespace),
+        verb,
+    )?;
+    let mut keys = NamespaceKeys::load(&store, config)?;
+    store.copy(&src, &dst, overwrite, keep_ttl, |value| {
+        keys.reseal(&src, &dst, value)
+    })
+}
+
+/// Copy a whole namespace (`src` may carry a key prefix) into `dst` in one transaction.
+pub fn copy_namespace(
+    config: &SqliteConnectionConfig,
+    src: &str,
+    dst: &str,
+    keep_ttl: bool,
+) -> CrudResult<usize> {
+    let verb = CrudVerb::Create;
+    let src = parse_scope(config, src, verb)?;
+    let dst = parse_namespace(config, dst, verb)?;
+    let mut store = open_store(config)?;
+    let policy = store.access_policy()?;
+    let user = config.user.as_deref();
+    policy.check(user, &src.project, &src.namespace, Permission::Read, verb)?;
+    check_reserved(
+        config,
+        &dst.project,
+        &dst.namespace,
+        Permission::Write,
+        verb,
+    )?;
+    policy.check(user, &dst.project, &dst.namespace, Permission::Write, verb)?;
+    refuse_cross_namespace(
+        &store,
+        config,
+        (&src.project, &src.namespace),
+        (&dst.project, &dst.namespace),
+        verb,
+    )?;
+    store.copy_namespace(&src, &dst, keep_ttl)
+}
+
+/// Set many `(address, value)` pairs in a single transaction.
+///
+/// Every address is validated before the database is touched, so a typo in
+/// the last pair leaves the keystore unchanged.
+pub fn set_values(
+    config: &SqliteConnectionConfig,
+    entries: &[(String, String)],
+) -> CrudResult<usize> {
+    let parsed = entries
+        .iter()
+        .map(|(address, value)| {
+            Ok((
+                parse_address(config, address, CrudVerb::Create)?,
+                value.clone(),
+            ))
+        })
+        .collect::<CrudResult<Vec<_>>>()?;
+    let writes = parsed.iter().map(|(addr, _)| (addr, Permission::Write));
+    let mut store = open_authorized_all(config, writes, CrudVerb::Create)?;
+    for (addr, _) in &parsed {
+        check_namespace_known(&store, config, addr, CrudVerb::Create)?;
+    }
+    let mut keys = NamespaceKeys::load(&store, config)?;
+    let sealed = parsed
+        .into_iter()
+        .map(|(addr, value)| {
+            let value = keys.seal(&addr, &value)?;
+            Ok((addr, value))
+        })
+        .collect::<CrudResult<Vec<_>>>()?;
+    store.set_many(&sealed)
+}
+
+/// Bulk-load `(address, value, ttl)` rows in one transaction (see `ImportStrategy`).
+pub fn import_values(
+    config: &SqliteConnectionConfig,
+    entries: &[(String, String, Option<u64>)],
+    strategy: ImportStrategy,
+) -> CrudResult<ImportReport> {
+    let parsed = entries
+        .iter()
+        .map(|(address, value, ttl)| {
+            Ok((
+                parse_address(config, address, CrudVerb::Create)?,
+                value.clone(),
+                *ttl,
+            ))
+        })
+        .collect::<CrudResult<Vec<_>>>()?;
+    let writes = parsed.iter().map(|(addr, _, _)| (addr, Permission::Write));
+    let mut store = open_authorized_all(config, writes, CrudVerb::Create)?;
+    let mut keys = NamespaceKeys::load(&store, config)?;
+    let sealed = parsed
+        .into_iter()
+        .map(|(addr, value, ttl)| {
+            let value = keys.seal(&addr, &value)?;
+            Ok((addr, value, ttl))
+        })
+        .collect::<CrudResult<Vec<_>>>()?;
+    store.import_many(&sealed, strategy)
+}
+
+/// Get many addresses in a single read transaction; missing keys yield `None`.
+pub fn get_values(
+    config: &SqliteConnectionConfig,
+    addresses: &[String],
+) -> CrudResult<Vec<Option<String>>> {
+    let parsed = addresses
+        .iter()
+        .map(|address| parse_address(config, address, CrudVerb::Read))
+        .collect::<CrudResult<Vec<_>>>()?;
+    let reads = parsed.iter().map(|addr| (addr, Permission::Read));
+    let mut store = open_authorized_all(config, reads, CrudVerb::Read)?;
+    let values = store.get_many(&parsed)?;
+    let mut keys = NamespaceKeys::load(&store, config)?;
+    parsed
+        .iter()
+        .zip(values)
+        .map(|(addr, value)| value.map(|value| keys.open(addr, value)).transpose())
+        .collect()
+}
+
+/// Open the keystore once `config.user` is known to hold `need` in `project.namespace`.
+fn open_authorized(
+    config: &SqliteConnectionConfig,
+    project: &str,
+    namespace: &str,
+    need: Permission,
+    verb: CrudVerb,
+) -> CrudResult<PooledKeystore> {
+    check_writable(config, need, verb)?;
+    check_reserved(config, project, namespace, need, verb)?;
+    let store = open_store(config)?;
+    store.check_access(config.user.as_deref(), project, namespace, need, verb)?;
+    Ok(store)
+}
+
+/// Writes need a writable `config`: read-only mode refuses them before SQLite would.
+pub(super) fn check_writable(
+    config: &SqliteConnectionConfig,
+    need: Permission,
+    verb: CrudVerb,
+) -> CrudResult<()> {
+    if need == Permission::Read || !config.read_only {
+        return Ok(());
+    }
+    Err(CrudError::permission_denied(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Record,
+        verb,
+        "refused in read-only mode (drop --read-only or use a writable cursor)",
+    ))
+}
+
+/// Writes to a reserved namespace (`sys.*`, `pipe.cache`) need `config.system_access`;
+/// reads are always allowed.
+pub(super) fn check_reserved(
+    config: &SqliteConnectionConfig,
+    project: &str,
+    namespace: &str,
+    need: Permission,
+    verb: CrudVerb,
+) -> CrudResult<()> {
+    if need == Permission::Read
+        || config.system_access
+        || !is_reserved_namespace(project, namespace)
+    {
+        return Ok(());
+    }
+    Err(CrudError::permission_denied(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Record,
+        verb,
+        format!(
+            "{}.{} is a reserved system namespace (pass --system to change it)",
+            project, namespace
+        ),
+    ))
+}
+
+/// `open_authorized` for several addresses, checked against one ACL snapshot.
+fn open_authorized_all<'a, I>(
+    config: &SqliteConnectionConfig,
+    needs: I,
+    verb: CrudVerb,
+) -> CrudResult<PooledKeystore>
+where
+    I: IntoIterator<Item = (&'a Address, Permission)>,
+{
+    let store = open_store(config)?;
+    let policy = store.access_policy()?;
+    for (addr, need) in needs {
+        check_writable(config, need, verb)?;
+        check_reserved(config, &addr.project, &addr.namespace, need, verb)?;
+        policy.check(
+            config.user.as_deref(),
+            &addr.project,
+            &addr.namespace,
+            need,
+            verb,
+        )?;
+    }
+    Ok(store)
+}
+
+/// `PermissionDenied` for remote callers: `what` (hooks run shell commands) stays local-only.
+fn refuse_token(config: &SqliteConnectionConfig, what: &str, verb: CrudVerb) -> CrudResult<()> {
+    if config.token.is_none() {
+        return Ok(());
+    }
+    Err(CrudError::permission_denied(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Record,
+        verb,
+        format!("{} cannot be changed with an API token", what),
+    ))
+}
+
+/// Values cannot move between an encrypted namespace and any other, since keys differ.
+fn refuse_cross_namespace(
+    store: &SqliteKeystore,
+    config: &SqliteConnectionConfig,
+    src: (&str, &str),
+    dst: (&str, &str),
+    verb: CrudVerb,
+) -> CrudResult<()> {
+    if src == dst {
+        return Ok(());
+    }
+    let keys = NamespaceKeys::load(store, config)?;
+    keys.refuse(src.0, src.1, "moving values out of another namespace", verb)?;
+    keys.refuse(dst.0, dst.1, "moving values into another namespace", verb)
+}
+
+/// `store.projects()` narrowed to those with a namespace `config.user` may read.
+pub(super) fn readable_projects(
+    store: &SqliteKeystore,
+    config: &SqliteConnectionConfig,
+) -> CrudResult<Vec<String>> {
+    let policy = store.access_policy()?;
+    let mut projects = Vec::new();
+    for project in store.projects()? {
+        if !readable_namespaces(store, config, &policy, &project)?.is_empty() {
+            projects.push(project);
+        }
+    }
+    Ok(projects)
+}
+
+/// `store.namespaces(project)` narrowed to those `policy` lets `config.user` read.
+pub(super) fn readable_namespaces(
+    store: &SqliteKeystore,
+    config: &SqliteConnectionConfig,
+    policy: &AccessPolicy,
+    project: &str,
+) -> CrudResult<Vec<String>> {
+    let mut namespaces = store.namespaces(project)?;
+    namespaces.retain(|namespace| {
+        policy.allows(config.user.as_deref(), project, namespace, Permission::Read)
+    });
+    Ok(namespaces)
+}
+
+/// Wrap `visit` so rows `config.user` may not read are skipped (scans spanning namespaces).
+fn readable_only<F>(
+    config: &SqliteConnectionConfig,
+    policy: AccessPolicy,
+    mut visit: F,
+) -> impl FnMut(KvEntry) -> ControlFlow<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    let user = config.user.clone();
+    move |entry| {
+        let addr = &entry.address;
+        if policy.allows(
+            user.as_deref(),
+            &addr.project,
+            &addr.namespace,
+            Permission::Read,
+        ) {
+            visit(entry)
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Parse `address` in the database's layout, filling omitted segments from the
+/// config's address defaults.
+pub(super) fn parse_address(
+    config: &SqliteConnectionConfig,
+    address: &str,
+    verb: CrudVerb,
+) -> CrudResult<Address> {
+    let layout = match config.address_layout {
+        Some(layout) => layout,
+        None => SqliteKeystore::stored_address_layout(config.database_path())?,
+    };
+    let mut addr =
+        Address::parse_in_layout(address, DEFAULT_NS_DELIM, &config.address_defaults, layout)
+            .map_err(|err| coded_invalid_input(ErrorCode::InvalidAddress, verb, err.to_string()))?;
+    addr.project = meta_project(config, addr.project, verb)?;
+    Ok(addr)
+}
+
+pub(super) fn parse_scope(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    verb: CrudVerb,
+) -> CrudResult<NamespaceScope> {
+    let mut parsed = NamespaceScope::parse(scope)
+        .map_err(|err| coded_invalid_input(ErrorCode::InvalidScope, verb, err.to_string()))?;
+    parsed.project = meta_project(config, parsed.project, verb)?;
+    Ok(parsed)
+}
+
+/// `project` as stored under `config.meta_context` (`<meta>.<project>`); unchanged without one.
+///
+/// A meta context is a single plain segment: no delimiter, `__`, globs or whitespace.
+fn meta_project(
+    config: &SqliteConnectionConfig,
+    project: String,
+    verb: CrudVerb,
+) -> CrudResult<String> {
+    let Some(meta) = config.meta_context.as_deref() else {
+        return Ok(project);
+    };
+    let plain = !meta.is_empty()
+        && !meta.contains(DEFAULT_NS_DELIM)
+        && !meta.contains("__")
+        && !KeyPattern::has_glob(meta)
+        && !meta.chars().any(char::is_whitespace);
+    if !plain {
+        return Err(coded_invalid_input(
+            ErrorCode::InvalidAddress,
+            verb,
+            format!("invalid meta context '{}'", meta),
+        ));
+    }
+    Ok(format!("{}{}{}", meta, DEFAULT_NS_DELIM, project))
+}
+
+/// `None`, `project`, or `project.namespace` as optional filters.
+fn parse_database_scope(
+    config: &SqliteConnectionConfig,
+    scope: Option<&str>,
+    verb: CrudVerb,
+) -> CrudResult<(Option<String>, Option<String>)> {
+    match scope {
+        None => Ok((None, None)),
+        Some(project) if !project.contains(DEFAULT_NS_DELIM) => {
+            Ok((Some(meta_project(config, project.to_string(), verb)?), None))
+        }
+        Some(scope) => {
+            let scope = parse_namespace(config, scope, verb)?;
+            Ok((Some(scope.project), Some(scope.namespace)))
+        }
+    }
+}
+
+fn parse_regex(regex: &str, verb: CrudVerb) -> CrudResult<Regex> {
+    Regex::new(regex).map_err(|err| {
+        coded_invalid_input(
+            ErrorCode::InvalidPattern,
+            verb,
+            format!("invalid regex '{}': {}", regex, err),
+        )
+    })
+}
+
+fn parse_pattern(
+    config: &SqliteConnectionConfig,
+    pattern: &str,
+    verb: CrudVerb,
+) -> CrudResult<KeyPattern> {
+    let mut parsed = KeyPattern::parse(pattern)
+        .map_err(|err| coded_invalid_input(ErrorCode::InvalidScope, verb, err.to_string()))?;
+    parsed.project = meta_project(config, parsed.project, verb)?;
+    Ok(parsed)
+}
+
+/// Like `parse_scope`, but a key prefix is rejected.
+fn parse_namespace(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    verb: CrudVerb,
+) -> CrudResult<NamespaceScope> {
+    let parsed = parse_scope(config, scope, verb)?;
+    if parsed.prefix.is_some() {
+        return Err(coded_invalid_input(
+            ErrorCode::InvalidScope,
+            verb,
+            format!("expected project.namespace, got '{}'", scope),
+        ));
+    }
+    Ok(parsed)
+}
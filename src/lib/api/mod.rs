@@ -0,0 +1,50 @@
+//! Application API: string addresses in, keystore operations out.
+//! MODULE_SPEC: orchestrator only; operations live in sibling files.
+
+mod backend;
+mod backup;
+mod client;
+#[cfg(feature = "async")]
+mod client_async;
+mod error;
+mod kv;
+mod options;
+mod pool;
+mod remote;
+mod session;
+mod stream;
+mod sync;
+mod vault;
+
+pub use backend::BackendClient;
+pub use backup::{
+    create_backup, default_backup_dir, list_backups, merge_backup, preview_restore, prune_backups,
+    restore_backup, snapshot_database, verify_backup, BackupArchive, BackupKind, BackupOptions,
+    BackupRetention, BackupVerification, PruneReport, RestoreChange, RestorePreview,
+};
+pub use client::ProntoClient;
+#[cfg(feature = "async")]
+pub use client_async::AsyncProntoClient;
+pub use error::{ErrorCode, ProntoError};
+pub use kv::{
+    address_layout, allow_key_ttl, append_value, changes_since, copy_namespace, copy_value,
+    count_keys, create_cache, delete_matching, delete_value, enable_oplog, entries_inserted_after,
+    expire_value, expiry_hooks, export_entries, get_entry, get_value, get_value_including_expired,
+    get_values, grep_values, import_values, increment_value, json_delete_value, json_get_value,
+    json_set_value, keys_matching, list_contexts, list_entries, list_entries_in_range,
+    list_entries_matching, list_entries_where, list_namespaces, list_projects, mark_secret,
+    move_value, persist_value, remove_expiry_hook, resolve_address, retention_policies,
+    scan_entries, scan_entries_in_range, scan_entries_matching, scan_entries_sorted,
+    scan_entries_where, search_values, secret_marks, set_address_layout, set_expiry_hook,
+    set_retention, set_typed_value, set_validation_mode, set_value, set_value_if,
+    set_value_with_ttl, set_values, validation_mode, value_exists,
+};
+pub use options::StorageOptions;
+pub use pool::close_idle_connections;
+pub use remote::{remote_target, RemoteTarget};
+pub use session::{KeyChange, Session};
+pub use stream::{scan_iter, EntryIter};
+pub use sync::{
+    diff_databases, import_database, merge_databases, scan_databases, sync_database, DatabaseDiff,
+    DatabaseImport, KeyDiff, MergeConflict, MergeReport, MergeSide, MergeStrategy, SyncDirection,
+};
@@ -0,0 +1,202 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::lib::addr::{AddressDefaults, AddressLayout};
+use crate::lib::adpt::sqlite::{
+    SqliteConnectionConfig, SqliteKeystore, SqlitePathResolver, ValidationMode,
+};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+use crate::lib::cursor::{CursorData, CursorManager, DEFAULT_USER};
+
+/// Which database to open and how, resolved into a `SqliteConnectionConfig`.
+///
+/// The database is the first of: an explicit `path`, registry database
+/// `database` (see `db create`), the cursor (`cursor`, looked up for
+/// `user`), the resolver default. A cursor also supplies the
+/// default project/namespace, each falling back to the user's saved
+/// `UserDefaults`, and can force read-only. The CLI builds one of these from
+/// its flags; library callers build their own.
+#[derive(Clone, Debug)]
+pub struct StorageOptions {
+    path: Option<PathBuf>,
+    database: Option<String>,
+    cursor: Option<String>,
+    cursor_home: Option<PathBuf>,
+    user: Option<String>,
+    key_file: Option<PathBuf>,
+    read_only: bool,
+    system_access: bool,
+    busy_timeout: Option<Duration>,
+    create_if_missing: bool,
+    reuse_connections: bool,
+    address_layout: Option<AddressLayout>,
+    validation_mode: Option<ValidationMode>,
+}
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StorageOptions {
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            database: None,
+            cursor: None,
+            cursor_home: None,
+            user: None,
+            key_file: None,
+            read_only: false,
+            system_access: false,
+            busy_timeout: None,
+            create_if_missing: true,
+            reuse_connections: true,
+            address_layout: None,
+            validation_mode: None,
+        }
+    }
+
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Registry database `<cursor home>/<name>/<name>.sqlite3`.
+    pub fn database<S: Into<String>>(mut self, name: S) -> Self {
+        self.database = Some(name.into());
+        self
+    }
+
+    /// Named cursor of `user` (else the default user), looked up on `resolve`.
+    pub fn cursor<S: Into<String>>(mut self, name: S) -> Self {
+        self.cursor = Some(name.into());
+        self
+    }
+
+    /// Cursor home for `database` and `cursor`; defaults to `CursorManager::new()`'s.
+    pub fn cursor_home<P: AsRef<Path>>(mut self, home: P) -> Self {
+        self.cursor_home = Some(home.as_ref().to_path_buf());
+        self
+    }
+
+    /// Acting user for namespace ACLs and owner of `cursor`.
+    pub fn user<S: Into<String>>(mut self, user: Option<S>) -> Self {
+        self.user = user.map(Into::into);
+        self
+    }
+
+    pub fn key_file<P: AsRef<Path>>(mut self, key_file: Option<P>) -> Self {
+        self.key_file = key_file.map(|path| path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Allow writes and deletes in reserved namespaces (`sys.*`, `pipe.cache`).
+    pub fn system_access(mut self, system: bool) -> Self {
+        self.system_access = system;
+        self
+    }
+
+    pub fn busy_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.busy_timeout = timeout;
+        self
+    }
+
+    pub fn create_if_missing(mut self, create: bool) -> Self {
+        self.create_if_missing = create;
+        self
+    }
+
+    /// Share idle connections between `api` calls and sessions in this process (the default).
+    pub fn reuse_connections(mut self, reuse: bool) -> Self {
+        self.reuse_connections = reuse;
+        self
+    }
+
+    /// Override the address layout recorded in the database.
+    pub fn address_layout(mut self, layout: Option<AddressLayout>) -> Self {
+        self.address_layout = layout;
+        self
+    }
+
+    /// Override the validation mode recorded in the database.
+    pub fn validation_mode(mut self, mode: Option<ValidationMode>) -> Self {
+        self.validation_mode = mode;
+        self
+    }
+
+    /// The connection config these options describe; a named cursor that does not exist is `NotFound`.
+    pub fn resolve(&self) -> CrudResult<SqliteConnectionConfig> {
+        let cursor = match &self.cursor {
+            Some(name) => Some(self.find_cursor(name)?),
+            None => None,
+        };
+        Ok(self.resolve_with(cursor.as_ref()))
+    }
+
+    /// `resolve` with a cursor the caller already looked up (the CLI does so once
+    /// per invocation); `cursor` names are ignored.
+    pub fn resolve_with(&self, cursor: Option<&CursorData>) -> SqliteConnectionConfig {
+        let database_path = match (&self.path, &self.database, cursor) {
+            (Some(path), _, _) => path.clone(),
+            (None, Some(name), _) => self.manager().database_path(name),
+            (None, None, Some(cursor)) => cursor.database_path.clone(),
+            (None, None, None) => SqlitePathResolver::database_path_from_env(CrudDomain::Sqlite),
+        };
+        // A corrupt defaults file is reported by `cursor defaults`, not on every open.
+        let own = self
+            .manager()
+            .user_defaults(self.user.as_deref().unwrap_or(DEFAULT_USER))
+            .unwrap_or_default();
+        let project = cursor.and_then(|cursor| cursor.default_project.as_deref());
+        let namespace = cursor.and_then(|cursor| cursor.default_namespace.as_deref());
+        let defaults = AddressDefaults::new(
+            project.or(own.project.as_deref()),
+            namespace.or(own.namespace.as_deref()),
+        );
+        let read_only = self.read_only || cursor.is_some_and(|cursor| cursor.read_only);
+        // Read once here so each address parsed later need not; a database that
+        // cannot be read yet is left to fail on first use.
+        let layout = self
+            .address_layout
+            .or_else(|| SqliteKeystore::stored_address_layout(&database_path).ok());
+        SqliteConnectionConfig::new(database_path)
+            .with_address_defaults(defaults)
+            .with_address_layout(layout)
+            .with_validation_mode(self.validation_mode)
+            .with_user(self.user.clone())
+            .with_key_file(self.key_file.clone())
+            .with_read_only(read_only)
+            .with_system_access(self.system_access)
+            .with_busy_timeout(self.busy_timeout)
+            .with_create_if_missing(self.create_if_missing)
+            .with_connection_reuse(self.reuse_connections)
+    }
+
+    fn manager(&self) -> CursorManager {
+        match &self.cursor_home {
+            Some(home) => CursorManager::with_home(home),
+            None => CursorManager::new(),
+        }
+    }
+
+    fn find_cursor(&self, name: &str) -> CrudResult<CursorData> {
+        let user = self.user.as_deref().unwrap_or(DEFAULT_USER);
+        let cwd = env::current_dir().unwrap_or_default();
+        self.manager().resolve(name, user, &cwd)?.ok_or_else(|| {
+            CrudError::not_found(
+                CrudDomain::Filesystem,
+                CrudObjectKind::Alias,
+                CrudVerb::Read,
+                format!("cursor '{}' not found for user '{}'", name, user),
+            )
+        })
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use crate::lib::adpt::sqlite::{SqliteConnectionConfig, SqliteKeystore};
+use crate::lib::core::crud::CrudResult;
+
+/// Idle connections kept per database and identity; more are closed on return.
+const MAX_IDLE: usize = 4;
+
+/// What a connection was opened with; only identical configs share one.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct PoolKey {
+    path: PathBuf,
+    read_only: bool,
+    journal_wal: bool,
+    user: Option<String>,
+    busy_timeout: Option<Duration>,
+    create_if_missing: bool,
+}
+
+impl PoolKey {
+    fn of(config: &SqliteConnectionConfig) -> Self {
+        Self {
+            path: config.database_path.clone(),
+            read_only: config.read_only,
+            journal_wal: config.journal_wal,
+            user: config.user.clone(),
+            busy_timeout: config.busy_timeout,
+            create_if_missing: config.create_if_missing,
+        }
+    }
+}
+
+static IDLE: OnceLock<Mutex<HashMap<PoolKey, Vec<SqliteKeystore>>>> = OnceLock::new();
+
+fn idle() -> MutexGuard<'static, HashMap<PoolKey, Vec<SqliteKeystore>>> {
+    let pool = IDLE.get_or_init(Default::default);
+    // Idle connections hold no transaction, so a panic elsewhere leaves them usable.
+    match pool.lock() {
+        Ok(pool) => pool,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// A keystore for one `api` call; with `reuse_connections` it goes back to the
+/// per-process pool when dropped instead of closing.
+pub(super) struct PooledKeystore {
+    store: Option<SqliteKeystore>,
+    key: Option<PoolKey>,
+}
+
+impl Deref for PooledKeystore {
+    type Target = SqliteKeystore;
+
+    fn deref(&self) -> &SqliteKeystore {
+        self.store.as_ref().expect("keystore taken before drop")
+    }
+}
+
+impl DerefMut for PooledKeystore {
+    fn deref_mut(&mut self) -> &mut SqliteKeystore {
+        self.store.as_mut().expect("keystore taken before drop")
+    }
+}
+
+impl Drop for PooledKeystore {
+    fn drop(&mut self) {
+        if let (Some(store), Some(key)) = (self.store.take(), self.key.take()) {
+            let mut pool = idle();
+            let stores = pool.entry(key).or_default();
+            if stores.len() < MAX_IDLE {
+                stores.push(store);
+            }
+        }
+    }
+}
+
+/// `SqliteKeystore::open`, or an idle connection opened with the same config.
+///
+/// A reused connection re-authenticates `config.token`, so a revoked token
+/// is noticed on the next call.
+pub(super) fn open_store(config: &SqliteConnectionConfig) -> CrudResult<PooledKeystore> {
+    if !config.reuse_connections {
+        return Ok(PooledKeystore {
+            store: Some(SqliteKeystore::open(config)?),
+            key: None,
+        });
+    }
+    let key = PoolKey::of(config);
+    let reused = idle().get_mut(&key).and_then(Vec::pop);
+    let store = match reused {
+        Some(mut store) => {
+            store.refresh(config)?;
+            store
+        }
+        None => SqliteKeystore::open(config)?,
+    };
+    Ok(PooledKeystore {
+        store: Some(store),
+        key: Some(key),
+    })
+}
+
+/// Close every idle pooled connection (to `path` only, when given); returns how many.
+///
+/// Call it after replacing or deleting a database file outside `api`, since
+/// idle connections keep the old file open.
+pub fn close_idle_connections(path: Option<&Path>) -> usize {
+    let mut pool = idle();
+    let mut closed = 0;
+    pool.retain(|key, stores| {
+        if path.is_none_or(|path| key.path == path) {
+            closed += stores.len();
+            false
+        } else {
+            true
+        }
+    });
+    closed
+}
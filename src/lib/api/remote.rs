@@ -0,0 +1,421 @@
+use std::path::Path;
+
+#[cfg(any(feature = "backup-s3", feature = "backup-sftp"))]
+use std::env;
+#[cfg(any(feature = "backup-s3", feature = "backup-sftp"))]
+use std::fs::File;
+#[cfg(feature = "backup-s3")]
+use std::io::{self, Read};
+#[cfg(feature = "backup-sftp")]
+use std::net::TcpStream;
+#[cfg(feature = "backup-sftp")]
+use std::path::PathBuf;
+#[cfg(feature = "backup-s3")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "backup-s3")]
+use hmac::{Hmac, Mac};
+#[cfg(any(feature = "backup-s3", feature = "backup-sftp"))]
+use hub::error_ext::anyhow;
+#[cfg(feature = "backup-s3")]
+use sha2::{Digest, Sha256};
+#[cfg(feature = "backup-sftp")]
+use ssh2::{CheckResult, KnownHostFileKind, Session};
+
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+#[cfg(feature = "backup-s3")]
+use crate::lib::core::helpers::format_timestamp;
+
+/// A place `backup --output` copies an archive to once it is written locally.
+pub trait RemoteTarget {
+    /// Upload `archive` under its file name; returns where it went.
+    fn upload(&self, archive: &Path) -> CrudResult<String>;
+}
+
+/// The remote target `output` names (`s3://bucket/prefix`, `sftp://[user@]host[:port]/dir`);
+/// `None` for a local directory.
+///
+/// Parsing works in every build, so a target whose feature is missing fails
+/// on upload with a message naming it rather than becoming a local path.
+pub fn remote_target(output: &str) -> CrudResult<Option<Box<dyn RemoteTarget>>> {
+    let verb = CrudVerb::Backup;
+    if let Some(rest) = output.strip_prefix("s3://") {
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(bad_target(output, verb, "it names no bucket"));
+        }
+        return Ok(Some(Box::new(S3Target {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })));
+    }
+    if let Some(rest) = output.strip_prefix("sftp://") {
+        let (authority, dir) = rest.split_once('/').unwrap_or((rest, ""));
+        let (user, host) = match authority.rsplit_once('@') {
+            Some((user, host)) => (Some(user.to_string()), host),
+            None => (None, authority),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) => match port.parse::<u16>() {
+                Ok(port) => (host, port),
+                Err(_) => return Err(bad_target(output, verb, "its port is not a number")),
+            },
+            None => (host, 22),
+        };
+        if host.is_empty() {
+            return Err(bad_target(output, verb, "it names no host"));
+        }
+        return Ok(Some(Box::new(SftpTarget {
+            user,
+            host: host.to_string(),
+            port,
+            dir: format!("/{}", dir.trim_end_matches('/')),
+        })));
+    }
+    match output.split_once("://") {
+        Some((scheme, _)) => Err(bad_target(
+            output,
+            verb,
+            &format!("{}:// is not a backup target (s3://, sftp://)", scheme),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// `s3://bucket/prefix`, signed with SigV4 from the standard `AWS_*` variables.
+///
+/// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and the optional
+/// `AWS_SESSION_TOKEN` sign the request; `AWS_REGION` (or
+/// `AWS_DEFAULT_REGION`, else `us-east-1`) picks the endpoint, and
+/// `AWS_ENDPOINT_URL` points at an S3-compatible store with path-style URLs.
+#[cfg_attr(not(feature = "backup-s3"), allow(dead_code))]
+struct S3Target {
+    bucket: String,
+    prefix: String,
+}
+
+/// `sftp://[user@]host[:port]/dir`, checked against `~/.ssh/known_hosts`.
+///
+/// Logs in as the URL user (else `$USER`) with `PRONTO_SFTP_KEY` (a private
+/// key, `PRONTO_SFTP_PASSPHRASE` if it has one), else the ssh-agent, else
+/// `PRONTO_SFTP_PASSWORD`.
+#[cfg_attr(not(feature = "backup-sftp"), allow(dead_code))]
+struct SftpTarget {
+    user: Option<String>,
+    host: String,
+    port: u16,
+    dir: String,
+}
+
+#[cfg(feature = "backup-s3")]
+impl RemoteTarget for S3Target {
+    fn upload(&self, archive: &Path) -> CrudResult<String> {
+        let verb = CrudVerb::Backup;
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let (access_key, secret_key) =
+            match (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+                (Some(access_key), Some(secret_key)) => (access_key, secret_key),
+                _ => {
+                    return Err(CrudError::permission_denied(
+                        CrudDomain::Sqlite,
+                        CrudObjectKind::Base,
+                        verb,
+                        "s3:// uploads need AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY",
+                    ))
+                }
+            };
+        let region = var("AWS_REGION")
+            .or_else(|| var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        let key = match self.prefix.as_str() {
+            "" => file_name(archive),
+            prefix => format!("{}/{}", prefix, file_name(archive)),
+        };
+        let (base, path) = match var("AWS_ENDPOINT_URL") {
+            Some(endpoint) => (
+                endpoint.trim_end_matches('/').to_string(),
+                format!("/{}/{}", uri_encode(&self.bucket), uri_encode(&key)),
+            ),
+            None => (
+                format!("https://{}.s3.{}.amazonaws.com", self.bucket, region),
+                format!("/{}", uri_encode(&key)),
+            ),
+        };
+        let host = base
+            .split_once("://")
+            .map_or(base.as_str(), |(_, host)| host)
+            .to_string();
+
+        let length = archive.metadata().map_err(|err| io_error(verb, err))?.len();
+        let payload_hash = hex(&sha256_file(archive).map_err(|err| io_error(verb, err))?);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default();
+        let amz_date: String = format_timestamp(now)
+            .chars()
+            .filter(|ch| !matches!(ch, '-' | ':'))
+            .collect();
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/s3/aws4_request", date, region);
+
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = var("AWS_SESSION_TOKEN") {
+            headers.push(("x-amz-security-token", token));
+        }
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let canonical_request = format!(
+            "PUT\n{}\n\n{}\n{}\n{}",
+            path, canonical_headers, signed_headers, payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signing_key = [date, region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", secret_key).into_bytes(), |key, part| {
+                hmac_sha256(&key, part.as_bytes())
+            });
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            access_key,
+            scope,
+            signed_headers,
+            hex(&hmac_sha256(&signing_key, string_to_sign.as_bytes()))
+        );
+
+        let url = format!("{}{}", base, path);
+        let mut request = ureq::put(&url)
+            .set("Authorization", &authorization)
+            .set("Content-Length", &length.to_string());
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.set(name, value);
+        }
+        let body = File::open(archive).map_err(|err| io_error(verb, err))?;
+        match request.send(body) {
+            Ok(_) => Ok(format!("s3://{}/{}", self.bucket, key)),
+            Err(ureq::Error::Status(status, response)) => Err(upload_failed(
+                verb,
+                &url,
+                format!(
+                    "HTTP {}: {}",
+                    status,
+                    response.into_string().unwrap_or_default().trim()
+                ),
+            )),
+            Err(err) => Err(upload_failed(verb, &url, err.to_string())),
+        }
+    }
+}
+
+#[cfg(not(feature = "backup-s3"))]
+impl RemoteTarget for S3Target {
+    fn upload(&self, _archive: &Path) -> CrudResult<String> {
+        Err(feature_missing("s3", "backup-s3"))
+    }
+}
+
+#[cfg(feature = "backup-sftp")]
+impl RemoteTarget for SftpTarget {
+    fn upload(&self, archive: &Path) -> CrudResult<String> {
+        let verb = CrudVerb::Backup;
+        let location = format!("sftp://{}:{}{}", self.host, self.port, self.dir);
+        let failed = |err: ssh2::Error| upload_failed(verb, &location, err.to_string());
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let user = self
+            .user
+            .clone()
+            .or_else(|| var("USER"))
+            .unwrap_or_else(|| "root".to_string());
+
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .map_err(|err| upload_failed(verb, &location, err.to_string()))?;
+        let mut session = Session::new().map_err(failed)?;
+        session.set_tcp_stream(tcp);
+        session.handshake().map_err(failed)?;
+
+        let (host_key, _) = session
+            .host_key()
+            .ok_or_else(|| upload_failed(verb, &location, "no host key offered".to_string()))?;
+        let mut known = session.known_hosts().map_err(failed)?;
+        if let Some(home) = var("HOME") {
+            let _ = known.read_file(
+                &PathBuf::from(home).join(".ssh/known_hosts"),
+                KnownHostFileKind::OpenSSH,
+            );
+        }
+        if !matches!(
+            known.check_port(&self.host, self.port, host_key),
+            CheckResult::Match
+        ) {
+            return Err(CrudError::permission_denied(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Base,
+                verb,
+                format!(
+                    "{} is not a known host; add it to ~/.ssh/known_hosts first",
+                    self.host
+                ),
+            ));
+        }
+
+        match var("PRONTO_SFTP_KEY") {
+            Some(key) => session
+                .userauth_pubkey_file(
+                    &user,
+                    None,
+                    Path::new(&key),
+                    var("PRONTO_SFTP_PASSPHRASE").as_deref(),
+                )
+                .map_err(failed)?,
+            None => {
+                if session.userauth_agent(&user).is_err() {
+                    if let Some(password) = var("PRONTO_SFTP_PASSWORD") {
+                        session
+                            .userauth_password(&user, &password)
+                            .map_err(failed)?;
+                    }
+                }
+            }
+        }
+        if !session.authenticated() {
+            return Err(CrudError::permission_denied(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Base,
+                verb,
+                format!(
+                    "{}@{} refused every key; set PRONTO_SFTP_KEY or PRONTO_SFTP_PASSWORD",
+                    user, self.host
+                ),
+            ));
+        }
+
+        // Written under a dot name and renamed, so a listing never shows half an archive.
+        let sftp = session.sftp().map_err(failed)?;
+        let name = file_name(archive);
+        let target = Path::new(&self.dir).join(&name);
+        let partial = Path::new(&self.dir).join(format!(".{}.partial", name));
+        let mut source = File::open(archive).map_err(|err| io_error(verb, err))?;
+        let mut remote = sftp.create(&partial).map_err(failed)?;
+        std::io::copy(&mut source, &mut remote)
+            .map_err(|err| upload_failed(verb, &location, err.to_string()))?;
+        drop(remote);
+        sftp.rename(&partial, &target, None).map_err(failed)?;
+        Ok(format!(
+            "sftp://{}@{}:{}{}",
+            user,
+            self.host,
+            self.port,
+            target.display()
+        ))
+    }
+}
+
+#[cfg(not(feature = "backup-sftp"))]
+impl RemoteTarget for SftpTarget {
+    fn upload(&self, _archive: &Path) -> CrudResult<String> {
+        Err(feature_missing("sftp", "backup-sftp"))
+    }
+}
+
+#[cfg(feature = "backup-s3")]
+fn sha256_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        match file.read(&mut buf)? {
+            0 => return Ok(hasher.finalize().to_vec()),
+            read => hasher.update(&buf[..read]),
+        }
+    }
+}
+
+#[cfg(feature = "backup-s3")]
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(feature = "backup-s3")]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent-encode `path` for a SigV4 canonical URI, keeping `/` between segments.
+#[cfg(feature = "backup-s3")]
+fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(any(feature = "backup-s3", feature = "backup-sftp"))]
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn bad_target(output: &str, verb: CrudVerb, detail: &str) -> CrudError {
+    CrudError::invalid_input(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Base,
+        verb,
+        format!("bad backup target {}: {}", output, detail),
+    )
+}
+
+#[cfg(any(feature = "backup-s3", feature = "backup-sftp"))]
+fn upload_failed(verb: CrudVerb, location: &str, detail: String) -> CrudError {
+    CrudError::internal(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Base,
+        verb,
+        anyhow::anyhow!("upload to {} failed: {}", location, detail),
+    )
+}
+
+#[cfg(any(feature = "backup-s3", feature = "backup-sftp"))]
+fn io_error(verb: CrudVerb, err: std::io::Error) -> CrudError {
+    CrudError::internal(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Base,
+        verb,
+        anyhow::Error::new(err),
+    )
+}
+
+#[cfg(any(not(feature = "backup-s3"), not(feature = "backup-sftp")))]
+fn feature_missing(scheme: &str, feature: &str) -> CrudError {
+    CrudError::invalid_input(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Base,
+        CrudVerb::Backup,
+        format!(
+            "{}:// backup targets need prontodb built with the {} feature",
+            scheme, feature
+        ),
+    )
+}
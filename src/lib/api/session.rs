@@ -0,0 +1,204 @@
+use std::ops::ControlFlow;
+
+use crate::lib::addr::Address;
+use crate::lib::adpt::sqlite::{
+    EntryOrder, KvEntry, Permission, SecretMarks, SqliteConnectionConfig,
+};
+use crate::lib::core::crud::{CrudResult, CrudVerb};
+
+use super::kv::{
+    check_namespace_known, check_reserved, check_writable, checked_ttl, parse_address,
+    readable_namespaces, readable_projects, scan_store,
+};
+use super::pool::{open_store, PooledKeystore};
+use super::stream::{scan_iter_store, EntryIter};
+use super::vault::NamespaceKeys;
+
+/// A write made through a `Session`: the new value, or `None` for a delete.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KeyChange {
+    pub address: Address,
+    pub value: Option<String>,
+    /// The address is marked secret; listeners that print values should mask it.
+    pub secret: bool,
+}
+
+/// One keystore connection reused across calls, for long-running front-ends such as `serve`.
+///
+/// Each call behaves like the `api` function of the same name given
+/// `config` (ACLs, TTL policy, encrypted namespaces, read-only mode); only
+/// the connection and its schema setup are kept between calls. With
+/// `config.reuse_connections` the connection returns to the per-process
+/// pool when the session is dropped, so short sessions (one per request)
+/// stay cheap.
+pub struct Session {
+    config: SqliteConnectionConfig,
+    store: PooledKeystore,
+    listener: Option<Box<dyn Fn(&KeyChange) + Send>>,
+}
+
+impl Session {
+    /// Opens `config`'s database; an unset `address_layout` is read from it once here.
+    pub fn open(config: &SqliteConnectionConfig) -> CrudResult<Self> {
+        let store = open_store(config)?;
+        let mut config = config.clone();
+        if config.address_layout.is_none() {
+            config.address_layout = Some(store.address_layout()?);
+        }
+        Ok(Self {
+            config,
+            store,
+            listener: None,
+        })
+    }
+
+    /// Call `listener` after each successful `set` and each `delete` that removed a key.
+    pub fn on_change<F>(&mut self, listener: F)
+    where
+        F: Fn(&KeyChange) + Send + 'static,
+    {
+        self.listener = Some(Box::new(listener));
+    }
+
+    pub fn config(&self) -> &SqliteConnectionConfig {
+        &self.config
+    }
+
+    /// See `api::get_value`.
+    pub fn get(&self, address: &str) -> CrudResult<Option<String>> {
+        let verb = CrudVerb::Read;
+        let addr = parse_address(&self.config, address, verb)?;
+        self.check(&addr.project, &addr.namespace, Permission::Read, verb)?;
+        match self.store.get(&addr)? {
+            Some(value) => Ok(Some(
+                NamespaceKeys::load(&self.store, &self.config)?.open(&addr, value)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// See `api::set_value`, and `api::set_value_with_ttl` when `ttl_secs` is given.
+    pub fn set(&self, address: &str, value: &str, ttl_secs: Option<u64>) -> CrudResult<()> {
+        let verb = CrudVerb::Create;
+        let addr = parse_address(&self.config, address, verb)?;
+        self.check(&addr.project, &addr.namespace, Permission::Write, verb)?;
+        check_namespace_known(&self.store, &self.config, &addr, verb)?;
+        let ttl_secs = match ttl_secs {
+            Some(ttl_secs) => Some(checked_ttl(&self.store, &addr, ttl_secs, verb)?),
+            None => None,
+        };
+        let sealed = NamespaceKeys::load(&self.store, &self.config)?.seal(&addr, value)?;
+        self.store.set(&addr, &sealed, ttl_secs)?;
+        self.notify(addr, Some(value));
+        Ok(())
+    }
+
+    /// See `api::get_entry` (live keys only).
+    pub fn get_entry(&self, address: &str) -> CrudResult<Option<KvEntry>> {
+        let verb = CrudVerb::Read;
+        let addr = parse_address(&self.config, address, verb)?;
+        self.check(&addr.project, &addr.namespace, Permission::Read, verb)?;
+        match self.store.get_entry(&addr)? {
+            Some(entry) => Ok(Some(
+                NamespaceKeys::load(&self.store, &self.config)?.open_entry(entry)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// See `api::value_exists`.
+    pub fn exists(&self, address: &str) -> CrudResult<bool> {
+        let verb = CrudVerb::Find;
+        let addr = parse_address(&self.config, address, verb)?;
+        self.check(&addr.project, &addr.namespace, Permission::Read, verb)?;
+        self.store.exists(&addr)
+    }
+
+    /// See `api::expire_value`.
+    pub fn expire(&self, address: &str, ttl_secs: u64) -> CrudResult<bool> {
+        let verb = CrudVerb::Update;
+        let addr = parse_address(&self.config, address, verb)?;
+        self.check(&addr.project, &addr.namespace, Permission::Write, verb)?;
+        let ttl_secs = checked_ttl(&self.store, &addr, ttl_secs, verb)?;
+        self.store.expire(&addr, ttl_secs)
+    }
+
+    /// See `api::delete_value`.
+    pub fn delete(&self, address: &str) -> CrudResult<bool> {
+        let verb = CrudVerb::Delete;
+        let addr = parse_address(&self.config, address, verb)?;
+        self.check(&addr.project, &addr.namespace, Permission::Write, verb)?;
+        let deleted = self.store.delete(&addr)?;
+        if deleted {
+            self.notify(addr, None);
+        }
+        Ok(deleted)
+    }
+
+    /// See `api::scan_entries`.
+    pub fn scan<F>(&self, scope: &str, visit: F) -> CrudResult<()>
+    where
+        F: FnMut(KvEntry) -> ControlFlow<()>,
+    {
+        scan_store(
+            &self.store,
+            &self.config,
+            scope,
+            EntryOrder::default(),
+            visit,
+        )
+    }
+
+    /// See `api::scan_iter`.
+    pub fn scan_iter(&self, scope: &str) -> CrudResult<EntryIter<'_>> {
+        scan_iter_store(&self.store, &self.config, scope)
+    }
+
+    /// See `api::list_projects`.
+    pub fn projects(&self) -> CrudResult<Vec<String>> {
+        readable_projects(&self.store, &self.config)
+    }
+
+    /// See `api::list_namespaces`.
+    pub fn namespaces(&self, project: &str) -> CrudResult<Vec<String>> {
+        readable_namespaces(
+            &self.store,
+            &self.config,
+            &self.store.access_policy()?,
+            project,
+        )
+    }
+
+    /// See `api::secret_marks`.
+    pub fn secret_marks(&self) -> CrudResult<SecretMarks> {
+        self.store.secret_marks()
+    }
+
+    /// The write has already happened, so marks that cannot be read count as secret.
+    fn notify(&self, address: Address, value: Option<&str>) {
+        if let Some(listener) = &self.listener {
+            let secret = self
+                .store
+                .secret_marks()
+                .is_none_or(|marks| marks.is_secret(&address));
+            listener(&KeyChange {
+                address,
+                value: value.map(str::to_string),
+                secret,
+            });
+        }
+    }
+
+    fn check(
+        &self,
+        project: &str,
+        namespace: &str,
+        need: Permission,
+        verb: CrudVerb,
+    ) -> CrudResult<()> {
+        check_writable(&self.config, need, verb)?;
+        check_reserved(&self.config, project, namespace, need, verb)?;
+        self.store
+            .check_access(self.config.user.as_deref(), project, namespace, need, verb)
+    }
+}
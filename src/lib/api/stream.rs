@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+
+use crate::lib::addr::{Address, KeyPattern, NamespaceScope};
+use crate::lib::adpt::sqlite::{KvEntry, Permission, SqliteConnectionConfig, SqliteKeystore};
+use crate::lib::core::crud::{CrudResult, CrudVerb};
+
+use super::error::{coded_invalid_input, ErrorCode};
+use super::kv::parse_scope;
+use super::pool::{open_store, PooledKeystore};
+use super::vault::NamespaceKeys;
+
+/// Rows fetched per query unless `batch_size` says otherwise.
+const DEFAULT_BATCH: usize = 512;
+
+/// Live entries of one `project.namespace[.prefix]` scope, in key order,
+/// read a batch at a time as the iterator is advanced.
+///
+/// Only one batch is held in memory, so a namespace of any size can be
+/// walked with plain iterator adapters. Keys written behind the current
+/// position are not revisited; keys written ahead of it are picked up.
+/// After the first error the iterator ends.
+pub struct EntryIter<'a> {
+    store: StoreRef<'a>,
+    keys: NamespaceKeys,
+    scope: NamespaceScope,
+    batch: VecDeque<KvEntry>,
+    batch_size: usize,
+    last: Option<Address>,
+    done: bool,
+}
+
+enum StoreRef<'a> {
+    Borrowed(&'a SqliteKeystore),
+    Pooled(PooledKeystore),
+}
+
+impl StoreRef<'_> {
+    fn get(&self) -> &SqliteKeystore {
+        match self {
+            Self::Borrowed(store) => store,
+            Self::Pooled(store) => store,
+        }
+    }
+}
+
+/// Iterate live entries under `project.namespace[.prefix]` lazily; globs are `INVALID_SCOPE`
+/// (use `scan_entries` for those).
+pub fn scan_iter(config: &SqliteConnectionConfig, scope: &str) -> CrudResult<EntryIter<'static>> {
+    let store = open_store(config)?;
+    EntryIter::new(StoreRef::Pooled(store), config, scope)
+}
+
+/// `scan_iter` over an already open `store`.
+pub(super) fn scan_iter_store<'a>(
+    store: &'a SqliteKeystore,
+    config: &SqliteConnectionConfig,
+    scope: &str,
+) -> CrudResult<EntryIter<'a>> {
+    EntryIter::new(StoreRef::Borrowed(store), config, scope)
+}
+
+impl<'a> EntryIter<'a> {
+    fn new(store: StoreRef<'a>, config: &SqliteConnectionConfig, scope: &str) -> CrudResult<Self> {
+        let verb = CrudVerb::List;
+        if KeyPattern::is_pattern(scope) {
+            return Err(coded_invalid_input(
+                ErrorCode::InvalidScope,
+                verb,
+                format!(
+                    "'{}' is a pattern; iterate a project.namespace[.prefix] scope",
+                    scope
+                ),
+            ));
+        }
+        let scope = parse_scope(config, scope, verb)?;
+        store.get().check_access(
+            config.user.as_deref(),
+            &scope.project,
+            &scope.namespace,
+            Permission::Read,
+            verb,
+        )?;
+        let keys = NamespaceKeys::load(store.get(), config)?;
+        Ok(Self {
+            store,
+            keys,
+            scope,
+            batch: VecDeque::new(),
+            batch_size: DEFAULT_BATCH,
+            last: None,
+            done: false,
+        })
+    }
+
+    /// Rows read per query (at least 1).
+    pub fn batch_size(mut self, rows: usize) -> Self {
+        self.batch_size = rows.max(1);
+        self
+    }
+
+    fn refill(&mut self) -> CrudResult<()> {
+        let rows =
+            self.store
+                .get()
+                .entries_after(&self.scope, self.last.as_ref(), self.batch_size)?;
+        if rows.len() < self.batch_size {
+            self.done = true;
+        }
+        if let Some(entry) = rows.last() {
+            self.last = Some(entry.address.clone());
+        }
+        self.batch.extend(rows);
+        Ok(())
+    }
+}
+
+impl Iterator for EntryIter<'_> {
+    type Item = CrudResult<KvEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.batch.is_empty() && !self.done {
+            if let Err(error) = self.refill() {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+        let entry = self.batch.pop_front()?;
+        let opened = self.keys.open_entry(entry);
+        if opened.is_err() {
+            self.done = true;
+            self.batch.clear();
+        }
+        Some(opened)
+    }
+}
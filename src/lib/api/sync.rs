@@ -0,0 +1,529 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+
+use hub::error_ext::anyhow;
+
+use crate::lib::addr::Address;
+use crate::lib::adpt::sqlite::{
+    EncryptedNamespace, ImportStrategy, KvEntry, Permission, SqliteConnectionConfig,
+    SqliteKeystore, SyncReport,
+};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+
+use super::kv::{export_entries, import_values, parse_scope};
+use super::vault::NamespaceKeys;
+
+/// Which way `sync_database` copies keys, seen from the local database.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SyncDirection {
+    /// Local changes into the peer.
+    Push,
+    /// Peer changes into the local database.
+    Pull,
+}
+
+impl SyncDirection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncDirection::Push => "push",
+            SyncDirection::Pull => "pull",
+        }
+    }
+}
+
+/// `sys_sync` direction under which `merge_databases` keeps when each side last merged.
+const MERGE_DIRECTION: &str = "merge";
+
+/// How `merge_databases` settles a key both sides changed since they last merged.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MergeStrategy {
+    /// The row with the later `updated_at`; ours on a tie.
+    Newest,
+    Ours,
+    Theirs,
+}
+
+impl MergeStrategy {
+    pub fn resolve(self, ours: &KvEntry, theirs: &KvEntry) -> MergeSide {
+        match self {
+            MergeStrategy::Newest if theirs.updated_at > ours.updated_at => MergeSide::Theirs,
+            MergeStrategy::Newest | MergeStrategy::Ours => MergeSide::Ours,
+            MergeStrategy::Theirs => MergeSide::Theirs,
+        }
+    }
+}
+
+/// Which database's row a merge keeps for a conflicting key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MergeSide {
+    /// The local database.
+    Ours,
+    /// The database merged with.
+    Theirs,
+}
+
+/// A key both databases changed since they last merged, each row as stored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MergeConflict {
+    pub ours: KvEntry,
+    pub theirs: KvEntry,
+    /// The side both databases now hold; `None` when the key was left as it is on each.
+    pub resolved: Option<MergeSide>,
+}
+
+/// What `merge_databases` did.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeReport {
+    /// Keys written into the local database.
+    pub to_ours: usize,
+    /// Keys written into the other database.
+    pub to_theirs: usize,
+    /// In address order.
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// How one key differs between two databases, going from the first to the second.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum KeyDiff {
+    /// Only in the second database.
+    Added(KvEntry),
+    /// Only in the first.
+    Removed(KvEntry),
+    /// In both with a different value or type: the first's row, then the second's.
+    Changed(KvEntry, KvEntry),
+}
+
+impl KeyDiff {
+    pub fn address(&self) -> &Address {
+        match self {
+            KeyDiff::Added(entry) | KeyDiff::Removed(entry) | KeyDiff::Changed(entry, _) => {
+                &entry.address
+            }
+        }
+    }
+}
+
+/// What `diff_databases` found, differences in address order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DatabaseDiff {
+    pub changes: Vec<KeyDiff>,
+    /// Keys both databases hold with the same value and type.
+    pub unchanged: usize,
+}
+
+/// What `import_database` did.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DatabaseImport {
+    /// Keys the database did not hold.
+    pub added: usize,
+    /// Conflicting keys replaced (`ImportStrategy::Overwrite`).
+    pub overwritten: usize,
+    /// Keys the database already held with the same value.
+    pub unchanged: usize,
+    /// Keys the database held with another value, in address order; under
+    /// `ImportStrategy::Fail` any conflict means nothing was written.
+    pub conflicts: Vec<Address>,
+}
+
+/// Copy the keys changed since the last sync in `direction` between the database of
+/// `config` and the one at `peer`.
+///
+/// Where each peer and direction stopped is kept in the local `sys_sync`
+/// table, keyed by the peer's canonical path, so a run only reads rows
+/// updated since the one before; the first run copies everything. Rows are
+/// copied as `SqliteKeystore::sync_to` describes: newer edits on the
+/// receiving side win and deletions are not carried. A push creates the
+/// peer if needed; a pull needs it to exist.
+pub fn sync_database(
+    config: &SqliteConnectionConfig,
+    peer: &Path,
+    direction: SyncDirection,
+) -> CrudResult<SyncReport> {
+    let verb = CrudVerb::Update;
+    if direction == SyncDirection::Pull && !peer.is_file() {
+        return Err(CrudError::not_found(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("no database at {}", peer.display()),
+        ));
+    }
+    let mut local = SqliteKeystore::open(config)?;
+    let mut remote = SqliteKeystore::open(
+        &config
+            .clone()
+            .with_database_path(peer)
+            .with_token(None::<String>),
+    )?;
+    let (local_path, peer_key) = distinct_paths(config.database_path(), peer)?;
+
+    let since = local
+        .sync_watermark(&peer_key, direction.as_str())?
+        .unwrap_or(0);
+    let report = match direction {
+        SyncDirection::Push => local.sync_to(peer, since)?,
+        SyncDirection::Pull => remote.sync_to(Path::new(&local_path), since)?,
+    };
+    local.set_sync_watermark(&peer_key, direction.as_str(), report.watermark)?;
+    Ok(report)
+}
+
+/// Reconcile the database of `config` with the one at `other`, writing to both.
+///
+/// A key only one side holds is copied to the other; deletions are not
+/// carried. A key held with different values (or type or expiry) goes to
+/// the side that has not changed it since the two last merged, as kept in
+/// each database's `sys_sync`. When both have, or they never merged, it is a
+/// conflict: `resolve` picks the row both keep, or `None` to leave each
+/// side as it is. Values are compared as stored, so an encrypted namespace
+/// must share its key on both sides, and a side lacking it takes the
+/// other's `sys_encryption` row. Each database is written in its own
+/// transaction.
+pub fn merge_databases<F>(
+    config: &SqliteConnectionConfig,
+    other: &Path,
+    mut resolve: F,
+) -> CrudResult<MergeReport>
+where
+    F: FnMut(&KvEntry, &KvEntry) -> Option<MergeSide>,
+{
+    let verb = CrudVerb::Update;
+    if !other.is_file() {
+        return Err(CrudError::not_found(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("no database at {}", other.display()),
+        ));
+    }
+    let mut ours = SqliteKeystore::open(config)?;
+    let mut theirs = SqliteKeystore::open(
+        &config
+            .clone()
+            .with_database_path(other)
+            .with_token(None::<String>),
+    )?;
+    let (our_path, their_path) = distinct_paths(config.database_path(), other)?;
+    let our_mark = ours.sync_watermark(&their_path, MERGE_DIRECTION)?;
+    let their_mark = theirs.sync_watermark(&our_path, MERGE_DIRECTION)?;
+    let (our_now, their_now) = (ours.now(), theirs.now());
+
+    let our_entries = ours.entries(None, None)?;
+    let their_entries = theirs.entries(None, None)?;
+    let our_namespaces = namespaces_of(&our_entries);
+    let their_namespaces = namespaces_of(&their_entries);
+    let mut remaining: BTreeMap<Address, KvEntry> = their_entries
+        .into_iter()
+        .map(|entry| (entry.address.clone(), entry))
+        .collect();
+    let changed = |entry: &KvEntry, mark: Option<i64>| mark.is_none_or(|at| entry.updated_at >= at);
+    let mut report = MergeReport::default();
+    let (mut into_ours, mut into_theirs) = (Vec::new(), Vec::new());
+    for entry in our_entries {
+        let other = match remaining.remove(&entry.address) {
+            Some(other) => other,
+            None => {
+                into_theirs.push(entry);
+                continue;
+            }
+        };
+        if other.value == entry.value
+            && other.value_type == entry.value_type
+            && other.expires_at == entry.expires_at
+        {
+            continue;
+        }
+        match (changed(&entry, our_mark), changed(&other, their_mark)) {
+            (true, false) => into_theirs.push(entry),
+            (false, true) => into_ours.push(other),
+            _ => {
+                let resolved = resolve(&entry, &other);
+                match resolved {
+                    Some(MergeSide::Ours) => into_theirs.push(entry.clone()),
+                    Some(MergeSide::Theirs) => into_ours.push(other.clone()),
+                    None => {}
+                }
+                report.conflicts.push(MergeConflict {
+                    ours: entry,
+                    theirs: other,
+                    resolved,
+                });
+            }
+        }
+    }
+    into_ours.extend(remaining.into_values());
+
+    let our_keys = ours.encrypted_namespaces()?;
+    let their_keys = theirs.encrypted_namespaces()?;
+    let for_ours = encryption_for(&into_ours, &their_keys, &our_keys, &our_namespaces)?;
+    let for_theirs = encryption_for(&into_theirs, &our_keys, &their_keys, &their_namespaces)?;
+    ours.write_entries(&into_ours, &for_ours)?;
+    theirs.write_entries(&into_theirs, &for_theirs)?;
+    ours.set_sync_watermark(&their_path, MERGE_DIRECTION, our_now)?;
+    theirs.set_sync_watermark(&our_path, MERGE_DIRECTION, their_now)?;
+    report.to_ours = into_ours.len();
+    report.to_theirs = into_theirs.len();
+    Ok(report)
+}
+
+/// Compare the live keys of two databases under `scope` (`project` or `project.namespace`,
+/// `None` for all).
+///
+/// Both sides are read as `export_entries` reads them, so values of
+/// encrypted namespaces are compared in the clear and ACLs apply to each.
+/// Expiry times are not compared, as caches seldom agree on them. Both
+/// databases must exist.
+pub fn diff_databases(
+    from: &SqliteConnectionConfig,
+    to: &SqliteConnectionConfig,
+    scope: Option<&str>,
+) -> CrudResult<DatabaseDiff> {
+    let read = |config: &SqliteConnectionConfig| -> CrudResult<Vec<KvEntry>> {
+        if config.database_path().is_file() {
+            export_entries(config, scope)
+        } else {
+            Err(CrudError::not_found(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Base,
+                CrudVerb::List,
+                format!("no database at {}", config.database_path().display()),
+            ))
+        }
+    };
+    let mut remaining: BTreeMap<Address, KvEntry> = read(to)?
+        .into_iter()
+        .map(|entry| (entry.address.clone(), entry))
+        .collect();
+    let mut diff = DatabaseDiff::default();
+    for entry in read(from)? {
+        match remaining.remove(&entry.address) {
+            None => diff.changes.push(KeyDiff::Removed(entry)),
+            Some(other) if other.value != entry.value || other.value_type != entry.value_type => {
+                diff.changes.push(KeyDiff::Changed(entry, other))
+            }
+            Some(_) => diff.unchanged += 1,
+        }
+    }
+    diff.changes
+        .extend(remaining.into_values().map(KeyDiff::Added));
+    diff.changes.sort_by(|a, b| a.address().cmp(b.address()));
+    Ok(diff)
+}
+
+/// Import every key of the database at `source` into the database of `config`.
+///
+/// With `prefix`, each key lands in project `<prefix>_<project>`, keeping
+/// another database's keys apart from this one's. Both sides are read as
+/// `export_entries` reads them and written through `import_values`, so
+/// ACLs apply, encrypted values are re-sealed for this database and keys
+/// keep the time they had left. A key held here with another value is a
+/// conflict: `Skip` keeps this database's value, `Overwrite` takes the
+/// source's and `Fail` writes nothing.
+pub fn import_database(
+    config: &SqliteConnectionConfig,
+    source: &Path,
+    prefix: Option<&str>,
+    strategy: ImportStrategy,
+) -> CrudResult<DatabaseImport> {
+    let verb = CrudVerb::Create;
+    if !source.is_file() {
+        return Err(CrudError::not_found(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("no database at {}", source.display()),
+        ));
+    }
+    if prefix.is_some_and(|prefix| prefix.is_empty() || prefix.contains('.')) {
+        return Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            "the prefix must be a non-empty name without '.'",
+        ));
+    }
+    let now = SqliteKeystore::open(config)?.now();
+    distinct_paths(config.database_path(), source)?;
+    let source_config = config
+        .clone()
+        .with_database_path(source)
+        .with_read_only(true);
+    let current: BTreeMap<Address, KvEntry> = export_entries(config, None)?
+        .into_iter()
+        .map(|entry| (entry.address.clone(), entry))
+        .collect();
+
+    let mut report = DatabaseImport::default();
+    let mut records = Vec::new();
+    for entry in export_entries(&source_config, None)? {
+        let mut address = entry.address.clone();
+        if let Some(prefix) = prefix {
+            address.project = format!("{}_{}", prefix, address.project);
+        }
+        match current.get(&address) {
+            Some(existing) if existing.value == entry.value => {
+                report.unchanged += 1;
+                continue;
+            }
+            Some(_) => {
+                report.conflicts.push(address.clone());
+                if strategy != ImportStrategy::Overwrite {
+                    continue;
+                }
+                report.overwritten += 1;
+            }
+            None => report.added += 1,
+        }
+        let ttl = entry.ttl_remaining(now).map(|secs| secs as u64);
+        records.push((address.to_string(), entry.value, ttl));
+    }
+    report.conflicts.sort();
+    if strategy == ImportStrategy::Fail && !report.conflicts.is_empty() {
+        report.added = 0;
+        return Ok(report);
+    }
+    import_values(config, &records, ImportStrategy::Overwrite)?;
+    Ok(report)
+}
+
+/// Live entries in `scope` (`project.namespace[.prefix]`) from every database in
+/// `databases`, each with the index of the one it came from.
+///
+/// The databases are opened read-only to check access and load their
+/// encryption keys, then read in one query with the others ATTACHed to the
+/// first; rows come ordered by key, then database.
+pub fn scan_databases(
+    config: &SqliteConnectionConfig,
+    databases: &[PathBuf],
+    scope: &str,
+) -> CrudResult<Vec<(usize, KvEntry)>> {
+    let verb = CrudVerb::List;
+    let scope = parse_scope(config, scope, verb)?;
+    let mut stores = Vec::new();
+    let mut keys = Vec::new();
+    for path in databases {
+        if !path.is_file() {
+            return Err(CrudError::not_found(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Base,
+                verb,
+                format!("no database at {}", path.display()),
+            ));
+        }
+        let config = config.clone().with_database_path(path).with_read_only(true);
+        let store = SqliteKeystore::open(&config)?;
+        store.check_access(
+            config.user.as_deref(),
+            &scope.project,
+            &scope.namespace,
+            Permission::Read,
+            verb,
+        )?;
+        keys.push(NamespaceKeys::load(&store, &config)?);
+        stores.push(store);
+    }
+    let Some((first, _)) = stores.split_first() else {
+        return Ok(Vec::new());
+    };
+    let others: Vec<&Path> = databases[1..].iter().map(PathBuf::as_path).collect();
+    let mut entries = Vec::new();
+    let mut failure = None;
+    first.for_each_entry_across(&others, &scope, |index, entry| {
+        match keys[index].open_entry(entry) {
+            Ok(entry) => {
+                entries.push((index, entry));
+                ControlFlow::Continue(())
+            }
+            Err(error) => {
+                failure = Some(error);
+                ControlFlow::Break(())
+            }
+        }
+    })?;
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(entries),
+    }
+}
+
+/// The `sys_encryption` rows a side holding keys in `populated` needs to take `incoming`
+/// sealed under `from`; a namespace encrypted differently there is refused.
+fn encryption_for(
+    incoming: &[KvEntry],
+    from: &[EncryptedNamespace],
+    to: &[EncryptedNamespace],
+    populated: &HashSet<(String, String)>,
+) -> CrudResult<Vec<EncryptedNamespace>> {
+    let find = |rows: &[EncryptedNamespace], addr: &Address| {
+        rows.iter()
+            .find(|row| row.project == addr.project && row.namespace == addr.namespace)
+            .cloned()
+    };
+    let mut needed: Vec<EncryptedNamespace> = Vec::new();
+    for addr in incoming.iter().map(|entry| &entry.address) {
+        let namespace = (addr.project.clone(), addr.namespace.clone());
+        match (find(from, addr), find(to, addr)) {
+            (None, None) => {}
+            (Some(source), Some(target)) if source.salt == target.salt => {}
+            (Some(source), None) if !populated.contains(&namespace) => {
+                if !needed.contains(&source) {
+                    needed.push(source);
+                }
+            }
+            _ => {
+                return Err(CrudError::invalid_input(
+                    CrudDomain::Sqlite,
+                    CrudObjectKind::Base,
+                    CrudVerb::Update,
+                    format!(
+                        "{}.{} is encrypted differently in the two databases",
+                        addr.project, addr.namespace
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(needed)
+}
+
+fn namespaces_of(entries: &[KvEntry]) -> HashSet<(String, String)> {
+    entries
+        .iter()
+        .map(|entry| {
+            (
+                entry.address.project.clone(),
+                entry.address.namespace.clone(),
+            )
+        })
+        .collect()
+}
+
+/// Canonical paths of the local database and `peer`, refusing the same file twice.
+fn distinct_paths(local: &Path, peer: &Path) -> CrudResult<(String, String)> {
+    let verb = CrudVerb::Update;
+    let canonical = |path: &Path| -> CrudResult<PathBuf> {
+        fs::canonicalize(path).map_err(|err| io_error(verb, err))
+    };
+    let (local, remote) = (canonical(local)?, canonical(peer)?);
+    if local == remote {
+        return Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Base,
+            verb,
+            format!("{} is this database", peer.display()),
+        ));
+    }
+    Ok((local.display().to_string(), remote.display().to_string()))
+}
+
+fn io_error(verb: CrudVerb, err: io::Error) -> CrudError {
+    CrudError::internal(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Base,
+        verb,
+        anyhow::Error::new(err),
+    )
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+
+use crate::lib::addr::Address;
+use crate::lib::adpt::sqlite::{
+    read_key_file, EncryptedNamespace, KvEntry, NamespaceCipher, SqliteConnectionConfig,
+    SqliteKeystore,
+};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+
+use super::error::{coded_invalid_input, ErrorCode};
+
+/// Ciphers for the encrypted namespaces of one database, unlocked on first use.
+///
+/// Values of encrypted namespaces are sealed before they are stored and
+/// opened after they are read. Touching one without `config.key_file` is a
+/// `KEY_REQUIRED` error, never ciphertext output.
+pub(super) struct NamespaceKeys {
+    encrypted: Vec<EncryptedNamespace>,
+    key_file: Option<PathBuf>,
+    unlocked: BTreeMap<(String, String), NamespaceCipher>,
+}
+
+impl NamespaceKeys {
+    pub(super) fn load(
+        store: &SqliteKeystore,
+        config: &SqliteConnectionConfig,
+    ) -> CrudResult<Self> {
+        Ok(Self {
+            encrypted: store.encrypted_namespaces()?,
+            key_file: config.key_file.clone(),
+            unlocked: BTreeMap::new(),
+        })
+    }
+
+    pub(super) fn is_encrypted(&self, project: &str, namespace: &str) -> bool {
+        self.encrypted
+            .iter()
+            .any(|entry| entry.project == project && entry.namespace == namespace)
+    }
+
+    /// `value` as it should be stored at `addr`.
+    pub(super) fn seal(&mut self, addr: &Address, value: &str) -> CrudResult<String> {
+        match self.cipher(&addr.project, &addr.namespace, CrudVerb::Create)? {
+            Some(cipher) => cipher.encrypt(&bound_address(addr), value),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    /// The plaintext of `stored`, read from `addr`.
+    pub(super) fn open(&mut self, addr: &Address, stored: String) -> CrudResult<String> {
+        match self.cipher(&addr.project, &addr.namespace, CrudVerb::Read)? {
+            Some(cipher) => cipher.decrypt(&bound_address(addr), &stored),
+            None => Ok(stored),
+        }
+    }
+
+    /// `stored` from `src` as it should be stored at `dst`, re-sealed when the key changes.
+    pub(super) fn reseal(
+        &mut self,
+        src: &Address,
+        dst: &Address,
+        stored: String,
+    ) -> CrudResult<String> {
+        if bound_address(src) == bound_address(dst) {
+            return Ok(stored);
+        }
+        let value = self.open(src, stored)?;
+        self.seal(dst, &value)
+    }
+
+    pub(super) fn open_entry(&mut self, mut entry: KvEntry) -> CrudResult<KvEntry> {
+        entry.value = self.open(&entry.address, entry.value)?;
+        Ok(entry)
+    }
+
+    /// `InvalidInput` when `what` (an operation on stored bytes) targets an encrypted namespace.
+    pub(super) fn refuse(
+        &self,
+        project: &str,
+        namespace: &str,
+        what: &str,
+        verb: CrudVerb,
+    ) -> CrudResult<()> {
+        if !self.is_encrypted(project, namespace) {
+            return Ok(());
+        }
+        Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            CrudObjectKind::Record,
+            verb,
+            format!(
+                "{} is not supported in encrypted namespace {}.{}",
+                what, project, namespace
+            ),
+        ))
+    }
+
+    fn cipher(
+        &mut self,
+        project: &str,
+        namespace: &str,
+        verb: CrudVerb,
+    ) -> CrudResult<Option<&NamespaceCipher>> {
+        let slot = (project.to_string(), namespace.to_string());
+        if !self.unlocked.contains_key(&slot) {
+            let encrypted = match self
+                .encrypted
+                .iter()
+                .find(|entry| entry.project == project && entry.namespace == namespace)
+            {
+                Some(encrypted) => encrypted,
+                None => return Ok(None),
+            };
+            let key_file = self.key_file.as_deref().ok_or_else(|| {
+                coded_invalid_input(
+                    ErrorCode::KeyRequired,
+                    verb,
+                    format!(
+                        "{}.{} is encrypted; pass --key-file or set PRONTO_KEY_FILE",
+                        project, namespace
+                    ),
+                )
+            })?;
+            let cipher = NamespaceCipher::unlock(&read_key_file(key_file)?, encrypted)?;
+            self.unlocked.insert(slot.clone(), cipher);
+        }
+        Ok(self.unlocked.get(&slot))
+    }
+}
+
+/// The associated data a value at `addr` is sealed with: its address without a context.
+fn bound_address(addr: &Address) -> String {
+    format!("{}.{}.{}", addr.project, addr.namespace, addr.key)
+}
+
+/// Run `scan` with each entry decrypted before `visit` sees it.
+///
+/// The first value that cannot be decrypted stops the scan and becomes its error.
+pub(super) fn scan_decrypted<F, S>(
+    store: &SqliteKeystore,
+    config: &SqliteConnectionConfig,
+    mut visit: F,
+    scan: S,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+    S: FnOnce(&mut dyn FnMut(KvEntry) -> ControlFlow<()>) -> CrudResult<()>,
+{
+    let mut keys = NamespaceKeys::load(store, config)?;
+    let mut failure = None;
+    scan(&mut |entry| match keys.open_entry(entry) {
+        Ok(entry) => visit(entry),
+        Err(error) => {
+            failure = Some(error);
+            ControlFlow::Break(())
+        }
+    })?;
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt;
 use std::str::FromStr;
 
+use crate::lib::adpt::sqlite::{AuditQuery, IndexKind, Permission, KEY_FILE_ENV};
 use crate::lib::core::crud::{CrudObjectKind, CrudVerb};
 use rsb::prelude::*;
 
@@ -27,18 +28,266 @@ impl std::error::Error for CommandError {}
 
 #[derive(Clone, Debug)]
 pub enum AdminCommand {
+    /// Grant `user` a permission on `project.namespace` (`namespace` may be `*`).
+    AclGrant {
+        user: String,
+        project: String,
+        namespace: String,
+        permission: Permission,
+    },
+    /// Remove `user`'s rule on `project.namespace`.
+    AclRevoke {
+        user: String,
+        project: String,
+        namespace: String,
+    },
+    /// List every ACL rule.
+    AclList,
+    /// Start logging `set`/`del`/`copy` to the append-only `sys_audit` table.
+    AuditEnable,
+    /// Print the newest audit rows matching `query` (all rows for `--audit-tail`).
+    Audit {
+        query: AuditQuery,
+    },
     Capabilities,
+    /// Persist a runtime toggle for `verb` on `object`, honoured by the SQLite adapters.
+    CapabilityToggle {
+        object: CrudObjectKind,
+        verb: CrudVerb,
+        enabled: bool,
+    },
+    /// List cache (TTL) namespaces with their default TTL and key counts.
+    Caches,
+    /// Register `project.namespace`; with `key_file`, its values are encrypted with that key.
+    CreateNamespace {
+        project: String,
+        namespace: String,
+        key_file: Option<String>,
+    },
     Crud {
         object: CrudObjectKind,
         verb: CrudVerb,
     },
+    /// Add (or with `drop`, remove) a secondary index on `project.namespace`.
+    Index {
+        project: String,
+        namespace: String,
+        kind: IndexKind,
+        drop: bool,
+    },
+    /// List registered secondary indexes.
+    Indexes,
+    /// Delete expired keys right away, optionally only in one project or namespace.
+    PurgeExpired {
+        project: Option<String>,
+        namespace: Option<String>,
+    },
+    /// Delete expired keys; with `daemon`, repeat every `interval_secs` until killed.
+    Sweep {
+        daemon: bool,
+        interval_secs: u64,
+        batch_size: usize,
+    },
+    /// Issue an API token for remote access to `project.namespace` (`namespace` may be `*`).
+    TokenCreate {
+        project: String,
+        namespace: String,
+        permission: Permission,
+    },
+    /// List issued API tokens (ids and scopes; raw tokens are never stored).
+    TokenList,
+    TokenRevoke {
+        id: i64,
+    },
 }
 
+pub const DEFAULT_SWEEP_INTERVAL_SECS: u64 = 60;
+pub const DEFAULT_SWEEP_BATCH_SIZE: usize = 500;
+pub const DEFAULT_AUDIT_LIMIT: usize = 20;
+
 pub fn resolve_command() -> Result<AdminCommand, CommandError> {
     if has_var("opt_capabilities") {
         return Ok(AdminCommand::Capabilities);
     }
 
+    if has_var("opt_caches") {
+        return Ok(AdminCommand::Caches);
+    }
+
+    if has_var("opt_indexes") {
+        return Ok(AdminCommand::Indexes);
+    }
+
+    if has_var("opt_acl_list") {
+        return Ok(AdminCommand::AclList);
+    }
+
+    if has_var("opt_token_list") {
+        return Ok(AdminCommand::TokenList);
+    }
+
+    if has_var("opt_token_revoke") {
+        let id = numeric_option("id", 0)?;
+        if id <= 0 {
+            return Err(CommandError::new("--token-revoke needs --id=N"));
+        }
+        return Ok(AdminCommand::TokenRevoke { id });
+    }
+
+    if has_var("opt_token_create") {
+        let scope = get_var("opt_scope");
+        let (scope, access) = scope.rsplit_once(':').unwrap_or((scope.as_str(), "r"));
+        let permission = match access {
+            "r" => Permission::Read,
+            "rw" => Permission::Write,
+            other => {
+                return Err(CommandError::new(format!(
+                    "token access must be :r or :rw, got ':{}'",
+                    other
+                )))
+            }
+        };
+        let (project, namespace) = scope
+            .split_once('.')
+            .filter(|(project, namespace)| !project.is_empty() && !namespace.is_empty())
+            .ok_or_else(|| {
+                CommandError::new("--token-create needs --scope=project.namespace[:r|rw]")
+            })?;
+        return Ok(AdminCommand::TokenCreate {
+            project: project.to_string(),
+            namespace: namespace.to_string(),
+            permission,
+        });
+    }
+
+    if has_var("opt_audit_enable") {
+        return Ok(AdminCommand::AuditEnable);
+    }
+
+    if has_var("opt_audit_tail") || has_var("opt_audit_search") {
+        let limit = numeric_option("limit", DEFAULT_AUDIT_LIMIT)?;
+        if !has_var("opt_audit_search") {
+            return Ok(AdminCommand::Audit {
+                query: AuditQuery {
+                    limit,
+                    ..AuditQuery::default()
+                },
+            });
+        }
+        let filter = |name: &str| Some(get_var(name)).filter(|value| !value.is_empty());
+        return Ok(AdminCommand::Audit {
+            query: AuditQuery {
+                user: filter("opt_user"),
+                command: filter("opt_command"),
+                address_prefix: filter("opt_scope"),
+                limit,
+            },
+        });
+    }
+
+    if has_var("opt_acl_grant") || has_var("opt_acl_revoke") {
+        let user = get_var("opt_user");
+        if user.is_empty() {
+            return Err(CommandError::new("ACL commands need --user=NAME"));
+        }
+        let scope = get_var("opt_scope");
+        let (project, namespace) = scope
+            .split_once('.')
+            .filter(|(project, namespace)| !project.is_empty() && !namespace.is_empty())
+            .ok_or_else(|| {
+                CommandError::new("ACL commands need --scope=project.namespace (or project.*)")
+            })?;
+        let (project, namespace) = (project.to_string(), namespace.to_string());
+        if has_var("opt_acl_revoke") {
+            return Ok(AdminCommand::AclRevoke {
+                user,
+                project,
+                namespace,
+            });
+        }
+        let raw = get_var("opt_permission");
+        let permission = Permission::parse(&raw).ok_or_else(|| {
+            CommandError::new(format!("--permission must be read or write, got '{}'", raw))
+        })?;
+        return Ok(AdminCommand::AclGrant {
+            user,
+            project,
+            namespace,
+            permission,
+        });
+    }
+
+    if has_var("opt_create_namespace") {
+        let scope = get_var("opt_scope");
+        let (project, namespace) = scope
+            .split_once('.')
+            .filter(|(project, namespace)| !project.is_empty() && !namespace.is_empty())
+            .ok_or_else(|| {
+                CommandError::new("--create-namespace needs --scope=project.namespace")
+            })?;
+        let key_file = if has_var("opt_encrypt") {
+            let key_file = get_var("opt_key_file");
+            let key_file = if key_file.is_empty() {
+                std::env::var(KEY_FILE_ENV).unwrap_or_default()
+            } else {
+                key_file
+            };
+            if key_file.is_empty() {
+                return Err(CommandError::new(
+                    "--encrypt needs --key-file=PATH (or PRONTO_KEY_FILE)",
+                ));
+            }
+            Some(key_file)
+        } else {
+            None
+        };
+        return Ok(AdminCommand::CreateNamespace {
+            project: project.to_string(),
+            namespace: namespace.to_string(),
+            key_file,
+        });
+    }
+
+    if has_var("opt_create_index") || has_var("opt_drop_index") {
+        let scope = get_var("opt_scope");
+        let (project, namespace) = scope
+            .split_once('.')
+            .filter(|(project, namespace)| !project.is_empty() && !namespace.is_empty())
+            .ok_or_else(|| CommandError::new("index commands need --scope=project.namespace"))?;
+        let kind = if has_var("opt_fts") {
+            IndexKind::FullText
+        } else if has_var("opt_json_path") {
+            IndexKind::JsonPath(get_var("opt_json_path"))
+        } else {
+            return Err(CommandError::new(
+                "index commands need an index type: --fts or --json-path=.field",
+            ));
+        };
+        return Ok(AdminCommand::Index {
+            project: project.to_string(),
+            namespace: namespace.to_string(),
+            kind,
+            drop: has_var("opt_drop_index"),
+        });
+    }
+
+    if has_var("opt_purge_expired") {
+        let scope = get_var("opt_scope");
+        let mut parts = scope.splitn(2, '.').filter(|part| !part.is_empty());
+        return Ok(AdminCommand::PurgeExpired {
+            project: parts.next().map(str::to_string),
+            namespace: parts.next().map(str::to_string),
+        });
+    }
+
+    if has_var("opt_sweep") {
+        return Ok(AdminCommand::Sweep {
+            daemon: has_var("opt_daemon"),
+            interval_secs: numeric_option("interval", DEFAULT_SWEEP_INTERVAL_SECS)?,
+            batch_size: numeric_option("batch_size", DEFAULT_SWEEP_BATCH_SIZE)?,
+        });
+    }
+
     let object_raw = get_var("opt_object");
     let verb_raw = get_var("opt_verb");
 
@@ -48,14 +297,40 @@ pub fn resolve_command() -> Result<AdminCommand, CommandError> {
         ));
     }
 
-    let object = CrudObjectKind::from_str(&object_raw)
+    let (object, verb) = parse_capability(&object_raw, &verb_raw)?;
+
+    if has_var("opt_capability_enable") || has_var("opt_capability_disable") {
+        return Ok(AdminCommand::CapabilityToggle {
+            object,
+            verb,
+            enabled: has_var("opt_capability_enable"),
+        });
+    }
+
+    Ok(AdminCommand::Crud { object, verb })
+}
+
+/// An `<object> <verb>` pair as typed on the command line.
+pub fn parse_capability(
+    object_raw: &str,
+    verb_raw: &str,
+) -> Result<(CrudObjectKind, CrudVerb), CommandError> {
+    let object = CrudObjectKind::from_str(object_raw)
         .map_err(|_| CommandError::new(format!("unknown object kind: {}", object_raw)))?;
-    let verb = CrudVerb::from_str(&verb_raw)
+    let verb = CrudVerb::from_str(verb_raw)
         .map_err(|_| CommandError::new(format!("unknown verb: {}", verb_raw)))?;
+    Ok((object, verb))
+}
 
-    Ok(AdminCommand::Crud { object, verb })
+fn numeric_option<T: FromStr>(name: &str, default: T) -> Result<T, CommandError> {
+    let raw = get_var(&format!("opt_{}", name));
+    if raw.is_empty() {
+        return Ok(default);
+    }
+    raw.parse()
+        .map_err(|_| CommandError::new(format!("invalid --{}: {}", name.replace('_', "-"), raw)))
 }
 
 pub fn usage() -> &'static str {
-    "prontodb-admin --object=<base|table|record> --verb=<create|read|update|delete|list|find|backup|restore|alias> [--database-path=PATH] [--target-path=PATH] [--source-path=PATH]"
+    "prontodb-admin --object=<base|table|record> --verb=<create|read|update|delete|list|find|backup|restore|alias> [--database-path=PATH] [--target-path=PATH] [--source-path=PATH]\n       prontodb-admin --capability-enable|--capability-disable --object=<base|table|record> --verb=VERB [--database-path=PATH]\n       prontodb-admin --sweep [--daemon] [--interval=SECS] [--batch-size=N] [--database-path=PATH]\n       prontodb-admin --purge-expired [--scope=project[.namespace]] [--database-path=PATH]\n       prontodb-admin --caches [--database-path=PATH]\n       prontodb-admin --create-index|--drop-index --scope=project.namespace --fts|--json-path=.field [--database-path=PATH]\n       prontodb-admin --indexes [--database-path=PATH]\n       prontodb-admin --create-namespace --scope=project.namespace [--encrypt --key-file=PATH] [--database-path=PATH]\n       prontodb-admin --acl-grant --user=NAME --scope=project.namespace|project.* --permission=read|write [--database-path=PATH]\n       prontodb-admin --acl-revoke --user=NAME --scope=project.namespace|project.* [--database-path=PATH]\n       prontodb-admin --acl-list [--database-path=PATH]\n       prontodb-admin --token-create --scope=project.namespace|project.*[:r|rw] [--database-path=PATH]\n       prontodb-admin --token-list [--database-path=PATH]\n       prontodb-admin --token-revoke --id=N [--database-path=PATH]\n       prontodb-admin --audit-enable [--database-path=PATH]\n       prontodb-admin --audit-tail [--limit=N] [--database-path=PATH]\n       prontodb-admin --audit-search [--user=NAME] [--command=set|del|copy] [--scope=ADDRESS_PREFIX] [--limit=N] [--database-path=PATH]"
 }
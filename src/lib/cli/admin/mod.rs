@@ -3,5 +3,11 @@
 mod commands;
 mod runner;
 
-pub use commands::{usage, AdminCommand, CommandError};
-pub use runner::{ensure_capability_toggle, run_admin_cli};
+pub use commands::{
+    parse_capability, usage, AdminCommand, CommandError, DEFAULT_SWEEP_BATCH_SIZE,
+    DEFAULT_SWEEP_INTERVAL_SECS,
+};
+pub use runner::{
+    ensure_capability_toggle, print_caches, print_capabilities, run_admin_cli, run_purge,
+    run_sweep, set_capability,
+};
@@ -1,9 +1,19 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
 use crate::lib::adpt::sqlite::{
-    SqliteBaseAdapter, SqliteConnectionConfig, SqliteRecordAdapter, SqliteTableAdapter,
+    read_key_file, set_capability_enabled, AuditQuery, CapabilityToggles, Clock, ExpiryHook,
+    IndexKind, SqliteBaseAdapter, SqliteConnectionConfig, SqliteKeystore, SqliteRecordAdapter,
+    SqliteTableAdapter, SweepReport, SystemClock,
 };
 use crate::lib::core::crud::{
     CrudContext, CrudDomain, CrudError, CrudObjectKind, CrudResource, CrudVerb,
 };
+use crate::lib::core::helpers::format_timestamp;
+use crate::lib::core::identity::{os_user, owns_path};
 use rsb::prelude::*;
 
 use super::commands::{self, AdminCommand, CommandError};
@@ -13,10 +23,135 @@ pub fn run_admin_cli() -> i32 {
     options!(&args);
 
     match commands::resolve_command() {
-        Ok(AdminCommand::Capabilities) => {
-            print_capabilities();
-            0
+        Ok(AdminCommand::AclGrant {
+            user,
+            project,
+            namespace,
+            permission,
+        }) => match open_acl_admin(&project, &namespace, CrudVerb::Create)
+            .and_then(|keystore| keystore.grant_access(&user, &project, &namespace, permission))
+        {
+            Ok(()) => {
+                println!(
+                    "[acl] {} may {} {}.{}",
+                    user, permission, project, namespace
+                );
+                0
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::AclRevoke {
+            user,
+            project,
+            namespace,
+        }) => match open_acl_admin(&project, &namespace, CrudVerb::Delete)
+            .and_then(|keystore| keystore.revoke_access(&user, &project, &namespace))
+        {
+            Ok(true) => {
+                println!("[acl] revoked {} on {}.{}", user, project, namespace);
+                0
+            }
+            Ok(false) => {
+                println!("[acl] no rule for {} on {}.{}", user, project, namespace);
+                0
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::AclList) => match print_acl() {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::TokenCreate {
+            project,
+            namespace,
+            permission,
+        }) => match open_acl_admin(&project, &namespace, CrudVerb::Create)
+            .and_then(|keystore| keystore.create_token(&project, &namespace, permission))
+        {
+            Ok((token, raw)) => {
+                eprintln!(
+                    "[token] #{} may {} {}.{}; store it now, it is not shown again",
+                    token.id, permission, project, namespace
+                );
+                println!("{}", raw);
+                0
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::TokenList) => match print_tokens() {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::TokenRevoke { id }) => match revoke_token(id) {
+            Ok(true) => {
+                println!("[token] revoked #{}", id);
+                0
+            }
+            Ok(false) => {
+                eprintln!("error: no token #{}", id);
+                1
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::AuditEnable) => {
+            match open_keystore().and_then(|mut keystore| keystore.enable_audit()) {
+                Ok(true) => {
+                    println!("[audit] enabled; set, del and copy are now logged");
+                    0
+                }
+                Ok(false) => {
+                    println!("[audit] already enabled");
+                    0
+                }
+                Err(error) => {
+                    eprintln!("error: {}", error);
+                    1
+                }
+            }
         }
+        Ok(AdminCommand::Audit { query }) => match print_audit(&query) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::Capabilities) => match print_capabilities(&admin_config()) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::CapabilityToggle {
+            object,
+            verb,
+            enabled,
+        }) => match set_capability(&admin_config(), &object, verb, enabled) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
         Ok(AdminCommand::Crud { object, verb }) => match execute_crud(object, verb) {
             Ok(_) => 0,
             Err(error) => {
@@ -24,6 +159,63 @@ pub fn run_admin_cli() -> i32 {
                 1
             }
         },
+        Ok(AdminCommand::Caches) => match print_caches(&admin_config()) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::CreateNamespace {
+            project,
+            namespace,
+            key_file,
+        }) => match create_namespace(&project, &namespace, key_file.as_deref()) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::Index {
+            project,
+            namespace,
+            kind,
+            drop,
+        }) => match run_index(&project, &namespace, &kind, drop) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::Indexes) => match print_indexes() {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
+        Ok(AdminCommand::PurgeExpired { project, namespace }) => {
+            match run_purge(&admin_config(), project.as_deref(), namespace.as_deref()) {
+                Ok(()) => 0,
+                Err(error) => {
+                    eprintln!("error: {}", error);
+                    1
+                }
+            }
+        }
+        Ok(AdminCommand::Sweep {
+            daemon,
+            interval_secs,
+            batch_size,
+        }) => match run_sweep(&admin_config(), daemon, interval_secs, batch_size) {
+            Ok(()) => 0,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                1
+            }
+        },
         Err(error) => {
             eprintln!("{}\nUsage: {}", error, commands::usage());
             1
@@ -31,11 +223,11 @@ pub fn run_admin_cli() -> i32 {
     }
 }
 
-fn print_capabilities() {
-    let config = SqliteConnectionConfig::default();
+/// Print each adapter's enabled verbs, then any verbs toggled off in `config`'s database.
+pub fn print_capabilities(config: &SqliteConnectionConfig) -> Result<(), CrudError> {
     let base = SqliteBaseAdapter::new(config.clone());
     let table = SqliteTableAdapter::new(config.clone());
-    let record = SqliteRecordAdapter::new(config);
+    let record = SqliteRecordAdapter::new(config.clone());
 
     println!("[capabilities] base");
     render_capability_entries(base.capabilities());
@@ -43,6 +235,48 @@ fn print_capabilities() {
     render_capability_entries(table.capabilities());
     println!("[capabilities] record");
     render_capability_entries(record.capabilities());
+
+    let toggles = CapabilityToggles::load(config)?;
+    let disabled: Vec<String> = toggles
+        .disabled()
+        .map(|(object, verb)| format!("{} {}", object, verb))
+        .collect();
+    if !disabled.is_empty() {
+        println!("[capabilities] disabled");
+        for entry in disabled {
+            println!("  {}", entry);
+        }
+    }
+    Ok(())
+}
+
+/// Persist a toggle for `verb` on `object`; only the SQLite adapter kinds can be toggled.
+pub fn set_capability(
+    config: &SqliteConnectionConfig,
+    object: &CrudObjectKind,
+    verb: CrudVerb,
+    enabled: bool,
+) -> Result<(), CrudError> {
+    if !matches!(
+        object,
+        CrudObjectKind::Base | CrudObjectKind::Table | CrudObjectKind::Record
+    ) || verb == CrudVerb::Invalid
+    {
+        return Err(CrudError::invalid_input(
+            CrudDomain::Sqlite,
+            object.clone(),
+            verb,
+            format!("{} {} cannot be toggled", object, verb),
+        ));
+    }
+    set_capability_enabled(config, object, verb, enabled)?;
+    println!(
+        "[capability] {} {} {}",
+        object,
+        verb,
+        if enabled { "enabled" } else { "disabled" }
+    );
+    Ok(())
 }
 
 fn execute_crud(object: CrudObjectKind, verb: CrudVerb) -> Result<(), CrudError> {
@@ -61,8 +295,371 @@ fn execute_crud(object: CrudObjectKind, verb: CrudVerb) -> Result<(), CrudError>
     Ok(())
 }
 
+/// Keystore at `--database-path`, or the default database.
+fn admin_config() -> SqliteConnectionConfig {
+    let config = SqliteConnectionConfig::default();
+    let database_path = get_var("opt_database_path");
+    if database_path.is_empty() {
+        config
+    } else {
+        config.with_database_path(database_path)
+    }
+}
+
+fn open_keystore() -> Result<SqliteKeystore, CrudError> {
+    SqliteKeystore::open(&admin_config())
+}
+
+/// `open_keystore` for ACL changes and tokens in `project.namespace`; see `administers`.
+fn open_acl_admin(
+    project: &str,
+    namespace: &str,
+    verb: CrudVerb,
+) -> Result<SqliteKeystore, CrudError> {
+    let config = admin_config();
+    let keystore = SqliteKeystore::open(&config)?;
+    if administers(&keystore, &config, project, namespace)? {
+        return Ok(keystore);
+    }
+    Err(not_acl_admin(project, namespace, verb))
+}
+
+/// Whether the OS user may manage ACLs and tokens in `project.namespace`: it owns
+/// the database file (or is root), or already holds write access there.
+fn administers(
+    keystore: &SqliteKeystore,
+    config: &SqliteConnectionConfig,
+    project: &str,
+    namespace: &str,
+) -> Result<bool, CrudError> {
+    if owns_path(config.database_path()) {
+        return Ok(true);
+    }
+    let user = os_user().unwrap_or_default();
+    Ok(keystore
+        .access_policy()?
+        .grants_write(&user, project, namespace))
+}
+
+fn not_acl_admin(project: &str, namespace: &str, verb: CrudVerb) -> CrudError {
+    CrudError::permission_denied(
+        CrudDomain::Sqlite,
+        CrudObjectKind::Record,
+        verb,
+        format!(
+            "user '{}' neither owns the database nor holds write access to {}.{}",
+            os_user().unwrap_or_default(),
+            project,
+            namespace
+        ),
+    )
+}
+
+/// Print each TTL cache namespace in `config`'s database with its settings and live key count.
+pub fn print_caches(config: &SqliteConnectionConfig) -> Result<(), CrudError> {
+    let caches = SqliteKeystore::open(config)?.caches()?;
+    if caches.is_empty() {
+        println!("(no cache namespaces)");
+        return Ok(());
+    }
+
+    let now = SystemClock.now();
+    println!(
+        "{:<32} {:>10} {:>12} {:>8} {:>8}  NEXT EXPIRY",
+        "NAMESPACE", "TTL", "MAX TTL", "SLIDING", "KEYS"
+    );
+    for cache in caches {
+        let next_expiry = match cache.next_expiry {
+            Some(at) => format!("in {}s", (at - now).max(0)),
+            None => "-".to_string(),
+        };
+        let max_ttl = match cache.settings.max_ttl_secs {
+            Some(secs) if cache.settings.clamp_ttl => format!("{}s clamp", secs),
+            Some(secs) => format!("{}s", secs),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:<32} {:>9}s {:>12} {:>8} {:>8}  {}",
+            format!("{}.{}", cache.project, cache.namespace),
+            cache.settings.ttl_secs,
+            max_ttl,
+            if cache.settings.sliding { "yes" } else { "no" },
+            cache.keys,
+            next_expiry
+        );
+    }
+    Ok(())
+}
+
+fn create_namespace(
+    project: &str,
+    namespace: &str,
+    key_file: Option<&str>,
+) -> Result<(), CrudError> {
+    let secret = match key_file {
+        Some(path) => Some(read_key_file(Path::new(path))?),
+        None => None,
+    };
+    open_keystore()?.create_namespace(project, namespace, secret.as_deref())?;
+    match key_file {
+        Some(path) => println!(
+            "[namespace] {}.{} created, encrypted with {}",
+            project, namespace, path
+        ),
+        None => println!("[namespace] {}.{} created", project, namespace),
+    }
+    Ok(())
+}
+
+fn run_index(
+    project: &str,
+    namespace: &str,
+    kind: &IndexKind,
+    drop: bool,
+) -> Result<(), CrudError> {
+    let mut keystore = open_keystore()?;
+    let scope = format!("{}.{}", project, namespace);
+    if drop {
+        if keystore.drop_index(project, namespace, kind)? {
+            println!("[index] dropped {} index on {}", kind, scope);
+        } else {
+            println!("[index] no {} index on {}", kind, scope);
+        }
+    } else if keystore.create_index(project, namespace, kind)? {
+        println!("[index] created {} index on {}", kind, scope);
+    } else {
+        println!("[index] {} index on {} already exists", kind, scope);
+    }
+    Ok(())
+}
+
+fn print_indexes() -> Result<(), CrudError> {
+    let indexes = open_keystore()?.indexes()?;
+    if indexes.is_empty() {
+        println!("(no indexes)");
+        return Ok(());
+    }
+    println!("{:<32} KIND", "NAMESPACE");
+    for index in indexes {
+        println!(
+            "{:<32} {}",
+            format!("{}.{}", index.project, index.namespace),
+            index.kind
+        );
+    }
+    Ok(())
+}
+
+fn print_acl() -> Result<(), CrudError> {
+    let rules = open_keystore()?.access_rules()?;
+    if rules.is_empty() {
+        println!("(no ACL rules; every namespace is open)");
+        return Ok(());
+    }
+    println!("{:<32} {:<16} PERMISSION", "NAMESPACE", "USER");
+    for rule in rules {
+        println!(
+            "{:<32} {:<16} {}",
+            format!("{}.{}", rule.project, rule.namespace),
+            rule.user,
+            rule.permission
+        );
+    }
+    Ok(())
+}
+
+/// Tokens whose namespace the OS user administers (all of them for the database owner).
+fn print_tokens() -> Result<(), CrudError> {
+    let config = admin_config();
+    let keystore = SqliteKeystore::open(&config)?;
+    let mut tokens = Vec::new();
+    for token in keystore.tokens()? {
+        if administers(&keystore, &config, &token.project, &token.namespace)? {
+            tokens.push(token);
+        }
+    }
+    if tokens.is_empty() {
+        println!("(no API tokens)");
+        return Ok(());
+    }
+    println!(
+        "{:<6} {:<32} {:<10} CREATED",
+        "ID", "NAMESPACE", "PERMISSION"
+    );
+    for token in tokens {
+        println!(
+            "{:<6} {:<32} {:<10} {}",
+            token.id,
+            format!("{}.{}", token.project, token.namespace),
+            token.permission,
+            format_timestamp(token.created_at)
+        );
+    }
+    Ok(())
+}
+
+/// Revoke token `id` if the OS user administers its namespace; `false` when there is none.
+fn revoke_token(id: i64) -> Result<bool, CrudError> {
+    let config = admin_config();
+    let keystore = SqliteKeystore::open(&config)?;
+    let token = match keystore.tokens()?.into_iter().find(|token| token.id == id) {
+        Some(token) => token,
+        None => return Ok(false),
+    };
+    if !administers(&keystore, &config, &token.project, &token.namespace)? {
+        return Err(not_acl_admin(
+            &token.project,
+            &token.namespace,
+            CrudVerb::Delete,
+        ));
+    }
+    keystore.revoke_token(id)
+}
+
+fn print_audit(query: &AuditQuery) -> Result<(), CrudError> {
+    let keystore = open_keystore()?;
+    if !keystore.audit_enabled() {
+        println!("(audit log disabled; enable it with --audit-enable)");
+        return Ok(());
+    }
+    let entries = keystore.audit_search(query)?;
+    if entries.is_empty() {
+        println!("(no audit entries)");
+        return Ok(());
+    }
+    let short = |hash: &Option<String>| match hash {
+        Some(hash) => hash[..8].to_string(),
+        None => "-".to_string(),
+    };
+    println!(
+        "{:<20} {:<16} {:<6} {:<32} OLD -> NEW (md5)",
+        "AT", "USER", "CMD", "ADDRESS"
+    );
+    for entry in entries {
+        println!(
+            "{:<20} {:<16} {:<6} {:<32} {} -> {}",
+            format_timestamp(entry.at),
+            entry.user.as_deref().unwrap_or("-"),
+            entry.command,
+            entry.address,
+            short(&entry.old_hash),
+            short(&entry.new_hash)
+        );
+    }
+    Ok(())
+}
+
+/// Delete expired keys in `config`'s database now, limited to `project` and `namespace` when given.
+pub fn run_purge(
+    config: &SqliteConnectionConfig,
+    project: Option<&str>,
+    namespace: Option<&str>,
+) -> Result<(), CrudError> {
+    let keystore = SqliteKeystore::open(config)?;
+    let reports = keystore.purge_expired(project, namespace, commands::DEFAULT_SWEEP_BATCH_SIZE)?;
+    let total = report_removals(&keystore, &reports, "purge")?;
+    println!("[purge] deleted {} expired rows", total);
+    Ok(())
+}
+
+/// Reclaim expired rows and apply retention policies in `config`'s database,
+/// then repeat every `interval_secs` when `daemon` is set.
+pub fn run_sweep(
+    config: &SqliteConnectionConfig,
+    daemon: bool,
+    interval_secs: u64,
+    batch_size: usize,
+) -> Result<(), CrudError> {
+    let keystore = SqliteKeystore::open(config)?;
+
+    loop {
+        let reports = keystore.sweep_expired(batch_size)?;
+        let total = report_removals(&keystore, &reports, "sweep")?;
+        println!("[sweep] reclaimed {} rows", total);
+        for report in keystore.enforce_retention()? {
+            println!(
+                "[retention] {}.{}: {} trimmed",
+                report.project, report.namespace, report.removed
+            );
+        }
+
+        if !daemon {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(interval_secs.max(1)));
+    }
+}
+
+/// Print per-namespace removals, fire expiry hooks, and return the total removed.
+fn report_removals(
+    keystore: &SqliteKeystore,
+    reports: &[SweepReport],
+    label: &str,
+) -> Result<usize, CrudError> {
+    let hooks = keystore.expiry_hooks()?;
+    for report in reports {
+        println!(
+            "[{}] {}.{}: {} expired",
+            label, report.project, report.namespace, report.removed
+        );
+        let hook = hooks
+            .iter()
+            .find(|hook| hook.project == report.project && hook.namespace == report.namespace);
+        if let Some(hook) = hook {
+            if let Err(error) = run_expiry_hook(hook, report) {
+                eprintln!(
+                    "[{}] hook for {}.{} failed: {}",
+                    label, hook.project, hook.namespace, error
+                );
+            }
+        }
+    }
+    Ok(reports.iter().map(|report| report.removed).sum())
+}
+
+/// Run a hook with the sweep details in its environment and the removed keys on stdin.
+fn run_expiry_hook(hook: &ExpiryHook, report: &SweepReport) -> std::io::Result<()> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&hook.command)
+        .env("PRONTO_HOOK_PROJECT", &report.project)
+        .env("PRONTO_HOOK_NAMESPACE", &report.namespace)
+        .env("PRONTO_HOOK_REMOVED", report.removed.to_string())
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    // Drop stdin before waiting so the hook sees EOF; a hook that exits without
+    // reading every key closes the pipe, which is not a failure of its own.
+    let written = match child.stdin.take() {
+        Some(mut stdin) => report
+            .keys
+            .iter()
+            .try_for_each(|key| writeln!(stdin, "{}", key)),
+        None => Ok(()),
+    };
+
+    let status = child.wait()?;
+    if let Err(error) = written {
+        if error.kind() != std::io::ErrorKind::BrokenPipe {
+            return Err(error);
+        }
+    }
+    if !status.success() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            format!("exited with {}", status),
+        ));
+    }
+    Ok(())
+}
+
 pub fn ensure_capability_toggle() -> Result<(), CommandError> {
-    if !has_var("opt_object") && !has_var("opt_capabilities") {
+    if !has_var("opt_object")
+        && !has_var("opt_capabilities")
+        && !has_var("opt_caches")
+        && !has_var("opt_purge_expired")
+        && !has_var("opt_sweep")
+    {
         return Err(CommandError::new("no admin action requested"));
     }
     Ok(())
@@ -0,0 +1,273 @@
+use std::path::Path;
+
+use rsb::prelude::*;
+
+use crate::lib::addr::AddressLayout;
+use crate::lib::adpt::sqlite::{
+    ImportStrategy, RetentionPolicy, SqliteConnectionConfig, ValidationMode,
+};
+use crate::lib::api;
+use crate::lib::cli::admin::{
+    parse_capability, print_caches, print_capabilities, run_purge, run_sweep, set_capability,
+    DEFAULT_SWEEP_BATCH_SIZE, DEFAULT_SWEEP_INTERVAL_SECS,
+};
+use crate::lib::core::crud::CrudResult;
+use crate::lib::core::helpers::parse_duration;
+
+use super::context::{connection_config, CommandArgs, EXIT_CONFLICT, EXIT_ERROR, EXIT_OK};
+use super::output::report_error;
+use super::verbosity::say;
+
+/// `admin capability ...` manages adapter toggles; `admin retention ...` namespace trimming;
+/// `admin oplog enable` starts the change log read by `changes`;
+/// `admin layout [fixed|dotted-keys]` shows or sets how addresses past three segments split;
+/// `admin validation [lax|strict]` shows or sets whether `set` may create namespaces;
+/// `admin sweep [--daemon] [--interval SECS] [--batch-size N]` reclaims expired rows
+/// and applies retention policies, once or every `SECS`;
+/// `admin purge-expired [project[.namespace]]` deletes expired keys now, optionally in one scope;
+/// `admin caches` lists TTL cache namespaces with their settings and key counts;
+/// `admin snapshot [--out FILE]` copies the live database with the SQLite backup API;
+/// `admin merge --from PATH [--prefix META] [--strategy skip|overwrite|fail]` imports
+/// another database's keys, printing a `!` line per conflicting key (exit 3 under `fail`).
+pub fn do_admin(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(
+        &args,
+        &[
+            "max-age",
+            "max-keys",
+            "out",
+            "from",
+            "prefix",
+            "strategy",
+            "interval",
+            "batch-size",
+        ],
+    );
+    let config = connection_config();
+    let result = match parsed.positional.as_slice() {
+        [area, action] if area == "capability" && action == "list" => print_capabilities(&config),
+        [area, action, object, verb]
+            if area == "capability" && (action == "enable" || action == "disable") =>
+        {
+            let (object, verb) = match parse_capability(object, verb) {
+                Ok(parsed) => parsed,
+                Err(error) => {
+                    eprintln!("admin: {}", error);
+                    return EXIT_ERROR;
+                }
+            };
+            set_capability(&config, &object, verb, action == "enable")
+        }
+        [area, action, scope] if area == "retention" && action == "set" => {
+            let max_age_secs = match parsed.flag("max-age").map(parse_duration) {
+                None => None,
+                Some(Some(secs)) => Some(secs),
+                Some(None) => {
+                    eprintln!("admin: --max-age expects a duration such as 30d or 12h");
+                    return EXIT_ERROR;
+                }
+            };
+            let max_keys = match parsed.flag("max-keys").map(str::parse::<u64>) {
+                None => None,
+                Some(Ok(keys)) => Some(keys),
+                Some(Err(_)) => {
+                    eprintln!("admin: --max-keys expects a number");
+                    return EXIT_ERROR;
+                }
+            };
+            let policy = RetentionPolicy {
+                max_age_secs,
+                max_keys,
+            };
+            if policy.is_empty() {
+                eprintln!("admin: retention set needs --max-age and/or --max-keys");
+                return EXIT_ERROR;
+            }
+            api::set_retention(&config, scope, &policy)
+                .map(|()| say(format!("retention for {}: {}", scope, describe(&policy))))
+        }
+        [area, action, scope] if area == "retention" && action == "clear" => {
+            api::set_retention(&config, scope, &RetentionPolicy::default())
+                .map(|()| say(format!("retention for {} cleared", scope)))
+        }
+        [area, action] if area == "retention" && action == "list" => print_retention(&config),
+        [area, action] if area == "oplog" && action == "enable" => {
+            api::enable_oplog(&config).map(|enabled| {
+                if enabled {
+                    say("oplog enabled; every write is now logged (see `prontodb changes`)")
+                } else {
+                    say("oplog already enabled")
+                }
+            })
+        }
+        [area] if area == "layout" => {
+            api::address_layout(&config).map(|layout| println!("{}", layout))
+        }
+        [area, name] if area == "layout" => {
+            let layout = match AddressLayout::parse(name) {
+                Ok(layout) => layout,
+                Err(error) => {
+                    eprintln!("admin: {}", error);
+                    return EXIT_ERROR;
+                }
+            };
+            api::set_address_layout(&config, layout)
+                .map(|()| say(format!("address layout: {}", layout)))
+        }
+        [area] if area == "validation" => {
+            api::validation_mode(&config).map(|mode| println!("{}", mode))
+        }
+        [area, name] if area == "validation" => {
+            let mode = match ValidationMode::parse(name) {
+                Some(mode) => mode,
+                None => {
+                    eprintln!(
+                        "admin: unknown validation mode '{}' (expected lax|strict)",
+                        name
+                    );
+                    return EXIT_ERROR;
+                }
+            };
+            api::set_validation_mode(&config, mode)
+                .map(|()| say(format!("validation mode: {}", mode)))
+        }
+        [area] if area == "sweep" => {
+            let interval_secs = match parsed.flag("interval").map(str::parse::<u64>) {
+                None => DEFAULT_SWEEP_INTERVAL_SECS,
+                Some(Ok(secs)) => secs,
+                Some(Err(_)) => {
+                    eprintln!("admin: --interval expects a number of seconds");
+                    return EXIT_ERROR;
+                }
+            };
+            let batch_size = match parsed.flag("batch-size").map(str::parse::<usize>) {
+                None => DEFAULT_SWEEP_BATCH_SIZE,
+                Some(Ok(rows)) if rows > 0 => rows,
+                Some(_) => {
+                    eprintln!("admin: --batch-size expects a positive number");
+                    return EXIT_ERROR;
+                }
+            };
+            run_sweep(
+                &config,
+                parsed.has_flag("daemon"),
+                interval_secs,
+                batch_size,
+            )
+        }
+        [area] if area == "purge-expired" => run_purge(&config, None, None),
+        [area, scope] if area == "purge-expired" => {
+            let mut parts = scope.splitn(2, '.').filter(|part| !part.is_empty());
+            run_purge(&config, parts.next(), parts.next())
+        }
+        [area] if area == "caches" => print_caches(&config),
+        [area] if area == "merge" => return do_admin_merge(&parsed, &config),
+        [area] if area == "snapshot" => {
+            api::snapshot_database(&config, parsed.flag("out").map(Path::new)).map(|path| {
+                say(format!(
+                    "Snapshot of {} written to {}",
+                    config.database_path().display(),
+                    path.display()
+                ))
+            })
+        }
+        _ => {
+            eprintln!("Usage: prontodb admin capability list");
+            eprintln!("       prontodb admin capability enable|disable <base|table|record> <verb>");
+            eprintln!("       prontodb admin retention set <p.ns> [--max-age 30d] [--max-keys N]");
+            eprintln!("       prontodb admin retention clear <p.ns>");
+            eprintln!("       prontodb admin retention list");
+            eprintln!("       prontodb admin oplog enable");
+            eprintln!("       prontodb admin layout [fixed|dotted-keys]");
+            eprintln!("       prontodb admin validation [lax|strict]");
+            eprintln!("       prontodb admin sweep [--daemon] [--interval SECS] [--batch-size N]");
+            eprintln!("       prontodb admin purge-expired [project[.namespace]]");
+            eprintln!("       prontodb admin caches");
+            eprintln!("       prontodb admin snapshot [--out FILE]");
+            eprintln!("       prontodb admin merge --from PATH [--prefix META] [--strategy S]");
+            return EXIT_ERROR;
+        }
+    };
+
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            report_error("admin", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `admin merge`: one `!` line per conflicting key, then the totals.
+fn do_admin_merge(parsed: &CommandArgs, config: &SqliteConnectionConfig) -> i32 {
+    let source = match parsed.flag("from") {
+        Some(source) if !source.is_empty() => source,
+        _ => {
+            eprintln!("admin: merge needs --from PATH");
+            return EXIT_ERROR;
+        }
+    };
+    let strategy = match parsed.flag("strategy").unwrap_or("skip") {
+        "skip" => ImportStrategy::Skip,
+        "overwrite" => ImportStrategy::Overwrite,
+        "fail" => ImportStrategy::Fail,
+        other => {
+            eprintln!(
+                "admin: unknown --strategy '{}' (expected skip|overwrite|fail)",
+                other
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let report =
+        match api::import_database(config, Path::new(source), parsed.flag("prefix"), strategy) {
+            Ok(report) => report,
+            Err(error) => {
+                report_error("admin", &error);
+                return EXIT_ERROR;
+            }
+        };
+    let outcome = match strategy {
+        ImportStrategy::Skip => "kept",
+        ImportStrategy::Overwrite => "overwritten",
+        ImportStrategy::Fail => "conflicts",
+    };
+    for address in &report.conflicts {
+        println!("! {} ({})", address, outcome);
+    }
+    if strategy == ImportStrategy::Fail && !report.conflicts.is_empty() {
+        eprintln!(
+            "admin: {} keys differ; nothing merged (--strategy fail)",
+            report.conflicts.len()
+        );
+        return EXIT_CONFLICT;
+    }
+    say(format!(
+        "Merged {}: {} added, {} overwritten, {} unchanged, {} conflicts",
+        source,
+        report.added,
+        report.overwritten,
+        report.unchanged,
+        report.conflicts.len()
+    ));
+    EXIT_OK
+}
+
+fn print_retention(config: &SqliteConnectionConfig) -> CrudResult<()> {
+    for (project, namespace, policy) in api::retention_policies(config)? {
+        println!("{}.{}\t{}", project, namespace, describe(&policy));
+    }
+    Ok(())
+}
+
+/// `max-age=2592000s max-keys=10000`, omitting unset limits.
+fn describe(policy: &RetentionPolicy) -> String {
+    let mut parts = Vec::new();
+    if let Some(secs) = policy.max_age_secs {
+        parts.push(format!("max-age={}s", secs));
+    }
+    if let Some(keys) = policy.max_keys {
+        parts.push(format!("max-keys={}", keys));
+    }
+    parts.join(" ")
+}
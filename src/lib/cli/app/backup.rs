@@ -0,0 +1,427 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use rsb::prelude::*;
+
+use crate::lib::adpt::sqlite::{Clock, ImportStrategy, SqliteConnectionConfig, SystemClock};
+use crate::lib::api;
+use crate::lib::core::helpers::format_timestamp;
+use crate::lib::core::schedule::CronSchedule;
+
+use super::context::{connection_config, CommandArgs, EXIT_CONFLICT, EXIT_ERROR, EXIT_OK};
+use super::output::report_error;
+use super::verbosity::say;
+
+/// `backup [--incremental] [--encrypt] [--output DIR]`, `backup --list [--output DIR]`,
+/// `backup --scope P[.NS]`, `backup --merge FILE [--strategy overwrite|skip|fail]`,
+/// `backup --restore FILE [--dry-run]`, `backup --verify FILE`,
+/// `backup prune [--keep-daily N] [--keep-weekly N] [--dir DIR]`,
+/// `backup --schedule "MIN HOUR DAY MONTH WEEKDAY" [--daemon]`
+///
+/// Archives go to `--output`, or `backups/` beside the database, as
+/// `prontodb_<name>_<YYYYMMDD>[_nNN].tar.gz`. `--incremental` keeps only what
+/// changed since the newest archive there; restoring it lays the chain over
+/// the full backup below. `--encrypt` writes `.tar.gz.enc` sealed with the
+/// `--key-file` contents as passphrase; `--restore` decrypts with the same
+/// key file. `--restore` replaces the whole database, so the dispatcher asks
+/// first (`--yes` when scripted); with `--dry-run` it only lists the keys
+/// it would add (`+`), overwrite (`~`) and remove (`-`). `--verify` checks
+/// that an archive would restore, leaving the database alone. `prune`
+/// deletes archives outside the retention policy (`--dir` is the same as
+/// `--output`).
+///
+/// `--scope` archives just one project or namespace, values decrypted (add
+/// `--encrypt` to seal the archive), as `prontodb_<name>_<scope>_<YYYYMMDD>`;
+/// `--merge` loads such an archive into the current database, which may be
+/// on another machine, keeping every other key. `--strategy` is as for `import`.
+///
+/// An `s3://` or `sftp://` `--output` (features `backup-s3`, `backup-sftp`)
+/// writes the archive to `backups/` as usual, then uploads it there.
+///
+/// `--schedule` prints when the cron expression (UTC) next runs; with
+/// `--daemon` it stays up and backs up at each match, pruning to
+/// `--keep-daily`/`--keep-weekly` afterwards when either is given.
+pub fn do_backup(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(
+        &args,
+        &[
+            "output",
+            "dir",
+            "restore",
+            "merge",
+            "strategy",
+            "scope",
+            "verify",
+            "keep-daily",
+            "keep-weekly",
+            "schedule",
+        ],
+    );
+    let config = connection_config();
+    let output = parsed
+        .flag("output")
+        .or_else(|| parsed.flag("dir"))
+        .filter(|dir| !dir.is_empty());
+    let remote = match output.map(api::remote_target).transpose() {
+        Ok(remote) => remote.flatten(),
+        Err(error) => {
+            report_error("backup", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let dir = match (&remote, output) {
+        (None, Some(output)) => PathBuf::from(output),
+        _ => api::default_backup_dir(&config),
+    };
+    let listing = parsed.has_flag("list") || parsed.positional.iter().any(|arg| arg == "prune");
+    if remote.is_some() && listing {
+        eprintln!("prontodb backup: --list and prune work on local directories only");
+        return EXIT_ERROR;
+    }
+    match parsed.positional.as_slice() {
+        [] => {}
+        [sub] if sub == "prune" => return do_backup_prune(&parsed, &config, &dir),
+        _ => {
+            eprintln!(
+                "Usage: prontodb backup [--incremental | --scope P[.NS]] [--encrypt] [--output DIR] | --list [--output DIR] | --restore FILE [--dry-run] | --merge FILE [--strategy S] | --verify FILE | prune [--keep-daily N] [--keep-weekly N] [--dir DIR] | --schedule CRON [--daemon]"
+            );
+            return EXIT_ERROR;
+        }
+    }
+
+    if let Some(archive) = parsed.flag("restore") {
+        return restore(&config, Path::new(archive), parsed.has_flag("dry-run"));
+    }
+
+    if let Some(archive) = parsed.flag("merge") {
+        return do_backup_merge(&parsed, &config, Path::new(archive));
+    }
+
+    if let Some(archive) = parsed.flag("verify") {
+        return match api::verify_backup(&config, Path::new(archive)) {
+            Ok(verified) => {
+                let mut detail = vec![verified
+                    .kind
+                    .map_or("full", api::BackupKind::as_str)
+                    .to_string()];
+                if verified.encrypted {
+                    detail.push("encrypted".to_string());
+                }
+                if verified.archives > 1 {
+                    detail.push(format!("{} archives", verified.archives));
+                }
+                say(format!(
+                    "Verified {} ({}): {} keys{}",
+                    archive,
+                    detail.join(", "),
+                    verified.keys,
+                    if verified.matched_manifest {
+                        " match the manifest"
+                    } else {
+                        ", no manifest to compare"
+                    }
+                ));
+                EXIT_OK
+            }
+            Err(error) => {
+                report_error("backup", &error);
+                EXIT_ERROR
+            }
+        };
+    }
+
+    if parsed.has_flag("list") {
+        return match api::list_backups(&dir) {
+            Ok(archives) => {
+                for archive in archives {
+                    let label = match (archive.kind, archive.encrypted) {
+                        (Some(kind), _) => kind.as_str(),
+                        (None, true) => "encrypted",
+                        (None, false) => "",
+                    };
+                    let line = format!("{}\t{} bytes", archive.path.display(), archive.size_bytes);
+                    if label.is_empty() {
+                        println!("{}", line);
+                    } else {
+                        println!("{}\t{}", line, label);
+                    }
+                }
+                EXIT_OK
+            }
+            Err(error) => {
+                report_error("backup", &error);
+                EXIT_ERROR
+            }
+        };
+    }
+
+    let options = api::BackupOptions {
+        incremental: parsed.has_flag("incremental"),
+        encrypt: parsed.has_flag("encrypt"),
+        scope: parsed.flag("scope").map(str::to_string),
+    };
+    if let Some(expr) = parsed.flag("schedule") {
+        let schedule = match CronSchedule::parse(expr) {
+            Ok(schedule) => schedule,
+            Err(message) => {
+                eprintln!("prontodb backup: --schedule {:?}: {}", expr, message);
+                return EXIT_ERROR;
+            }
+        };
+        let retention = match retention_flags(&parsed) {
+            Ok(retention) => retention,
+            Err(message) => {
+                eprintln!("prontodb backup: {}", message);
+                return EXIT_ERROR;
+            }
+        };
+        if !parsed.has_flag("daemon") {
+            return match schedule.next_after(SystemClock.now()) {
+                Some(next) => {
+                    println!("{}", format_timestamp(next));
+                    EXIT_OK
+                }
+                None => {
+                    eprintln!("prontodb backup: --schedule {:?} never runs", expr);
+                    EXIT_ERROR
+                }
+            };
+        }
+        let retention = (retention != api::BackupRetention::default()).then_some(retention);
+        return run_schedule(
+            &config,
+            &dir,
+            &options,
+            remote.as_deref(),
+            &schedule,
+            retention,
+        );
+    }
+    take_backup(&config, &dir, &options, remote.as_deref(), say)
+}
+
+/// `backup --restore FILE [--dry-run]`, also run as `restore FILE [--dry-run]`.
+pub(super) fn restore(config: &SqliteConnectionConfig, archive: &Path, dry_run: bool) -> i32 {
+    if dry_run {
+        return match api::preview_restore(config, archive) {
+            Ok(preview) => {
+                print_restore_preview(&preview);
+                EXIT_OK
+            }
+            Err(error) => {
+                report_error("backup", &error);
+                EXIT_ERROR
+            }
+        };
+    }
+    match api::restore_backup(config, archive) {
+        Ok(()) => {
+            say(format!(
+                "Restored {} from {}",
+                config.database_path().display(),
+                archive.display()
+            ));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("backup", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// Write one archive, upload it to `remote` if any, and pass what happened to `log`.
+fn take_backup(
+    config: &SqliteConnectionConfig,
+    dir: &Path,
+    options: &api::BackupOptions,
+    remote: Option<&dyn api::RemoteTarget>,
+    log: fn(String),
+) -> i32 {
+    match api::create_backup(config, dir, options.clone()) {
+        Ok(archive) => {
+            log(format!(
+                "Backed up {} to {} ({} bytes, {}{})",
+                config.database_path().display(),
+                archive.path.display(),
+                archive.size_bytes,
+                archive.kind.map_or("full", api::BackupKind::as_str),
+                if archive.encrypted { ", encrypted" } else { "" }
+            ));
+            match remote.map(|remote| remote.upload(&archive.path)) {
+                None => EXIT_OK,
+                Some(Ok(location)) => {
+                    log(format!("Uploaded to {}", location));
+                    EXIT_OK
+                }
+                Some(Err(error)) => {
+                    report_error("backup", &error);
+                    EXIT_ERROR
+                }
+            }
+        }
+        Err(error) => {
+            report_error("backup", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `backup --schedule EXPR --daemon`: back up at every match of `schedule` (UTC) until killed.
+///
+/// A failed backup is reported and the next run still happens; with a
+/// retention policy, archives outside it are pruned after each backup.
+fn run_schedule(
+    config: &SqliteConnectionConfig,
+    dir: &Path,
+    options: &api::BackupOptions,
+    remote: Option<&dyn api::RemoteTarget>,
+    schedule: &CronSchedule,
+    retention: Option<api::BackupRetention>,
+) -> i32 {
+    let log = |line: String| say(format!("{} {}", format_timestamp(SystemClock.now()), line));
+    log(format!(
+        "Scheduled backups of {} to {}",
+        config.database_path().display(),
+        dir.display()
+    ));
+    loop {
+        let next = match schedule.next_after(SystemClock.now()) {
+            Some(next) => next,
+            None => {
+                eprintln!("prontodb backup: the schedule never runs again");
+                return EXIT_ERROR;
+            }
+        };
+        log(format!("Next backup at {}", format_timestamp(next)));
+        // Short naps, so a suspended machine or a clock change does not skip a run.
+        loop {
+            let now = SystemClock.now();
+            if now >= next {
+                break;
+            }
+            thread::sleep(Duration::from_secs((next - now).min(60) as u64));
+        }
+        take_backup(config, dir, options, remote, log);
+        if let Some(retention) = retention {
+            match api::prune_backups(config, dir, retention) {
+                Ok(report) => {
+                    for archive in &report.removed {
+                        log(format!("Removed {}", archive.path.display()));
+                    }
+                }
+                Err(error) => report_error("backup", &error),
+            }
+        }
+    }
+}
+
+/// One `+`/`~`/`-` line per changed key, then the totals and the namespaces they touch.
+fn print_restore_preview(preview: &api::RestorePreview) {
+    let mut counts = [0usize; 3];
+    let mut namespaces = BTreeSet::new();
+    for (address, change) in &preview.changes {
+        let (mark, slot) = match change {
+            api::RestoreChange::Added => ('+', 0),
+            api::RestoreChange::Overwritten => ('~', 1),
+            api::RestoreChange::Removed => ('-', 2),
+        };
+        counts[slot] += 1;
+        namespaces.insert((address.project.as_str(), address.namespace.as_str()));
+        println!("{} {}", mark, address);
+    }
+    say(format!(
+        "Would add {}, overwrite {} and remove {} keys in {} namespaces ({} unchanged)",
+        counts[0],
+        counts[1],
+        counts[2],
+        namespaces.len(),
+        preview.unchanged
+    ));
+}
+
+/// `backup --merge FILE`: load a scoped archive into the database and print the totals.
+fn do_backup_merge(parsed: &CommandArgs, config: &SqliteConnectionConfig, archive: &Path) -> i32 {
+    let strategy = match parsed.flag("strategy").unwrap_or("overwrite") {
+        "overwrite" => ImportStrategy::Overwrite,
+        "skip" => ImportStrategy::Skip,
+        "fail" => ImportStrategy::Fail,
+        other => {
+            eprintln!(
+                "prontodb backup: unknown --strategy '{}' (expected overwrite|skip|fail)",
+                other
+            );
+            return EXIT_ERROR;
+        }
+    };
+    match api::merge_backup(config, archive, strategy) {
+        Ok(report) if report.conflicted > 0 => {
+            eprintln!(
+                "prontodb backup: {} keys already exist; nothing merged (--strategy fail)",
+                report.conflicted
+            );
+            EXIT_CONFLICT
+        }
+        Ok(report) => {
+            say(format!(
+                "Merged {} into {} ({} inserted, {} skipped)",
+                archive.display(),
+                config.database_path().display(),
+                report.inserted,
+                report.skipped
+            ));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("backup", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `backup prune`: print each removed archive, then the totals.
+fn do_backup_prune(parsed: &CommandArgs, config: &SqliteConnectionConfig, dir: &Path) -> i32 {
+    let policy = match retention_flags(parsed) {
+        Ok(policy) => policy,
+        Err(message) => {
+            eprintln!("prontodb backup prune: {}", message);
+            return EXIT_ERROR;
+        }
+    };
+    match api::prune_backups(config, dir, policy) {
+        Ok(report) => {
+            for archive in &report.removed {
+                say(format!("Removed {}", archive.path.display()));
+            }
+            say(format!(
+                "Pruned {} archive(s) from {}, kept {}",
+                report.removed.len(),
+                dir.display(),
+                report.kept.len()
+            ));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("backup", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `--keep-daily N` and `--keep-weekly N`, each 0 when absent.
+fn retention_flags(parsed: &CommandArgs) -> Result<api::BackupRetention, String> {
+    let mut policy = api::BackupRetention::default();
+    for (flag, keep) in [
+        ("keep-daily", &mut policy.keep_daily),
+        ("keep-weekly", &mut policy.keep_weekly),
+    ] {
+        match parsed.flag(flag).map(str::parse::<usize>) {
+            None => {}
+            Some(Ok(count)) => *keep = count,
+            Some(Err(_)) => return Err(format!("--{} takes a count", flag)),
+        }
+    }
+    Ok(policy)
+}
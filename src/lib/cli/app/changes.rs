@@ -0,0 +1,72 @@
+use rsb::prelude::*;
+
+use crate::lib::adpt::sqlite::OplogSince;
+use crate::lib::api;
+use crate::lib::core::helpers::{format_timestamp, parse_timestamp};
+
+use super::context::{connection_config, CommandArgs, EXIT_ERROR, EXIT_OK};
+use super::output::{output_mode, print_json, report_error, ChangeView, OutputMode};
+
+/// `changes [--since SEQ|YYYY-MM-DDTHH:MM:SSZ] [--limit N]`
+///
+/// Prints oplog rows oldest first as `SEQ<TAB>AT<TAB>OP<TAB>ADDRESS<TAB>MD5<TAB>USER`
+/// (`-` for a missing hash or user). A feed resumes by passing the last SEQ
+/// back as `--since`; the oplog is enabled with `admin oplog enable`.
+pub fn do_changes(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["since", "limit"]);
+    if !parsed.positional.is_empty() {
+        eprintln!("Usage: prontodb changes [--since SEQ|YYYY-MM-DDTHH:MM:SSZ] [--limit N]");
+        return EXIT_ERROR;
+    }
+    let since = match parsed.flag("since") {
+        None => OplogSince::Seq(0),
+        Some(raw) => match raw.parse::<i64>() {
+            Ok(seq) if seq >= 0 => OplogSince::Seq(seq),
+            _ => match parse_timestamp(raw) {
+                Some(at) => OplogSince::Time(at),
+                None => {
+                    eprintln!("changes: --since expects a sequence number or YYYY-MM-DDTHH:MM:SSZ");
+                    return EXIT_ERROR;
+                }
+            },
+        },
+    };
+    let limit = match parsed.flag("limit").map(str::parse::<usize>) {
+        None => usize::MAX,
+        Some(Ok(limit)) if limit > 0 => limit,
+        Some(_) => {
+            eprintln!("changes: --limit expects a positive number");
+            return EXIT_ERROR;
+        }
+    };
+
+    let changes = match api::changes_since(&connection_config(), since, limit) {
+        Ok(Some(changes)) => changes,
+        Ok(None) => {
+            eprintln!(
+                "changes: the oplog is disabled; enable it with `prontodb admin oplog enable`"
+            );
+            return EXIT_ERROR;
+        }
+        Err(error) => {
+            report_error("changes", &error);
+            return EXIT_ERROR;
+        }
+    };
+    if output_mode() == OutputMode::Json {
+        let views: Vec<ChangeView<'_>> = changes.iter().map(ChangeView::new).collect();
+        return print_json(&views);
+    }
+    for change in &changes {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            change.seq,
+            format_timestamp(change.at),
+            change.op,
+            change.address,
+            change.value_hash.as_deref().unwrap_or("-"),
+            change.user.as_deref().unwrap_or("-")
+        );
+    }
+    EXIT_OK
+}
@@ -0,0 +1,613 @@
+use rsb::prelude::*;
+
+use crate::lib::addr::KeyPattern;
+use crate::lib::adpt::sqlite::{CacheSettings, SetCondition, ValueType};
+use crate::lib::api;
+
+use super::context::{
+    command_config, connection_config, exit_code, CommandArgs, EXIT_ERROR, EXIT_MISS, EXIT_OK,
+};
+use super::output::{output_mode, print_json, report_error, EntryView, OutputMode, ResolvedView};
+use super::verbosity::{say, trace_address};
+
+/// `set <address> <value>`; in a strict database (`admin validation strict`)
+/// a namespace that does not exist yet needs `--create`. `--meta <ctx>` (also
+/// on `get`, `del`, `keys` and `scan`) addresses another organization context.
+pub fn do_set(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["if-value", "ttl", "type", "meta"]);
+    let (address, value) = match parsed.positional.as_slice() {
+        [address, value] => (address, value),
+        _ => {
+            eprintln!(
+                "Usage: prontodb set <address> <value> [--ttl <secs>] [--type string|int|bool|json] [--if-absent | --if-value <expected>] [--create] [--meta <ctx>]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let config = command_config(&parsed).with_namespace_creation(parsed.has_flag("create"));
+    trace_address(&config, address);
+
+    let ttl = match parsed.flag("ttl").map(str::parse::<u64>) {
+        None => None,
+        Some(Ok(ttl)) => Some(ttl),
+        Some(Err(_)) => {
+            eprintln!("set: --ttl expects a number of seconds");
+            return EXIT_ERROR;
+        }
+    };
+
+    let condition = match (parsed.has_flag("if-absent"), parsed.flag("if-value")) {
+        (false, None) => None,
+        (true, None) => Some(SetCondition::IfAbsent),
+        (false, Some(expected)) => Some(SetCondition::IfValue(expected.to_string())),
+        (true, Some(_)) => {
+            eprintln!("set: --if-absent and --if-value are mutually exclusive");
+            return EXIT_ERROR;
+        }
+    };
+    if ttl.is_some() && condition.is_some() {
+        eprintln!("set: --ttl cannot be combined with --if-absent or --if-value");
+        return EXIT_ERROR;
+    }
+    let value_type = match parsed.flag("type").map(|raw| (raw, ValueType::parse(raw))) {
+        None => None,
+        Some((_, Some(value_type))) => Some(value_type),
+        Some((raw, None)) => {
+            eprintln!(
+                "set: unknown --type '{}' (expected string, int, bool, or json)",
+                raw
+            );
+            return EXIT_ERROR;
+        }
+    };
+    if value_type.is_some() && condition.is_some() {
+        eprintln!("set: --type cannot be combined with --if-absent or --if-value");
+        return EXIT_ERROR;
+    }
+
+    let result = match (value_type, &condition, ttl) {
+        (Some(value_type), _, ttl) => {
+            api::set_typed_value(&config, address, value, value_type, ttl)
+        }
+        (None, Some(condition), _) => api::set_value_if(&config, address, value, condition),
+        (None, None, Some(ttl)) => api::set_value_with_ttl(&config, address, value, ttl),
+        (None, None, None) => api::set_value(&config, address, value),
+    };
+    match result {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            report_error("set", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+pub fn do_get(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["meta"]);
+    let address = match parsed.positional.as_slice() {
+        [address] => address,
+        _ => {
+            eprintln!("Usage: prontodb get <address> [--include-expired] [--meta <ctx>]");
+            return EXIT_ERROR;
+        }
+    };
+    let config = command_config(&parsed);
+    trace_address(&config, address);
+
+    let include_expired = parsed.has_flag("include-expired");
+    if output_mode() == OutputMode::Json {
+        return match api::get_entry(&config, address, include_expired) {
+            Ok(Some(entry)) => print_json(&EntryView::new(&entry)),
+            Ok(None) => EXIT_MISS,
+            Err(error) => {
+                report_error("get", &error);
+                exit_code(&error)
+            }
+        };
+    }
+
+    let result = if include_expired {
+        api::get_value_including_expired(&config, address)
+    } else {
+        api::get_value(&config, address)
+    };
+    match result {
+        Ok(Some(value)) => {
+            println!("{}", value);
+            EXIT_OK
+        }
+        Ok(None) => EXIT_MISS,
+        Err(error) => {
+            report_error("get", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `exists <address>` — silent presence check: exit 0 when found, 2 when missing.
+pub fn do_exists(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let address = match argv.as_slice() {
+        [address] => address,
+        _ => {
+            eprintln!("Usage: prontodb exists <address>");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    trace_address(&config, address);
+
+    match api::value_exists(&config, address) {
+        Ok(true) => EXIT_OK,
+        Ok(false) => EXIT_MISS,
+        Err(error) => {
+            report_error("exists", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `resolve <address>`
+///
+/// Prints where `address` is stored under this invocation's `--cursor`/`--user`
+/// (defaults filled in, context split off, layout applied) without reading
+/// the value, to explain why a `get` missed.
+pub fn do_resolve(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let address = match argv.as_slice() {
+        [address] => address,
+        _ => {
+            eprintln!("Usage: prontodb resolve <address> [--cursor NAME] [--user USER]");
+            return EXIT_ERROR;
+        }
+    };
+
+    let config = connection_config();
+    match api::resolve_address(&config, address) {
+        Ok(addr) => {
+            let layout = config.address_layout.unwrap_or_default();
+            let view = ResolvedView::new(address, &addr, layout, config.database_path());
+            if output_mode() == OutputMode::Json {
+                return print_json(&view);
+            }
+            for (field, value) in view.lines() {
+                println!("{:<10} {}", format!("{}:", field), value);
+            }
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("resolve", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `create-cache <project.namespace> <ttl-secs> [--sliding] [--max-ttl <secs> [--clamp]]`
+pub fn do_create_cache(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["max-ttl"]);
+    let (scope, ttl) = match parsed.positional.as_slice() {
+        [scope, ttl] => (scope, ttl),
+        _ => {
+            eprintln!(
+                "Usage: prontodb create-cache <project.namespace> <ttl-secs> [--sliding] [--max-ttl <secs> [--clamp]]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let ttl_secs = match ttl.parse::<u64>() {
+        Ok(ttl_secs) => ttl_secs,
+        Err(_) => {
+            eprintln!("create-cache: invalid TTL: {}", ttl);
+            return EXIT_ERROR;
+        }
+    };
+
+    let mut settings = CacheSettings::new(ttl_secs).with_sliding(parsed.has_flag("sliding"));
+    if let Some(raw) = parsed.flag("max-ttl") {
+        match raw.parse::<u64>() {
+            Ok(max_ttl_secs) => {
+                settings = settings.with_max_ttl(max_ttl_secs, parsed.has_flag("clamp"));
+            }
+            Err(_) => {
+                eprintln!("create-cache: invalid --max-ttl: {}", raw);
+                return EXIT_ERROR;
+            }
+        }
+    }
+
+    match api::create_cache(&connection_config(), scope, &settings) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            report_error("create-cache", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `allow-ttl <project.namespace> [--off]` opts a namespace into `set --ttl`.
+pub fn do_allow_ttl(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &[]);
+    let scope = match parsed.positional.as_slice() {
+        [scope] => scope,
+        _ => {
+            eprintln!("Usage: prontodb allow-ttl <project.namespace> [--off]");
+            return EXIT_ERROR;
+        }
+    };
+
+    match api::allow_key_ttl(&connection_config(), scope, !parsed.has_flag("off")) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            report_error("allow-ttl", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `secret <project.namespace[.key]> [--off]`: `scan` masks marked values unless `--reveal`.
+pub fn do_secret(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &[]);
+    let target = match parsed.positional.as_slice() {
+        [target] => target,
+        _ => {
+            eprintln!("Usage: prontodb secret <project.namespace[.key]> [--off]");
+            return EXIT_ERROR;
+        }
+    };
+
+    match api::mark_secret(&connection_config(), target, !parsed.has_flag("off")) {
+        Ok(_) => EXIT_OK,
+        Err(error) => {
+            report_error("secret", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `hook set <project.namespace> <command>`, `hook rm <project.namespace>`, `hook list`.
+///
+/// Hooks run from `prontodb-admin --sweep` after expired keys are removed; the
+/// command sees `PRONTO_HOOK_PROJECT`, `PRONTO_HOOK_NAMESPACE` and
+/// `PRONTO_HOOK_REMOVED`, and receives the removed keys on stdin, one per line.
+pub fn do_hook(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let config = connection_config();
+    let result =
+        match argv.as_slice() {
+            [verb, scope, command @ ..] if verb == "set" && !command.is_empty() => {
+                api::set_expiry_hook(&config, scope, &command.join(" ")).map(|()| EXIT_OK)
+            }
+            [verb, scope] if verb == "rm" => api::remove_expiry_hook(&config, scope)
+                .map(|removed| if removed { EXIT_OK } else { EXIT_MISS }),
+            [verb] if verb == "list" => api::expiry_hooks(&config).map(|hooks| {
+                for hook in hooks {
+                    println!("{}.{}\t{}", hook.project, hook.namespace, hook.command);
+                }
+                EXIT_OK
+            }),
+            _ => {
+                eprintln!("Usage: prontodb hook set <project.namespace> <command>");
+                eprintln!("       prontodb hook rm <project.namespace>");
+                eprintln!("       prontodb hook list");
+                return EXIT_ERROR;
+            }
+        };
+
+    result.unwrap_or_else(|error| {
+        report_error("hook", &error);
+        exit_code(&error)
+    })
+}
+
+/// `persist <address>` removes the TTL from a key (exit 2 if missing).
+pub fn do_persist(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let address = match argv.as_slice() {
+        [address] => address,
+        _ => {
+            eprintln!("Usage: prontodb persist <address>");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    trace_address(&config, address);
+
+    match api::persist_value(&config, address) {
+        Ok(true) => EXIT_OK,
+        Ok(false) => EXIT_MISS,
+        Err(error) => {
+            report_error("persist", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `count <project.namespace[.prefix]>` prints how many live keys match.
+pub fn do_count(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let scope = match argv.as_slice() {
+        [scope] => scope,
+        _ => {
+            eprintln!("Usage: prontodb count <project.namespace[.prefix]>");
+            return EXIT_ERROR;
+        }
+    };
+
+    match api::count_keys(&connection_config(), scope) {
+        Ok(count) => {
+            println!("{}", count);
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("count", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `del <address>`; exit 2 if nothing was there.
+///
+/// A glob (`del 'app.cache.*'`) deletes every matching key in one transaction;
+/// the dispatcher asks first (`--yes` when scripted, see `confirm`), and
+/// `--dry-run` only lists them.
+pub fn do_del(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["meta"]);
+    let address = match parsed.positional.as_slice() {
+        [address] => address,
+        _ => {
+            eprintln!("Usage: prontodb del <address> [--meta <ctx>]");
+            eprintln!("       prontodb del '<project.ns.glob>' [--yes] [--dry-run]");
+            return EXIT_ERROR;
+        }
+    };
+    if KeyPattern::has_glob(address) {
+        return delete_matching(address, &parsed);
+    }
+    let config = command_config(&parsed);
+    trace_address(&config, address);
+
+    match api::delete_value(&config, address) {
+        Ok(true) => EXIT_OK,
+        Ok(false) => EXIT_MISS,
+        Err(error) => {
+            report_error("del", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+fn delete_matching(pattern: &str, parsed: &CommandArgs) -> i32 {
+    let config = command_config(parsed);
+    let matched = match api::keys_matching(&config, pattern) {
+        Ok(matched) => matched,
+        Err(error) => {
+            report_error("del", &error);
+            return exit_code(&error);
+        }
+    };
+    if matched.is_empty() {
+        say(format!("no keys match {}", pattern));
+        return EXIT_MISS;
+    }
+    if parsed.has_flag("dry-run") {
+        for addr in &matched {
+            println!("{}", addr);
+        }
+        say(format!("{} keys would be deleted", matched.len()));
+        return EXIT_OK;
+    }
+    match api::delete_matching(&config, pattern) {
+        Ok(deleted) => {
+            say(format!("deleted {} keys matching {}", deleted, pattern));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("del", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `mv <src> <dst> [--force]` — atomic rename; exit 2 if `src` is missing, 3 if `dst` exists.
+pub fn do_mv(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &[]);
+    let (src, dst) = match parsed.positional.as_slice() {
+        [src, dst] => (src, dst),
+        _ => {
+            eprintln!("Usage: prontodb mv <src-address> <dst-address> [--force]");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    trace_address(&config, src);
+    trace_address(&config, dst);
+
+    match api::move_value(&config, src, dst, parsed.has_flag("force")) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            report_error("mv", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `copy <src> <dst> [--force] [--keep-ttl]` copies one key.
+///
+/// `copy project.ns.* other.ns` (or `copy --namespace project.ns other.ns`)
+/// copies a whole namespace in one transaction, overwriting existing keys.
+pub fn do_copy(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &[]);
+    let (src, dst) = match parsed.positional.as_slice() {
+        [src, dst] => (src, dst),
+        _ => {
+            eprintln!("Usage: prontodb copy <src-address> <dst-address> [--force] [--keep-ttl]");
+            eprintln!("       prontodb copy <project.ns.*> <project.ns> [--keep-ttl]");
+            return EXIT_ERROR;
+        }
+    };
+
+    let config = connection_config();
+    let keep_ttl = parsed.has_flag("keep-ttl");
+    if parsed.has_flag("namespace") || src.ends_with('*') {
+        return match api::copy_namespace(&config, src, dst, keep_ttl) {
+            Ok(count) => {
+                say(format!("Copied {} keys from {} to {}", count, src, dst));
+                EXIT_OK
+            }
+            Err(error) => {
+                report_error("copy", &error);
+                exit_code(&error)
+            }
+        };
+    }
+
+    trace_address(&config, src);
+    trace_address(&config, dst);
+    match api::copy_value(&config, src, dst, parsed.has_flag("force"), keep_ttl) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            report_error("copy", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `append <address> <text>` — concatenates onto the existing value.
+pub fn do_append(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let (address, text) = match argv.as_slice() {
+        [address, text] => (address, text),
+        _ => {
+            eprintln!("Usage: prontodb append <address> <text>");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    trace_address(&config, address);
+
+    match api::append_value(&config, address, text) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            report_error("append", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `mset k1=v1 k2=v2 ...` — all pairs are written in one transaction.
+pub fn do_mset(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &[]);
+    let argv = &parsed.positional;
+    if argv.is_empty() {
+        eprintln!("Usage: prontodb mset <address>=<value> [<address>=<value> ...] [--create]");
+        return EXIT_ERROR;
+    }
+
+    let mut entries = Vec::with_capacity(argv.len());
+    for pair in argv {
+        match pair.split_once('=') {
+            Some((address, value)) if !address.is_empty() => {
+                entries.push((address.to_string(), value.to_string()))
+            }
+            _ => {
+                eprintln!("mset: expected <address>=<value>, got '{}'", pair);
+                return EXIT_ERROR;
+            }
+        }
+    }
+
+    let config = connection_config().with_namespace_creation(parsed.has_flag("create"));
+    match api::set_values(&config, &entries) {
+        Ok(_) => EXIT_OK,
+        Err(error) => {
+            report_error("mset", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `mget k1 k2 ...` — prints one line per key in argument order.
+///
+/// Missing keys print an empty line so output stays aligned with the
+/// arguments; the exit code is `EXIT_MISS` if any key was missing.
+pub fn do_mget(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    if argv.is_empty() {
+        eprintln!("Usage: prontodb mget <address> [<address> ...]");
+        return EXIT_ERROR;
+    }
+
+    match api::get_values(&connection_config(), &argv) {
+        Ok(values) => {
+            let mut missing = false;
+            for value in values {
+                match value {
+                    Some(value) => println!("{}", value),
+                    None => {
+                        missing = true;
+                        println!();
+                    }
+                }
+            }
+            if missing {
+                EXIT_MISS
+            } else {
+                EXIT_OK
+            }
+        }
+        Err(error) => {
+            report_error("mget", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `incr <address> [--by N]` — prints the new value.
+pub fn do_incr(args: Args) -> i32 {
+    adjust_counter(args, "incr", 1)
+}
+
+/// `decr <address> [--by N]` — prints the new value.
+pub fn do_decr(args: Args) -> i32 {
+    adjust_counter(args, "decr", -1)
+}
+
+fn adjust_counter(args: Args, command: &str, sign: i64) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["by"]);
+    let address = match parsed.positional.as_slice() {
+        [address] => address,
+        _ => {
+            eprintln!("Usage: prontodb {} <address> [--by N]", command);
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    trace_address(&config, address);
+
+    let step = match parsed.flag("by").map(str::parse::<i64>) {
+        None => 1,
+        Some(Ok(step)) => step,
+        Some(Err(_)) => {
+            eprintln!("{}: --by expects an integer", command);
+            return EXIT_ERROR;
+        }
+    };
+    let delta = match step.checked_mul(sign) {
+        Some(delta) => delta,
+        None => {
+            eprintln!("{}: --by value out of range", command);
+            return EXIT_ERROR;
+        }
+    };
+
+    match api::increment_value(&config, address, delta) {
+        Ok(value) => {
+            println!("{}", value);
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error(command, &error);
+            EXIT_ERROR
+        }
+    }
+}
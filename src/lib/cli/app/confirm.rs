@@ -0,0 +1,128 @@
+use std::io::{self, BufRead, Write};
+
+use crate::lib::addr::KeyPattern;
+
+/// How hard a destructive command is to run by accident.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Danger {
+    /// Interactive `y`, or `--yes` / `--force` when scripted.
+    Confirm,
+    /// Typing `yes` interactively, or `--force` when scripted (`--yes` is not enough).
+    Elevated,
+}
+
+/// A bulk delete that `pronto_dispatch` must confirm before the handler runs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Destructive {
+    pub command: String,
+    /// What will be lost, shown in the prompt and the refusal.
+    pub effect: String,
+    pub danger: Danger,
+}
+
+/// The destructive command in `args`, if any.
+pub fn destructive_command(args: &[String]) -> Option<Destructive> {
+    let flagged = |flag: &str| args.iter().any(|arg| arg == flag);
+    let words: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    let (command, effect, danger) = match words.as_slice() {
+        ["cursor", "reset", ..] if flagged("--all") => (
+            "cursor reset --all",
+            "delete every cursor of every user",
+            Danger::Elevated,
+        ),
+        ["cursor", "reset", ..] => (
+            "cursor reset",
+            "delete every cursor of the user",
+            Danger::Confirm,
+        ),
+        ["del", rest @ ..]
+            if !flagged("--dry-run") && rest.iter().any(|word| KeyPattern::has_glob(word)) =>
+        {
+            (
+                "del",
+                "delete every key matching the pattern",
+                Danger::Confirm,
+            )
+        }
+        ["db", "drop", ..] => (
+            "db drop",
+            "delete the database directory and its cursors",
+            Danger::Confirm,
+        ),
+        ["backup", ..] if flagged("--restore") && !flagged("--dry-run") => (
+            "backup --restore",
+            "replace the whole database with the backup",
+            Danger::Confirm,
+        ),
+        ["restore", ..] if !flagged("--dry-run") => (
+            "restore",
+            "replace the whole database with the backup",
+            Danger::Confirm,
+        ),
+        ["uninstall", ..] if flagged("--purge") => (
+            "uninstall --purge",
+            "delete every cursor and registry database",
+            Danger::Elevated,
+        ),
+        ["backup", ..] if words.contains(&"prune") => (
+            "backup prune",
+            "delete the archives the retention policy does not keep",
+            Danger::Confirm,
+        ),
+        _ => return None,
+    };
+    Some(Destructive {
+        command: command.to_string(),
+        effect: effect.to_string(),
+        danger,
+    })
+}
+
+/// `Ok` when `args` may run `destructive`; otherwise the refusal to print.
+///
+/// `--force` always passes and `--yes` passes `Danger::Confirm`. Without
+/// them, an interactive stdin is asked; anything else is refused.
+pub fn confirm_destructive(destructive: &Destructive, args: &[String]) -> Result<(), String> {
+    let flagged = |flag: &str| args.iter().any(|arg| arg == flag);
+    if flagged("--force") || (destructive.danger == Danger::Confirm && flagged("--yes")) {
+        return Ok(());
+    }
+    let bypass = match destructive.danger {
+        Danger::Confirm => "--yes",
+        Danger::Elevated => "--force",
+    };
+    let refusal = format!(
+        "prontodb {}: would {}; confirm interactively or pass {}",
+        destructive.command, destructive.effect, bypass
+    );
+    if !atty::is(atty::Stream::Stdin) {
+        return Err(refusal);
+    }
+
+    let question = match destructive.danger {
+        Danger::Confirm => "Continue? [y/N] ",
+        Danger::Elevated => "Type 'yes' to continue: ",
+    };
+    eprint!(
+        "prontodb {} will {}. {}",
+        destructive.command, destructive.effect, question
+    );
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return Err(refusal);
+    }
+    let accepted = match destructive.danger {
+        Danger::Confirm => matches!(answer.trim(), "y" | "Y" | "yes"),
+        Danger::Elevated => answer.trim() == "yes",
+    };
+    if accepted {
+        Ok(())
+    } else {
+        Err(format!("prontodb {}: aborted", destructive.command))
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+use rsb::prelude::*;
+
+use crate::lib::adpt::sqlite::{SqliteConnectionConfig, KEY_FILE_ENV};
+use crate::lib::api::{ProntoError, StorageOptions};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+use crate::lib::cursor::{
+    resolve_working_directory_cursor, work_mode_enabled, CursorData, CursorManager, DEFAULT_CURSOR,
+    DEFAULT_USER,
+};
+
+use super::verbosity::is_verbosity_flag;
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_ERROR: i32 = 1;
+/// Key not found (or expired).
+pub const EXIT_MISS: i32 = 2;
+/// Conditional write precondition failed (e.g. `set --if-absent` on an existing key).
+pub const EXIT_CONFLICT: i32 = 3;
+/// The database was busy or locked by another writer; retrying may succeed.
+pub const EXIT_BUSY: i32 = 4;
+
+/// Exit code for a failed call, from its `ProntoError` cause.
+pub fn exit_code(error: &CrudError) -> i32 {
+    match ProntoError::from(error) {
+        ProntoError::KeyNotFound(_) | ProntoError::CursorMissing(_) => EXIT_MISS,
+        ProntoError::Conflict(_) => EXIT_CONFLICT,
+        ProntoError::StorageBusy(_) => EXIT_BUSY,
+        _ => EXIT_ERROR,
+    }
+}
+
+/// Per-command view of handler arguments: positionals plus `--flag[=value]` pairs.
+///
+/// Flags named in `value_flags` take a value, so both `--by 5` and `--by=5`
+/// work; any other `--flag` is a boolean switch. A bare `--` ends flag parsing.
+/// The global `-q`/`-v`/`-vv` switches are dropped before `--`.
+pub struct CommandArgs {
+    pub positional: Vec<String>,
+    flags: BTreeMap<String, String>,
+}
+
+impl CommandArgs {
+    pub fn parse(args: &Args, value_flags: &[&str]) -> Self {
+        Self::parse_with_short(args, value_flags, &[])
+    }
+
+    /// `parse`, plus `(short, long)` pairs such as `("-p", "project")` for value flags.
+    pub fn parse_with_short(args: &Args, value_flags: &[&str], short: &[(&str, &str)]) -> Self {
+        let mut positional = Vec::new();
+        let mut flags = BTreeMap::new();
+        let mut flags_done = false;
+        let mut iter = args.all().to_vec().into_iter();
+
+        while let Some(arg) = iter.next() {
+            if flags_done {
+                positional.push(arg);
+                continue;
+            }
+            if is_verbosity_flag(&arg) {
+                continue;
+            }
+            if let Some((_, long)) = short.iter().find(|(short, _)| *short == arg) {
+                flags.insert(long.to_string(), iter.next().unwrap_or_default());
+                continue;
+            }
+            match arg.strip_prefix("--") {
+                Some("") => flags_done = true,
+                Some(flag) => match flag.split_once('=') {
+                    Some((name, value)) => {
+                        flags.insert(name.to_string(), value.to_string());
+                    }
+                    None if value_flags.contains(&flag) => {
+                        flags.insert(flag.to_string(), iter.next().unwrap_or_default());
+                    }
+                    None => {
+                        flags.insert(flag.to_string(), String::new());
+                    }
+                },
+                None => positional.push(arg),
+            }
+        }
+
+        Self { positional, flags }
+    }
+
+    pub fn flag(&self, name: &str) -> Option<&str> {
+        self.flags.get(name).map(|value| value.as_str())
+    }
+
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.flags.contains_key(name)
+    }
+}
+
+/// Context keys holding the resolved cursor, so handlers never re-read cursor files.
+const CURSOR_PATH_VAR: &str = "cursor_database_path";
+const CURSOR_PROJECT_VAR: &str = "cursor_default_project";
+const CURSOR_NAMESPACE_VAR: &str = "cursor_default_namespace";
+const CURSOR_READ_ONLY_VAR: &str = "cursor_read_only";
+
+/// Called once by the dispatcher: resolve the cursor for this invocation.
+///
+/// That is `--cursor=NAME` (owned by `--user=U`, else the default user), or
+/// the default cursor of the nearest `.prontodb` when `PRONTO_WORK_MODE` is
+/// on. A named cursor that does not exist is `NotFound`.
+pub fn init_cursor() -> CrudResult<()> {
+    let user = cursor_user();
+    let cwd = env::current_dir().unwrap_or_default();
+    let name = get_var("opt_cursor");
+    let cursor = if name.is_empty() {
+        if !work_mode_enabled() {
+            return Ok(());
+        }
+        resolve_working_directory_cursor(&cwd, DEFAULT_CURSOR, &user)?
+    } else {
+        match CursorManager::new().resolve(&name, &user, &cwd)? {
+            Some(cursor) => Some(cursor),
+            None => {
+                return Err(CrudError::not_found(
+                    CrudDomain::Filesystem,
+                    CrudObjectKind::Alias,
+                    CrudVerb::Read,
+                    format!("cursor '{}' not found for user '{}'", name, user),
+                ))
+            }
+        }
+    };
+
+    if let Some(cursor) = cursor {
+        set_var(CURSOR_PATH_VAR, &cursor.database_path.display().to_string());
+        set_var(
+            CURSOR_PROJECT_VAR,
+            cursor.default_project.as_deref().unwrap_or(""),
+        );
+        set_var(
+            CURSOR_NAMESPACE_VAR,
+            cursor.default_namespace.as_deref().unwrap_or(""),
+        );
+        if cursor.read_only {
+            set_var(CURSOR_READ_ONLY_VAR, "1");
+        }
+    }
+    Ok(())
+}
+
+/// Database named by `target` on the command line: a cursor of `--user=U` (else the
+/// default user) when one has that name, otherwise a file path.
+pub fn peer_database_path(target: &str) -> CrudResult<PathBuf> {
+    let cursor_name = target
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+    if cursor_name {
+        let cwd = env::current_dir().unwrap_or_default();
+        if let Some(cursor) = CursorManager::new().resolve(target, &cursor_user(), &cwd)? {
+            return Ok(cursor.database_path);
+        }
+    }
+    Ok(PathBuf::from(target))
+}
+
+/// Owner of the cursors this invocation resolves: `--user=U`, else the default user.
+fn cursor_user() -> String {
+    let user = get_var("opt_user");
+    if user.is_empty() {
+        DEFAULT_USER.to_string()
+    } else {
+        user
+    }
+}
+
+/// Connection config for this invocation.
+///
+/// The database is `--database-path=PATH`, else registry database
+/// `--database=NAME` (see `db create`), else the cursor database, else the
+/// resolver default. Partial addresses take the cursor's default
+/// project/namespace, ACLs are checked for the OS user, encrypted
+/// namespaces unlock with `--key-file=PATH` (or `PRONTO_KEY_FILE`), reserved
+/// namespaces (`sys.*`, `pipe.cache`) accept writes only with `--system`, and
+/// SQLite is opened read-only in read-only mode.
+pub fn connection_config() -> SqliteConnectionConfig {
+    // `--user` picks whose address defaults apply; the keystore decides
+    // whether ACLs honour it (see `AccessPolicy`).
+    let mut options = StorageOptions::new()
+        .user(claimed_user())
+        .key_file(
+            non_empty("opt_key_file")
+                .or_else(|| env::var(KEY_FILE_ENV).ok().filter(|path| !path.is_empty())),
+        )
+        .read_only(has_var("opt_read_only"))
+        .system_access(has_var("opt_system"))
+        // One-shot commands swap database files (restore, db rename); `serve`
+        // and `watch` opt back in.
+        .reuse_connections(false);
+    if let Some(path) = non_empty("opt_database_path") {
+        options = options.path(path);
+    }
+    if let Some(name) = non_empty("opt_database") {
+        options = options.database(name);
+    }
+    options.resolve_with(active_cursor().as_ref())
+}
+
+/// `connection_config` under the command's `--meta <ctx>`, if given: addresses
+/// and scopes then name projects inside that organization context.
+pub fn command_config(parsed: &CommandArgs) -> SqliteConnectionConfig {
+    connection_config().with_meta_context(parsed.flag("meta"))
+}
+
+/// The cursor `init_cursor` resolved, rebuilt from the context keys.
+fn active_cursor() -> Option<CursorData> {
+    let mut cursor = CursorData::new(non_empty(CURSOR_PATH_VAR)?, &cursor_user());
+    cursor.default_project = non_empty(CURSOR_PROJECT_VAR);
+    cursor.default_namespace = non_empty(CURSOR_NAMESPACE_VAR);
+    cursor.read_only = !get_var(CURSOR_READ_ONLY_VAR).is_empty();
+    Some(cursor)
+}
+
+fn non_empty(name: &str) -> Option<String> {
+    Some(get_var(name)).filter(|value| !value.is_empty())
+}
+
+/// `--read-only`, or a cursor saved with `cursor set --read-only`.
+pub fn read_only_mode() -> bool {
+    has_var("opt_read_only") || !get_var(CURSOR_READ_ONLY_VAR).is_empty()
+}
+
+/// Environment fallback for `--user=U` when checking namespace ACLs.
+pub const PRONTO_USER_ENV: &str = "PRONTO_USER";
+
+fn claimed_user() -> Option<String> {
+    non_empty("opt_user").or_else(|| {
+        env::var(PRONTO_USER_ENV)
+            .ok()
+            .filter(|user| !user.is_empty())
+    })
+}
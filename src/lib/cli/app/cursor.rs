@@ -0,0 +1,340 @@
+use std::fs;
+use std::io::{self, Read};
+
+use rsb::prelude::*;
+
+use crate::lib::adpt::sqlite::{Clock, SystemClock};
+use crate::lib::core::helpers::parse_duration;
+use crate::lib::cursor::{CursorBundle, CursorData, CursorManager, UserDefaults, DEFAULT_USER};
+
+use super::context::{exit_code, CommandArgs, EXIT_ERROR, EXIT_MISS, EXIT_OK};
+use super::output::{output_mode, print_json, report_error, CursorView, OutputMode};
+use super::verbosity::say;
+
+/// `cursor <set|list|show|rm|rename|copy|check|prune|reset|export|import|defaults>` — manage cursors.
+///
+/// Every subcommand takes `--user U` (default `default`); cursors of
+/// different users never collide. `export` without `--user` covers all users.
+pub fn do_cursor(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(
+        &args,
+        &[
+            "user",
+            "project",
+            "namespace",
+            "meta",
+            "out",
+            "from",
+            "to",
+            "sort",
+            "older-than",
+        ],
+    );
+    let user = parsed.flag("user").unwrap_or(DEFAULT_USER);
+    let manager = CursorManager::new();
+
+    let result = match parsed.positional.as_slice() {
+        [verb, name, database_path] if verb == "set" => {
+            let data = CursorData::new(database_path.as_str(), user)
+                .with_project(parsed.flag("project").map(str::to_string))
+                .with_namespace(parsed.flag("namespace").map(str::to_string))
+                .with_meta_context(parsed.flag("meta").map(str::to_string))
+                .with_read_only(parsed.has_flag("read-only"));
+            manager.set(name, &data).map(|path| {
+                say(format!("cursor '{}' -> {}", name, path.display()));
+                EXIT_OK
+            })
+        }
+        [verb] if verb == "list" => {
+            let by_use = match parsed.flag("sort").unwrap_or("name") {
+                "name" => false,
+                "used" => true,
+                other => {
+                    eprintln!("cursor: unknown --sort '{}' (expected name|used)", other);
+                    return EXIT_ERROR;
+                }
+            };
+            manager.list(user).map(|cursors| {
+                if output_mode() == OutputMode::Json {
+                    return print_json(&cursors);
+                }
+                let mut rows: Vec<_> = cursors.iter().collect();
+                if by_use {
+                    // Least recently used first, so prune candidates lead.
+                    rows.sort_by_key(|(name, data)| (data.last_activity(), name.as_str()));
+                }
+                for (name, data) in rows {
+                    if by_use {
+                        let used = data.last_used_at.as_deref().unwrap_or("never");
+                        println!("{}\t{}\t{}", name, data.database_path.display(), used);
+                    } else {
+                        println!("{}\t{}", name, data.database_path.display());
+                    }
+                }
+                EXIT_OK
+            })
+        }
+        [verb] if verb == "prune" => {
+            let older_than = match parsed.flag("older-than").and_then(parse_duration) {
+                Some(older_than) => older_than,
+                None => {
+                    eprintln!("Usage: prontodb cursor prune --older-than 90d [--dry-run]");
+                    return EXIT_ERROR;
+                }
+            };
+            let dry_run = parsed.has_flag("dry-run");
+            manager
+                .prune(user, older_than, SystemClock.now(), dry_run)
+                .map(|pruned| {
+                    for name in &pruned {
+                        println!("{}", name);
+                    }
+                    let verb = if dry_run { "Would prune" } else { "Pruned" };
+                    say(format!("{} {} cursors", verb, pruned.len()));
+                    EXIT_OK
+                })
+        }
+        [verb] if verb == "reset" => {
+            let everyone = parsed.has_flag("all");
+            manager
+                .reset(if everyone { None } else { Some(user) })
+                .map(|removed| {
+                    say(format!("Removed {} cursors", removed));
+                    EXIT_OK
+                })
+        }
+        [verb, name] if verb == "show" => manager.find(name, user).map(|found| match found {
+            Some(found) => {
+                let view = CursorView::new(name, &found);
+                if output_mode() == OutputMode::Json {
+                    return print_json(&view);
+                }
+                for (field, value) in view.lines() {
+                    println!("{:<10} {}", format!("{}:", field), value);
+                }
+                EXIT_OK
+            }
+            None => EXIT_MISS,
+        }),
+        [verb, name] if verb == "rm" => {
+            manager
+                .delete(name, user)
+                .map(|removed| if removed { EXIT_OK } else { EXIT_MISS })
+        }
+        [verb, old, new] if verb == "rename" => manager
+            .rename(old, new, user, parsed.has_flag("force"))
+            .map(|path| {
+                say(format!(
+                    "cursor '{}' -> '{}' ({})",
+                    old,
+                    new,
+                    path.display()
+                ));
+                EXIT_OK
+            }),
+        [verb, name] if verb == "copy" => {
+            match (parsed.flag("from"), parsed.flag("to")) {
+                (Some(from), Some(to)) => manager
+                    .copy(name, from, to, parsed.has_flag("force"))
+                    .map(|path| {
+                        say(format!(
+                            "cursor '{}' copied {} -> {} ({})",
+                            name,
+                            from,
+                            to,
+                            path.display()
+                        ));
+                        EXIT_OK
+                    }),
+                _ => {
+                    eprintln!("Usage: prontodb cursor copy <name> --from USER --to USER [--force]");
+                    return EXIT_ERROR;
+                }
+            }
+        }
+        [verb, name @ ..] if verb == "check" && name.len() <= 1 => {
+            return check_cursors(&manager, name.first().map(String::as_str))
+        }
+        [verb] if verb == "export" => {
+            return export_cursors(&manager, parsed.flag("user"), parsed.flag("out"))
+        }
+        [verb, path] if verb == "import" => {
+            return import_cursors(&manager, path, parsed.has_flag("force"))
+        }
+        [verb] if verb == "defaults" => {
+            return user_defaults(&manager, user, &parsed);
+        }
+        _ => {
+            eprintln!("Usage: prontodb cursor set <name> <database-path> [--project P] [--namespace N] [--meta M] [--read-only]");
+            eprintln!("       prontodb cursor list [--sort name|used]");
+            eprintln!("       prontodb cursor show <name>");
+            eprintln!("       prontodb cursor rm <name>");
+            eprintln!("       prontodb cursor rename <old> <new> [--force]");
+            eprintln!("       prontodb cursor copy <name> --from USER --to USER [--force]");
+            eprintln!("       prontodb cursor check [name]");
+            eprintln!("       prontodb cursor prune --older-than 90d [--dry-run]");
+            eprintln!(
+                "       prontodb cursor reset [--all] (asks first; --yes, or --force for --all)"
+            );
+            eprintln!("       prontodb cursor export [--out FILE]");
+            eprintln!("       prontodb cursor import <FILE|-> [--force]");
+            eprintln!("       prontodb cursor defaults [--project P] [--namespace N] [--clear]");
+            eprintln!("       (all accept --user U)");
+            return EXIT_ERROR;
+        }
+    };
+
+    result.unwrap_or_else(|error| {
+        report_error("cursor", &error);
+        exit_code(&error)
+    })
+}
+
+/// `cursor check [name]` — validate cursors of every user, legacy copies included.
+///
+/// Prints one line per cursor file and every problem under it; exit 1 when
+/// any cursor is broken, 2 when `name` matches no cursor file.
+fn check_cursors(manager: &CursorManager, name: Option<&str>) -> i32 {
+    let reports = manager.check(name);
+    if reports.is_empty() && name.is_some() {
+        return EXIT_MISS;
+    }
+
+    let mut broken = 0;
+    for report in &reports {
+        let origin = if report.legacy { " (legacy)" } else { "" };
+        let status = if report.is_ok() { "ok" } else { "broken" };
+        println!(
+            "{}\t{}\t{}\t{}{}",
+            status,
+            report.name,
+            report.user,
+            report.file.display(),
+            origin
+        );
+        for problem in &report.problems {
+            println!("  - {}", problem);
+        }
+        if !report.is_ok() {
+            broken += 1;
+        }
+    }
+    say(format!("{} of {} cursors broken", broken, reports.len()));
+    if broken == 0 {
+        EXIT_OK
+    } else {
+        EXIT_ERROR
+    }
+}
+
+/// `cursor export [--user U] [--out FILE]` — write cursor definitions as a JSON bundle.
+fn export_cursors(manager: &CursorManager, user: Option<&str>, out: Option<&str>) -> i32 {
+    let bundle = match manager.export(user) {
+        Ok(bundle) => bundle,
+        Err(error) => {
+            report_error("cursor", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let json = match bundle.to_json() {
+        Ok(json) => json,
+        Err(error) => {
+            report_error("cursor", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let count = bundle.cursors.len();
+
+    match out {
+        Some(path) => match fs::write(path, format!("{}\n", json)) {
+            Ok(()) => {
+                say(format!("Exported {} cursors to {}", count, path));
+                EXIT_OK
+            }
+            Err(error) => {
+                eprintln!("cursor export: {}: {}", path, error);
+                EXIT_ERROR
+            }
+        },
+        None => {
+            println!("{}", json);
+            EXIT_OK
+        }
+    }
+}
+
+/// `cursor import <FILE|-> [--force]` — load a bundle; exit 3 (nothing written) if a cursor exists.
+fn import_cursors(manager: &CursorManager, path: &str, overwrite: bool) -> i32 {
+    let input = if path == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input).map(|_| input)
+    } else {
+        fs::read_to_string(path)
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("cursor import: {}: {}", path, error);
+            return EXIT_ERROR;
+        }
+    };
+
+    match CursorBundle::from_json(&input).and_then(|bundle| manager.import(&bundle, overwrite)) {
+        Ok(count) => {
+            say(format!("Imported {} cursors", count));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("cursor", &error);
+            exit_code(&error)
+        }
+    }
+}
+
+/// `cursor defaults` — show, or with `--project`/`--namespace`/`--clear` change, the
+/// project and namespace bare keys use when the cursor sets none.
+fn user_defaults(manager: &CursorManager, user: &str, parsed: &CommandArgs) -> i32 {
+    let current = match manager.user_defaults(user) {
+        Ok(current) => current,
+        Err(error) => {
+            report_error("cursor", &error);
+            return exit_code(&error);
+        }
+    };
+    let (project, namespace) = (parsed.flag("project"), parsed.flag("namespace"));
+    if !parsed.has_flag("clear") && project.is_none() && namespace.is_none() {
+        if output_mode() == OutputMode::Json {
+            return print_json(&current);
+        }
+        println!("project: {}", current.project.as_deref().unwrap_or("-"));
+        println!("namespace: {}", current.namespace.as_deref().unwrap_or("-"));
+        return EXIT_OK;
+    }
+
+    let mut defaults = if parsed.has_flag("clear") {
+        UserDefaults::default()
+    } else {
+        current
+    };
+    if let Some(project) = project {
+        defaults.project = Some(project.to_string()).filter(|project| !project.is_empty());
+    }
+    if let Some(namespace) = namespace {
+        defaults.namespace = Some(namespace.to_string()).filter(|namespace| !namespace.is_empty());
+    }
+    match manager.set_user_defaults(user, &defaults) {
+        Ok(()) => {
+            say(format!(
+                "defaults for '{}': {}.{}",
+                user,
+                defaults.project.as_deref().unwrap_or("default"),
+                defaults.namespace.as_deref().unwrap_or("default")
+            ));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("cursor", &error);
+            exit_code(&error)
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use rsb::prelude::*;
+
+use crate::lib::core::helpers::format_timestamp;
+use crate::lib::cursor::CursorManager;
+
+use super::context::{exit_code, CommandArgs, EXIT_ERROR, EXIT_OK};
+use super::output::{output_mode, print_json, report_error, OutputMode};
+use super::verbosity::say;
+
+/// `db <list|create|drop|rename>` — the databases kept under the cursor home.
+///
+/// `db create NAME` makes `<home>/NAME/NAME.sqlite3`, which `--database=NAME`
+/// then opens. `db list` prints `NAME<TAB>SIZE<TAB>MODIFIED<TAB>CURSORS<TAB>PATH`
+/// for every database-scoped directory, including those cursors created for
+/// databases elsewhere (marked `external`). `db drop` deletes a directory,
+/// cursors included, after confirmation; files outside it are never touched.
+pub fn do_db(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &[]);
+    let manager = CursorManager::new();
+    let result = match parsed.positional.as_slice() {
+        [verb] if verb == "list" => manager.databases().map(|databases| {
+            if output_mode() == OutputMode::Json {
+                return print_json(&databases);
+            }
+            for database in databases {
+                let path = match (&database.path, database.managed) {
+                    (Some(path), true) => path.display().to_string(),
+                    (Some(path), false) => format!("{} (external)", path.display()),
+                    (None, _) => "-".to_string(),
+                };
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    database.name,
+                    database.size,
+                    database
+                        .modified_at
+                        .map_or_else(|| "-".to_string(), format_timestamp),
+                    database.cursors,
+                    path
+                );
+            }
+            EXIT_OK
+        }),
+        [verb, name] if verb == "create" => manager.create_database(name).map(|path| {
+            say(format!("database '{}' -> {}", name, path.display()));
+            EXIT_OK
+        }),
+        [verb, name] if verb == "drop" => manager.drop_database(name).map(|dropped| {
+            let kept = match (&dropped.path, dropped.managed) {
+                (Some(path), false) => format!("; {} was left in place", path.display()),
+                _ => String::new(),
+            };
+            say(format!(
+                "database '{}' dropped ({} cursors){}",
+                name, dropped.cursors, kept
+            ));
+            EXIT_OK
+        }),
+        [verb, old, new] if verb == "rename" => manager.rename_database(old, new).map(|path| {
+            say(format!(
+                "database '{}' -> '{}' ({})",
+                old,
+                new,
+                path.display()
+            ));
+            EXIT_OK
+        }),
+        _ => {
+            eprintln!("Usage: prontodb db list");
+            eprintln!("       prontodb db create <name>");
+            eprintln!("       prontodb db drop <name> [--yes]");
+            eprintln!("       prontodb db rename <old> <new>");
+            return EXIT_ERROR;
+        }
+    };
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            report_error("db", &error);
+            exit_code(&error)
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use rsb::prelude::*;
+
+use crate::lib::adpt::sqlite::{SecretMarks, SqliteConnectionConfig};
+use crate::lib::api::{self, KeyDiff};
+use crate::lib::core::crud::CrudResult;
+
+use super::context::{connection_config, peer_database_path, CommandArgs, EXIT_ERROR, EXIT_OK};
+use super::listing::masked;
+use super::output::report_error;
+use super::verbosity::say;
+
+/// `diff <cursor|path> <cursor|path> [project[.ns]] [--values] [--reveal]`
+///
+/// Prints a `+` line for each key only the second database holds, `-` for
+/// keys only the first holds and `~` for keys whose value differs, then the
+/// totals. `--values` adds the values (`~ key: old -> new`); keys marked
+/// `secret` in either database print as `****` unless `--reveal` is given.
+pub fn do_diff(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &[]);
+    let (from, to, scope) = match parsed.positional.as_slice() {
+        [from, to] => (from, to, None),
+        [from, to, scope] => (from, to, Some(scope.as_str())),
+        _ => {
+            eprintln!(
+                "Usage: prontodb diff <cursor|path> <cursor|path> [project[.ns]] [--values] [--reveal]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let side = |target: &str| -> CrudResult<SqliteConnectionConfig> {
+        Ok(connection_config()
+            .with_database_path(peer_database_path(target)?)
+            .with_read_only(true))
+    };
+    let compared = side(from).and_then(|from| {
+        let to = side(to)?;
+        let diff = api::diff_databases(&from, &to, scope)?;
+        let secrets = if parsed.has_flag("values") && !parsed.has_flag("reveal") {
+            (api::secret_marks(&from)?, api::secret_marks(&to)?)
+        } else {
+            (SecretMarks::default(), SecretMarks::default())
+        };
+        Ok((diff, secrets))
+    });
+    let (diff, (from_secrets, to_secrets)) = match compared {
+        Ok(compared) => compared,
+        Err(error) => {
+            report_error("diff", &error);
+            return EXIT_ERROR;
+        }
+    };
+
+    let values = parsed.has_flag("values");
+    let mut counts = [0usize; 3];
+    for change in &diff.changes {
+        let (mark, slot, detail) = match change {
+            KeyDiff::Added(entry) => {
+                let entry = masked(entry.clone(), &to_secrets);
+                ('+', 0, format!(" = {}", entry.value))
+            }
+            KeyDiff::Removed(entry) => {
+                let entry = masked(entry.clone(), &from_secrets);
+                ('-', 1, format!(" = {}", entry.value))
+            }
+            KeyDiff::Changed(old, new) => {
+                let old = masked(old.clone(), &from_secrets);
+                let new = masked(new.clone(), &to_secrets);
+                ('~', 2, format!(": {} -> {}", old.value, new.value))
+            }
+        };
+        counts[slot] += 1;
+        if values {
+            println!("{} {}{}", mark, change.address(), detail);
+        } else {
+            println!("{} {}", mark, change.address());
+        }
+    }
+    say(format!(
+        "{} added, {} removed, {} changed ({} unchanged)",
+        counts[0], counts[1], counts[2], diff.unchanged
+    ));
+    EXIT_OK
+}
@@ -1,40 +1,322 @@
-// Minimal dispatcher for testing routing
 use rsb::prelude::*;
 
 // Import RSB visual macros directly (compiler suggested)
 use rsb::info;
 
+use super::admin::do_admin;
+use super::changes::do_changes;
+use super::commands::{
+    do_allow_ttl, do_append, do_copy, do_count, do_create_cache, do_decr, do_del, do_exists,
+    do_get, do_hook, do_incr, do_mget, do_mset, do_mv, do_persist, do_resolve, do_secret, do_set,
+};
+use super::confirm::{confirm_destructive, destructive_command};
+use super::context::{connection_config, init_cursor, read_only_mode, EXIT_ERROR, EXIT_OK};
+use super::cursor::do_cursor;
+use super::db::do_db;
+use super::diff::do_diff;
+use super::document::{do_jdel, do_jget, do_jset};
+use super::exchange::{do_env, do_export, do_import};
+use super::listing::{
+    do_contexts, do_grep, do_keys, do_namespaces, do_projects, do_scan, do_search,
+};
+use super::notify::do_subscribe;
+use super::output::report_error;
+use super::serve::do_serve;
+use super::stream::{do_load, do_stream};
+use super::sync::do_sync;
+use super::verbosity::{init_verbosity, trace, Verbosity};
+use super::watch::{do_tail, do_watch};
+
 pub fn pronto_dispatch(args: rsb::args::Args) -> i32 {
     info!("Dispatch called with {} args", args.all().len());
-    
+
     if args.len() == 0 {
         info!("No command provided, showing help");
         return do_help(args);
     }
 
-    let command = args.get_or(1, "");
-    info!("Processing command: '{}'", command);
-    
-    // Try RSB dispatch! macro (now that global functions are fixed)
+    if let Err(code) = preflight(args.all()) {
+        return code;
+    }
+
     dispatch!(&args, {
+        "set" => do_set,
+        "get" => do_get,
+        "exists" => do_exists,
+        "del" => do_del,
+        "create-cache" => do_create_cache,
+        "allow-ttl" => do_allow_ttl,
+        "persist" => do_persist,
+        "hook" => do_hook,
+        "secret" => do_secret,
+        "count" => do_count,
+        "keys" => do_keys,
+        "scan" => do_scan,
+        "grep" => do_grep,
+        "search" => do_search,
+        "projects" => do_projects,
+        "namespaces" => do_namespaces,
+        "resolve" => do_resolve,
+        "contexts" => do_contexts,
+        "export" => do_export,
+        "import" => do_import,
+        "stream" => do_stream,
+        "load" => do_load,
+        "env" => do_env,
+        "mv" => do_mv,
+        "copy" => do_copy,
+        "append" => do_append,
+        "mset" => do_mset,
+        "mget" => do_mget,
+        "incr" => do_incr,
+        "decr" => do_decr,
+        "jset" => do_jset,
+        "jget" => do_jget,
+        "jdel" => do_jdel,
+        "watch" => do_watch,
+        "tail" => do_tail,
+        "serve" => do_serve,
+        "sync" => do_sync,
+        "diff" => do_diff,
+        "changes" => do_changes,
+        "subscribe" => do_subscribe,
+        "cursor" => do_cursor,
+        "db" => do_db,
+        "admin" => do_admin,
         "version" => do_version,
         "help" => do_help
     })
 }
 
-// Command stubs - RSB dispatch expects fn(Args) -> i32
-fn do_set(mut args: Args) -> i32 {
-    info!("Executing: set");
-    // Surface parsed options for E2E verification
-    if has_var("opt_verbose") { info!("Verbose mode enabled: {}", get_var("opt_verbose")); }
-    if has_var("opt_debug") { info!("Debug mode enabled: {}", get_var("opt_debug")); }
-    if has_var("opt_config") { info!("Config path: {}", get_var("opt_config")); }
-    0
+/// What every command goes through before its handler, including the
+/// lifecycle commands `main` runs ahead of `pronto_dispatch`: verbosity, the
+/// cursor, the read-only refusal and confirmation of destructive commands.
+/// `Err` holds the exit code to stop with.
+pub(super) fn preflight(args: &[String]) -> Result<(), i32> {
+    init_verbosity(args);
+    if let Err(error) = init_cursor() {
+        report_error("cursor", &error);
+        return Err(EXIT_ERROR);
+    }
+    if read_only_mode() {
+        if let Some(command) = mutating_command(args) {
+            eprintln!(
+                "prontodb {}: refused in read-only mode (drop --read-only or use a writable cursor)",
+                command
+            );
+            return Err(EXIT_ERROR);
+        }
+    }
+    if let Some(destructive) = destructive_command(args) {
+        if let Err(refusal) = confirm_destructive(&destructive, args) {
+            eprintln!("{}", refusal);
+            return Err(EXIT_ERROR);
+        }
+    }
+    trace(
+        Verbosity::Verbose,
+        format!("database: {}", connection_config().database_path.display()),
+    );
+    trace(Verbosity::Debug, format!("args: {:?}", args));
+    Ok(())
+}
+
+/// Commands refused in read-only mode; `hook` is refused unless it is `hook list`.
+///
+/// This is the early, friendly refusal: writes through the API and `Session`
+/// (so `serve` too) are refused in read-only mode whatever the command.
+const MUTATING_COMMANDS: [&str; 18] = [
+    "set",
+    "del",
+    "create-cache",
+    "allow-ttl",
+    "persist",
+    "import",
+    "stream",
+    "load",
+    "mv",
+    "copy",
+    "append",
+    "mset",
+    "incr",
+    "decr",
+    "jset",
+    "jdel",
+    "secret",
+    "sync",
+];
+
+/// The command in `args` when it writes to the database.
+fn mutating_command(args: &[String]) -> Option<String> {
+    let words: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| !arg.starts_with('-'))
+        .collect();
+    match words.as_slice() {
+        ["hook", "list", ..] => None,
+        ["admin", _, "list", ..] => None,
+        ["admin", "snapshot", ..] => None,
+        ["admin", "merge", ..] => Some("admin merge".to_string()),
+        ["admin", action @ ("sweep" | "purge-expired"), ..] => Some(format!("admin {}", action)),
+        ["admin", area, action, ..] => Some(format!("admin {} {}", area, action)),
+        ["hook", sub, ..] => Some(format!("hook {}", sub)),
+        ["db", action @ ("create" | "drop" | "rename"), ..] => Some(format!("db {}", action)),
+        ["backup", "prune", ..] => Some("backup prune".to_string()),
+        ["restore", ..] if !args.iter().any(|arg| arg == "--dry-run") => {
+            Some("restore".to_string())
+        }
+        // Saving another read-only cursor cannot widen access, so it stays allowed.
+        ["cursor", "set", ..] if !args.iter().any(|arg| arg == "--read-only") => {
+            Some("cursor set".to_string())
+        }
+        ["cursor", action, ..]
+            if matches!(
+                *action,
+                "rm" | "rename" | "copy" | "prune" | "reset" | "import"
+            ) =>
+        {
+            Some(format!("cursor {}", action))
+        }
+        ["backup", ..]
+            if args.iter().any(|arg| arg == "--restore")
+                && !args.iter().any(|arg| arg == "--dry-run") =>
+        {
+            Some("backup --restore".to_string())
+        }
+        ["backup", ..] if args.iter().any(|arg| arg == "--merge") => {
+            Some("backup --merge".to_string())
+        }
+        [command, ..] if MUTATING_COMMANDS.contains(command) => Some(command.to_string()),
+        _ => None,
+    }
 }
 
-fn do_version(mut args: Args) -> i32 { info!("Executing: version"); 0 }
+fn do_version(_args: Args) -> i32 {
+    println!("prontodb {}", env!("CARGO_PKG_VERSION"));
+    EXIT_OK
+}
 
-fn do_help(mut args: Args) -> i32 {
-    info!("ProntoDB - Available Commands:");
-    0
+fn do_help(_args: Args) -> i32 {
+    println!("ProntoDB - Available Commands:");
+    println!("  set <address> <value>           Store a value (--ttl <secs> in TTL namespaces)");
+    println!("      [--if-absent | --if-value V]  Conditional set (exit 3 if precondition fails)");
+    println!("      [--type string|int|bool|json]  Validate and tag the value (native in --json)");
+    println!("      [--create]                  Allow a new namespace in a strict database");
+    println!("      [--meta CTX]                Organization context (set/get/del/keys/scan)");
+    println!("  get <address>                   Print a value (exit 2 if missing)");
+    println!("      [--include-expired]         Also return expired keys not yet swept");
+    println!("  exists <address>                Exit 0 if present, 2 if missing (no output)");
+    println!("  resolve <address>               Show where an address is stored (reads no data)");
+    println!("  del <address>                   Delete a value (exit 2 if missing)");
+    println!("  create-cache <p.ns> <ttl>       Make a TTL cache (--sliding, --max-ttl)");
+    println!("  allow-ttl <p.ns> [--off]        Allow set --ttl in a non-cache namespace");
+    println!("  persist <address>               Remove the TTL from a key (exit 2 if missing)");
+    println!("  hook set|rm|list [p.ns] [cmd]   Manage commands run when keys expire");
+    println!("  secret <p.ns[.key]> [--off]     Mask values in scan output (scan --reveal)");
+    println!("  count <project.ns[.prefix]>     Print the number of live keys");
+    println!("  keys <project.ns[.prefix]>      List keys (--long: ttl, size, timestamps)");
+    println!("      [--regex RE]                Keep keys matching a regex (keys/scan)");
+    println!("      [--from KEY] [--to KEY]     Keys in the range [from, to) (keys)");
+    println!(
+        "  scan <project.ns[.prefix]>      List key=value pairs (--ndjson streams JSON lines)"
+    );
+    println!("      [--format plain|table|csv|tsv]  Output format for keys/scan");
+    println!("      [--where .field=value]      Keep JSON values with that field (scan)");
+    println!("      [--sort key|updated|size]   Row order for scan (--desc to reverse)");
+    println!("      [--context CTX]             Keep only key__CTX values (scan)");
+    println!("      [--databases main,staging]  Scan several databases, rows labeled by database");
+    println!("  grep <pattern> [project[.ns]]   Print addresses whose value matches (--regex)");
+    println!("  search <query> [project[.ns]]   Ranked full-text search (needs an --fts index)");
+    println!("  projects                        List projects");
+    println!("  namespaces <project>            List namespaces of a project");
+    println!("  contexts <project.ns.key>       List the contexts stored for a key");
+    println!("  export [project[.ns]]           Dump keys (--format json|toml|env, --out FILE)");
+    println!("  import <file|->                 Load keys (--strategy overwrite|skip|fail)");
+    println!("  stream                          Apply key=value; tokens from stdin (ns=p.ns)");
+    println!("  load [-p PROJECT] [-n NS]       Store key=value lines from stdin atomically");
+    println!("  env export <p.ns> [--prefix P]  Print NAME=value lines (--export for shells)");
+    println!("  mv <src> <dst> [--force]        Atomically rename a key (keeps TTL/context)");
+    println!("  copy <src> <dst> [--keep-ttl]   Copy a key, or a namespace with project.ns.*");
+    println!("  append <address> <text>         Append text to a value (creates if missing)");
+    println!("  mset <addr>=<val> ...           Store many values in one transaction");
+    println!("  mget <addr> ...                 Print many values, one per line");
+    println!("  incr <address> [--by N]         Atomically add to an integer value");
+    println!("  decr <address> [--by N]         Atomically subtract from an integer value");
+    println!("  jset <address> <.path> <value>  Set a field inside a JSON value");
+    println!("  jget <address> <.path>          Print a field of a JSON value (exit 2 if missing)");
+    println!("  jdel <address> <.path>          Remove a field from a JSON value");
+    println!("  watch <address|p.ns.*>          Print changes as they happen (--interval 1s)");
+    println!("      [--exec CMD] [--count N]    Run CMD per change / exit after N changes");
+    println!("  tail <project.ns> [-n 10]       Print newest keys, then follow new ones");
+    println!("  serve --socket PATH             Answer GET/SET/DEL/SCAN on a unix socket");
+    println!("      [--grpc HOST:PORT]          Serve gRPC instead (grpc feature)");
+    println!("      [--resp [HOST:PORT]]        Serve a Redis subset (default port 6380)");
+    println!("      [--ui [HOST:PORT]]          Serve a web page to browse and edit keys");
+    println!("      [--notify PATH]             Publish writes on a socket for subscribe");
+    println!("      (clients send an API token: AUTH, or an Authorization header)");
+    println!("  subscribe <prefix> --socket P   Print writes from a serve --notify socket");
+    println!("  backup [--output DIR]           Archive the database (backups/ beside it)");
+    println!("      [--incremental]             Only what changed since the newest archive");
+    println!("      [--encrypt]                 Seal it with --key-file as the passphrase");
+    println!("      [--scope P[.NS]]            Only that project or namespace, values decrypted");
+    println!("      [--output s3://B/P|sftp://H/D]  Also upload it (backup-s3/backup-sftp builds)");
+    println!("      [--list] [--restore FILE]   List archives / replace the database (--yes)");
+    println!("      [--dry-run]                 With --restore: list the keys it would change");
+    println!("      [--merge FILE]              Load a --scope archive (--strategy as import)");
+    println!("      [--verify FILE]             Check an archive would restore cleanly");
+    println!("  restore FILE [--dry-run]        Same as backup --restore FILE (--yes)");
+    println!("  backup prune [--dir DIR]        Delete archives outside the policy (--yes)");
+    println!("      [--keep-daily N] [--keep-weekly N]");
+    println!("  backup --schedule CRON          Next run of a UTC cron expression, such as \"0 3 * * *\"");
+    println!("      [--daemon]                  Back up at each run (prunes with --keep-*)");
+    println!("  sync push --to <cursor|path>    Copy keys changed since the last push there");
+    println!("  sync pull --from <cursor|path>  Copy keys changed there since the last pull");
+    println!("  sync merge <cursor|path>        Reconcile both (--strategy newest|ours|theirs|interactive)");
+    println!("  diff <cursor|path> <cursor|path> [p[.ns]]  Keys added/removed/changed (--values)");
+    println!("  changes [--since SEQ|TIME]      Oplog rows after a seq or UTC time (--limit N)");
+    println!("  cursor set|list|rm|rename       Named database cursors (--user U per user)");
+    println!("      [--read-only]               Cursor opens its database read-only (set)");
+    println!("      [--sort name|used]          Cursor list order (used: stalest first)");
+    println!("  cursor show <name>              Print every cursor field and its file");
+    println!("  cursor copy <name> --from U --to U  Give another user the same cursor");
+    println!("  cursor check [name]             Validate cursor databases (exit 1 if broken)");
+    println!("  cursor prune --older-than 90d   Delete cursors unused that long (--dry-run)");
+    println!("  cursor reset [--all]            Delete all cursors of --user U (--all: everyone)");
+    println!("  cursor export|import            Share cursors as JSON (--out FILE, --force)");
+    println!("  db list                         Registry databases: size, modified, cursors");
+    println!("  db create|drop <name>           Make / delete <cursor home>/<name> (drop: --yes)");
+    println!("  db rename <old> <new>           Rename a registry database; its cursors follow");
+    println!("  admin capability list           Show adapter verbs and which are disabled");
+    println!("  admin capability enable|disable <object> <verb>  Toggle an adapter verb");
+    println!("  admin retention set <p.ns>      Trim a namespace when the admin sweeper runs");
+    println!("      [--max-age 30d] [--max-keys N]  Drop keys unwritten that long / the oldest");
+    println!("  admin retention clear|list      Remove a namespace policy / show all policies");
+    println!("  admin oplog enable              Log every write for changes (seq, op, md5, user)");
+    println!("  admin validation [lax|strict]   Strict: set refuses unknown namespaces");
+    println!("  admin sweep [--daemon]          Reclaim expired rows, apply retention policies");
+    println!("      [--interval SECS] [--batch-size N]  Repeat every SECS (60) / rows per pass");
+    println!("  admin purge-expired [p[.ns]]    Delete expired keys now, everywhere or in a scope");
+    println!("  admin caches                    TTL cache namespaces: ttl, keys, next expiry");
+    println!("  admin snapshot [--out FILE]     Consistent copy of the live database (backup API)");
+    println!("  admin merge --from PATH [--prefix META]  Import another database's keys");
+    println!("                                  (--strategy skip|overwrite|fail, default skip)");
+    println!("  install [--target DIR]          Copy prontodb to DIR (~/.local/bin) (--force)");
+    println!("  uninstall [--target DIR]        Remove it; --purge also deletes cursors and");
+    println!("      [--purge]                   registry databases (--force)");
+    println!("  version                         Show version");
+    println!();
+    println!("Addresses: key | namespace.key | project.namespace.key, optional __context suffix");
+    println!("Scopes:    project.ns[.prefix], or globs such as app.cfg.db_* and app.*.enabled");
+    println!("Options:   --database-path=PATH  --json (get/keys/scan/projects/namespaces)");
+    println!("           --database=NAME (registry database made by db create)");
+    println!("           --cursor=NAME [--user=U]  (cursor database and default project.ns)");
+    println!("           Namespace ACLs see the OS user");
+    println!("           --read-only: open SQLite read-only and refuse writes (or cursor flag)");
+    println!("           --system: allow writes/deletes in reserved sys.* and pipe.cache");
+    println!("           --key-file=PATH (or PRONTO_KEY_FILE): unlock encrypted namespaces");
+    println!("           PRONTO_WORK_MODE=1: use the nearest .prontodb above the cwd");
+    println!("           --yes: skip the prompt of bulk deletes; --force also for --all");
+    println!("           -q (no success chatter)  -v (db path, address expansion)  -vv (argv)");
+    EXIT_OK
 }
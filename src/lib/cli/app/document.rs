@@ -0,0 +1,81 @@
+use rsb::prelude::*;
+
+use crate::lib::api;
+
+use super::context::{connection_config, CommandArgs, EXIT_ERROR, EXIT_MISS, EXIT_OK};
+use super::output::report_error;
+use super::verbosity::trace_address;
+
+/// `jset <address> <.path> <value>` — set one field of a JSON value in place.
+///
+/// `value` is parsed as JSON when it can be (`5432`, `true`, `{"a":1}`) and
+/// stored as a string otherwise; a missing key starts as `{}`.
+pub fn do_jset(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let (address, path, value) = match argv.as_slice() {
+        [address, path, value] => (address, path, value),
+        _ => {
+            eprintln!("Usage: prontodb jset <address> <.path> <value>");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    trace_address(&config, address);
+
+    match api::json_set_value(&config, address, path, value) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            report_error("jset", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `jget <address> <.path>` — print one field; strings unquoted, everything else as JSON.
+pub fn do_jget(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let (address, path) = match argv.as_slice() {
+        [address, path] => (address, path),
+        _ => {
+            eprintln!("Usage: prontodb jget <address> <.path>");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    trace_address(&config, address);
+
+    match api::json_get_value(&config, address, path) {
+        Ok(Some(value)) => {
+            println!("{}", value);
+            EXIT_OK
+        }
+        Ok(None) => EXIT_MISS,
+        Err(error) => {
+            report_error("jget", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `jdel <address> <.path>` — remove one field; exit 2 when the key or field is missing.
+pub fn do_jdel(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let (address, path) = match argv.as_slice() {
+        [address, path] => (address, path),
+        _ => {
+            eprintln!("Usage: prontodb jdel <address> <.path>");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    trace_address(&config, address);
+
+    match api::json_delete_value(&config, address, path) {
+        Ok(true) => EXIT_OK,
+        Ok(false) => EXIT_MISS,
+        Err(error) => {
+            report_error("jdel", &error);
+            EXIT_ERROR
+        }
+    }
+}
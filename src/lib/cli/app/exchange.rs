@@ -0,0 +1,537 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Read};
+
+use hub::data_ext::serde_json;
+use hub::serde::Serialize;
+use rsb::prelude::*;
+
+use crate::lib::addr::Address;
+use crate::lib::adpt::sqlite::{Clock, ImportStrategy, KvEntry, SystemClock};
+use crate::lib::api;
+
+use super::context::{connection_config, CommandArgs, EXIT_CONFLICT, EXIT_ERROR, EXIT_OK};
+use super::output::report_error;
+use super::verbosity::say;
+
+/// File formats understood by `export` (and read back by `import`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExchangeFormat {
+    /// Pretty JSON array of records; keeps contexts and TTL metadata.
+    Json,
+    /// `[[keys]]` array of tables with the same fields as JSON.
+    Toml,
+    /// `address=value` lines with shell quoting (multi-line values use dotenv `\n`); no TTLs.
+    Env,
+}
+
+impl ExchangeFormat {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "env" => Some(Self::Env),
+            _ => None,
+        }
+    }
+}
+
+/// One exported key; `ttl` is the lifetime left at export time, so imports restart the clock.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+struct ExportRecord<'a> {
+    project: &'a str,
+    namespace: &'a str,
+    key: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context: Option<&'a str>,
+    value: &'a str,
+    ttl: Option<i64>,
+    expires_at: Option<i64>,
+}
+
+impl<'a> ExportRecord<'a> {
+    fn new(entry: &'a KvEntry, now: i64) -> Self {
+        Self {
+            project: &entry.address.project,
+            namespace: &entry.address.namespace,
+            key: &entry.address.key,
+            context: entry.address.context.as_deref(),
+            value: &entry.value,
+            ttl: entry.ttl_remaining(now),
+            expires_at: entry.expires_at,
+        }
+    }
+}
+
+/// `export [project[.namespace]] [--format json|toml|env] [--out FILE]`
+pub fn do_export(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["format", "out"]);
+    let scope = match parsed.positional.as_slice() {
+        [] => None,
+        [scope] => Some(scope.as_str()),
+        _ => {
+            eprintln!(
+                "Usage: prontodb export [project[.namespace]] [--format json|toml|env] [--out FILE]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let format = match exchange_format(&parsed, "export") {
+        Some(format) => format,
+        None => return EXIT_ERROR,
+    };
+
+    let entries = match api::export_entries(&connection_config(), scope) {
+        Ok(entries) => entries,
+        Err(error) => {
+            report_error("export", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let now = SystemClock.now();
+    let records: Vec<ExportRecord<'_>> = entries
+        .iter()
+        .map(|entry| ExportRecord::new(entry, now))
+        .collect();
+    let rendered = match format {
+        ExchangeFormat::Json => match serde_json::to_string_pretty(&records) {
+            Ok(json) => json + "\n",
+            Err(error) => {
+                eprintln!("export: {}", error);
+                return EXIT_ERROR;
+            }
+        },
+        ExchangeFormat::Toml => render_toml(&records),
+        ExchangeFormat::Env => render_env(&entries),
+    };
+
+    match parsed.flag("out") {
+        Some(path) => match fs::write(path, rendered) {
+            Ok(()) => {
+                say(format!("Exported {} keys to {}", records.len(), path));
+                EXIT_OK
+            }
+            Err(error) => {
+                eprintln!("export: {}: {}", path, error);
+                EXIT_ERROR
+            }
+        },
+        None => {
+            print!("{}", rendered);
+            EXIT_OK
+        }
+    }
+}
+
+/// `import FILE [--format json|toml|env] [--strategy overwrite|skip|fail]`
+///
+/// Loads every record in one transaction. `FILE` may be `-` for stdin. Under
+/// `--strategy fail` any existing key aborts the import (exit 3, nothing written).
+pub fn do_import(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["format", "strategy"]);
+    let path = match parsed.positional.as_slice() {
+        [path] => path,
+        _ => {
+            eprintln!(
+                "Usage: prontodb import <FILE|-> [--format json|toml|env] [--strategy overwrite|skip|fail]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let format = match exchange_format(&parsed, "import") {
+        Some(format) => format,
+        None => return EXIT_ERROR,
+    };
+    let strategy = match parsed.flag("strategy").unwrap_or("overwrite") {
+        "overwrite" => ImportStrategy::Overwrite,
+        "skip" => ImportStrategy::Skip,
+        "fail" => ImportStrategy::Fail,
+        other => {
+            eprintln!(
+                "import: unknown --strategy '{}' (expected overwrite|skip|fail)",
+                other
+            );
+            return EXIT_ERROR;
+        }
+    };
+
+    let input = if path == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input).map(|_| input)
+    } else {
+        fs::read_to_string(path)
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("import: {}: {}", path, error);
+            return EXIT_ERROR;
+        }
+    };
+    let records = match format {
+        ExchangeFormat::Json => parse_json(&input),
+        ExchangeFormat::Toml => parse_toml(&input),
+        ExchangeFormat::Env => parse_env(&input),
+    };
+    let records = match records {
+        Ok(records) => records,
+        Err(message) => {
+            eprintln!("import: {}: {}", path, message);
+            return EXIT_ERROR;
+        }
+    };
+
+    match api::import_values(&connection_config(), &records, strategy) {
+        Ok(report) if report.conflicted > 0 => {
+            eprintln!(
+                "import: {} keys already exist; nothing imported (--strategy fail)",
+                report.conflicted
+            );
+            EXIT_CONFLICT
+        }
+        Ok(report) => {
+            say(format!(
+                "Imported {} keys ({} inserted, {} skipped, {} conflicted)",
+                records.len(),
+                report.inserted,
+                report.skipped,
+                report.conflicted
+            ));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("import", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `env export <project.namespace> [--prefix P] [--export]`
+///
+/// Prints `NAME=value` lines for a namespace, where `NAME` is the prefix plus
+/// the upper-cased key (non-alphanumerics become `_`). Values are shell-quoted,
+/// so the output can be `eval`ed or sourced; `--export` prefixes each line with `export `.
+pub fn do_env(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["prefix"]);
+    let scope = match parsed.positional.as_slice() {
+        [action, scope] if action == "export" => scope,
+        _ => {
+            eprintln!("Usage: prontodb env export <project.namespace> [--prefix P] [--export]");
+            return EXIT_ERROR;
+        }
+    };
+    let prefix = parsed.flag("prefix").unwrap_or("");
+    let keyword = if parsed.has_flag("export") {
+        "export "
+    } else {
+        ""
+    };
+
+    match api::list_entries(&connection_config(), scope) {
+        Ok(entries) => {
+            for entry in entries {
+                println!(
+                    "{}{}={}",
+                    keyword,
+                    env_name(prefix, &entry.address.key_path()),
+                    shell_quote(&entry.value)
+                );
+            }
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("env", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `--format` for `export`/`import`, defaulting to JSON; reports unknown values itself.
+fn exchange_format(parsed: &CommandArgs, command: &str) -> Option<ExchangeFormat> {
+    match parsed.flag("format") {
+        None => Some(ExchangeFormat::Json),
+        Some(raw) => {
+            let format = ExchangeFormat::parse(raw);
+            if format.is_none() {
+                eprintln!(
+                    "{}: unknown --format '{}' (expected json|toml|env)",
+                    command, raw
+                );
+            }
+            format
+        }
+    }
+}
+
+fn render_toml(records: &[ExportRecord<'_>]) -> String {
+    let mut out = String::from("# prontodb export\n");
+    for record in records {
+        out.push_str("\n[[keys]]\n");
+        let _ = writeln!(out, "project = {}", toml_string(record.project));
+        let _ = writeln!(out, "namespace = {}", toml_string(record.namespace));
+        let _ = writeln!(out, "key = {}", toml_string(record.key));
+        if let Some(context) = record.context {
+            let _ = writeln!(out, "context = {}", toml_string(context));
+        }
+        let _ = writeln!(out, "value = {}", toml_string(record.value));
+        if let Some(ttl) = record.ttl {
+            let _ = writeln!(out, "ttl = {}", ttl);
+        }
+        if let Some(expires_at) = record.expires_at {
+            let _ = writeln!(out, "expires_at = {}", expires_at);
+        }
+    }
+    out
+}
+
+fn render_env(entries: &[KvEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| format!("{}={}\n", entry.address, env_value(&entry.value)))
+        .collect()
+}
+
+/// `(address, value, ttl)` rows handed to `api::import_values`.
+type ImportRows = Vec<(String, String, Option<u64>)>;
+
+/// Address path for an exported record's segments.
+fn record_address(project: &str, namespace: &str, key: &str, context: Option<String>) -> String {
+    Address::new(project, namespace, key, context).to_string()
+}
+
+/// Array of `{project, namespace, key, context?, value, ttl?}` objects, as written by `export`.
+fn parse_json(input: &str) -> Result<ImportRows, String> {
+    let records: Vec<serde_json::Value> =
+        serde_json::from_str(input).map_err(|err| format!("invalid JSON: {}", err))?;
+    records
+        .iter()
+        .enumerate()
+        .map(|(index, record)| {
+            let field = |name: &str| record.get(name).and_then(|value| value.as_str());
+            let required = |name: &str| {
+                field(name).ok_or_else(|| format!("record {}: missing '{}'", index + 1, name))
+            };
+            let address = record_address(
+                required("project")?,
+                required("namespace")?,
+                required("key")?,
+                field("context").map(str::to_string),
+            );
+            let ttl = record.get("ttl").and_then(|ttl| ttl.as_u64());
+            Ok((address, required("value")?.to_string(), ttl))
+        })
+        .collect()
+}
+
+/// The `[[keys]]` subset of TOML that `export --format toml` writes.
+fn parse_toml(input: &str) -> Result<ImportRows, String> {
+    fn finish(
+        rows: &mut ImportRows,
+        fields: &mut BTreeMap<String, String>,
+        line: usize,
+    ) -> Result<(), String> {
+        if fields.is_empty() {
+            return Ok(());
+        }
+        let context = fields.remove("context");
+        let ttl = fields.remove("ttl");
+        let mut take = |name: &str| {
+            fields
+                .remove(name)
+                .ok_or_else(|| format!("[[keys]] ending at line {}: missing '{}'", line, name))
+        };
+        let address = record_address(
+            &take("project")?,
+            &take("namespace")?,
+            &take("key")?,
+            context,
+        );
+        let value = take("value")?;
+        let ttl = match ttl {
+            Some(ttl) => Some(
+                ttl.parse::<u64>()
+                    .map_err(|_| format!("line {}: ttl must be a whole number", line))?,
+            ),
+            None => None,
+        };
+        rows.push((address, value, ttl));
+        fields.clear();
+        Ok(())
+    }
+
+    let mut rows = Vec::new();
+    let mut fields = BTreeMap::new();
+    for (index, raw) in input.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line == "[[keys]]" {
+            finish(&mut rows, &mut fields, index)?;
+            continue;
+        }
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected name = value", index + 1))?;
+        let value = value.trim();
+        let value = if value.starts_with('"') {
+            toml_unquote(value).ok_or_else(|| format!("line {}: bad string", index + 1))?
+        } else {
+            value.to_string()
+        };
+        fields.insert(name.trim().to_string(), value);
+    }
+    finish(&mut rows, &mut fields, input.lines().count())?;
+    Ok(rows)
+}
+
+/// `address=value` lines (optionally prefixed with `export `); `#` lines are comments.
+fn parse_env(input: &str) -> Result<ImportRows, String> {
+    let mut rows = Vec::new();
+    for (index, raw) in input.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (address, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected address=value", index + 1))?;
+        let value =
+            shell_unquote(value).ok_or_else(|| format!("line {}: unbalanced quotes", index + 1))?;
+        rows.push((address.to_string(), value, None));
+    }
+    Ok(rows)
+}
+
+/// TOML basic string: quotes, backslashes, and control characters escaped.
+fn toml_string(raw: &str) -> String {
+    let mut quoted = String::with_capacity(raw.len() + 2);
+    quoted.push('"');
+    for ch in raw.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            ch if ch.is_control() => {
+                let _ = write!(quoted, "\\u{:04X}", ch as u32);
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `prefix` + upper-cased key with non-alphanumerics mapped to `_`; never starts with a digit.
+fn env_name(prefix: &str, key: &str) -> String {
+    let mut name = String::from(prefix);
+    name.extend(key.chars().map(|ch| {
+        if ch.is_ascii_alphanumeric() {
+            ch.to_ascii_uppercase()
+        } else {
+            '_'
+        }
+    }));
+    if name.starts_with(|ch: char| ch.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// One-line env value: shell quoting, or dotenv-style `"...\n..."` when the value spans lines.
+fn env_value(raw: &str) -> String {
+    if !raw.contains(['\n', '\r']) {
+        return shell_quote(raw);
+    }
+    let mut quoted = String::with_capacity(raw.len() + 2);
+    quoted.push('"');
+    for ch in raw.chars() {
+        match ch {
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '"' | '\\' | '$' | '`' => {
+                quoted.push('\\');
+                quoted.push(ch);
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// POSIX shell quoting: bare when every byte is safe, otherwise single quotes with `'\''`.
+fn shell_quote(raw: &str) -> String {
+    let safe = !raw.is_empty()
+        && raw
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "_-./:@%+,=".contains(ch));
+    if safe {
+        raw.to_string()
+    } else {
+        format!("'{}'", raw.replace('\'', "'\\''"))
+    }
+}
+
+/// Inverse of `toml_string`; `None` on a malformed or unterminated string.
+fn toml_unquote(raw: &str) -> Option<String> {
+    let inner = raw.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            value.push(ch);
+            continue;
+        }
+        match chars.next()? {
+            '"' => value.push('"'),
+            '\\' => value.push('\\'),
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            'b' => value.push('\u{8}'),
+            'f' => value.push('\u{c}'),
+            'u' => {
+                let hex: String = chars.by_ref().take(4).collect();
+                value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+            }
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+/// Inverse of `env_value`: single-quoted, double-quoted (with `\n`/`\r` escapes), or bare.
+fn shell_unquote(raw: &str) -> Option<String> {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' => loop {
+                match chars.next()? {
+                    '\'' => break,
+                    ch => value.push(ch),
+                }
+            },
+            '"' => loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => match chars.next()? {
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        ch => value.push(ch),
+                    },
+                    ch => value.push(ch),
+                }
+            },
+            '\\' => value.push(chars.next()?),
+            ch => value.push(ch),
+        }
+    }
+    Some(value)
+}
@@ -0,0 +1,209 @@
+use std::net::SocketAddr;
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::transport::Server;
+use tonic::{Code, Request, Response, Status};
+
+use crate::lib::adpt::sqlite::SqliteConnectionConfig;
+use crate::lib::api::{ErrorCode, Session};
+use crate::lib::core::crud::{CrudError, CrudErrorKind};
+
+use super::listing::masked;
+use super::serve::{bearer_token, Gate};
+use super::watch::{changes, WatchTarget};
+
+mod proto {
+    tonic::include_proto!("prontodb.v1");
+}
+
+use proto::keystore_server::{Keystore, KeystoreServer};
+use proto::{
+    Change, DeleteReply, DeleteRequest, Entry, GetReply, GetRequest, ScanRequest, SetReply,
+    SetRequest, WatchRequest,
+};
+
+/// Changes a `Watch` stream buffers before its poller waits for the client.
+const WATCH_BUFFER: usize = 64;
+
+/// Serve the `Keystore` service of `proto/prontodb.proto` on `addr` until the process exits.
+///
+/// Every call needs an API token in its `authorization` metadata
+/// (`Bearer pdb_...`) and runs as that token; calls without one fail
+/// `UNAUTHENTICATED`. In read-only mode (`--read-only` or a read-only
+/// cursor) the sessions refuse `Set` and `Delete` with `PERMISSION_DENIED`.
+pub fn serve_grpc(gate: Gate, addr: SocketAddr) -> Result<(), String> {
+    let service = KeystoreService { gate };
+    let runtime = tokio::runtime::Runtime::new().map_err(|err| err.to_string())?;
+    runtime
+        .block_on(
+            Server::builder()
+                .add_service(KeystoreServer::new(service))
+                .serve(addr),
+        )
+        .map_err(|err| err.to_string())
+}
+
+struct KeystoreService {
+    gate: Gate,
+}
+
+impl KeystoreService {
+    /// The config of the token `request` carries, for `Watch` pollers' own connections.
+    fn config<T>(&self, request: &Request<T>) -> Result<SqliteConnectionConfig, Status> {
+        self.gate
+            .token_config(token(request))
+            .map_err(unauthenticated)
+    }
+
+    /// A session for the token `request` carries.
+    fn session<T>(&self, request: &Request<T>) -> Result<Session, Status> {
+        self.gate.open(token(request)).map_err(unauthenticated)
+    }
+}
+
+/// The API token in `request`'s `authorization` metadata.
+fn token<T>(request: &Request<T>) -> Option<&str> {
+    request
+        .metadata()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .map(bearer_token)
+}
+
+type ReplyStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Keystore for KeystoreService {
+    async fn get(&self, request: Request<GetRequest>) -> Result<Response<GetReply>, Status> {
+        let value = self
+            .session(&request)?
+            .get(&request.into_inner().address)
+            .map_err(status)?;
+        Ok(Response::new(GetReply {
+            found: value.is_some(),
+            value: value.unwrap_or_default(),
+        }))
+    }
+
+    async fn set(&self, request: Request<SetRequest>) -> Result<Response<SetReply>, Status> {
+        let session = self.session(&request)?;
+        let request = request.into_inner();
+        session
+            .set(&request.address, &request.value, request.ttl_secs)
+            .map_err(status)?;
+        Ok(Response::new(SetReply {}))
+    }
+
+    async fn delete(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteReply>, Status> {
+        let deleted = self
+            .session(&request)?
+            .delete(&request.into_inner().address)
+            .map_err(status)?;
+        Ok(Response::new(DeleteReply { deleted }))
+    }
+
+    type ScanStream = ReplyStream<Entry>;
+
+    async fn scan(
+        &self,
+        request: Request<ScanRequest>,
+    ) -> Result<Response<Self::ScanStream>, Status> {
+        let session = self.session(&request)?;
+        let scope = request.into_inner().scope;
+        let mut entries = Vec::new();
+        {
+            let secrets = session.secret_marks().map_err(status)?;
+            session
+                .scan(&scope, |entry| {
+                    let entry = masked(entry, &secrets);
+                    entries.push(Ok(Entry {
+                        address: entry.address.to_string(),
+                        value: entry.value,
+                        updated_at: entry.updated_at,
+                        expires_at: entry.expires_at,
+                    }));
+                    ControlFlow::Continue(())
+                })
+                .map_err(status)?;
+        }
+        Ok(Response::new(Box::pin(tokio_stream::iter(entries))))
+    }
+
+    type WatchStream = ReplyStream<Change>;
+
+    /// Polls on its own thread (like `prontodb watch`) until the client goes away.
+    async fn watch(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let config = self.config(&request)?;
+        let secrets = self.session(&request)?.secret_marks().map_err(status)?;
+        let request = request.into_inner();
+        let target = WatchTarget::parse(&request.target);
+        let interval = Duration::from_secs(request.interval_secs.max(1));
+        let mut previous = target.snapshot(&config).map_err(status)?;
+
+        let (sender, receiver) = mpsc::channel(WATCH_BUFFER);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            if sender.is_closed() {
+                return;
+            }
+            let current = match target.snapshot(&config) {
+                Ok(current) => current,
+                Err(error) => {
+                    let _ = sender.blocking_send(Err(status(error)));
+                    return;
+                }
+            };
+            for (address, value) in changes(&previous, &current, &secrets) {
+                let change = Change {
+                    address,
+                    deleted: value.is_none(),
+                    value: value.unwrap_or_default(),
+                };
+                if sender.blocking_send(Ok(change)).is_err() {
+                    return;
+                }
+            }
+            previous = current;
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+/// A token that was missing, unknown or revoked, as `UNAUTHENTICATED`.
+fn unauthenticated(error: CrudError) -> Status {
+    match error.kind {
+        CrudErrorKind::PermissionDenied => Status::unauthenticated(format!(
+            "{}: {}",
+            ErrorCode::of(&error).as_str(),
+            error.source()
+        )),
+        _ => status(error),
+    }
+}
+
+/// `error` as a gRPC status carrying its stable `ErrorCode` (`NS_NOT_TTL: ...`).
+fn status(error: CrudError) -> Status {
+    let stable = ErrorCode::of(&error);
+    let code = match error.kind {
+        _ if stable == ErrorCode::StorageBusy => Code::Unavailable,
+        CrudErrorKind::Unsupported => Code::Unimplemented,
+        CrudErrorKind::InvalidInput => Code::InvalidArgument,
+        CrudErrorKind::Conflict => Code::AlreadyExists,
+        CrudErrorKind::NotFound => Code::NotFound,
+        CrudErrorKind::PermissionDenied => Code::PermissionDenied,
+        CrudErrorKind::Internal => Code::Internal,
+    };
+    Status::new(code, format!("{}: {}", stable.as_str(), error.source()))
+}
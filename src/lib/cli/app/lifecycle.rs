@@ -0,0 +1,157 @@
+//! Lifecycle commands `main` runs through `pre_dispatch!`, ahead of the core
+//! `pronto_dispatch` table: `install`, `uninstall`, `backup` and `restore`.
+
+use std::env;
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use rsb::prelude::*;
+
+use crate::lib::cursor::CursorManager;
+
+use super::backup;
+use super::context::{connection_config, CommandArgs, EXIT_ERROR, EXIT_OK};
+use super::dispatch::preflight;
+use super::verbosity::say;
+
+/// `install [--target DIR] [--force]`
+///
+/// Copies this binary to `DIR/prontodb` (default `~/.local/bin`) and creates
+/// the directory cursors and registry databases live in. An existing binary
+/// is only replaced with `--force`.
+pub fn do_install(args: Args) -> i32 {
+    if let Err(code) = preflight_as("install", &args) {
+        return code;
+    }
+    let parsed = CommandArgs::parse(&args, &["target"]);
+    if !parsed.positional.is_empty() {
+        eprintln!("Usage: prontodb install [--target DIR] [--force]");
+        return EXIT_ERROR;
+    }
+    let target = installed_binary(&parsed);
+    if target.exists() && !parsed.has_flag("force") {
+        eprintln!(
+            "prontodb install: {} exists (pass --force to replace it)",
+            target.display()
+        );
+        return EXIT_ERROR;
+    }
+    let home = CursorManager::new().home().to_path_buf();
+    let installed = env::current_exe()
+        .and_then(|source| copy_executable(&source, &target))
+        .and_then(|()| fs::create_dir_all(&home));
+    match installed {
+        Ok(()) => {
+            say(format!(
+                "Installed {} (cursors and databases in {})",
+                target.display(),
+                home.display()
+            ));
+            EXIT_OK
+        }
+        Err(error) => {
+            eprintln!("prontodb install: {}", error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `uninstall [--target DIR] [--purge]`
+///
+/// Removes `DIR/prontodb` (default `~/.local/bin`). `--purge` also deletes
+/// the cursor directory with every cursor and registry database in it, so
+/// the dispatcher asks first (`--force` when scripted).
+pub fn do_uninstall(args: Args) -> i32 {
+    if let Err(code) = preflight_as("uninstall", &args) {
+        return code;
+    }
+    let parsed = CommandArgs::parse(&args, &["target"]);
+    if !parsed.positional.is_empty() {
+        eprintln!("Usage: prontodb uninstall [--target DIR] [--purge]");
+        return EXIT_ERROR;
+    }
+    let target = installed_binary(&parsed);
+    match fs::remove_file(&target) {
+        Ok(()) => say(format!("Removed {}", target.display())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            say(format!("{} is not installed", target.display()))
+        }
+        Err(error) => {
+            eprintln!("prontodb uninstall: {}: {}", target.display(), error);
+            return EXIT_ERROR;
+        }
+    }
+    if !parsed.has_flag("purge") {
+        return EXIT_OK;
+    }
+    let home = CursorManager::new().home().to_path_buf();
+    match fs::remove_dir_all(&home) {
+        Ok(()) => say(format!("Removed {}", home.display())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => {
+            eprintln!("prontodb uninstall: {}: {}", home.display(), error);
+            return EXIT_ERROR;
+        }
+    }
+    EXIT_OK
+}
+
+/// `backup ...`, with the checks every core command gets; see `backup::do_backup`.
+pub fn do_backup(args: Args) -> i32 {
+    if let Err(code) = preflight_as("backup", &args) {
+        return code;
+    }
+    backup::do_backup(args)
+}
+
+/// `restore FILE [--dry-run]` — the same as `backup --restore FILE [--dry-run]`.
+pub fn do_restore(args: Args) -> i32 {
+    if let Err(code) = preflight_as("restore", &args) {
+        return code;
+    }
+    let parsed = CommandArgs::parse(&args, &[]);
+    match parsed.positional.as_slice() {
+        [archive] => backup::restore(
+            &connection_config(),
+            Path::new(archive),
+            parsed.has_flag("dry-run"),
+        ),
+        _ => {
+            eprintln!("Usage: prontodb restore FILE [--dry-run]");
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `preflight` for `command`, which `pre_dispatch!` has already taken off `args`.
+fn preflight_as(command: &str, args: &Args) -> Result<(), i32> {
+    let words: Vec<String> = std::iter::once(command.to_string())
+        .chain(args.all().iter().cloned())
+        .collect();
+    preflight(&words)
+}
+
+/// `--target DIR`, else `~/.local/bin`, joined with the binary name.
+fn installed_binary(parsed: &CommandArgs) -> PathBuf {
+    let dir = match parsed.flag("target").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => env::var("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(".local/bin"),
+    };
+    dir.join("prontodb")
+}
+
+fn copy_executable(source: &Path, target: &Path) -> io::Result<()> {
+    if let Some(dir) = target.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::copy(source, target)?;
+    #[cfg(unix)]
+    fs::set_permissions(target, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
@@ -0,0 +1,625 @@
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+
+use hub::data_ext::serde_json;
+use rsb::prelude::*;
+
+use crate::lib::addr::{Address, KeyPattern};
+use crate::lib::adpt::sqlite::{
+    EntryOrder, KvEntry, SecretMarks, SortField, SqliteConnectionConfig,
+};
+use crate::lib::api;
+use crate::lib::core::crud::CrudResult;
+
+use super::context::{
+    command_config, connection_config, peer_database_path, CommandArgs, EXIT_ERROR, EXIT_MISS,
+    EXIT_OK,
+};
+use super::output::{
+    output_mode, print_json, print_rows, report_error, DatabaseEntryView, EntryView, ListFormat,
+    LongEntryView, OutputMode, SearchView,
+};
+
+/// Results returned by `search` when `--limit` is not given.
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Printed by `scan` in place of values marked with `secret`.
+pub const SECRET_MASK: &str = "****";
+
+/// `keys <project.namespace[.prefix]> [--format plain|table|csv|tsv] [--long]`
+///
+/// The scope may be a glob (`app.cfg.db_*`, `app.*.enabled`); when it spans
+/// namespaces, keys are printed as full addresses. `--regex <pattern>` further
+/// keeps only keys (including any `__context` suffix) the regex matches.
+/// `--from <key>` / `--to <key>` select the half-open range `[from, to)` in a
+/// plain scope, e.g. `--from 2025-01 --to 2025-02` over time-prefixed keys.
+/// `--meta <ctx>` lists the scope inside another organization context.
+///
+/// `--long` adds TTL remaining, value size, and created/updated timestamps;
+/// it renders as a table unless another `--format` is given.
+pub fn do_keys(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["format", "regex", "from", "to", "meta"]);
+    let scope = match parsed.positional.as_slice() {
+        [scope] => scope,
+        _ => {
+            eprintln!(
+                "Usage: prontodb keys <project.namespace[.prefix]> [--format plain|table|csv|tsv] [--long] [--regex RE | --from KEY --to KEY] [--meta CTX]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let (from, to) = (parsed.flag("from"), parsed.flag("to"));
+    let filter = match parsed.flag("regex") {
+        Some(_) if from.is_some() || to.is_some() => {
+            eprintln!("keys: --regex and --from/--to cannot be combined");
+            return EXIT_ERROR;
+        }
+        Some(regex) => EntryFilter::KeyRegex(regex),
+        None if from.is_some() || to.is_some() => EntryFilter::Range { from, to },
+        None => EntryFilter::All,
+    };
+    let format = match list_format(&parsed, "keys") {
+        Some(format) => format,
+        None => return EXIT_ERROR,
+    };
+
+    let long = parsed.has_flag("long");
+
+    let label = key_label(scope);
+
+    let config = command_config(&parsed);
+    match list_entries(&config, scope, filter, None, EntryOrder::default()) {
+        Ok(entries) if long => {
+            let views: Vec<LongEntryView> = entries
+                .iter()
+                .map(|entry| LongEntryView {
+                    key: label(&entry.address),
+                    ..LongEntryView::new(entry)
+                })
+                .collect();
+            if output_mode() == OutputMode::Json {
+                return print_json(&views);
+            }
+            let format = match format {
+                ListFormat::Plain => ListFormat::Table,
+                format => format,
+            };
+            let rows: Vec<Vec<String>> = views.iter().map(LongEntryView::row).collect();
+            print_rows(format, &LongEntryView::HEADERS, &rows);
+            EXIT_OK
+        }
+        Ok(entries) => {
+            let keys: Vec<String> = entries.iter().map(|entry| label(&entry.address)).collect();
+            if output_mode() == OutputMode::Json {
+                return print_json(&keys);
+            }
+            let rows: Vec<Vec<String>> = keys.into_iter().map(|key| vec![key]).collect();
+            print_rows(format, &["key"], &rows);
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("keys", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `scan <project.namespace[.prefix]> [--format plain|table|csv|tsv]`
+///
+/// Accepts the same glob scopes, `--regex <pattern>` key filter and `--meta` as `keys`.
+/// `--where '.status=active'` instead keeps JSON values with that field value,
+/// using the namespace's `--json-path` index when there is one.
+///
+/// `--sort key|updated|size [--desc]` orders rows in the SQL query, so output
+/// stays streamable and needs no `sort` over `key=value` lines.
+///
+/// The default `plain` format prints `key=value` lines; use csv/tsv/table when
+/// values may contain `=`, delimiters, or newlines. `--ndjson` streams one JSON
+/// object per row instead.
+///
+/// `--databases main,staging` reads the scope from several databases (cursor
+/// names or paths; `main` is the current one) in one ATTACHed query and labels
+/// each row with its database, the copies of a key side by side.
+///
+/// `--context prod` keeps only the `__prod` values of each key; `contexts`
+/// lists which contexts a key has.
+///
+/// Values of keys marked with `secret` print as `****` unless `--reveal` is given.
+pub fn do_scan(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(
+        &args,
+        &[
+            "format",
+            "regex",
+            "where",
+            "sort",
+            "databases",
+            "context",
+            "meta",
+        ],
+    );
+    let scope = match parsed.positional.as_slice() {
+        [scope] => scope,
+        _ => {
+            eprintln!(
+                "Usage: prontodb scan <project.namespace[.prefix]> [--format plain|table|csv|tsv] [--regex RE | --where .PATH=VALUE] [--sort key|updated|size] [--desc] [--context CTX] [--meta CTX] [--databases A,B] [--reveal]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let filter = match (parsed.flag("regex"), parsed.flag("where")) {
+        (Some(_), Some(_)) => {
+            eprintln!("scan: --regex and --where cannot be combined");
+            return EXIT_ERROR;
+        }
+        (regex, None) => EntryFilter::key_regex(regex),
+        (None, Some(filter)) => EntryFilter::Where(filter),
+    };
+    let field = match parsed.flag("sort").unwrap_or("key") {
+        "key" => SortField::Key,
+        "updated" => SortField::Updated,
+        "size" => SortField::Size,
+        other => {
+            eprintln!(
+                "scan: unknown --sort '{}' (expected key, updated, or size)",
+                other
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let order = EntryOrder {
+        field,
+        descending: parsed.has_flag("desc"),
+    };
+    let format = match list_format(&parsed, "scan") {
+        Some(format) => format,
+        None => return EXIT_ERROR,
+    };
+    let config = command_config(&parsed);
+    if let Some(databases) = parsed.flag("databases") {
+        let single = ["regex", "where", "sort", "context", "ndjson"];
+        if single.iter().any(|flag| parsed.has_flag(flag)) {
+            eprintln!(
+                "scan: --databases cannot be combined with --regex, --where, --sort, --context or --ndjson"
+            );
+            return EXIT_ERROR;
+        }
+        return scan_databases(&config, scope, databases, format, parsed.has_flag("reveal"));
+    }
+    let secrets = if parsed.has_flag("reveal") {
+        SecretMarks::default()
+    } else {
+        match api::secret_marks(&config) {
+            Ok(secrets) => secrets,
+            Err(error) => {
+                report_error("scan", &error);
+                return EXIT_ERROR;
+            }
+        }
+    };
+    let context = parsed.flag("context");
+    if parsed.has_flag("ndjson") {
+        return scan_ndjson(&config, scope, filter, context, order, &secrets);
+    }
+    let label = key_label(scope);
+
+    match list_entries(&config, scope, filter, context, order) {
+        Ok(entries) => {
+            let entries: Vec<KvEntry> = entries
+                .into_iter()
+                .map(|entry| masked(entry, &secrets))
+                .collect();
+            if output_mode() == OutputMode::Json {
+                let views: Vec<EntryView<'_>> = entries
+                    .iter()
+                    .map(|entry| EntryView {
+                        key: label(&entry.address),
+                        ..EntryView::new(entry)
+                    })
+                    .collect();
+                return print_json(&views);
+            }
+            let rows: Vec<Vec<String>> = entries
+                .into_iter()
+                .map(|entry| vec![label(&entry.address), entry.value])
+                .collect();
+            print_rows(format, &["key", "value"], &rows);
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("scan", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `grep <pattern> [project[.namespace]] [--regex]`
+///
+/// Prints the address of every live key whose value contains `pattern` (or
+/// matches it as a regex with `--regex`), streaming rows as they are found.
+/// Exits 2 when nothing matched.
+pub fn do_grep(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &[]);
+    let (pattern, scope) = match parsed.positional.as_slice() {
+        [pattern] => (pattern, None),
+        [pattern, scope] => (pattern, Some(scope.as_str())),
+        _ => {
+            eprintln!("Usage: prontodb grep <pattern> [project[.namespace]] [--regex]");
+            return EXIT_ERROR;
+        }
+    };
+
+    let json = output_mode() == OutputMode::Json;
+    let mut matches = Vec::new();
+    let mut found = false;
+    let result = api::grep_values(
+        &connection_config(),
+        scope,
+        pattern,
+        parsed.has_flag("regex"),
+        |entry| {
+            found = true;
+            if json {
+                matches.push(entry.address.to_string());
+            } else {
+                println!("{}", entry.address);
+            }
+            ControlFlow::Continue(())
+        },
+    );
+
+    match result {
+        Ok(()) if json => print_json(&matches),
+        Ok(()) if found => EXIT_OK,
+        Ok(()) => EXIT_MISS,
+        Err(error) => {
+            report_error("grep", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `search <query> [project[.namespace]] [--limit N] [--format table|csv|tsv]`
+///
+/// Full-text search over namespaces indexed with
+/// `prontodb-admin --create-index --fts`, best matches first. `query` uses
+/// FTS5 syntax (`word`, `"a phrase"`, `pre*`, `a OR b`). Exits 2 on no match.
+pub fn do_search(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["format", "limit"]);
+    let (query, scope) = match parsed.positional.as_slice() {
+        [query] => (query, None),
+        [query, scope] => (query, Some(scope.as_str())),
+        _ => {
+            eprintln!("Usage: prontodb search <query> [project[.namespace]] [--limit N]");
+            return EXIT_ERROR;
+        }
+    };
+    let limit = match parsed.flag("limit").map(str::parse::<usize>) {
+        None => DEFAULT_SEARCH_LIMIT,
+        Some(Ok(limit)) if limit > 0 => limit,
+        Some(_) => {
+            eprintln!("search: --limit expects a positive number");
+            return EXIT_ERROR;
+        }
+    };
+    let format = match list_format(&parsed, "search") {
+        Some(ListFormat::Plain) => ListFormat::Table,
+        Some(format) => format,
+        None => return EXIT_ERROR,
+    };
+
+    match api::search_values(&connection_config(), query, scope, limit) {
+        Ok(hits) => {
+            if output_mode() == OutputMode::Json {
+                let views: Vec<SearchView<'_>> = hits.iter().map(SearchView::new).collect();
+                return print_json(&views);
+            }
+            if hits.is_empty() {
+                return EXIT_MISS;
+            }
+            let rows: Vec<Vec<String>> = hits
+                .iter()
+                .map(|hit| {
+                    vec![
+                        hit.entry.address.to_string(),
+                        format!("{:.3}", hit.score),
+                        hit.snippet.clone(),
+                    ]
+                })
+                .collect();
+            print_rows(format, &["address", "score", "snippet"], &rows);
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("search", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// How `keys`/`scan` narrow the entries in a scope.
+#[derive(Clone, Copy)]
+enum EntryFilter<'a> {
+    All,
+    /// `--regex` on the key path.
+    KeyRegex(&'a str),
+    /// `--where .path=value` on a JSON value.
+    Where(&'a str),
+    /// `--from`/`--to` key bounds, `[from, to)`.
+    Range {
+        from: Option<&'a str>,
+        to: Option<&'a str>,
+    },
+}
+
+impl<'a> EntryFilter<'a> {
+    fn key_regex(regex: Option<&'a str>) -> Self {
+        regex.map_or(EntryFilter::All, EntryFilter::KeyRegex)
+    }
+}
+
+/// Entries in `scope` narrowed by `filter` (and to `context`, when given), in `order`.
+fn list_entries(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    filter: EntryFilter<'_>,
+    context: Option<&str>,
+    order: EntryOrder,
+) -> CrudResult<Vec<KvEntry>> {
+    let mut entries = Vec::new();
+    scan_entries(config, scope, filter, context, order, |entry| {
+        entries.push(entry);
+        ControlFlow::Continue(())
+    })?;
+    Ok(entries)
+}
+
+fn scan_entries<F>(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    filter: EntryFilter<'_>,
+    context: Option<&str>,
+    order: EntryOrder,
+    mut visit: F,
+) -> CrudResult<()>
+where
+    F: FnMut(KvEntry) -> ControlFlow<()>,
+{
+    let visit = |entry: KvEntry| match context {
+        Some(context) if entry.address.context.as_deref() != Some(context) => {
+            ControlFlow::Continue(())
+        }
+        _ => visit(entry),
+    };
+    match filter {
+        EntryFilter::All => api::scan_entries_sorted(config, scope, order, visit),
+        EntryFilter::KeyRegex(regex) => {
+            api::scan_entries_matching(config, scope, regex, order, visit)
+        }
+        EntryFilter::Where(filter) => api::scan_entries_where(config, scope, filter, order, visit),
+        EntryFilter::Range { from, to } => {
+            api::scan_entries_in_range(config, scope, from, to, order, visit)
+        }
+    }
+}
+
+/// `scan --databases`: rows labeled with the name each database was given as.
+fn scan_databases(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    names: &str,
+    format: ListFormat,
+    reveal: bool,
+) -> i32 {
+    let names: Vec<&str> = names.split(',').map(str::trim).collect();
+    if names.iter().any(|name| name.is_empty()) {
+        eprintln!("scan: --databases expects comma-separated cursor names or paths");
+        return EXIT_ERROR;
+    }
+    let scanned = names
+        .iter()
+        .map(|name| match *name {
+            "main" => Ok(config.database_path().to_path_buf()),
+            name => peer_database_path(name),
+        })
+        .collect::<CrudResult<Vec<_>>>()
+        .and_then(|paths| {
+            let entries = api::scan_databases(config, &paths, scope)?;
+            let secrets = paths
+                .iter()
+                .map(|path| {
+                    if reveal {
+                        return Ok(SecretMarks::default());
+                    }
+                    api::secret_marks(&config.clone().with_database_path(path).with_read_only(true))
+                })
+                .collect::<CrudResult<Vec<_>>>()?;
+            Ok((entries, secrets))
+        });
+    let (entries, secrets) = match scanned {
+        Ok(scanned) => scanned,
+        Err(error) => {
+            report_error("scan", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let entries: Vec<(&str, KvEntry)> = entries
+        .into_iter()
+        .map(|(index, entry)| (names[index], masked(entry, &secrets[index])))
+        .collect();
+
+    if output_mode() == OutputMode::Json {
+        let views: Vec<DatabaseEntryView<'_>> = entries
+            .iter()
+            .map(|(database, entry)| DatabaseEntryView {
+                database,
+                entry: EntryView::new(entry),
+            })
+            .collect();
+        return print_json(&views);
+    }
+    let rows: Vec<Vec<String>> = entries
+        .into_iter()
+        .map(|(database, entry)| match format {
+            ListFormat::Plain => vec![
+                format!("{}:{}", database, entry.address.key_path()),
+                entry.value,
+            ],
+            _ => vec![database.to_string(), entry.address.key_path(), entry.value],
+        })
+        .collect();
+    print_rows(format, &["database", "key", "value"], &rows);
+    EXIT_OK
+}
+
+/// `entry` with its value replaced by `SECRET_MASK` when it is marked secret.
+pub fn masked(mut entry: KvEntry, secrets: &SecretMarks) -> KvEntry {
+    if secrets.is_secret(&entry.address) {
+        entry.value = SECRET_MASK.to_string();
+        entry.value_type = None;
+    }
+    entry
+}
+
+/// Keys are shown relative to the namespace, or as full addresses when a glob spans namespaces.
+fn key_label(scope: &str) -> fn(&Address) -> String {
+    let spans = KeyPattern::is_pattern(scope)
+        && KeyPattern::parse(scope).is_ok_and(|pattern| pattern.spans_namespaces());
+    if spans {
+        |addr: &Address| addr.to_string()
+    } else {
+        Address::key_path
+    }
+}
+
+/// Write each entry as it is read; stops quietly when stdout closes (e.g. `| head`).
+fn scan_ndjson(
+    config: &SqliteConnectionConfig,
+    scope: &str,
+    filter: EntryFilter<'_>,
+    context: Option<&str>,
+    order: EntryOrder,
+    secrets: &SecretMarks,
+) -> i32 {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut write_error = None;
+    let label = key_label(scope);
+    let visit = |entry: KvEntry| {
+        let entry = masked(entry, secrets);
+        let view = EntryView {
+            key: label(&entry.address),
+            ..EntryView::new(&entry)
+        };
+        let written = serde_json::to_writer(&mut out, &view)
+            .map_err(io::Error::from)
+            .and_then(|()| out.write_all(b"\n"));
+        match written {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(error) => {
+                write_error = Some(error);
+                ControlFlow::Break(())
+            }
+        }
+    };
+    let result = scan_entries(config, scope, filter, context, order, visit);
+
+    match (result, write_error) {
+        (Err(error), _) => {
+            report_error("scan", &error);
+            EXIT_ERROR
+        }
+        (Ok(()), Some(error)) if error.kind() != io::ErrorKind::BrokenPipe => {
+            eprintln!("scan: {}", error);
+            EXIT_ERROR
+        }
+        (Ok(()), _) => match out.flush() {
+            Ok(()) => EXIT_OK,
+            Err(error) if error.kind() == io::ErrorKind::BrokenPipe => EXIT_OK,
+            Err(error) => {
+                eprintln!("scan: {}", error);
+                EXIT_ERROR
+            }
+        },
+    }
+}
+
+pub fn do_projects(_args: Args) -> i32 {
+    match api::list_projects(&connection_config()) {
+        Ok(projects) => print_list(&projects),
+        Err(error) => {
+            report_error("projects", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `namespaces <project>`
+pub fn do_namespaces(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let project = match argv.as_slice() {
+        [project] => project,
+        _ => {
+            eprintln!("Usage: prontodb namespaces <project>");
+            return EXIT_ERROR;
+        }
+    };
+
+    match api::list_namespaces(&connection_config(), project) {
+        Ok(namespaces) => print_list(&namespaces),
+        Err(error) => {
+            report_error("namespaces", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `contexts <project.namespace.key>`
+///
+/// Lists the contexts stored for a key (`prod` for `key__prod`), one per line.
+/// Any context on the address itself is ignored. Exits 2 when there are none.
+pub fn do_contexts(args: Args) -> i32 {
+    let argv = CommandArgs::parse(&args, &[]).positional;
+    let address = match argv.as_slice() {
+        [address] => address,
+        _ => {
+            eprintln!("Usage: prontodb contexts <project.namespace.key>");
+            return EXIT_ERROR;
+        }
+    };
+
+    match api::list_contexts(&connection_config(), address) {
+        Ok(contexts) if contexts.is_empty() && output_mode() == OutputMode::Plain => EXIT_MISS,
+        Ok(contexts) => print_list(&contexts),
+        Err(error) => {
+            report_error("contexts", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// One item per line, or a JSON array under `--json`.
+fn print_list(items: &[String]) -> i32 {
+    match output_mode() {
+        OutputMode::Json => print_json(items),
+        OutputMode::Plain => {
+            for item in items {
+                println!("{}", item);
+            }
+            EXIT_OK
+        }
+    }
+}
+
+/// `--format` value for `command`, defaulting to `plain`; prints an error for unknown formats.
+fn list_format(parsed: &CommandArgs, command: &str) -> Option<ListFormat> {
+    let raw = parsed.flag("format").unwrap_or("plain");
+    let format = ListFormat::parse(raw);
+    if format.is_none() {
+        eprintln!(
+            "{}: unknown --format '{}' (expected plain, table, csv, or tsv)",
+            command, raw
+        );
+    }
+    format
+}
@@ -0,0 +1,31 @@
+//! Core `prontodb` CLI front-end (MODULE_SPEC orchestrator).
+
+mod admin;
+mod backup;
+mod changes;
+mod commands;
+mod confirm;
+mod context;
+mod cursor;
+mod db;
+mod diff;
+mod dispatch;
+mod document;
+mod exchange;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod lifecycle;
+mod listing;
+mod notify;
+mod output;
+mod resp;
+mod serve;
+mod stream;
+mod sync;
+mod ui;
+mod verbosity;
+mod watch;
+
+pub use context::{exit_code, EXIT_BUSY, EXIT_CONFLICT, EXIT_ERROR, EXIT_MISS, EXIT_OK};
+pub use dispatch::pronto_dispatch;
+pub use lifecycle::{do_backup, do_install, do_restore, do_uninstall};
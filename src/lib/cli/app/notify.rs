@@ -0,0 +1,179 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use rsb::prelude::*;
+
+use crate::lib::api::KeyChange;
+
+use super::context::{CommandArgs, EXIT_ERROR, EXIT_OK};
+use super::listing::SECRET_MASK;
+use super::serve::{escape, unescape};
+use super::verbosity::say;
+
+/// Sent by the server once a subscriber is registered, before any event.
+const SUBSCRIBED: &str = "OK";
+
+/// Processes subscribed to `serve --notify PATH`, each fed by its own writer thread.
+///
+/// A subscriber connects, sends one prefix line and reads `set ADDRESS=VALUE`
+/// or `del ADDRESS` lines (values escaped as in `serve --socket`, secrets
+/// masked) for every matching write made through the server.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+struct Subscriber {
+    prefix: String,
+    events: Sender<String>,
+}
+
+impl Notifier {
+    /// Accept subscribers on `listener` from a background thread.
+    pub fn start(listener: UnixListener) -> Self {
+        let notifier = Notifier::default();
+        let accepting = notifier.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let notifier = accepting.clone();
+                        thread::spawn(move || {
+                            if let Err(error) = notifier.feed(stream) {
+                                eprintln!("serve: subscriber: {}", error);
+                            }
+                        });
+                    }
+                    Err(error) => eprintln!("serve: accept subscriber: {}", error),
+                }
+            }
+        });
+        notifier
+    }
+
+    /// Queue `change` for every subscriber whose prefix covers it; gone subscribers are dropped.
+    pub fn publish(&self, change: &KeyChange) {
+        let address = change.address.to_string();
+        let event = match &change.value {
+            Some(_) if change.secret => format!("set {}={}", address, SECRET_MASK),
+            Some(value) => format!("set {}={}", address, escape(value)),
+            None => format!("del {}", address),
+        };
+        let mut subscribers = self.lock();
+        subscribers.retain(|subscriber| {
+            !covers(&subscriber.prefix, &address) || subscriber.events.send(event.clone()).is_ok()
+        });
+    }
+
+    /// Register one subscriber, then write its events until it disconnects.
+    fn feed(&self, stream: UnixStream) -> io::Result<()> {
+        let mut prefix = String::new();
+        BufReader::new(stream.try_clone()?).read_line(&mut prefix)?;
+        let (events, pending) = mpsc::channel();
+        self.lock().push(Subscriber {
+            prefix: normalize(prefix.trim_end_matches(['\r', '\n'])),
+            events,
+        });
+
+        let mut out = io::BufWriter::new(stream);
+        writeln!(out, "{}", SUBSCRIBED)?;
+        out.flush()?;
+        for event in pending {
+            writeln!(out, "{}", event)?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Subscriber>> {
+        match self.subscribers.lock() {
+            Ok(subscribers) => subscribers,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// `app.cfg`, `app.cfg.` and `app.cfg.*` all mean "app.cfg and below"; `*` means everything.
+fn normalize(prefix: &str) -> String {
+    prefix
+        .trim_end_matches('*')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Whether `address` is `prefix` itself or sits below it (`app.cfg` covers `app.cfg.host`, not `app.cfgx`).
+fn covers(prefix: &str, address: &str) -> bool {
+    prefix.is_empty()
+        || address
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// `subscribe <prefix> --socket PATH [--count N]`
+///
+/// Connects to the `--notify` socket of a running `serve` and prints
+/// `set ADDRESS=VALUE` or `del ADDRESS` for each write under `prefix`
+/// made through that server. `--count N` exits after N events.
+pub fn do_subscribe(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["socket", "count"]);
+    let (prefix, socket) = match (parsed.positional.as_slice(), parsed.flag("socket")) {
+        ([prefix], Some(socket)) if !socket.is_empty() => (prefix.as_str(), socket),
+        _ => {
+            eprintln!("Usage: prontodb subscribe <prefix> --socket PATH [--count N]");
+            return EXIT_ERROR;
+        }
+    };
+    let count = match parsed.flag("count").map(str::parse::<usize>) {
+        None => None,
+        Some(Ok(count)) if count > 0 => Some(count),
+        Some(_) => {
+            eprintln!("subscribe: --count expects a positive number");
+            return EXIT_ERROR;
+        }
+    };
+
+    match follow(socket, prefix, count) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            eprintln!("subscribe: {}: {}", socket, error);
+            EXIT_ERROR
+        }
+    }
+}
+
+fn follow(socket: &str, prefix: &str, count: Option<usize>) -> io::Result<()> {
+    let mut stream = UnixStream::connect(socket)?;
+    writeln!(stream, "{}", prefix)?;
+    let mut lines = BufReader::new(stream).lines();
+    match lines.next().transpose()? {
+        Some(line) if line == SUBSCRIBED => say(format!("Subscribed to {}", prefix)),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a serve --notify socket",
+            ))
+        }
+    }
+    let _ = io::stdout().flush();
+
+    let mut seen = 0;
+    for line in lines {
+        let line = line?;
+        match line.strip_prefix("set ") {
+            Some(assignment) => println!("set {}", unescape(assignment)),
+            None => println!("{}", line),
+        }
+        let _ = io::stdout().flush();
+        seen += 1;
+        if count.is_some_and(|count| seen >= count) {
+            return Ok(());
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "the server closed the connection",
+    ))
+}
@@ -0,0 +1,392 @@
+use std::path::Path;
+
+use hub::data_ext::serde_json;
+use hub::serde::Serialize;
+use rsb::prelude::*;
+
+use crate::lib::addr::{Address, AddressLayout};
+use crate::lib::adpt::sqlite::{Clock, KvEntry, OplogEntry, SearchHit, SystemClock, ValueType};
+use crate::lib::api::ErrorCode;
+use crate::lib::core::crud::CrudError;
+use crate::lib::core::helpers::format_timestamp;
+use crate::lib::cursor::{CursorData, FoundCursor};
+
+use super::context::{EXIT_ERROR, EXIT_OK};
+
+/// How handlers render results; chosen once per invocation from global flags.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputMode {
+    Plain,
+    Json,
+}
+
+/// `--json` is a global option (parsed by `options!`), so every command sees the same mode.
+pub fn output_mode() -> OutputMode {
+    if has_var("opt_json") {
+        OutputMode::Json
+    } else {
+        OutputMode::Plain
+    }
+}
+
+/// JSON shape of a stored key: `{"key": ..., "value": ..., "ttl": ...}`.
+///
+/// Values tagged `int`/`bool`/`json` are emitted natively and carry a `"type"`.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+pub struct EntryView<'a> {
+    pub key: String,
+    pub value: ValueView<'a>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<&'static str>,
+    /// Seconds until expiry, `null` for permanent keys.
+    pub ttl: Option<i64>,
+}
+
+impl<'a> EntryView<'a> {
+    pub fn new(entry: &'a KvEntry) -> Self {
+        Self {
+            key: entry.address.key_path(),
+            value: ValueView::new(entry),
+            value_type: entry.value_type.map(ValueType::as_str),
+            ttl: entry.ttl_remaining(SystemClock.now()),
+        }
+    }
+}
+
+/// A value as a JSON string, or as its native JSON form when typed.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde", untagged)]
+pub enum ValueView<'a> {
+    Text(&'a str),
+    Native(serde_json::Value),
+}
+
+impl<'a> ValueView<'a> {
+    fn new(entry: &'a KvEntry) -> Self {
+        match entry.value_type {
+            Some(ValueType::Int | ValueType::Bool | ValueType::Json) => {
+                serde_json::from_str(&entry.value)
+                    .map(ValueView::Native)
+                    .unwrap_or(ValueView::Text(&entry.value))
+            }
+            _ => ValueView::Text(&entry.value),
+        }
+    }
+}
+
+/// `keys --long` row: key metadata without the value itself.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+pub struct LongEntryView {
+    pub key: String,
+    /// Seconds until expiry, `null` for permanent keys.
+    pub ttl: Option<i64>,
+    /// Value length in bytes.
+    pub size: usize,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl LongEntryView {
+    pub const HEADERS: [&'static str; 5] = ["key", "ttl", "size", "created", "updated"];
+
+    pub fn new(entry: &KvEntry) -> Self {
+        Self {
+            key: entry.address.key_path(),
+            ttl: entry.ttl_remaining(SystemClock.now()),
+            size: entry.value.len(),
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+        }
+    }
+
+    /// Text columns matching `HEADERS`; permanent keys show `-` for TTL.
+    pub fn row(&self) -> Vec<String> {
+        vec![
+            self.key.clone(),
+            self.ttl
+                .map_or_else(|| "-".to_string(), |ttl| format!("{}s", ttl)),
+            self.size.to_string(),
+            format_timestamp(self.created_at),
+            format_timestamp(self.updated_at),
+        ]
+    }
+}
+
+/// `scan --databases --json` row: an entry and the database it was read from.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+pub struct DatabaseEntryView<'a> {
+    pub database: &'a str,
+    #[serde(flatten)]
+    pub entry: EntryView<'a>,
+}
+
+/// `search --json` row.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+pub struct SearchView<'a> {
+    pub address: String,
+    pub score: f64,
+    pub snippet: &'a str,
+    pub value: &'a str,
+}
+
+impl<'a> SearchView<'a> {
+    pub fn new(hit: &'a SearchHit) -> Self {
+        Self {
+            address: hit.entry.address.to_string(),
+            score: hit.score,
+            snippet: &hit.snippet,
+            value: &hit.entry.value,
+        }
+    }
+}
+
+/// `changes --json` row; `at` is rendered like every other timestamp.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+pub struct ChangeView<'a> {
+    pub seq: i64,
+    pub at: String,
+    pub op: &'a str,
+    pub address: &'a str,
+    pub value_hash: Option<&'a str>,
+    pub user: Option<&'a str>,
+}
+
+impl<'a> ChangeView<'a> {
+    pub fn new(entry: &'a OplogEntry) -> Self {
+        Self {
+            seq: entry.seq,
+            at: format_timestamp(entry.at),
+            op: &entry.op,
+            address: &entry.address,
+            value_hash: entry.value_hash.as_deref(),
+            user: entry.user.as_deref(),
+        }
+    }
+}
+
+/// `cursor show --json` object: the cursor fields plus where they were found.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+pub struct CursorView<'a> {
+    pub name: &'a str,
+    #[serde(flatten)]
+    pub data: &'a CursorData,
+    pub file: String,
+    pub legacy: bool,
+}
+
+impl<'a> CursorView<'a> {
+    pub fn new(name: &'a str, found: &'a FoundCursor) -> Self {
+        Self {
+            name,
+            data: &found.data,
+            file: found.file.display().to_string(),
+            legacy: found.legacy,
+        }
+    }
+
+    /// `field: value` lines for plain output; unset defaults show `-`.
+    pub fn lines(&self) -> Vec<(&'static str, String)> {
+        let or_dash = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        vec![
+            ("name", self.name.to_string()),
+            ("database", self.data.database_path.display().to_string()),
+            ("user", self.data.user.clone()),
+            ("created", self.data.created_at.clone()),
+            ("project", or_dash(&self.data.default_project)),
+            ("namespace", or_dash(&self.data.default_namespace)),
+            ("meta", or_dash(&self.data.meta_context)),
+            ("file", self.file.clone()),
+            (
+                "read-only",
+                if self.data.read_only { "yes" } else { "no" }.to_string(),
+            ),
+            ("legacy", if self.legacy { "yes" } else { "no" }.to_string()),
+        ]
+    }
+}
+
+/// `resolve` result: the address as typed and where it is stored.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+pub struct ResolvedView<'a> {
+    pub input: &'a str,
+    pub address: String,
+    pub project: &'a str,
+    pub namespace: &'a str,
+    pub key: &'a str,
+    pub context: Option<&'a str>,
+    pub layout: &'static str,
+    pub database: String,
+}
+
+impl<'a> ResolvedView<'a> {
+    pub fn new(input: &'a str, addr: &'a Address, layout: AddressLayout, database: &Path) -> Self {
+        Self {
+            input,
+            address: addr.to_string(),
+            project: &addr.project,
+            namespace: &addr.namespace,
+            key: &addr.key,
+            context: addr.context.as_deref(),
+            layout: layout.as_str(),
+            database: database.display().to_string(),
+        }
+    }
+
+    /// `field: value` lines for plain output; no context shows `-`.
+    pub fn lines(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("address", self.address.clone()),
+            ("project", self.project.to_string()),
+            ("namespace", self.namespace.to_string()),
+            ("key", self.key.to_string()),
+            ("context", self.context.unwrap_or("-").to_string()),
+            ("layout", self.layout.to_string()),
+            ("database", self.database.clone()),
+        ]
+    }
+}
+
+/// Print `value` as one line of JSON, returning the exit code for the handler.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> i32 {
+    match serde_json::to_string(value) {
+        Ok(json) => {
+            println!("{}", json);
+            EXIT_OK
+        }
+        Err(error) => {
+            eprintln!("json: {}", error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `--format` for row listings (`keys`, `scan`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ListFormat {
+    /// Legacy `key=value` lines (ambiguous when values contain `=` or newlines).
+    Plain,
+    Table,
+    Csv,
+    Tsv,
+}
+
+impl ListFormat {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "plain" => Some(Self::Plain),
+            "table" => Some(Self::Table),
+            "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            _ => None,
+        }
+    }
+}
+
+/// Print `rows` under `headers`; `Plain` joins the columns with `=` and prints no header.
+pub fn print_rows(format: ListFormat, headers: &[&str], rows: &[Vec<String>]) {
+    match format {
+        ListFormat::Plain => {
+            for row in rows {
+                println!("{}", row.join("="));
+            }
+        }
+        ListFormat::Csv => {
+            println!("{}", headers.join(","));
+            for row in rows {
+                let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                println!("{}", fields.join(","));
+            }
+        }
+        ListFormat::Tsv => {
+            println!("{}", headers.join("\t"));
+            for row in rows {
+                let fields: Vec<String> = row.iter().map(|field| escape_control(field)).collect();
+                println!("{}", fields.join("\t"));
+            }
+        }
+        ListFormat::Table => {
+            let rows: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| row.iter().map(|field| escape_control(field)).collect())
+                .collect();
+            let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+            for row in &rows {
+                for (width, field) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(field.chars().count());
+                }
+            }
+            let headers: Vec<String> = headers.iter().map(|header| header.to_uppercase()).collect();
+            print_table_row(&widths, &headers);
+            for row in &rows {
+                print_table_row(&widths, row);
+            }
+        }
+    }
+}
+
+fn print_table_row(widths: &[usize], fields: &[String]) {
+    let cells: Vec<String> = fields
+        .iter()
+        .zip(widths)
+        .map(|(field, width)| format!("{:<width$}", field, width = *width))
+        .collect();
+    println!("{}", cells.join("  ").trim_end());
+}
+
+/// RFC 4180 quoting: wrap in quotes when needed and double embedded quotes.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Backslash-escape tabs, newlines and backslashes so each row stays on one line.
+fn escape_control(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for ch in field.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+/// `--json` error object written to stderr.
+#[derive(Debug, Serialize)]
+#[serde(crate = "hub::serde")]
+struct ErrorView<'a> {
+    code: &'static str,
+    message: String,
+    command: &'a str,
+}
+
+/// Report a failed command: `command: error` text, or `{"code", "message", "command"}` under `--json`.
+pub fn report_error(command: &str, error: &CrudError) {
+    match output_mode() {
+        OutputMode::Json => {
+            let view = ErrorView {
+                code: ErrorCode::of(error).as_str(),
+                message: error.source().to_string(),
+                command,
+            };
+            match serde_json::to_string(&view) {
+                Ok(json) => eprintln!("{}", json),
+                Err(_) => eprintln!("{}: {}", command, error),
+            }
+        }
+        OutputMode::Plain => eprintln!("{}: {}", command, error),
+    }
+}
@@ -0,0 +1,291 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::ControlFlow;
+use std::thread;
+
+use crate::lib::adpt::sqlite::{Clock, SystemClock};
+use crate::lib::api::{ErrorCode, Session};
+use crate::lib::core::crud::CrudError;
+
+use super::context::EXIT_ERROR;
+use super::serve::Gate;
+use super::verbosity::say;
+
+/// `serve --resp` without an address; one port above Redis so both can run.
+pub const DEFAULT_RESP_ADDR: &str = "127.0.0.1:6380";
+
+/// Largest bulk string accepted from a client (512 MiB, as in Redis).
+const MAX_BULK_BYTES: usize = 512 * 1024 * 1024;
+
+/// One parsed client command.
+type RespCommand = Vec<String>;
+
+/// `serve --resp [HOST:PORT]` — a Redis (RESP2) subset for local development.
+///
+/// Redis keys are ProntoDB addresses (`project.namespace.key`, or shorter
+/// forms resolved with the cursor defaults) and `KEYS` patterns are scopes,
+/// with `*` meaning every key. Supported: `GET`, `SET [EX s|PX ms]`, `DEL`,
+/// `EXISTS`, `KEYS`, `EXPIRE`, `TTL`, `PING`, `QUIT` and an empty `COMMAND`
+/// reply for `redis-cli`. Errors carry the stable code: `-NS_NOT_TTL ...`.
+///
+/// Clients must `AUTH <token>` (or `AUTH <user> <token>`, the user being
+/// ignored) with an API token first; until then everything but `PING`,
+/// `QUIT` and `COMMAND` answers `-NOAUTH`, as Redis does.
+pub fn serve_resp(gate: Gate, addr: &str) -> i32 {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("serve: {}: {}", addr, error);
+            return EXIT_ERROR;
+        }
+    };
+    say(format!(
+        "Serving {} over RESP on {}",
+        gate.config().database_path().display(),
+        addr
+    ));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let gate = gate.clone();
+                thread::spawn(move || {
+                    if let Err(error) = serve_client(&gate, stream) {
+                        eprintln!("serve: client: {}", error);
+                    }
+                });
+            }
+            Err(error) => eprintln!("serve: accept: {}", error),
+        }
+    }
+    EXIT_ERROR
+}
+
+fn serve_client(gate: &Gate, stream: TcpStream) -> io::Result<()> {
+    let mut out = io::BufWriter::new(stream.try_clone()?);
+    let mut reader = BufReader::new(stream);
+    let mut session = None;
+    while let Some(command) = read_command(&mut reader)? {
+        if command.is_empty() {
+            continue;
+        }
+        if command[0].eq_ignore_ascii_case("AUTH") {
+            authenticate(gate, &mut session, &command[1..], &mut out)?;
+            out.flush()?;
+            continue;
+        }
+        let keep_open = match &session {
+            Some(session) => respond(session, &command, &mut out)?,
+            None => unauthenticated(&command, &mut out)?,
+        };
+        out.flush()?;
+        if !keep_open {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The next `*N` array of bulk strings, or an inline command; `None` at end of stream.
+fn read_command<R: BufRead>(reader: &mut R) -> io::Result<Option<RespCommand>> {
+    let header = match read_line(reader)? {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+    let count = match header.strip_prefix('*') {
+        Some(count) => parse_length(count)?,
+        None => {
+            return Ok(Some(
+                header.split_whitespace().map(str::to_string).collect(),
+            ))
+        }
+    };
+    let mut command = Vec::with_capacity(count);
+    for _ in 0..count {
+        let length = match read_line(reader)? {
+            Some(line) => match line.strip_prefix('$') {
+                Some(length) => parse_length(length)?,
+                None => return Err(protocol_error("expected a $ bulk string")),
+            },
+            None => return Ok(None),
+        };
+        if length > MAX_BULK_BYTES {
+            return Err(protocol_error("bulk string too large"));
+        }
+        let mut bulk = vec![0; length + 2];
+        reader.read_exact(&mut bulk)?;
+        bulk.truncate(length);
+        command.push(String::from_utf8_lossy(&bulk).into_owned());
+    }
+    Ok(Some(command))
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn parse_length(raw: &str) -> io::Result<usize> {
+    raw.parse()
+        .map_err(|_| protocol_error("invalid length in request"))
+}
+
+fn protocol_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// `AUTH [user] token`: replace the client's session with one for `token`.
+fn authenticate<W: Write>(
+    gate: &Gate,
+    session: &mut Option<Session>,
+    args: &[String],
+    out: &mut W,
+) -> io::Result<()> {
+    *session = None;
+    let token = match args {
+        [token] | [_, token] => token,
+        _ => return write!(out, "-ERR wrong number of arguments for 'auth' command\r\n"),
+    };
+    match gate.open(Some(token.as_str())) {
+        Ok(opened) => {
+            *session = Some(opened);
+            write!(out, "+OK\r\n")
+        }
+        Err(error) => write_error(out, &error),
+    }
+}
+
+/// Reply to a command sent before `AUTH`; only the connection commands work.
+fn unauthenticated<W: Write>(command: &[String], out: &mut W) -> io::Result<bool> {
+    match (command[0].to_ascii_uppercase().as_str(), &command[1..]) {
+        ("PING", []) => write!(out, "+PONG\r\n")?,
+        ("QUIT", _) => {
+            write!(out, "+OK\r\n")?;
+            return Ok(false);
+        }
+        ("COMMAND", _) => write!(out, "*0\r\n")?,
+        _ => write!(out, "-NOAUTH Authentication required.\r\n")?,
+    }
+    Ok(true)
+}
+
+/// Reply to one command; `false` once the client sent `QUIT`.
+fn respond<W: Write>(session: &Session, command: &[String], out: &mut W) -> io::Result<bool> {
+    let name = command[0].to_ascii_uppercase();
+    let args = &command[1..];
+    match (name.as_str(), args) {
+        ("PING", []) => write!(out, "+PONG\r\n")?,
+        ("PING", [message]) => write_bulk(out, Some(message))?,
+        ("QUIT", _) => {
+            write!(out, "+OK\r\n")?;
+            return Ok(false);
+        }
+        ("COMMAND", _) => write!(out, "*0\r\n")?,
+        ("GET", [key]) => match session.get(key) {
+            Ok(value) => write_bulk(out, value.as_deref())?,
+            Err(error) => write_error(out, &error)?,
+        },
+        ("SET", [key, value, options @ ..]) => match set_ttl(options) {
+            Ok(ttl_secs) => match session.set(key, value, ttl_secs) {
+                Ok(()) => write!(out, "+OK\r\n")?,
+                Err(error) => write_error(out, &error)?,
+            },
+            Err(message) => write!(out, "-ERR {}\r\n", message)?,
+        },
+        ("DEL", keys) | ("EXISTS", keys) if !keys.is_empty() => {
+            let mut count = 0;
+            for key in keys {
+                let result = if name == "DEL" {
+                    session.delete(key)
+                } else {
+                    session.get_entry(key).map(|entry| entry.is_some())
+                };
+                match result {
+                    Ok(true) => count += 1,
+                    Ok(false) => {}
+                    Err(error) => {
+                        write_error(out, &error)?;
+                        return Ok(true);
+                    }
+                }
+            }
+            write!(out, ":{}\r\n", count)?;
+        }
+        ("KEYS", [pattern]) => {
+            let scope = if pattern == "*" { "*.*.*" } else { pattern };
+            let mut keys = Vec::new();
+            let scanned = session.scan(scope, |entry| {
+                keys.push(entry.address.to_string());
+                ControlFlow::Continue(())
+            });
+            match scanned {
+                Ok(()) => {
+                    write!(out, "*{}\r\n", keys.len())?;
+                    for key in &keys {
+                        write_bulk(out, Some(key))?;
+                    }
+                }
+                Err(error) => write_error(out, &error)?,
+            }
+        }
+        ("EXPIRE", [key, secs]) => match secs.parse::<u64>() {
+            Ok(secs) => match session.expire(key, secs) {
+                Ok(found) => write!(out, ":{}\r\n", u8::from(found))?,
+                Err(error) => write_error(out, &error)?,
+            },
+            Err(_) => write!(out, "-ERR value is not an integer or out of range\r\n")?,
+        },
+        ("TTL", [key]) => match session.get_entry(key) {
+            Ok(Some(entry)) => {
+                let ttl = entry.ttl_remaining(SystemClock.now()).unwrap_or(-1);
+                write!(out, ":{}\r\n", ttl)?
+            }
+            Ok(None) => write!(out, ":-2\r\n")?,
+            Err(error) => write_error(out, &error)?,
+        },
+        ("GET" | "SET" | "DEL" | "EXISTS" | "KEYS" | "EXPIRE" | "TTL", _) => write!(
+            out,
+            "-ERR wrong number of arguments for '{}' command\r\n",
+            name.to_ascii_lowercase()
+        )?,
+        _ => write!(out, "-ERR unknown command '{}'\r\n", command[0])?,
+    }
+    Ok(true)
+}
+
+/// The TTL of `SET key value [EX seconds | PX milliseconds]`.
+fn set_ttl(options: &[String]) -> Result<Option<u64>, String> {
+    match options {
+        [] => Ok(None),
+        [unit, amount] => {
+            let amount = amount
+                .parse::<u64>()
+                .map_err(|_| "value is not an integer or out of range".to_string())?;
+            match unit.to_ascii_uppercase().as_str() {
+                "EX" => Ok(Some(amount)),
+                // Stored TTLs are whole seconds; rounding up never expires a key early.
+                "PX" => Ok(Some(amount.div_ceil(1000))),
+                _ => Err(format!(
+                    "SET option '{}' is not supported (use EX or PX)",
+                    unit
+                )),
+            }
+        }
+        _ => Err("SET supports only EX seconds or PX milliseconds".to_string()),
+    }
+}
+
+fn write_bulk<W: Write>(out: &mut W, value: Option<&str>) -> io::Result<()> {
+    match value {
+        Some(value) => write!(out, "${}\r\n{}\r\n", value.len(), value),
+        None => write!(out, "$-1\r\n"),
+    }
+}
+
+fn write_error<W: Write>(out: &mut W, error: &CrudError) -> io::Result<()> {
+    let message = error.source().to_string().replace(['\r', '\n'], " ");
+    write!(out, "-{} {}\r\n", ErrorCode::of(error).as_str(), message)
+}
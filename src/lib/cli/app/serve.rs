@@ -0,0 +1,399 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::ops::ControlFlow;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::thread;
+
+use rsb::prelude::*;
+
+use crate::lib::adpt::sqlite::SqliteConnectionConfig;
+use crate::lib::api::{ErrorCode, Session};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+
+#[cfg(feature = "grpc")]
+use super::context::EXIT_OK;
+use super::context::{connection_config, CommandArgs, EXIT_ERROR};
+use super::listing::masked;
+use super::notify::Notifier;
+use super::output::report_error;
+use super::resp::{serve_resp, DEFAULT_RESP_ADDR};
+use super::ui::{serve_ui, DEFAULT_UI_ADDR};
+use super::verbosity::say;
+
+/// `serve --socket PATH` — answer line requests on a unix socket from one open keystore.
+///
+/// One request per line; values use `\n`, `\r` and `\\` escapes both ways:
+///
+/// - `AUTH token` → `OK`; required before any request below but `PING`
+/// - `GET addr` → `OK value` or `MISS`
+/// - `SET addr value`, `SETEX addr secs value` → `OK`
+/// - `DEL addr` → `OK` or `MISS`
+/// - `SCAN scope` → `addr=value` lines (secrets masked as in `scan`), then `END`
+/// - `PING` → `PONG`; `QUIT` closes the connection
+///
+/// Failures answer `ERR CODE message`. The socket is created mode 0600.
+///
+/// Every protocol runs each client as the API token it presents (see
+/// `Gate`): `AUTH` here and for `--resp`, `authorization` metadata for
+/// `--grpc`, an `Authorization: Bearer` header for `--ui`.
+///
+/// `serve --grpc HOST:PORT` serves `proto/prontodb.proto` instead (`grpc`
+/// feature), `serve --resp [HOST:PORT]` a Redis subset (see `resp`) and
+/// `serve --ui [HOST:PORT]` a browser page over JSON routes (see `ui`).
+/// With any of them, `--notify PATH` publishes each write on a second
+/// socket for `prontodb subscribe` (see `notify`).
+pub fn do_serve(args: Args) -> i32 {
+    // `--resp` and `--ui` take an optional address, so it arrives as the one positional.
+    let parsed = CommandArgs::parse(&args, &["socket", "grpc", "notify"]);
+    let non_empty = |name: &str| parsed.flag(name).filter(|value| !value.is_empty());
+    let optional_addr = |name: &str, default| match parsed.positional.as_slice() {
+        [] => Some(non_empty(name).unwrap_or(default)),
+        [addr] if non_empty(name).is_none() => Some(addr.as_str()),
+        _ => None,
+    };
+    let listen = match (
+        non_empty("socket"),
+        non_empty("grpc"),
+        parsed.has_flag("resp"),
+        parsed.has_flag("ui"),
+    ) {
+        (Some(path), None, false, false) if parsed.positional.is_empty() => {
+            Some(Listen::Socket(path))
+        }
+        (None, Some(addr), false, false) if parsed.positional.is_empty() => {
+            Some(Listen::Grpc(addr))
+        }
+        (None, None, true, false) => optional_addr("resp", DEFAULT_RESP_ADDR).map(Listen::Resp),
+        (None, None, false, true) => optional_addr("ui", DEFAULT_UI_ADDR).map(Listen::Ui),
+        _ => None,
+    };
+    let listen = match listen {
+        Some(listen) => listen,
+        None => {
+            eprintln!(
+                "Usage: prontodb serve --socket PATH | --grpc HOST:PORT | --resp [HOST:PORT] | --ui [HOST:PORT] [--notify PATH]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+
+    // Opened once so a bad database fails here, not on the first client;
+    // client sessions then borrow pooled connections instead of reopening.
+    let config = match Session::open(&connection_config().with_connection_reuse(true)) {
+        Ok(session) => session.config().clone(),
+        Err(error) => {
+            report_error("serve", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let mut gate = Gate {
+        config,
+        notifier: None,
+    };
+    if let Some(path) = non_empty("notify") {
+        match bind(Path::new(path)) {
+            Ok(listener) => gate.notifier = Some(Notifier::start(listener)),
+            Err(message) => {
+                eprintln!("serve: {}: {}", path, message);
+                return EXIT_ERROR;
+            }
+        }
+    }
+    match listen {
+        Listen::Socket(path) => serve_socket(gate, path),
+        Listen::Grpc(addr) => serve_grpc_on(gate, addr),
+        Listen::Resp(addr) => serve_resp(gate, addr),
+        Listen::Ui(addr) => serve_ui(gate, addr),
+    }
+}
+
+/// How `serve` clients get a session: only by presenting an API token.
+///
+/// Requests never run as the server process. A session opened here carries
+/// the client's token, so the keystore refuses unknown or revoked tokens and
+/// limits the rest to their `project.namespace` scope (`prontodb-admin
+/// --token-create`); a client without a token is refused outright.
+#[derive(Clone)]
+pub(super) struct Gate {
+    config: SqliteConnectionConfig,
+    notifier: Option<Notifier>,
+}
+
+impl Gate {
+    /// The database config, without any client's token.
+    pub(super) fn config(&self) -> &SqliteConnectionConfig {
+        &self.config
+    }
+
+    /// `config` as the client presenting `token` sees it; no token is `PermissionDenied`.
+    pub(super) fn token_config(&self, token: Option<&str>) -> CrudResult<SqliteConnectionConfig> {
+        match token.map(str::trim).filter(|token| !token.is_empty()) {
+            Some(token) => Ok(self.config.clone().with_token(Some(token))),
+            None => Err(CrudError::permission_denied(
+                CrudDomain::Sqlite,
+                CrudObjectKind::Record,
+                CrudVerb::Read,
+                "an API token is required",
+            )),
+        }
+    }
+
+    /// A session for the client presenting `token`, publishing its writes to `--notify`.
+    pub(super) fn open(&self, token: Option<&str>) -> CrudResult<Session> {
+        let mut session = Session::open(&self.token_config(token)?)?;
+        if let Some(notifier) = self.notifier.clone() {
+            session.on_change(move |change| notifier.publish(change));
+        }
+        Ok(session)
+    }
+}
+
+/// The token of an `Authorization` value: `Bearer pdb_...`, or the bare token.
+pub(super) fn bearer_token(value: &str) -> &str {
+    let value = value.trim();
+    match value.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.trim(),
+        _ => value,
+    }
+}
+
+/// Where `serve` listens, and which protocol it speaks there.
+enum Listen<'a> {
+    Socket(&'a str),
+    Grpc(&'a str),
+    Resp(&'a str),
+    Ui(&'a str),
+}
+
+fn serve_socket(gate: Gate, socket: &str) -> i32 {
+    let listener = match bind(Path::new(socket)) {
+        Ok(listener) => listener,
+        Err(message) => {
+            eprintln!("serve: {}: {}", socket, message);
+            return EXIT_ERROR;
+        }
+    };
+    say(format!(
+        "Serving {} on {}",
+        gate.config().database_path().display(),
+        socket
+    ));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let gate = gate.clone();
+                thread::spawn(move || {
+                    if let Err(error) = serve_client(&gate, stream) {
+                        eprintln!("serve: client: {}", error);
+                    }
+                });
+            }
+            Err(error) => eprintln!("serve: accept: {}", error),
+        }
+    }
+    EXIT_ERROR
+}
+
+#[cfg(feature = "grpc")]
+fn serve_grpc_on(gate: Gate, addr: &str) -> i32 {
+    let addr = match addr.parse() {
+        Ok(addr) => addr,
+        Err(_) => {
+            eprintln!("serve: --grpc expects HOST:PORT, such as 127.0.0.1:50051");
+            return EXIT_ERROR;
+        }
+    };
+    say(format!(
+        "Serving {} over gRPC on {}",
+        gate.config().database_path().display(),
+        addr
+    ));
+    match super::grpc::serve_grpc(gate, addr) {
+        Ok(()) => EXIT_OK,
+        Err(message) => {
+            eprintln!("serve: {}", message);
+            EXIT_ERROR
+        }
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+fn serve_grpc_on(_gate: Gate, _addr: &str) -> i32 {
+    eprintln!("serve: --grpc needs prontodb built with the grpc feature");
+    EXIT_ERROR
+}
+
+/// Listen on `path`, replacing a stale socket left by a previous `serve`.
+fn bind(path: &Path) -> Result<UnixListener, String> {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err("exists and is not a socket".to_string());
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err("another server is already listening".to_string());
+        }
+        fs::remove_file(path).map_err(|err| err.to_string())?;
+    }
+    let listener = UnixListener::bind(path).map_err(|err| err.to_string())?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|err| err.to_string())?;
+    Ok(listener)
+}
+
+fn serve_client(gate: &Gate, stream: UnixStream) -> io::Result<()> {
+    let mut out = io::BufWriter::new(stream.try_clone()?);
+    let mut session = None;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let keep_open = respond(gate, &mut session, line.trim_end_matches('\r'), &mut out)?;
+        out.flush()?;
+        if !keep_open {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Answer one request line; `false` once the client asked to `QUIT`.
+///
+/// `session` is the client's, set by `AUTH`; a failed `AUTH` clears it.
+fn respond<W: Write>(
+    gate: &Gate,
+    session: &mut Option<Session>,
+    line: &str,
+    out: &mut W,
+) -> io::Result<bool> {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let command = command.to_ascii_uppercase();
+    if command == "AUTH" {
+        *session = None;
+        match gate.open(Some(rest)) {
+            Ok(opened) => {
+                *session = Some(opened);
+                writeln!(out, "OK")?;
+            }
+            Err(error) => write_error(out, &error)?,
+        }
+        return Ok(true);
+    }
+    let session = match (command.as_str(), session.as_ref()) {
+        ("PING", _) => {
+            writeln!(out, "PONG")?;
+            return Ok(true);
+        }
+        ("QUIT", _) => return Ok(false),
+        (_, Some(session)) => session,
+        (_, None) => {
+            writeln!(out, "ERR PERMISSION_DENIED send AUTH <token> first")?;
+            return Ok(true);
+        }
+    };
+    match command.as_str() {
+        "GET" if !rest.is_empty() => match session.get(rest) {
+            Ok(Some(value)) => writeln!(out, "OK {}", escape(&value))?,
+            Ok(None) => writeln!(out, "MISS")?,
+            Err(error) => write_error(out, &error)?,
+        },
+        "SET" => match rest.split_once(' ') {
+            Some((address, value)) => {
+                write_result(out, session.set(address, &unescape(value), None))?
+            }
+            None => writeln!(out, "ERR INVALID_INPUT usage: SET addr value")?,
+        },
+        "SETEX" => {
+            let mut parts = rest.splitn(3, ' ');
+            match (
+                parts.next(),
+                parts.next().and_then(|secs| secs.parse::<u64>().ok()),
+                parts.next(),
+            ) {
+                (Some(address), Some(secs), Some(value)) => {
+                    write_result(out, session.set(address, &unescape(value), Some(secs)))?
+                }
+                _ => writeln!(out, "ERR INVALID_INPUT usage: SETEX addr secs value")?,
+            }
+        }
+        "DEL" if !rest.is_empty() => match session.delete(rest) {
+            Ok(true) => writeln!(out, "OK")?,
+            Ok(false) => writeln!(out, "MISS")?,
+            Err(error) => write_error(out, &error)?,
+        },
+        "SCAN" if !rest.is_empty() => {
+            let secrets = match session.secret_marks() {
+                Ok(secrets) => secrets,
+                Err(error) => {
+                    write_error(out, &error)?;
+                    return Ok(true);
+                }
+            };
+            let mut lines = Vec::new();
+            let scanned = session.scan(rest, |entry| {
+                let entry = masked(entry, &secrets);
+                lines.push(format!("{}={}", entry.address, escape(&entry.value)));
+                ControlFlow::Continue(())
+            });
+            match scanned {
+                Ok(()) => {
+                    for line in lines {
+                        writeln!(out, "{}", line)?;
+                    }
+                    writeln!(out, "END")?;
+                }
+                Err(error) => write_error(out, &error)?,
+            }
+        }
+        _ => writeln!(
+            out,
+            "ERR INVALID_INPUT unknown request (AUTH, GET, SET, SETEX, DEL, SCAN, PING, QUIT)"
+        )?,
+    }
+    Ok(true)
+}
+
+fn write_result<W: Write>(out: &mut W, result: Result<(), CrudError>) -> io::Result<()> {
+    match result {
+        Ok(()) => writeln!(out, "OK"),
+        Err(error) => write_error(out, &error),
+    }
+}
+
+fn write_error<W: Write>(out: &mut W, error: &CrudError) -> io::Result<()> {
+    writeln!(
+        out,
+        "ERR {} {}",
+        ErrorCode::of(error).as_str(),
+        escape(&error.source().to_string())
+    )
+}
+
+/// `raw` on one line: `\`, newline and carriage return escaped.
+pub(super) fn escape(raw: &str) -> String {
+    raw.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Reverse of `escape`; an unknown escape is kept as written.
+pub(super) fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            value.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('\\') => value.push('\\'),
+            Some(other) => {
+                value.push('\\');
+                value.push(other);
+            }
+            None => value.push('\\'),
+        }
+    }
+    value
+}
@@ -0,0 +1,277 @@
+use std::io::{self, Read};
+
+use rsb::prelude::*;
+
+use crate::lib::addr::DEFAULT_NS_DELIM;
+use crate::lib::adpt::sqlite::ImportStrategy;
+use crate::lib::api;
+
+use super::context::{connection_config, CommandArgs, EXIT_ERROR, EXIT_OK};
+use super::output::report_error;
+use super::verbosity::say;
+
+/// Largest input `stream` and `load` read from stdin (10 MiB).
+const MAX_STREAM_BYTES: u64 = 10 * 1024 * 1024;
+
+/// A parsed token stream: the writes in order, and the `sec:` identity they run as.
+#[derive(Debug, Default)]
+struct TokenStream {
+    /// `(address, value, ttl)` rows handed to `api::import_values`.
+    rows: Vec<(String, String, Option<u64>)>,
+    user: Option<String>,
+    token: Option<String>,
+}
+
+/// `stream` — apply `key=value;` tokens from stdin in one transaction.
+///
+/// `ns=project.namespace` switches the namespace of the keys after it and
+/// `other.ns:key=value` overrides it for one token. `meta:ttl=N` gives the
+/// following keys a TTL (`meta:ttl=` clears it). `sec:user=U` and
+/// `sec:token=pdb_...` set the identity the writes are authorized as and
+/// must come before the first key; `sec:user` counts only when the database
+/// owner runs the stream. A bad token or address stores nothing.
+pub fn do_stream(args: Args) -> i32 {
+    if !CommandArgs::parse(&args, &[]).positional.is_empty() {
+        eprintln!("Usage: echo 'ns=app.cfg; host=localhost; port=8080;' | prontodb stream");
+        return EXIT_ERROR;
+    }
+
+    let input = match read_stdin() {
+        Ok(input) => input,
+        Err(message) => {
+            eprintln!("stream: {}", message);
+            return EXIT_ERROR;
+        }
+    };
+    let stream = match parse_stream(&input) {
+        Ok(stream) if stream.rows.is_empty() => {
+            eprintln!("stream: no keys in the input (expected key=value; tokens)");
+            return EXIT_ERROR;
+        }
+        Ok(stream) => stream,
+        Err(message) => {
+            eprintln!("stream: {}", message);
+            return EXIT_ERROR;
+        }
+    };
+
+    let mut config = connection_config();
+    if stream.user.is_some() {
+        config = config.with_user(stream.user.clone());
+    }
+    if stream.token.is_some() {
+        config = config.with_token(stream.token.clone());
+    }
+    match api::import_values(&config, &stream.rows, ImportStrategy::Overwrite) {
+        Ok(report) => {
+            say(format!("Stored {} keys from the stream", report.inserted));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("stream", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `load [-p PROJECT] [-n NAMESPACE]` — store `key=value` lines from stdin in one transaction.
+///
+/// Bare keys land in `-p`/`-n` (resolved like `set` addresses when omitted);
+/// keys with a `.` are full addresses. Values run to the end of the line.
+pub fn do_load(args: Args) -> i32 {
+    let parsed = CommandArgs::parse_with_short(
+        &args,
+        &["project", "namespace"],
+        &[("-p", "project"), ("-n", "namespace")],
+    );
+    if !parsed.positional.is_empty() {
+        eprintln!(
+            "Usage: printf 'host=localhost\\nport=8080\\n' | prontodb load [-p PROJECT] [-n NS]"
+        );
+        return EXIT_ERROR;
+    }
+    let prefix = match (parsed.flag("project"), parsed.flag("namespace")) {
+        (Some(project), Some(namespace)) => format!(
+            "{}{}{}{}",
+            project, DEFAULT_NS_DELIM, namespace, DEFAULT_NS_DELIM
+        ),
+        (None, Some(namespace)) => format!("{}{}", namespace, DEFAULT_NS_DELIM),
+        (Some(_), None) => {
+            eprintln!("load: -p needs -n NAMESPACE as well");
+            return EXIT_ERROR;
+        }
+        (None, None) => String::new(),
+    };
+
+    let input = match read_stdin() {
+        Ok(input) => input,
+        Err(message) => {
+            eprintln!("load: {}", message);
+            return EXIT_ERROR;
+        }
+    };
+    let mut entries = Vec::new();
+    for (index, raw) in input.lines().enumerate() {
+        let line = raw.trim_start();
+        if line.trim_end().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => (key.trim(), value),
+            _ => {
+                eprintln!("load: line {}: expected key=value", index + 1);
+                return EXIT_ERROR;
+            }
+        };
+        let address = if key.contains(DEFAULT_NS_DELIM) {
+            key.to_string()
+        } else {
+            format!("{}{}", prefix, key)
+        };
+        entries.push((address, value.to_string()));
+    }
+
+    match api::set_values(&connection_config(), &entries) {
+        Ok(count) => {
+            say(format!("Loaded {} keys", count));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("load", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// All of stdin, refused past `MAX_STREAM_BYTES`.
+fn read_stdin() -> Result<String, String> {
+    let mut input = String::new();
+    io::stdin()
+        .take(MAX_STREAM_BYTES + 1)
+        .read_to_string(&mut input)
+        .map_err(|error| error.to_string())?;
+    if input.len() as u64 > MAX_STREAM_BYTES {
+        return Err(format!(
+            "input exceeds {} MiB",
+            MAX_STREAM_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(input)
+}
+
+fn parse_stream(input: &str) -> Result<TokenStream, String> {
+    let mut stream = TokenStream::default();
+    let mut namespace: Option<String> = None;
+    let mut ttl: Option<u64> = None;
+    for (index, token) in split_tokens(input)?.iter().enumerate() {
+        let position = index + 1;
+        let (name, value) = token
+            .split_once('=')
+            .ok_or_else(|| format!("token {}: expected key=value, got '{}'", position, token))?;
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!(
+                "token {}: '{}' is not a key (no spaces around '=')",
+                position, name
+            ));
+        }
+        let value = unquote(value);
+        match name.split_once(':') {
+            Some(("meta", "ttl")) => {
+                ttl = match value.as_str() {
+                    "" => None,
+                    secs => Some(secs.parse::<u64>().map_err(|_| {
+                        format!("token {}: meta:ttl must be a whole number", position)
+                    })?),
+                };
+            }
+            Some(("meta", other)) => {
+                return Err(format!(
+                    "token {}: unknown directive meta:{} (expected meta:ttl)",
+                    position, other
+                ));
+            }
+            Some(("sec", directive)) => {
+                if !stream.rows.is_empty() {
+                    return Err(format!(
+                        "token {}: sec:{} must come before the first key",
+                        position, directive
+                    ));
+                }
+                match directive {
+                    "user" => stream.user = Some(value),
+                    "token" => stream.token = Some(value),
+                    other => {
+                        return Err(format!(
+                            "token {}: unknown directive sec:{} (expected sec:user or sec:token)",
+                            position, other
+                        ))
+                    }
+                }
+            }
+            Some((scope, key)) => {
+                stream.rows.push((format!("{}.{}", scope, key), value, ttl));
+            }
+            None if name == "ns" => {
+                namespace = Some(value).filter(|namespace| !namespace.is_empty());
+            }
+            None => {
+                let address = match &namespace {
+                    Some(namespace) => format!("{}.{}", namespace, name),
+                    None => name.to_string(),
+                };
+                stream.rows.push((address, value, ttl));
+            }
+        }
+    }
+    Ok(stream)
+}
+
+/// Tokens separated by `;` or newlines, outside values quoted with `"` or `'`.
+fn split_tokens(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for ch in input.chars() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => {
+                quote = None;
+                current.push(ch);
+            }
+            (Some(_), ch) => current.push(ch),
+            (None, '"' | '\'') if current.ends_with('=') => {
+                quote = Some(ch);
+                current.push(ch);
+            }
+            (None, ';' | '\n') => {
+                push_token(&mut tokens, &current);
+                current.clear();
+            }
+            (None, ch) => current.push(ch),
+        }
+    }
+    if let Some(open) = quote {
+        return Err(format!("unterminated {} quote", open));
+    }
+    push_token(&mut tokens, &current);
+    Ok(tokens)
+}
+
+fn push_token(tokens: &mut Vec<String>, raw: &str) {
+    let token = raw.trim();
+    if !token.is_empty() && !token.starts_with('#') {
+        tokens.push(token.to_string());
+    }
+}
+
+/// `value` without one pair of surrounding `"` or `'` quotes.
+fn unquote(value: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|rest| rest.strip_suffix(quote))
+        {
+            return inner.to_string();
+        }
+    }
+    value.to_string()
+}
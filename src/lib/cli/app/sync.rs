@@ -0,0 +1,159 @@
+use std::io::{self, BufRead, Write};
+
+use rsb::prelude::*;
+
+use crate::lib::adpt::sqlite::KvEntry;
+use crate::lib::api::{self, MergeSide, MergeStrategy, SyncDirection};
+use crate::lib::core::helpers::format_timestamp;
+
+use super::context::{
+    connection_config, peer_database_path, CommandArgs, EXIT_CONFLICT, EXIT_ERROR, EXIT_OK,
+};
+use super::output::report_error;
+use super::verbosity::say;
+
+/// `sync push --to <cursor|path>`, `sync pull --from <cursor|path>`,
+/// `sync merge <cursor|path> [--strategy newest|ours|theirs|interactive]`
+///
+/// Push and pull copy the keys updated since the last push to (or pull
+/// from) that database, a cursor name or a file; the first run copies
+/// everything. Edits that are newer on the receiving side are kept, and
+/// deletions are not carried.
+///
+/// `merge` brings both databases in step and prints a line per conflict:
+/// a key both changed since they last merged. `--strategy` (default
+/// `newest`) picks the row to keep; `interactive` asks for each. Exits 3
+/// when conflicts were left unresolved.
+pub fn do_sync(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["to", "from", "strategy"]);
+    if let [sub, other] = parsed.positional.as_slice() {
+        if sub == "merge" {
+            return do_sync_merge(&parsed, other);
+        }
+    }
+    let (direction, target) = match (
+        parsed.positional.as_slice(),
+        parsed.flag("to"),
+        parsed.flag("from"),
+    ) {
+        ([sub], Some(to), None) if sub == "push" => (SyncDirection::Push, to),
+        ([sub], None, Some(from)) if sub == "pull" => (SyncDirection::Pull, from),
+        _ => {
+            eprintln!("Usage: prontodb sync push --to <cursor|path> | pull --from <cursor|path> | merge <cursor|path> [--strategy S]");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config();
+    let synced = peer_database_path(target).and_then(|peer| {
+        api::sync_database(&config, &peer, direction).map(|report| (peer, report))
+    });
+    match synced {
+        Ok((peer, report)) => {
+            let (verb, preposition) = match direction {
+                SyncDirection::Push => ("Pushed", "to"),
+                SyncDirection::Pull => ("Pulled", "from"),
+            };
+            say(format!(
+                "{} {} keys {} {} ({} skipped)",
+                verb,
+                report.copied,
+                preposition,
+                peer.display(),
+                report.skipped
+            ));
+            EXIT_OK
+        }
+        Err(error) => {
+            report_error("sync", &error);
+            EXIT_ERROR
+        }
+    }
+}
+
+/// `sync merge OTHER`: reconcile both databases, one line per conflict, then the totals.
+fn do_sync_merge(parsed: &CommandArgs, other: &str) -> i32 {
+    let strategy = match parsed.flag("strategy").unwrap_or("newest") {
+        "newest" => Some(MergeStrategy::Newest),
+        "ours" => Some(MergeStrategy::Ours),
+        "theirs" => Some(MergeStrategy::Theirs),
+        "interactive" => None,
+        unknown => {
+            eprintln!(
+                "prontodb sync: unknown --strategy '{}' (expected newest|ours|theirs|interactive)",
+                unknown
+            );
+            return EXIT_ERROR;
+        }
+    };
+    if strategy.is_none() && !atty::is(atty::Stream::Stdin) {
+        eprintln!("prontodb sync: --strategy interactive needs a terminal");
+        return EXIT_ERROR;
+    }
+    let config = connection_config();
+    let merged = peer_database_path(other).and_then(|peer| {
+        api::merge_databases(&config, &peer, |ours, theirs| match strategy {
+            Some(strategy) => Some(strategy.resolve(ours, theirs)),
+            None => ask_side(ours, theirs),
+        })
+        .map(|report| (peer, report))
+    });
+    let (peer, report) = match merged {
+        Ok(merged) => merged,
+        Err(error) => {
+            report_error("sync", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let mut unresolved = 0;
+    for conflict in &report.conflicts {
+        let outcome = match conflict.resolved {
+            Some(MergeSide::Ours) => "kept ours",
+            Some(MergeSide::Theirs) => "kept theirs",
+            None => {
+                unresolved += 1;
+                "left as it is on each side"
+            }
+        };
+        println!("! {} {}", conflict.ours.address, outcome);
+    }
+    say(format!(
+        "Merged with {}: {} keys in, {} keys out, {} conflicts",
+        peer.display(),
+        report.to_ours,
+        report.to_theirs,
+        report.conflicts.len()
+    ));
+    if unresolved > 0 {
+        EXIT_CONFLICT
+    } else {
+        EXIT_OK
+    }
+}
+
+/// Ask on the terminal which row of a conflicting key to keep; `None` to skip it.
+fn ask_side(ours: &KvEntry, theirs: &KvEntry) -> Option<MergeSide> {
+    eprintln!("{}", ours.address);
+    for (side, entry) in [("ours", ours), ("theirs", theirs)] {
+        eprintln!(
+            "  {:<7}{} (updated {})",
+            side,
+            entry.value,
+            format_timestamp(entry.updated_at)
+        );
+    }
+    loop {
+        eprint!("Keep [o]urs, [t]heirs or [s]kip? ");
+        let _ = io::stderr().flush();
+        let mut answer = String::new();
+        match io::stdin().lock().read_line(&mut answer) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) => {}
+        }
+        match answer.trim() {
+            "o" | "ours" => return Some(MergeSide::Ours),
+            "t" | "theirs" => return Some(MergeSide::Theirs),
+            "s" | "skip" => return None,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::ControlFlow;
+use std::thread;
+
+use hub::data_ext::serde_json;
+use hub::serde::Serialize;
+
+use crate::lib::api::{ErrorCode, Session};
+use crate::lib::core::crud::{CrudError, CrudErrorKind};
+
+use super::context::EXIT_ERROR;
+use super::listing::masked;
+use super::output::EntryView;
+use super::serve::{bearer_token, Gate};
+use super::verbosity::say;
+
+/// `serve --ui` without an address.
+pub const DEFAULT_UI_ADDR: &str = "127.0.0.1:8080";
+
+/// Largest request body accepted (a value written with `PUT`).
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// The browser page: plain HTML and script over the JSON routes below.
+const INDEX_HTML: &str = include_str!("ui.html");
+
+/// `serve --ui [HOST:PORT]` — a browser page for looking through and editing keys.
+///
+/// The page at `/` uses these JSON routes, which scripts can call as well:
+///
+/// - `GET /api/projects`, `GET /api/namespaces/PROJECT` → arrays of names
+/// - `GET /api/keys/PROJECT.NS` → `[{"key", "value", "type", "ttl"}]`, secrets masked
+/// - `GET /api/key/ADDRESS` → one such object, or `404`
+/// - `PUT /api/key/ADDRESS[?ttl=SECS]` with the value as body → `204`
+/// - `DELETE /api/key/ADDRESS` → `204`, or `404` when missing
+///
+/// Failures answer `{"code", "message"}` with the stable `ErrorCode`. Every
+/// `/api/` route needs `Authorization: Bearer <API token>` and runs as that
+/// token (`401` without a valid one); the page asks for the token once per
+/// browser tab. Requests whose `Host` is not this server or a loopback name
+/// are refused, and the default address is loopback only.
+pub fn serve_ui(gate: Gate, addr: &str) -> i32 {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("serve: {}: {}", addr, error);
+            return EXIT_ERROR;
+        }
+    };
+    say(format!(
+        "Serving {} at http://{}/",
+        gate.config().database_path().display(),
+        addr
+    ));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let gate = gate.clone();
+                let addr = addr.to_string();
+                thread::spawn(move || {
+                    if let Err(error) = serve_client(&gate, stream, &addr) {
+                        eprintln!("serve: client: {}", error);
+                    }
+                });
+            }
+            Err(error) => eprintln!("serve: accept: {}", error),
+        }
+    }
+    EXIT_ERROR
+}
+
+/// One parsed HTTP request; the connection closes after the reply.
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    host: Option<String>,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn json<T: Serialize>(value: &T) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Reply {
+                status: 200,
+                content_type: "application/json",
+                body,
+            },
+            Err(error) => Reply::error(500, ErrorCode::Internal, &error.to_string()),
+        }
+    }
+
+    fn empty() -> Self {
+        Reply {
+            status: 204,
+            content_type: "text/plain",
+            body: String::new(),
+        }
+    }
+
+    fn error(status: u16, code: ErrorCode, message: &str) -> Self {
+        let body = serde_json::json!({ "code": code.as_str(), "message": message });
+        Reply {
+            status,
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+
+    fn failed(error: &CrudError) -> Self {
+        let code = ErrorCode::of(error);
+        let status = match error.kind {
+            _ if code == ErrorCode::StorageBusy => 503,
+            CrudErrorKind::InvalidInput => 400,
+            CrudErrorKind::PermissionDenied => 403,
+            CrudErrorKind::NotFound => 404,
+            CrudErrorKind::Conflict => 409,
+            CrudErrorKind::Unsupported => 501,
+            CrudErrorKind::Internal => 500,
+        };
+        Reply::error(status, code, &error.source().to_string())
+    }
+}
+
+fn serve_client(gate: &Gate, stream: TcpStream, addr: &str) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let reply = match read_request(&mut reader)? {
+        None => return Ok(()),
+        Some(request) if !local_host(request.host.as_deref(), addr) => Reply::error(
+            403,
+            ErrorCode::PermissionDenied,
+            "Host is not this server; open the address serve printed",
+        ),
+        Some(request) => route(gate, &request),
+    };
+
+    let mut out = io::BufWriter::new(stream);
+    write!(
+        out,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        reply.status,
+        reason(reply.status),
+        reply.content_type,
+        reply.body.len()
+    )?;
+    out.write_all(reply.body.as_bytes())?;
+    out.flush()
+}
+
+fn route(gate: &Gate, request: &Request) -> Reply {
+    let path = request.path.as_str();
+    if path == "/" {
+        if request.method != "GET" {
+            return not_allowed();
+        }
+        return Reply {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: INDEX_HTML.to_string(),
+        };
+    }
+    let session = match gate.open(request.authorization.as_deref().map(bearer_token)) {
+        Ok(session) => session,
+        Err(error) if error.kind == CrudErrorKind::PermissionDenied => {
+            return Reply::error(
+                401,
+                ErrorCode::PermissionDenied,
+                &error.source().to_string(),
+            )
+        }
+        Err(error) => return Reply::failed(&error),
+    };
+    if let Some(address) = path.strip_prefix("/api/key/") {
+        return key(&session, request, &percent_decode(address));
+    }
+    if request.method != "GET" {
+        return not_allowed();
+    }
+    let listed = if path == "/api/projects" {
+        session.projects()
+    } else if let Some(project) = path.strip_prefix("/api/namespaces/") {
+        session.namespaces(&percent_decode(project))
+    } else if let Some(scope) = path.strip_prefix("/api/keys/") {
+        return list_keys(&session, &percent_decode(scope));
+    } else {
+        return Reply::error(404, ErrorCode::NotFound, "no such page");
+    };
+    match listed {
+        Ok(names) => Reply::json(&names),
+        Err(error) => Reply::failed(&error),
+    }
+}
+
+fn list_keys(session: &Session, scope: &str) -> Reply {
+    let secrets = match session.secret_marks() {
+        Ok(secrets) => secrets,
+        Err(error) => return Reply::failed(&error),
+    };
+    let mut entries = Vec::new();
+    let scanned = session.scan(scope, |entry| {
+        entries.push(masked(entry, &secrets));
+        ControlFlow::Continue(())
+    });
+    match scanned {
+        Ok(()) => Reply::json(&entries.iter().map(EntryView::new).collect::<Vec<_>>()),
+        Err(error) => Reply::failed(&error),
+    }
+}
+
+fn key(session: &Session, request: &Request, address: &str) -> Reply {
+    match request.method.as_str() {
+        "GET" => {
+            let secrets = match session.secret_marks() {
+                Ok(secrets) => secrets,
+                Err(error) => return Reply::failed(&error),
+            };
+            match session.get_entry(address) {
+                Ok(Some(entry)) => Reply::json(&EntryView::new(&masked(entry, &secrets))),
+                Ok(None) => Reply::error(404, ErrorCode::NotFound, "key not found"),
+                Err(error) => Reply::failed(&error),
+            }
+        }
+        "PUT" => {
+            let ttl_secs = match request.param("ttl").map(str::parse::<u64>) {
+                None => None,
+                Some(Ok(ttl_secs)) => Some(ttl_secs),
+                Some(Err(_)) => {
+                    return Reply::error(400, ErrorCode::InvalidInput, "ttl must be whole seconds")
+                }
+            };
+            let value = match std::str::from_utf8(&request.body) {
+                Ok(value) => value,
+                Err(_) => return Reply::error(400, ErrorCode::InvalidInput, "value is not UTF-8"),
+            };
+            match session.set(address, value, ttl_secs) {
+                Ok(()) => Reply::empty(),
+                Err(error) => Reply::failed(&error),
+            }
+        }
+        "DELETE" => match session.delete(address) {
+            Ok(true) => Reply::empty(),
+            Ok(false) => Reply::error(404, ErrorCode::NotFound, "key not found"),
+            Err(error) => Reply::failed(&error),
+        },
+        _ => not_allowed(),
+    }
+}
+
+fn not_allowed() -> Reply {
+    Reply::error(405, ErrorCode::Unsupported, "method not allowed here")
+}
+
+/// The request line, `Host`, `Authorization` and a `Content-Length` body; `None` for no request.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return Err(bad_request("malformed request line")),
+    };
+
+    let mut host = None;
+    let mut authorization = None;
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Err(bad_request("headers ended early"));
+        }
+        let header = header.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("host") {
+                host = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| bad_request("invalid Content-Length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY_BYTES {
+        return Err(bad_request("request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    Ok(Some(Request {
+        method,
+        path: path.to_string(),
+        query,
+        host,
+        authorization,
+        body,
+    }))
+}
+
+fn bad_request(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Whether `host` names this server, so pages on other sites cannot reach it by DNS rebinding.
+fn local_host(host: Option<&str>, addr: &str) -> bool {
+    let host = match host {
+        Some(host) => host,
+        None => return false,
+    };
+    if host == addr {
+        return true;
+    }
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|ch| ch.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// `%XX` escapes decoded (as `encodeURIComponent` writes them); invalid escapes are kept.
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        501 => "Not Implemented",
+        _ => "Internal Server Error",
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt::Display;
+
+use rsb::prelude::*;
+
+use crate::lib::adpt::sqlite::SqliteConnectionConfig;
+use crate::lib::api;
+
+/// Context key holding the resolved level, so handlers never re-scan argv.
+const VERBOSITY_VAR: &str = "opt_verbosity";
+
+/// How chatty a command is: `-q` silences success chatter, `-v`/`-vv` add diagnostics on stderr.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+    Debug,
+}
+
+impl Verbosity {
+    /// Resolve from argv and the `options!` context; `-q`/`--quiet` wins over any `-v`.
+    pub fn resolve(args: &[String]) -> Self {
+        let flagged = |short: &str, long: &str| {
+            args.iter().any(|arg| arg == short || arg == long)
+                || has_var(&format!("opt_{}", short.trim_start_matches('-')))
+                || has_var(&format!("opt_{}", long.trim_start_matches('-')))
+        };
+        let verbose_count = args.iter().filter(|arg| arg.as_str() == "-v").count();
+
+        if flagged("-q", "--quiet") {
+            Verbosity::Quiet
+        } else if verbose_count > 1 || flagged("-vv", "--debug") {
+            Verbosity::Debug
+        } else if verbose_count == 1 || flagged("-v", "--verbose") {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Verbosity::Quiet => "quiet",
+            Verbosity::Normal => "normal",
+            Verbosity::Verbose => "verbose",
+            Verbosity::Debug => "debug",
+        }
+    }
+}
+
+/// Short verbosity switches; `CommandArgs` drops them so they never become positionals.
+pub fn is_verbosity_flag(arg: &str) -> bool {
+    matches!(arg, "-q" | "-v" | "-vv")
+}
+
+/// Called once by the dispatcher before any handler runs.
+pub fn init_verbosity(args: &[String]) -> Verbosity {
+    let verbosity = Verbosity::resolve(args);
+    set_var(VERBOSITY_VAR, verbosity.as_str());
+    verbosity
+}
+
+pub fn verbosity() -> Verbosity {
+    match get_var(VERBOSITY_VAR).as_str() {
+        "quiet" => Verbosity::Quiet,
+        "verbose" => Verbosity::Verbose,
+        "debug" => Verbosity::Debug,
+        _ => Verbosity::Normal,
+    }
+}
+
+/// Success chatter on stdout (confirmations, summaries); suppressed by `-q`.
+pub fn say<M: Display>(message: M) {
+    if verbosity() > Verbosity::Quiet {
+        println!("{}", message);
+    }
+}
+
+/// Diagnostic line on stderr, shown at `level` and above.
+pub fn trace<M: Display>(level: Verbosity, message: M) {
+    if verbosity() >= level {
+        eprintln!("[{}] {}", level.as_str(), message);
+    }
+}
+
+/// At `-v`, show how a raw address expands under `config` (default or cursor
+/// project/namespace, `--meta` context, context suffix), as the command resolves it.
+pub fn trace_address(config: &SqliteConnectionConfig, raw: &str) {
+    if verbosity() < Verbosity::Verbose {
+        return;
+    }
+    if let Ok(addr) = api::resolve_address(config, raw) {
+        trace(
+            Verbosity::Verbose,
+            format!(
+                "address: {} -> {} (context: {})",
+                raw,
+                addr,
+                addr.context.as_deref().unwrap_or("none")
+            ),
+        );
+    }
+}
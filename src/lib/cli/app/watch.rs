@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use rsb::prelude::*;
+
+use crate::lib::addr::KeyPattern;
+use crate::lib::adpt::sqlite::{KvEntry, SecretMarks, SqliteConnectionConfig};
+use crate::lib::api;
+use crate::lib::core::crud::CrudResult;
+use crate::lib::core::helpers::parse_duration;
+
+use super::context::{connection_config, CommandArgs, EXIT_ERROR, EXIT_OK};
+use super::listing::masked;
+use super::output::report_error;
+
+/// What `watch` follows: one address, or every key of a scope.
+pub enum WatchTarget {
+    Address(String),
+    Scope(String),
+}
+
+impl WatchTarget {
+    /// `app.cfg.*`, `app.cfg.db_*`, `app.cfg.` and globs are scopes; anything else is an address.
+    pub fn parse(raw: &str) -> Self {
+        if raw.ends_with('*') || raw.ends_with('.') || KeyPattern::is_pattern(raw) {
+            WatchTarget::Scope(raw.to_string())
+        } else {
+            WatchTarget::Address(raw.to_string())
+        }
+    }
+
+    /// Live entries by address.
+    pub fn snapshot(
+        &self,
+        config: &SqliteConnectionConfig,
+    ) -> CrudResult<BTreeMap<String, KvEntry>> {
+        let mut entries = BTreeMap::new();
+        match self {
+            WatchTarget::Address(address) => {
+                if let Some(entry) = api::get_entry(config, address, false)? {
+                    entries.insert(entry.address.to_string(), entry);
+                }
+            }
+            WatchTarget::Scope(scope) => api::scan_entries(config, scope, |entry| {
+                entries.insert(entry.address.to_string(), entry);
+                ControlFlow::Continue(())
+            })?,
+        }
+        Ok(entries)
+    }
+}
+
+/// `watch <address|scope> [--interval 1s] [--exec CMD] [--count N] [--reveal]`
+///
+/// Polls every `--interval` and prints `set ADDRESS=VALUE` or `del ADDRESS`
+/// for each change since the previous poll; keys present at start are not
+/// printed. `--exec` runs `sh -c CMD` per change with `PRONTO_WATCH_EVENT`,
+/// `PRONTO_WATCH_ADDRESS` and `PRONTO_WATCH_VALUE` set. `--count N` exits
+/// after N changes. Secret values are masked when watching a scope, as in `scan`.
+pub fn do_watch(args: Args) -> i32 {
+    let parsed = CommandArgs::parse(&args, &["interval", "exec", "count"]);
+    let target = match parsed.positional.as_slice() {
+        [target] => WatchTarget::parse(target),
+        _ => {
+            eprintln!(
+                "Usage: prontodb watch <address|project.ns.*> [--interval 1s] [--exec CMD] [--count N]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let interval = match parse_duration(parsed.flag("interval").unwrap_or("1s")) {
+        Some(secs) if secs > 0 => Duration::from_secs(secs),
+        _ => {
+            eprintln!("watch: --interval expects a duration of at least 1s, such as 5s or 1m");
+            return EXIT_ERROR;
+        }
+    };
+    let count = match parsed.flag("count").map(str::parse::<usize>) {
+        None => None,
+        Some(Ok(count)) if count > 0 => Some(count),
+        Some(_) => {
+            eprintln!("watch: --count expects a positive number");
+            return EXIT_ERROR;
+        }
+    };
+    // Polling reuses one connection instead of reopening the database each interval.
+    let config = connection_config().with_connection_reuse(true);
+    let secrets = match target {
+        WatchTarget::Scope(_) if !parsed.has_flag("reveal") => match api::secret_marks(&config) {
+            Ok(secrets) => secrets,
+            Err(error) => {
+                report_error("watch", &error);
+                return EXIT_ERROR;
+            }
+        },
+        _ => SecretMarks::default(),
+    };
+
+    let mut previous = match target.snapshot(&config) {
+        Ok(entries) => entries,
+        Err(error) => {
+            report_error("watch", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let mut seen = 0;
+    loop {
+        thread::sleep(interval);
+        let current = match target.snapshot(&config) {
+            Ok(entries) => entries,
+            Err(error) => {
+                report_error("watch", &error);
+                return EXIT_ERROR;
+            }
+        };
+        for (address, value) in changes(&previous, &current, &secrets) {
+            match &value {
+                Some(value) => println!("set {}={}", address, value),
+                None => println!("del {}", address),
+            }
+            let _ = io::stdout().flush();
+            if let Some(command) = parsed.flag("exec") {
+                run_exec(command, &address, value.as_deref());
+            }
+            seen += 1;
+            if count.is_some_and(|count| seen >= count) {
+                return EXIT_OK;
+            }
+        }
+        previous = current;
+    }
+}
+
+/// Keys `tail` prints before following when `-n` is not given.
+const DEFAULT_TAIL_LINES: usize = 10;
+
+/// `tail <project.ns[.prefix]> [-n 10] [--interval 1s] [--count N] [--reveal]`
+///
+/// Prints the newest `-n` keys of a namespace as `key=value`, oldest first,
+/// then each key created after that as it arrives; rewriting an existing key
+/// prints nothing. `--count N` exits after N new keys. Secrets are masked as in `scan`.
+pub fn do_tail(args: Args) -> i32 {
+    let parsed =
+        CommandArgs::parse_with_short(&args, &["lines", "interval", "count"], &[("-n", "lines")]);
+    let scope = match parsed.positional.as_slice() {
+        [scope] => scope.as_str(),
+        _ => {
+            eprintln!(
+                "Usage: prontodb tail <project.namespace> [-n 10] [--interval 1s] [--count N]"
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let lines = match parsed.flag("lines").map(str::parse::<usize>) {
+        None => DEFAULT_TAIL_LINES,
+        Some(Ok(lines)) => lines,
+        Some(Err(_)) => {
+            eprintln!("tail: -n expects a number of keys");
+            return EXIT_ERROR;
+        }
+    };
+    let interval = match parse_duration(parsed.flag("interval").unwrap_or("1s")) {
+        Some(secs) if secs > 0 => Duration::from_secs(secs),
+        _ => {
+            eprintln!("tail: --interval expects a duration of at least 1s, such as 5s or 1m");
+            return EXIT_ERROR;
+        }
+    };
+    let count = match parsed.flag("count").map(str::parse::<usize>) {
+        None => None,
+        Some(Ok(count)) if count > 0 => Some(count),
+        Some(_) => {
+            eprintln!("tail: --count expects a positive number");
+            return EXIT_ERROR;
+        }
+    };
+    let config = connection_config().with_connection_reuse(true);
+    let secrets = if parsed.has_flag("reveal") {
+        SecretMarks::default()
+    } else {
+        match api::secret_marks(&config) {
+            Ok(secrets) => secrets,
+            Err(error) => {
+                report_error("tail", &error);
+                return EXIT_ERROR;
+            }
+        }
+    };
+    let print = |entry: KvEntry| {
+        let entry = masked(entry, &secrets);
+        println!("{}={}", entry.address.key_path(), entry.value);
+        let _ = io::stdout().flush();
+    };
+
+    // Fetch at least one row even for `-n 0`, so following starts after the newest key.
+    let newest = match api::entries_inserted_after(&config, scope, 0, Some(lines.max(1))) {
+        Ok(newest) => newest,
+        Err(error) => {
+            report_error("tail", &error);
+            return EXIT_ERROR;
+        }
+    };
+    let mut after = newest.last().map_or(0, |(row, _)| *row);
+    let skip = newest.len().saturating_sub(lines);
+    newest
+        .into_iter()
+        .skip(skip)
+        .for_each(|(_, entry)| print(entry));
+
+    let mut seen = 0;
+    loop {
+        thread::sleep(interval);
+        let arrived = match api::entries_inserted_after(&config, scope, after, None) {
+            Ok(arrived) => arrived,
+            Err(error) => {
+                report_error("tail", &error);
+                return EXIT_ERROR;
+            }
+        };
+        for (row, entry) in arrived {
+            after = row;
+            print(entry);
+            seen += 1;
+            if count.is_some_and(|count| seen >= count) {
+                return EXIT_OK;
+            }
+        }
+    }
+}
+
+/// `(address, Some(new value))` for set or changed keys and `(address, None)` for removed ones.
+pub fn changes(
+    previous: &BTreeMap<String, KvEntry>,
+    current: &BTreeMap<String, KvEntry>,
+    secrets: &SecretMarks,
+) -> Vec<(String, Option<String>)> {
+    let mut changes = Vec::new();
+    for (address, entry) in current {
+        let changed = previous
+            .get(address)
+            .is_none_or(|before| before.value != entry.value);
+        if changed {
+            let shown = masked(entry.clone(), secrets).value;
+            changes.push((address.clone(), Some(shown)));
+        }
+    }
+    for address in previous.keys() {
+        if !current.contains_key(address) {
+            changes.push((address.clone(), None));
+        }
+    }
+    changes
+}
+
+/// Run `--exec` for one change; a failing command is reported and watching goes on.
+fn run_exec(command: &str, address: &str, value: Option<&str>) {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env(
+            "PRONTO_WATCH_EVENT",
+            if value.is_some() { "set" } else { "del" },
+        )
+        .env("PRONTO_WATCH_ADDRESS", address)
+        .env("PRONTO_WATCH_VALUE", value.unwrap_or_default())
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("watch: --exec exited with {}", status),
+        Err(error) => eprintln!("watch: --exec failed: {}", error),
+    }
+}
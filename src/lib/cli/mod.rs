@@ -1,3 +1,4 @@
 //! CLI layer modules (admin tooling, app front-ends).
 
 pub mod admin;
+pub mod app;
@@ -1,122 +0,0 @@
-
-  // use crate::rsb::prelude::*;
-  // use std::path::PathBuf;
-  // use std::fs;
-  // use std::os::unix::fs::PermissionsExt;
-  
-
-  // fn do_install(args: rsb::args::Args) -> i32 {
-          
-  //     // Parse install options
-  //     let mut target_dir = None;
-  //     let mut force = false;
-  //     let mut quiet = false;
-      
-  //     let arg_list = args.all();
-  //     let mut i = 0;
-  //     while i < arg_list.len() {
-  //         match arg_list[i].as_str() {
-  //             "--target" | "-t" if i + 1 < arg_list.len() => {
-  //                 target_dir = Some(PathBuf::from(&arg_list[i + 1]));
-  //                 i += 2;
-  //             }
-  //             "--force" | "-f" => {
-  //                 force = true;
-  //                 i += 1;
-  //             }
-  //             "--quiet" | "-q" => {
-  //                 quiet = true;
-  //                 i += 1;
-  //             }
-  //             "--help" | "-h" => {
-  //                 println!("prontodb install - Install ProntoDB binary and setup environment");
-  //                 println!();
-  //                 println!("USAGE:");
-  //                 println!("  prontodb install [OPTIONS]");
-  //                 println!();
-  //                 println!("OPTIONS:");
-  //                 println!("  -t, --target <DIR>    Install to specific directory (default: ~/.local/bin)");
-  //                 println!("  -f, --force           Overwrite existing installation");
-  //                 println!("  -q, --quiet           Suppress output messages");
-  //                 println!("  -h, --help            Show this help message");
-  //                 return 0;
-  //             }
-  //             _ => {
-  //                 eprintln!("install: Unknown option '{}'", arg_list[i]);
-  //                 eprintln!("Use 'prontodb install --help' for usage information");
-  //                 return 1;
-  //             }
-  //         }
-  //     }
-      
-  //     // Determine installation target
-  //     let install_dir = target_dir.unwrap_or_else(|| {
-  //         xdg::XdgPaths::new().home.join(".local").join("bin")
-  //     });
-      
-  //     if !quiet {
-  //         println!("Installing ProntoDB to: {}", install_dir.display());
-  //     }
-      
-  //     // Create installation directory
-  //     if let Err(e) = fs::create_dir_all(&install_dir) {
-  //         eprintln!("install: Failed to create directory {}: {}", install_dir.display(), e);
-  //         return 1;
-  //     }
-      
-  //     // Get current executable path
-  //     let current_exe = match std::env::current_exe() {
-  //         Ok(path) => path,
-  //         Err(e) => {
-  //             eprintln!("install: Failed to get current executable path: {}", e);
-  //             return 1;
-  //         }
-  //     };
-      
-  //     let target_exe = install_dir.join("prontodb");
-      
-  //     // Check if already installed
-  //     if target_exe.exists() && !force {
-  //         eprintln!("install: ProntoDB already installed at {}", target_exe.display());
-  //         eprintln!("Use --force to overwrite existing installation");
-  //         return 1;
-  //     }
-      
-  //     // Copy binary
-  //     if let Err(e) = fs::copy(&current_exe, &target_exe) {
-  //         eprintln!("install: Failed to copy binary: {}", e);
-  //         return 1;
-  //     }
-      
-  //     // Make executable
-  //     if let Err(e) = fs::set_permissions(&target_exe, fs::Permissions::from_mode(0o755)) {
-  //         eprintln!("install: Failed to set executable permissions: {}", e);
-  //         return 1;
-  //     }
-      
-  //     // Setup XDG directory structure
-  //     let paths = xdg::XdgPaths::new();
-  //     if let Err(e) = paths.ensure_dirs() {
-  //         eprintln!("install: Failed to create XDG directories: {}", e);
-  //         return 1;
-  //     }
-      
-  //     // Initialize default cursor
-  //     let cursor_manager = cursor::CursorManager::new();
-  //     if let Err(e) = cursor_manager.ensure_default_cursor("default") {
-  //         eprintln!("install: Failed to initialize default cursor: {}", e);
-  //         return 1;
-  //     }
-      
-  //     if !quiet {
-  //         println!("Installation completed successfully!");
-  //         println!("Binary installed: {}", target_exe.display());
-  //         println!("Data directory: {}", paths.data_dir.display());
-  //         println!("Config directory: {}", paths.config_dir.display());
-  //         println!("Cursor directory: {}", paths.cursor_dir.display());
-  //         println!();
-  //         println!("Add {} to your PATH to use 'prontodb' command globally", install_dir.display());
-  //     }
-      
-  //     0
-  // }
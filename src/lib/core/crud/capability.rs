@@ -59,6 +59,12 @@ impl CapabilityMap {
         entry.verbs.insert(verb);
     }
 
+    pub fn deny(&mut self, object: &CrudObjectKind, verb: CrudVerb) {
+        if let Some(entry) = self.entries.get_mut(object) {
+            entry.verbs.remove(&verb);
+        }
+    }
+
     pub fn allows(&self, object: &CrudObjectKind, verb: CrudVerb) -> bool {
         self.entries
             .get(object)
@@ -12,6 +12,8 @@ pub enum CrudErrorKind {
     InvalidInput,
     Conflict,
     NotFound,
+    /// The acting user lacks the permission (see namespace ACLs).
+    PermissionDenied,
     Internal,
 }
 
@@ -100,6 +102,21 @@ impl CrudError {
         )
     }
 
+    pub fn permission_denied<S: Into<String>>(
+        domain: CrudDomain,
+        object: CrudObjectKind,
+        verb: CrudVerb,
+        message: S,
+    ) -> Self {
+        Self::new(
+            CrudErrorKind::PermissionDenied,
+            domain,
+            object,
+            verb,
+            anyhow::anyhow!(message.into()),
+        )
+    }
+
     pub fn internal(
         domain: CrudDomain,
         object: CrudObjectKind,
@@ -124,6 +141,7 @@ impl fmt::Display for CrudError {
                 CrudErrorKind::InvalidInput => "Invalid input",
                 CrudErrorKind::Conflict => "Conflict",
                 CrudErrorKind::NotFound => "Not found",
+                CrudErrorKind::PermissionDenied => "Permission denied",
                 CrudErrorKind::Internal => "Internal",
             },
             self.domain,
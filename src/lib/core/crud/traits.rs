@@ -29,6 +29,11 @@ pub trait CrudResource {
 
     fn capabilities(&self) -> CapabilityMap;
 
+    /// Refuse `verb` before any hook runs, e.g. when an operator toggled it off.
+    fn ensure_capability(&self, _verb: CrudVerb, _ctx: &CrudContext) -> CrudResult<()> {
+        Ok(())
+    }
+
     fn create(&self, _ctx: CrudContext) -> CrudResult<CrudOutcome> {
         Err(CrudError::unsupported(
             self.domain(),
@@ -114,6 +119,7 @@ pub trait CrudResource {
     }
 
     fn dispatch(&self, verb: CrudVerb, ctx: CrudContext) -> CrudResult<CrudOutcome> {
+        self.ensure_capability(verb, &ctx)?;
         self.hooks().before(verb, &ctx)?;
         let result = match verb {
             CrudVerb::Create => self.create(ctx.clone()),
@@ -0,0 +1,55 @@
+//! Who is running this process, as the operating system sees it.
+
+use std::env;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// Effective uid of this process (the owner of `/proc/self`), where the platform exposes it.
+#[cfg(unix)]
+pub fn os_uid() -> Option<u32> {
+    fs::metadata("/proc/self").ok().map(|meta| meta.uid())
+}
+
+#[cfg(not(unix))]
+pub fn os_uid() -> Option<u32> {
+    None
+}
+
+/// Login name of the process owner: its `/etc/passwd` entry (the bare uid
+/// when it has none), else `USER`/`LOGNAME` where uids are unavailable.
+pub fn os_user() -> Option<String> {
+    if let Some(uid) = os_uid() {
+        return Some(passwd_name(uid).unwrap_or_else(|| uid.to_string()));
+    }
+    ["USER", "LOGNAME"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|user| !user.is_empty()))
+}
+
+/// Whether the process owner owns `path`; root owns everything, and
+/// nothing counts as owned where uids are unavailable.
+#[cfg(unix)]
+pub fn owns_path(path: &Path) -> bool {
+    match (os_uid(), fs::metadata(path)) {
+        (Some(0), _) => true,
+        (Some(uid), Ok(meta)) => meta.uid() == uid,
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub fn owns_path(_path: &Path) -> bool {
+    false
+}
+
+fn passwd_name(uid: u32) -> Option<String> {
+    let passwd = fs::read_to_string("/etc/passwd").ok()?;
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let entry_uid = fields.nth(1)?.parse::<u32>().ok()?;
+        (entry_uid == uid).then(|| name.to_string())
+    })
+}
@@ -3,5 +3,7 @@
 
 pub mod crud;
 pub mod helpers;
+pub mod identity;
 pub mod options;
+pub mod schedule;
 pub mod xdg;
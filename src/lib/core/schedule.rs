@@ -0,0 +1,115 @@
+//! Five-field cron expressions (`minute hour day month weekday`), matched in UTC.
+
+use super::helpers::civil_from_days;
+
+/// Minutes `next_after` looks ahead before giving up on an expression that never matches.
+const LOOKAHEAD_MINUTES: i64 = 4 * 366 * 1_440;
+
+/// A parsed cron expression; each field is a bit set of the values it allows.
+///
+/// Fields take `*`, numbers, `a-b` ranges, `,` lists and `/n` steps
+/// (`*/15`, `1-5`, `0,30`); weekday 0 and 7 are both Sunday. As in cron,
+/// when both day of month and weekday are restricted a day matching either
+/// one runs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    either_day: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        }
+        let weekdays = parse_field(fields[4], 0, 7, "weekday")?;
+        Ok(CronSchedule {
+            minutes: parse_field(fields[0], 0, 59, "minute")?,
+            hours: parse_field(fields[1], 0, 23, "hour")?,
+            days: parse_field(fields[2], 1, 31, "day")?,
+            months: parse_field(fields[3], 1, 12, "month")?,
+            weekdays: (weekdays | (weekdays >> 7)) & 0x7f,
+            either_day: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+        })
+    }
+
+    /// The first whole minute after `secs` (unix seconds) that matches; `None` when
+    /// nothing matches for years (such as `0 0 30 2 *`).
+    pub fn next_after(&self, secs: i64) -> Option<i64> {
+        let mut minute = secs.div_euclid(60) + 1;
+        let last = minute + LOOKAHEAD_MINUTES;
+        while minute <= last {
+            let days = minute.div_euclid(1_440);
+            let (_, month, day) = civil_from_days(days);
+            // 1970-01-01 was a Thursday.
+            let weekday = (days + 4).rem_euclid(7);
+            let day_matches = if self.either_day {
+                allows(self.days, day) || allows(self.weekdays, weekday)
+            } else {
+                allows(self.days, day) && allows(self.weekdays, weekday)
+            };
+            if !allows(self.months, month) || !day_matches {
+                minute = (days + 1) * 1_440;
+                continue;
+            }
+            if !allows(self.hours, minute.rem_euclid(1_440) / 60) {
+                minute = (minute.div_euclid(60) + 1) * 60;
+                continue;
+            }
+            if allows(self.minutes, minute.rem_euclid(60)) {
+                return Some(minute * 60);
+            }
+            minute += 1;
+        }
+        None
+    }
+}
+
+fn allows(set: u64, value: i64) -> bool {
+    (set >> value) & 1 == 1
+}
+
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let number = |raw: &str| {
+        raw.parse::<u32>()
+            .map_err(|_| format!("{} field {:?}: {:?} is not a number", name, field, raw))
+    };
+    let mut set = 0u64;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match number(step)? {
+                0 => return Err(format!("{} field {:?}: step 0", name, field)),
+                step => (range, step),
+            },
+            None => (item, 1),
+        };
+        let (low, high) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((low, high)) => (number(low)?, number(high)?),
+            // `5/10` runs from 5 to the end of the field.
+            None if step > 1 => (number(range)?, max),
+            None => {
+                let value = number(range)?;
+                (value, value)
+            }
+        };
+        if low < min || high > max || low > high {
+            return Err(format!(
+                "{} field {:?}: {} is outside {}-{}",
+                name, field, item, min, max
+            ));
+        }
+        for value in (low..=high).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
@@ -0,0 +1,71 @@
+use hub::data_ext::serde_json;
+use hub::error_ext::anyhow;
+use hub::serde::{Deserialize, Serialize};
+
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+
+use super::data::CursorData;
+
+/// Format version written by `cursor export`; imports reject anything newer.
+pub const CURSOR_BUNDLE_VERSION: u32 = 1;
+
+/// Portable set of cursor definitions (`cursor export` / `cursor import`).
+///
+/// ```json
+/// {"version": 1, "cursors": [{"name": "prod", "database_path": "/srv/prod.sqlite3", "user": "default", ...}]}
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "hub::serde")]
+pub struct CursorBundle {
+    pub version: u32,
+    pub cursors: Vec<BundledCursor>,
+}
+
+/// One named cursor; the `CursorData` fields sit beside `name`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "hub::serde")]
+pub struct BundledCursor {
+    pub name: String,
+    #[serde(flatten)]
+    pub data: CursorData,
+}
+
+impl CursorBundle {
+    pub fn new(cursors: Vec<BundledCursor>) -> Self {
+        Self {
+            version: CURSOR_BUNDLE_VERSION,
+            cursors,
+        }
+    }
+
+    pub fn to_json(&self) -> CrudResult<String> {
+        serde_json::to_string_pretty(self).map_err(|err| {
+            CrudError::internal(
+                CrudDomain::Filesystem,
+                CrudObjectKind::Alias,
+                CrudVerb::Backup,
+                anyhow::Error::new(err),
+            )
+        })
+    }
+
+    pub fn from_json(raw: &str) -> CrudResult<Self> {
+        let invalid = |message: String| {
+            CrudError::invalid_input(
+                CrudDomain::Filesystem,
+                CrudObjectKind::Alias,
+                CrudVerb::Restore,
+                message,
+            )
+        };
+        let bundle: Self = serde_json::from_str(raw)
+            .map_err(|err| invalid(format!("invalid cursor bundle: {}", err)))?;
+        if bundle.version > CURSOR_BUNDLE_VERSION {
+            return Err(invalid(format!(
+                "cursor bundle version {} is newer than supported version {}",
+                bundle.version, CURSOR_BUNDLE_VERSION
+            )));
+        }
+        Ok(bundle)
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::lib::addr::DEFAULT_NS_DELIM;
+
+use super::data::CursorData;
+
+/// Outcome of `cursor check` for one cursor file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CursorReport {
+    pub name: String,
+    pub user: String,
+    /// The `.cursor` file itself.
+    pub file: PathBuf,
+    /// Found in the flat pre-scoping `cursors/` directory.
+    pub legacy: bool,
+    /// Empty when the cursor is usable.
+    pub problems: Vec<String>,
+}
+
+impl CursorReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Everything wrong with `data`: its database file and its meta context.
+pub(super) fn cursor_problems(data: &CursorData) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Some(problem) = database_problem(&data.database_path) {
+        problems.push(problem);
+    }
+    if let Some(meta) = &data.meta_context {
+        if let Some(problem) = meta_context_problem(meta) {
+            problems.push(problem);
+        }
+    }
+    problems
+}
+
+fn database_problem(path: &Path) -> Option<String> {
+    let shown = path.display();
+    match fs::metadata(path) {
+        Ok(meta) if !meta.is_file() => return Some(format!("{} is not a file", shown)),
+        Ok(_) => {}
+        Err(_) => return Some(format!("database {} does not exist", shown)),
+    }
+    if let Err(err) = fs::File::open(path) {
+        return Some(format!("database {} is not readable: {}", shown, err));
+    }
+    if let Err(err) = OpenOptions::new().write(true).open(path) {
+        return Some(format!("database {} is not writable: {}", shown, err));
+    }
+
+    let has_kv =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).and_then(|conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'kv'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+        });
+    match has_kv {
+        Ok(0) => Some(format!(
+            "{} is not a ProntoDB database (no kv table)",
+            shown
+        )),
+        Ok(_) => None,
+        Err(err) => Some(format!("{} is not a valid SQLite database: {}", shown, err)),
+    }
+}
+
+/// A meta context prefixes the project, so it must be a single address segment.
+fn meta_context_problem(meta: &str) -> Option<String> {
+    if meta.is_empty() {
+        Some("meta context is empty".to_string())
+    } else if meta.contains(DEFAULT_NS_DELIM) || meta.contains("__") {
+        Some(format!(
+            "meta context '{}' must not contain '{}' or '__'",
+            meta, DEFAULT_NS_DELIM
+        ))
+    } else if meta.chars().any(char::is_whitespace) {
+        Some(format!(
+            "meta context '{}' must not contain whitespace",
+            meta
+        ))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use hub::serde::{Deserialize, Serialize};
+
+use crate::lib::adpt::sqlite::{Clock, SystemClock};
+use crate::lib::core::helpers::{format_timestamp, parse_timestamp};
+
+/// Contents of a `.cursor` file: which database to use, plus addressing defaults.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "hub::serde")]
+pub struct CursorData {
+    pub database_path: PathBuf,
+    pub default_project: Option<String>,
+    pub default_namespace: Option<String>,
+    pub meta_context: Option<String>,
+    /// UTC `YYYY-MM-DDTHH:MM:SSZ`.
+    pub created_at: String,
+    pub user: String,
+    /// UTC `YYYY-MM-DDTHH:MM:SSZ` of the last `--cursor` resolution; absent until first use.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+    /// Open the database read-only and refuse mutating commands.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+}
+
+impl CursorData {
+    pub fn new<P: Into<PathBuf>>(database_path: P, user: &str) -> Self {
+        Self {
+            database_path: database_path.into(),
+            default_project: None,
+            default_namespace: None,
+            meta_context: None,
+            created_at: format_timestamp(SystemClock.now()),
+            user: user.to_string(),
+            last_used_at: None,
+            read_only: false,
+        }
+    }
+
+    pub fn with_project(mut self, project: Option<String>) -> Self {
+        self.default_project = project;
+        self
+    }
+
+    pub fn with_namespace(mut self, namespace: Option<String>) -> Self {
+        self.default_namespace = namespace;
+        self
+    }
+
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Unix seconds of the last use, falling back to creation; `None` if unparseable.
+    pub fn last_activity(&self) -> Option<i64> {
+        parse_timestamp(self.last_used_at.as_deref().unwrap_or(&self.created_at))
+    }
+
+    pub fn with_meta_context(mut self, meta_context: Option<String>) -> Self {
+        self.meta_context = meta_context;
+        self
+    }
+}
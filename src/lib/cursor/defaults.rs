@@ -0,0 +1,83 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use hub::data_ext::serde_json;
+use hub::serde::{Deserialize, Serialize};
+
+use crate::lib::core::crud::{CrudResult, CrudVerb};
+
+use super::manager::{invalid_input, io_error, CursorManager};
+
+/// Suffix of the per-user defaults file in the cursor home.
+const DEFAULTS_EXT: &str = ".defaults.json";
+
+/// A user's own project/namespace for bare keys.
+///
+/// These sit between the active cursor and the literal `default`: a cursor
+/// that sets `--project`/`--namespace` wins for that field, and whatever is
+/// still unset after both falls back to `default`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "hub::serde")]
+pub struct UserDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+impl UserDefaults {
+    pub fn is_empty(&self) -> bool {
+        self.project.is_none() && self.namespace.is_none()
+    }
+}
+
+impl CursorManager {
+    /// `<home>/<user>.defaults.json`, whether or not it exists.
+    pub fn user_defaults_path(&self, user: &str) -> PathBuf {
+        self.home().join(format!("{}{}", user, DEFAULTS_EXT))
+    }
+
+    /// The defaults saved for `user`; empty when there are none.
+    pub fn user_defaults(&self, user: &str) -> CrudResult<UserDefaults> {
+        let verb = CrudVerb::Read;
+        validate_user(user, verb)?;
+        let path = self.user_defaults_path(user);
+        let raw = match fs::read_to_string(&path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(UserDefaults::default()),
+            Err(err) => return Err(io_error(verb, err)),
+        };
+        serde_json::from_str(&raw).map_err(|err| {
+            invalid_input(
+                verb,
+                format!("corrupt defaults file {}: {}", path.display(), err),
+            )
+        })
+    }
+
+    /// Save `defaults` for `user`; empty defaults remove the file.
+    pub fn set_user_defaults(&self, user: &str, defaults: &UserDefaults) -> CrudResult<()> {
+        let verb = CrudVerb::Update;
+        validate_user(user, verb)?;
+        let path = self.user_defaults_path(user);
+        if defaults.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(io_error(verb, err)),
+                _ => Ok(()),
+            };
+        }
+        fs::create_dir_all(self.home()).map_err(|err| io_error(verb, err))?;
+        let json = serde_json::to_string_pretty(defaults)
+            .map_err(|err| invalid_input(verb, err.to_string()))?;
+        fs::write(&path, json).map_err(|err| io_error(verb, err))
+    }
+}
+
+/// Users become a file name, so `.` and path separators are refused.
+fn validate_user(user: &str, verb: CrudVerb) -> CrudResult<()> {
+    if !user.is_empty() && !user.contains(['.', '/', '\\']) {
+        return Ok(());
+    }
+    Err(invalid_input(verb, format!("invalid user '{}'", user)))
+}
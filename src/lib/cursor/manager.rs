@@ -0,0 +1,533 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use hub::data_ext::serde_json;
+use hub::error_ext::anyhow;
+
+use crate::lib::adpt::sqlite::{Clock, SystemClock};
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+use crate::lib::core::helpers::format_timestamp;
+
+use super::bundle::{BundledCursor, CursorBundle};
+use super::check::{cursor_problems, CursorReport};
+use super::data::CursorData;
+use super::workdir::{resolve_working_directory_cursor, work_mode_enabled};
+
+/// Overrides the directory cursors live under (defaults to `~/.local/data/odx/prontodb`).
+pub const CURSOR_HOME_ENV: &str = "PRONTO_CURSOR_HOME";
+
+/// Cursor used when none is named.
+pub const DEFAULT_CURSOR: &str = "default";
+
+/// User whose cursor files carry no user suffix.
+pub const DEFAULT_USER: &str = "default";
+
+/// Legacy flat directory, still read (and migrated on rename).
+pub(super) const LEGACY_DIR: &str = "cursors";
+
+const CURSOR_EXT: &str = ".cursor";
+
+/// Reads and writes cursor files.
+///
+/// Cursors are stored per database under `<home>/<db>/cursors/`, named
+/// `name.cursor` for the default user and `name.user.cursor` otherwise.
+/// Older installs kept every cursor in `<home>/cursors/`; lookups fall back
+/// there after the database-scoped directories.
+#[derive(Clone, Debug)]
+pub struct CursorManager {
+    home: PathBuf,
+}
+
+impl CursorManager {
+    /// Manager rooted at `PRONTO_CURSOR_HOME`, else the XDG-style data directory.
+    pub fn new() -> Self {
+        let home = match env::var(CURSOR_HOME_ENV) {
+            Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => env::var("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join(".local/data/odx/prontodb"),
+        };
+        Self::with_home(home)
+    }
+
+    pub fn with_home<P: Into<PathBuf>>(home: P) -> Self {
+        Self { home: home.into() }
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// Write `data` as cursor `name` for `data.user`, replacing any older copy.
+    pub fn set(&self, name: &str, data: &CursorData) -> CrudResult<PathBuf> {
+        let verb = CrudVerb::Create;
+        validate_name(name, verb)?;
+        validate_name(&data.user, verb)?;
+        let path = self.scoped_path(name, data);
+        let previous = self.locate(name, &data.user);
+        write_cursor(&path, data, verb)?;
+        // Drop the old copy only once the new one is in place, so the cursor never vanishes.
+        if let Some(previous) = previous.filter(|previous| *previous != path) {
+            match fs::remove_file(&previous) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(io_error(verb, err)),
+            }
+        }
+        Ok(path)
+    }
+
+    pub fn get(&self, name: &str, user: &str) -> CrudResult<Option<CursorData>> {
+        match self.locate(name, user) {
+            Some(path) => read_cursor(&path, CrudVerb::Read).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Cursor `name` of `user` together with the file it was read from.
+    pub fn find(&self, name: &str, user: &str) -> CrudResult<Option<FoundCursor>> {
+        let file = match self.locate(name, user) {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let data = read_cursor(&file, CrudVerb::Read)?;
+        let legacy = file.parent() == Some(self.home.join(LEGACY_DIR).as_path());
+        Ok(Some(FoundCursor { data, file, legacy }))
+    }
+
+    /// Cursor `name` as seen from `cwd`: a `.prontodb` definition (when work mode
+    /// is on) wins over the stored cursor file, which gets its `last_used_at` bumped.
+    pub fn resolve(&self, name: &str, user: &str, cwd: &Path) -> CrudResult<Option<CursorData>> {
+        if work_mode_enabled() {
+            if let Some(cursor) = resolve_working_directory_cursor(cwd, name, user)? {
+                return Ok(Some(cursor));
+            }
+        }
+        let found = match self.find(name, user)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let mut data = found.data;
+        data.last_used_at = Some(format_timestamp(SystemClock.now()));
+        // Usage tracking is best effort: a read-only cursor home must not block reads.
+        let _ = write_cursor(&found.file, &data, CrudVerb::Update);
+        Ok(Some(data))
+    }
+
+    /// Delete `user`'s cursors whose last use (or creation, if never used) is
+    /// more than `older_than` seconds before `now`. Returns the removed names.
+    ///
+    /// With `dry_run` nothing is deleted, but the same names are returned.
+    pub fn prune(
+        &self,
+        user: &str,
+        older_than: u64,
+        now: i64,
+        dry_run: bool,
+    ) -> CrudResult<Vec<String>> {
+        let cutoff = now.saturating_sub(i64::try_from(older_than).unwrap_or(i64::MAX));
+        let mut pruned = Vec::new();
+        for (name, data) in self.list(user)? {
+            let stale = data.last_activity().is_some_and(|at| at < cutoff);
+            if !stale {
+                continue;
+            }
+            if !dry_run {
+                self.delete(&name, user)?;
+            }
+            pruned.push(name);
+        }
+        Ok(pruned)
+    }
+
+    /// Cursors of `user` by name; database-scoped files win over legacy ones.
+    ///
+    /// Unreadable files are skipped.
+    pub fn list(&self, user: &str) -> CrudResult<BTreeMap<String, CursorData>> {
+        let mut cursors = BTreeMap::new();
+        for file in self.cursor_files() {
+            if file.user != user {
+                continue;
+            }
+            if let Ok(data) = read_cursor(&file.path, CrudVerb::List) {
+                cursors.insert(file.name, data);
+            }
+        }
+        Ok(cursors)
+    }
+
+    /// Snapshot of `user`'s cursors (every user when `None`) for `cursor export`.
+    pub fn export(&self, user: Option<&str>) -> CrudResult<CursorBundle> {
+        let mut cursors = Vec::new();
+        for file in self.cursor_files() {
+            if user.is_some_and(|user| file.user != user) {
+                continue;
+            }
+            if let Ok(data) = read_cursor(&file.path, CrudVerb::List) {
+                cursors.push(BundledCursor {
+                    name: file.name,
+                    data,
+                });
+            }
+        }
+        Ok(CursorBundle::new(cursors))
+    }
+
+    /// Write every cursor of `bundle`, returning how many were written.
+    ///
+    /// All names are checked before anything is written: an existing cursor
+    /// is a `Conflict` unless `overwrite`, so a failed import changes nothing.
+    pub fn import(&self, bundle: &CursorBundle, overwrite: bool) -> CrudResult<usize> {
+        let verb = CrudVerb::Create;
+        for cursor in &bundle.cursors {
+            validate_name(&cursor.name, verb)?;
+            validate_name(&cursor.data.user, verb)?;
+            if !overwrite && self.locate(&cursor.name, &cursor.data.user).is_some() {
+                return Err(conflict(
+                    verb,
+                    format!(
+                        "cursor '{}' already exists for user '{}'",
+                        cursor.name, cursor.data.user
+                    ),
+                ));
+            }
+        }
+        for cursor in &bundle.cursors {
+            self.set(&cursor.name, &cursor.data)?;
+        }
+        Ok(bundle.cursors.len())
+    }
+
+    /// Delete every cursor of `user` (of every user when `None`), legacy copies
+    /// included. Returns how many cursors were removed.
+    pub fn reset(&self, user: Option<&str>) -> CrudResult<usize> {
+        let mut removed = BTreeSet::new();
+        for file in self.all_cursor_files() {
+            if user.is_some_and(|user| file.user != user) {
+                continue;
+            }
+            fs::remove_file(&file.path).map_err(|err| io_error(CrudVerb::Delete, err))?;
+            removed.insert((file.name, file.user));
+        }
+        Ok(removed.len())
+    }
+
+    pub fn delete(&self, name: &str, user: &str) -> CrudResult<bool> {
+        match self.locate(name, user) {
+            Some(path) => {
+                fs::remove_file(path).map_err(|err| io_error(CrudVerb::Delete, err))?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Rename cursor `old` to `new`, keeping its path, defaults, and meta context.
+    ///
+    /// The file is moved into its database-scoped directory, so legacy cursors
+    /// are migrated on the way. An existing `new` is a `Conflict` unless `overwrite`.
+    pub fn rename(&self, old: &str, new: &str, user: &str, overwrite: bool) -> CrudResult<PathBuf> {
+        let verb = CrudVerb::Update;
+        validate_name(new, verb)?;
+        let source = self.locate(old, user).ok_or_else(|| {
+            not_found(
+                verb,
+                format!("cursor '{}' not found for user '{}'", old, user),
+            )
+        })?;
+        let data = read_cursor(&source, verb)?;
+        let target = self.scoped_path(new, &data);
+        if old == new {
+            return Ok(source);
+        }
+
+        if let Some(existing) = self.locate(new, user) {
+            if !overwrite {
+                return Err(conflict(
+                    verb,
+                    format!("cursor '{}' already exists for user '{}'", new, user),
+                ));
+            }
+            fs::remove_file(existing).map_err(|err| io_error(verb, err))?;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|err| io_error(verb, err))?;
+        }
+        fs::rename(&source, &target).map_err(|err| io_error(verb, err))?;
+        Ok(target)
+    }
+
+    /// Validate every cursor file of every user, or only those named `name`.
+    ///
+    /// Covers database-scoped and legacy directories, including legacy copies
+    /// shadowed by a scoped file, so stale definitions show up too.
+    pub fn check(&self, name: Option<&str>) -> Vec<CursorReport> {
+        self.all_cursor_files()
+            .into_iter()
+            .filter(|file| name.is_none_or(|name| file.name == name))
+            .map(|file| {
+                let problems = match read_cursor(&file.path, CrudVerb::Read) {
+                    Ok(data) => cursor_problems(&data),
+                    Err(err) => vec![err.source().to_string()],
+                };
+                CursorReport {
+                    name: file.name,
+                    user: file.user,
+                    file: file.path,
+                    legacy: file.legacy,
+                    problems,
+                }
+            })
+            .collect()
+    }
+
+    /// Copy cursor `name` from user `from` to user `to` (path, defaults, meta context, read-only).
+    ///
+    /// The copy lands in the database-scoped directory with a fresh
+    /// `created_at`; an existing cursor of `to` is a `Conflict` unless `overwrite`.
+    pub fn copy(&self, name: &str, from: &str, to: &str, overwrite: bool) -> CrudResult<PathBuf> {
+        let verb = CrudVerb::Create;
+        validate_name(to, verb)?;
+        let source = self.get(name, from)?.ok_or_else(|| {
+            not_found(
+                verb,
+                format!("cursor '{}' not found for user '{}'", name, from),
+            )
+        })?;
+        if from == to {
+            return Err(invalid_input(verb, "cursor copy needs two different users"));
+        }
+        if !overwrite && self.locate(name, to).is_some() {
+            return Err(conflict(
+                verb,
+                format!("cursor '{}' already exists for user '{}'", name, to),
+            ));
+        }
+
+        let copy = CursorData::new(source.database_path, to)
+            .with_project(source.default_project)
+            .with_namespace(source.default_namespace)
+            .with_meta_context(source.meta_context)
+            .with_read_only(source.read_only);
+        self.set(name, &copy)
+    }
+
+    /// File currently holding cursor `name` of `user`, if any.
+    fn locate(&self, name: &str, user: &str) -> Option<PathBuf> {
+        let file_name = cursor_file_name(name, user);
+        self.cursor_dirs()
+            .into_iter()
+            .map(|dir| dir.join(&file_name))
+            .find(|path| path.is_file())
+    }
+
+    /// The cursor file for each `(name, user)` that lookups actually use.
+    fn cursor_files(&self) -> Vec<CursorFile> {
+        let mut seen = BTreeSet::new();
+        self.all_cursor_files()
+            .into_iter()
+            .filter(|file| seen.insert((file.name.clone(), file.user.clone())))
+            .collect()
+    }
+
+    /// Every cursor file on disk in lookup order, including shadowed legacy copies.
+    fn all_cursor_files(&self) -> Vec<CursorFile> {
+        let legacy_dir = self.home.join(LEGACY_DIR);
+        let mut files = Vec::new();
+        for dir in self.cursor_dirs() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+            paths.sort();
+            for path in paths {
+                let (name, user) = match path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(parse_file_name)
+                {
+                    Some((name, user)) => (name.to_string(), user.to_string()),
+                    None => continue,
+                };
+                files.push(CursorFile {
+                    name,
+                    user,
+                    path,
+                    legacy: dir == legacy_dir,
+                });
+            }
+        }
+        files
+    }
+
+    /// Database-scoped cursor directories (sorted), then the legacy one.
+    fn cursor_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(&self.home)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.file_name() != LEGACY_DIR)
+                    .map(|entry| entry.path().join(LEGACY_DIR))
+                    .filter(|dir| dir.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default();
+        dirs.sort();
+        dirs.push(self.home.join(LEGACY_DIR));
+        dirs
+    }
+
+    fn scoped_path(&self, name: &str, data: &CursorData) -> PathBuf {
+        self.home
+            .join(database_name(&data.database_path))
+            .join(LEGACY_DIR)
+            .join(cursor_file_name(name, &data.user))
+    }
+}
+
+/// Result of `CursorManager::find`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FoundCursor {
+    pub data: CursorData,
+    /// The `.cursor` file the data came from.
+    pub file: PathBuf,
+    /// Read from the flat pre-scoping `cursors/` directory.
+    pub legacy: bool,
+}
+
+/// A cursor file found on disk, identified by its file name.
+struct CursorFile {
+    name: String,
+    user: String,
+    path: PathBuf,
+    legacy: bool,
+}
+
+impl Default for CursorManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Directory name for a database: its file stem (`/srv/prod.sqlite3` -> `prod`).
+fn database_name(database_path: &Path) -> String {
+    database_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or("main")
+        .to_string()
+}
+
+fn cursor_file_name(name: &str, user: &str) -> String {
+    if user == DEFAULT_USER {
+        format!("{}{}", name, CURSOR_EXT)
+    } else {
+        format!("{}.{}{}", name, user, CURSOR_EXT)
+    }
+}
+
+/// `(name, user)` from `name.cursor` or `name.user.cursor`.
+pub(super) fn parse_file_name(file_name: &str) -> Option<(&str, &str)> {
+    let stem = file_name.strip_suffix(CURSOR_EXT)?;
+    match stem.split_once('.') {
+        Some((name, user)) if !name.is_empty() && !user.contains('.') => Some((name, user)),
+        Some(_) => None,
+        None if !stem.is_empty() => Some((stem, DEFAULT_USER)),
+        None => None,
+    }
+}
+
+/// Names and users become file name segments, so `.` and path separators are refused.
+fn validate_name(name: &str, verb: CrudVerb) -> CrudResult<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid_input(
+            verb,
+            format!(
+                "invalid cursor name '{}' (use letters, digits, '_' or '-')",
+                name
+            ),
+        ))
+    }
+}
+
+pub(super) fn read_cursor(path: &Path, verb: CrudVerb) -> CrudResult<CursorData> {
+    let raw = fs::read_to_string(path).map_err(|err| io_error(verb, err))?;
+    serde_json::from_str(&raw).map_err(|err| {
+        invalid_input(
+            verb,
+            format!("corrupt cursor file {}: {}", path.display(), err),
+        )
+    })
+}
+
+pub(super) fn write_cursor(path: &Path, data: &CursorData, verb: CrudVerb) -> CrudResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| io_error(verb, err))?;
+    }
+    let json = serde_json::to_string_pretty(data)
+        .map_err(|err| internal(verb, anyhow::Error::new(err)))?;
+
+    // Write a sibling temp file and rename it over the cursor, so concurrent
+    // readers and writers only ever see a complete file (last writer wins).
+    let temp = temp_path(path);
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(json.as_bytes())?;
+        file.sync_all()
+    });
+    match written.and_then(|()| fs::rename(&temp, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            Err(io_error(verb, err))
+        }
+    }
+}
+
+/// `.name.cursor.<pid>-<n>.tmp` beside `path`; never matches the `.cursor` suffix.
+fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("cursor");
+    path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name,
+        process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+pub(super) fn io_error(verb: CrudVerb, err: io::Error) -> CrudError {
+    internal(verb, anyhow::Error::new(err))
+}
+
+fn internal(verb: CrudVerb, err: anyhow::Error) -> CrudError {
+    CrudError::internal(CrudDomain::Filesystem, CrudObjectKind::Alias, verb, err)
+}
+
+pub(super) fn invalid_input<S: Into<String>>(verb: CrudVerb, message: S) -> CrudError {
+    CrudError::invalid_input(CrudDomain::Filesystem, CrudObjectKind::Alias, verb, message)
+}
+
+pub(super) fn not_found<S: Into<String>>(verb: CrudVerb, message: S) -> CrudError {
+    CrudError::not_found(CrudDomain::Filesystem, CrudObjectKind::Alias, verb, message)
+}
+
+pub(super) fn conflict<S: Into<String>>(verb: CrudVerb, message: S) -> CrudError {
+    CrudError::conflict(CrudDomain::Filesystem, CrudObjectKind::Alias, verb, message)
+}
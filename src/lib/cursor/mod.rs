@@ -0,0 +1,20 @@
+//! Cursors: named, per-user database selections persisted as JSON files.
+//! MODULE_SPEC: orchestrator only; implementation lives in sibling files.
+
+mod bundle;
+mod check;
+mod data;
+mod defaults;
+mod manager;
+mod registry;
+mod workdir;
+
+pub use bundle::{BundledCursor, CursorBundle, CURSOR_BUNDLE_VERSION};
+pub use check::CursorReport;
+pub use data::CursorData;
+pub use defaults::UserDefaults;
+pub use manager::{CursorManager, FoundCursor, CURSOR_HOME_ENV, DEFAULT_CURSOR, DEFAULT_USER};
+pub use registry::RegisteredDatabase;
+pub use workdir::{
+    resolve_working_directory_cursor, work_mode_enabled, WORKDIR_FILE, WORK_MODE_ENV,
+};
@@ -0,0 +1,232 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use hub::serde::Serialize;
+
+use crate::lib::adpt::sqlite::{SqliteConnectionConfig, SqliteKeystore};
+use crate::lib::core::crud::{CrudResult, CrudVerb};
+
+use super::manager::{
+    conflict, invalid_input, io_error, not_found, parse_file_name, read_cursor, write_cursor,
+    CursorManager, LEGACY_DIR,
+};
+
+/// Extension of the databases `db create` makes.
+const DATABASE_EXT: &str = "sqlite3";
+
+/// SQLite files that belong to a database: the file itself and its WAL sidecars.
+const DATABASE_SUFFIXES: [&str; 3] = ["", "-wal", "-shm"];
+
+/// One database-scoped directory under the cursor home, as `db list` shows it.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(crate = "hub::serde")]
+pub struct RegisteredDatabase {
+    pub name: String,
+    /// The registry's own file, else the database its cursors point at.
+    pub path: Option<PathBuf>,
+    /// `path` lives in the directory (made by `db create`), so `db` may drop or rename it.
+    pub managed: bool,
+    /// Bytes of the database and its `-wal`/`-shm` files.
+    pub size: u64,
+    /// Newest modification time of those files (unix seconds).
+    pub modified_at: Option<i64>,
+    pub cursors: usize,
+}
+
+/// Databases kept as `<home>/<name>/<name>.sqlite3`, beside the `cursors/`
+/// directory of the same name.
+///
+/// Directories a cursor created for a database elsewhere are listed too,
+/// but `drop` only deletes their cursors and `rename` refuses them.
+impl CursorManager {
+    /// File of registry database `name`, whether or not it exists yet.
+    pub fn database_path(&self, name: &str) -> PathBuf {
+        self.home()
+            .join(name)
+            .join(format!("{}.{}", name, DATABASE_EXT))
+    }
+
+    /// Every database-scoped directory, sorted by name.
+    pub fn databases(&self) -> CrudResult<Vec<RegisteredDatabase>> {
+        let entries = match fs::read_dir(self.home()) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(io_error(CrudVerb::List, err)),
+        };
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir() && entry.file_name() != LEGACY_DIR)
+            .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+            .collect();
+        names.sort();
+        Ok(names.iter().map(|name| self.describe(name)).collect())
+    }
+
+    /// Create an empty registry database; returns its path.
+    pub fn create_database(&self, name: &str) -> CrudResult<PathBuf> {
+        let verb = CrudVerb::Create;
+        validate_database_name(name, verb)?;
+        if self.home().join(name).exists() {
+            return Err(conflict(
+                verb,
+                format!("database '{}' already exists", name),
+            ));
+        }
+        let path = self.database_path(name);
+        SqliteKeystore::open(&SqliteConnectionConfig::new(&path))?;
+        Ok(path)
+    }
+
+    /// Delete the directory of `name`: the database when it is managed, and its cursors.
+    pub fn drop_database(&self, name: &str) -> CrudResult<RegisteredDatabase> {
+        let verb = CrudVerb::Delete;
+        validate_database_name(name, verb)?;
+        let dir = self.home().join(name);
+        if !dir.is_dir() {
+            return Err(not_found(verb, format!("no database '{}'", name)));
+        }
+        let dropped = self.describe(name);
+        fs::remove_dir_all(&dir).map_err(|err| io_error(verb, err))?;
+        Ok(dropped)
+    }
+
+    /// Rename managed database `old` to `new`, moving its cursors along and
+    /// pointing them at the new file; returns the new path.
+    pub fn rename_database(&self, old: &str, new: &str) -> CrudResult<PathBuf> {
+        let verb = CrudVerb::Update;
+        validate_database_name(old, verb)?;
+        validate_database_name(new, verb)?;
+        let source = self.describe(old);
+        if !self.home().join(old).is_dir() {
+            return Err(not_found(verb, format!("no database '{}'", old)));
+        }
+        if !source.managed {
+            return Err(invalid_input(
+                verb,
+                format!(
+                    "database '{}' is not managed by the registry; rename its file and re-point the cursors",
+                    old
+                ),
+            ));
+        }
+        if self.home().join(new).exists() {
+            return Err(conflict(verb, format!("database '{}' already exists", new)));
+        }
+
+        let old_path = self.database_path(old);
+        fs::rename(self.home().join(old), self.home().join(new))
+            .map_err(|err| io_error(verb, err))?;
+        let new_path = self.database_path(new);
+        // The directory moved; the files in it still carry the old name.
+        let moved = self
+            .home()
+            .join(new)
+            .join(format!("{}.{}", old, DATABASE_EXT));
+        for suffix in DATABASE_SUFFIXES {
+            let from = sidecar(&moved, suffix);
+            if from.exists() {
+                fs::rename(&from, sidecar(&new_path, suffix)).map_err(|err| io_error(verb, err))?;
+            }
+        }
+        for file in cursor_files(&self.home().join(new)) {
+            let mut data = read_cursor(&file, verb)?;
+            if data.database_path == old_path {
+                data.database_path = new_path.clone();
+                write_cursor(&file, &data, verb)?;
+            }
+        }
+        Ok(new_path)
+    }
+
+    fn describe(&self, name: &str) -> RegisteredDatabase {
+        let dir = self.home().join(name);
+        let cursors = cursor_files(&dir);
+        let own = self.database_path(name);
+        let managed = own.is_file();
+        let path = if managed {
+            Some(own)
+        } else {
+            cursors
+                .iter()
+                .find_map(|file| read_cursor(file, CrudVerb::List).ok())
+                .map(|data| data.database_path)
+        };
+        let (size, modified_at) = path.as_deref().map(database_files).unwrap_or_default();
+        RegisteredDatabase {
+            name: name.to_string(),
+            path,
+            managed,
+            size,
+            modified_at,
+            cursors: cursors.len(),
+        }
+    }
+}
+
+/// Total size and newest mtime of `path` and its sidecars.
+fn database_files(path: &Path) -> (u64, Option<i64>) {
+    let mut size = 0;
+    let mut modified_at = None;
+    for suffix in DATABASE_SUFFIXES {
+        let meta = match fs::metadata(sidecar(path, suffix)) {
+            Ok(meta) => meta,
+            Err(_) => continue,
+        };
+        size += meta.len();
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+            .map(|at| at.as_secs() as i64);
+        modified_at = modified_at.max(modified);
+    }
+    (size, modified_at)
+}
+
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// The `.cursor` files in `<dir>/cursors/`, sorted.
+fn cursor_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir.join(LEGACY_DIR))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .and_then(parse_file_name)
+                        .is_some()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Names become a directory and a file name, so only cursor-name characters are allowed.
+fn validate_database_name(name: &str, verb: CrudVerb) -> CrudResult<()> {
+    let valid = !name.is_empty()
+        && name != LEGACY_DIR
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid_input(
+            verb,
+            format!(
+                "invalid database name '{}' (use letters, digits, '_' or '-')",
+                name
+            ),
+        ))
+    }
+}
@@ -0,0 +1,101 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use hub::data_ext::serde_json::{self, Value};
+
+use crate::lib::core::crud::{CrudDomain, CrudError, CrudObjectKind, CrudResult, CrudVerb};
+
+use super::data::CursorData;
+use super::manager::DEFAULT_CURSOR;
+
+/// Per-directory cursor file, found in the working directory or any parent.
+pub const WORKDIR_FILE: &str = ".prontodb";
+
+/// Opt-in switch for `.prontodb` discovery (`1`, `true`, or `on`).
+pub const WORK_MODE_ENV: &str = "PRONTO_WORK_MODE";
+
+pub fn work_mode_enabled() -> bool {
+    match env::var(WORK_MODE_ENV) {
+        Ok(value) => matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "on"),
+        Err(_) => false,
+    }
+}
+
+/// Cursor `name` of `user` from the nearest `.prontodb` at or above `start`.
+///
+/// Like `.git`, the nearest file wins and the walk stops there, even when that
+/// file does not define `name`. The file is either a bare database path (the
+/// default cursor) or JSON:
+///
+/// ```json
+/// {"path": "db/dev.sqlite3", "project": "app", "namespace": "config",
+///  "cursors": {"prod": "/srv/prod.sqlite3"},
+///  "users": {"alice": {"scratch": {"path": "/tmp/alice.sqlite3", "project": "tmp"}}}}
+/// ```
+///
+/// Cursor entries are a path or `{"path", "project", "namespace"}`; the top-level
+/// `project`/`namespace` apply to every entry that sets none. Relative paths are
+/// resolved against the directory holding the `.prontodb`.
+pub fn resolve_working_directory_cursor(
+    start: &Path,
+    name: &str,
+    user: &str,
+) -> CrudResult<Option<CursorData>> {
+    let file = match start
+        .ancestors()
+        .map(|dir| dir.join(WORKDIR_FILE))
+        .find(|file| file.is_file())
+    {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    let base = file.parent().unwrap_or(start).to_path_buf();
+    let raw = fs::read_to_string(&file).map_err(|err| invalid(&file, err.to_string()))?;
+    let raw = raw.trim();
+
+    if !raw.starts_with('{') {
+        if name != DEFAULT_CURSOR || raw.is_empty() {
+            return Ok(None);
+        }
+        return Ok(Some(CursorData::new(base.join(raw), user)));
+    }
+
+    let config: Value = serde_json::from_str(raw).map_err(|err| invalid(&file, err.to_string()))?;
+    let entry = config
+        .get("users")
+        .and_then(|users| users.get(user))
+        .and_then(|cursors| cursors.get(name))
+        .or_else(|| config.get("cursors").and_then(|cursors| cursors.get(name)));
+    let entry = match entry {
+        Some(entry) => entry,
+        // The top-level "path" is the default cursor.
+        None if name == DEFAULT_CURSOR && config.get("path").is_some() => &config,
+        None => return Ok(None),
+    };
+
+    let field =
+        |value: &Value, key: &str| value.get(key).and_then(Value::as_str).map(str::to_string);
+    let path = match entry {
+        Value::String(path) => path.clone(),
+        other => field(other, "path")
+            .ok_or_else(|| invalid(&file, format!("cursor '{}' has no \"path\"", name)))?,
+    };
+    let project = field(entry, "project").or_else(|| field(&config, "project"));
+    let namespace = field(entry, "namespace").or_else(|| field(&config, "namespace"));
+
+    Ok(Some(
+        CursorData::new(base.join(PathBuf::from(path)), user)
+            .with_project(project)
+            .with_namespace(namespace),
+    ))
+}
+
+fn invalid(file: &Path, message: String) -> CrudError {
+    CrudError::invalid_input(
+        CrudDomain::Filesystem,
+        CrudObjectKind::Alias,
+        CrudVerb::Read,
+        format!("{}: {}", file.display(), message),
+    )
+}
@@ -0,0 +1,176 @@
+//! C ABI over `ProntoClient` (feature `ffi`); the header is `include/prontodb.h`.
+//!
+//! Calls return the CLI's exit codes (0 ok, 1 error, 2 missing, 3 conflict,
+//! 4 busy), so C and Go callers branch the same way scripts do. Strings are
+//! NUL-terminated UTF-8; every string handed out must go back through
+//! `pronto_free`, and the message of the last failure is kept on the handle.
+
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+use crate::lib::api::ProntoClient;
+use crate::lib::cli::app::{exit_code, EXIT_ERROR, EXIT_MISS, EXIT_OK};
+use crate::lib::core::crud::CrudError;
+
+/// An open database; create with `pronto_open`, release with `pronto_close`.
+pub struct ProntoHandle {
+    client: ProntoClient,
+    last_error: Option<CString>,
+}
+
+impl ProntoHandle {
+    fn fail(&mut self, error: &CrudError) -> c_int {
+        self.last_error = Some(c_string(&error.to_string()));
+        exit_code(error)
+    }
+
+    fn reject(&mut self, message: &str) -> c_int {
+        self.last_error = Some(c_string(message));
+        EXIT_ERROR
+    }
+}
+
+/// Open (creating if needed) the database at `path`; null on failure.
+///
+/// # Safety
+/// `path` must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pronto_open(path: *const c_char) -> *mut ProntoHandle {
+    let path = match str_arg(path) {
+        Some(path) => path,
+        None => return ptr::null_mut(),
+    };
+    match ProntoClient::open(path) {
+        Ok(client) => Box::into_raw(Box::new(ProntoHandle {
+            client,
+            last_error: None,
+        })),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Close a handle from `pronto_open`; null is ignored.
+///
+/// # Safety
+/// `handle` must come from `pronto_open` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pronto_close(handle: *mut ProntoHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Store `value` at `address`; `ttl_secs` 0 means no expiry.
+///
+/// # Safety
+/// `handle` must be live; `address` and `value` valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn pronto_set(
+    handle: *mut ProntoHandle,
+    address: *const c_char,
+    value: *const c_char,
+    ttl_secs: u64,
+) -> c_int {
+    let handle = match handle.as_mut() {
+        Some(handle) => handle,
+        None => return EXIT_ERROR,
+    };
+    let (address, value) = match (str_arg(address), str_arg(value)) {
+        (Some(address), Some(value)) => (address, value),
+        _ => return handle.reject("address and value must be non-null UTF-8"),
+    };
+    let ttl_secs = (ttl_secs > 0).then_some(ttl_secs);
+    match handle.client.set(address, value, ttl_secs) {
+        Ok(()) => EXIT_OK,
+        Err(error) => handle.fail(&error),
+    }
+}
+
+/// Read `address` into `*out` (free it with `pronto_free`); 2 when missing, leaving `*out` null.
+///
+/// # Safety
+/// `handle` must be live, `address` a valid NUL-terminated string and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn pronto_get(
+    handle: *mut ProntoHandle,
+    address: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    let handle = match handle.as_mut() {
+        Some(handle) => handle,
+        None => return EXIT_ERROR,
+    };
+    if out.is_null() {
+        return handle.reject("out must be non-null");
+    }
+    *out = ptr::null_mut();
+    let address = match str_arg(address) {
+        Some(address) => address,
+        None => return handle.reject("address must be non-null UTF-8"),
+    };
+    match handle.client.get(address) {
+        Ok(Some(value)) => {
+            *out = c_string(&value).into_raw();
+            EXIT_OK
+        }
+        Ok(None) => EXIT_MISS,
+        Err(error) => handle.fail(&error),
+    }
+}
+
+/// Delete `address`; 2 when there was nothing to delete.
+///
+/// # Safety
+/// `handle` must be live and `address` a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn pronto_delete(handle: *mut ProntoHandle, address: *const c_char) -> c_int {
+    let handle = match handle.as_mut() {
+        Some(handle) => handle,
+        None => return EXIT_ERROR,
+    };
+    let address = match str_arg(address) {
+        Some(address) => address,
+        None => return handle.reject("address must be non-null UTF-8"),
+    };
+    match handle.client.delete(address) {
+        Ok(true) => EXIT_OK,
+        Ok(false) => EXIT_MISS,
+        Err(error) => handle.fail(&error),
+    }
+}
+
+/// Message of the handle's last failure, or null; valid until the next failure or `pronto_close`.
+///
+/// # Safety
+/// `handle` must be null or live.
+#[no_mangle]
+pub unsafe extern "C" fn pronto_last_error(handle: *const ProntoHandle) -> *const c_char {
+    handle
+        .as_ref()
+        .and_then(|handle| handle.last_error.as_ref())
+        .map_or(ptr::null(), |message| message.as_ptr())
+}
+
+/// Free a string returned by `pronto_get`; null is ignored.
+///
+/// # Safety
+/// `value` must come from this library and not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn pronto_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+unsafe fn str_arg<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        return None;
+    }
+    CStr::from_ptr(value).to_str().ok()
+}
+
+/// Values may hold NUL bytes; C sees them cut at the first one.
+fn c_string(value: &str) -> CString {
+    let end = value.find('\0').unwrap_or(value.len());
+    CString::new(&value[..end]).unwrap_or_default()
+}
@@ -1,5 +1,12 @@
 //! Library namespace for ProntoDB components (work in progress).
 
+pub mod addr;
 pub mod adpt;
+pub mod api;
 pub mod cli;
 pub mod core;
+pub mod cursor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
@@ -0,0 +1,154 @@
+//! Python module `prontodb` over `ProntoClient` (feature `python`, built with maturin).
+//!
+//! ```python
+//! import prontodb
+//! db = prontodb.open("app.sqlite3")
+//! db.set("app.config.debug", "true")
+//! db.get("app.config.debug")        # "true", or None when missing
+//! db["app.config.debug"]            # KeyError when missing
+//! ```
+//!
+//! Failures raise `prontodb.ProntoError` with the stable code first
+//! (`NS_NOT_TTL: namespace app.config is not TTL-enabled ...`).
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyKeyError};
+use pyo3::prelude::*;
+
+use crate::lib::adpt::sqlite::SqliteConnectionConfig;
+use crate::lib::api::{ErrorCode, ProntoClient};
+use crate::lib::core::crud::{CrudError, CrudResult};
+
+create_exception!(prontodb, ProntoError, PyException);
+
+/// Python's `prontodb.Client`: one open database.
+#[pyclass(name = "Client", module = "prontodb")]
+struct PyClient {
+    client: ProntoClient,
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    #[pyo3(signature = (path, user=None, key_file=None, read_only=false))]
+    fn new(
+        path: &str,
+        user: Option<String>,
+        key_file: Option<String>,
+        read_only: bool,
+    ) -> PyResult<Self> {
+        let config = SqliteConnectionConfig::new(path)
+            .with_user(user)
+            .with_key_file(key_file)
+            .with_read_only(read_only);
+        Ok(Self {
+            client: raised(ProntoClient::with_config(&config))?,
+        })
+    }
+
+    #[getter]
+    fn path(&self) -> String {
+        self.client.path().display().to_string()
+    }
+
+    #[pyo3(signature = (address, value, ttl=None))]
+    fn set(&self, address: &str, value: &str, ttl: Option<u64>) -> PyResult<()> {
+        raised(self.client.set(address, value, ttl))
+    }
+
+    #[pyo3(signature = (address, default=None))]
+    fn get(&self, address: &str, default: Option<String>) -> PyResult<Option<String>> {
+        Ok(raised(self.client.get(address))?.or(default))
+    }
+
+    /// Returns whether a key was removed.
+    fn delete(&self, address: &str) -> PyResult<bool> {
+        raised(self.client.delete(address))
+    }
+
+    fn exists(&self, address: &str) -> PyResult<bool> {
+        raised(self.client.exists(address))
+    }
+
+    fn expire(&self, address: &str, ttl: u64) -> PyResult<bool> {
+        raised(self.client.expire(address, ttl))
+    }
+
+    /// Full addresses of the live keys under `scope`.
+    fn keys(&self, scope: &str) -> PyResult<Vec<String>> {
+        let entries = raised(self.client.entries(scope))?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| entry.address.to_string())
+            .collect())
+    }
+
+    /// `(address, value)` pairs under `scope`, in key order.
+    fn items(&self, scope: &str) -> PyResult<Vec<(String, String)>> {
+        let entries = raised(self.client.entries(scope))?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| (entry.address.to_string(), entry.value))
+            .collect())
+    }
+
+    fn projects(&self) -> PyResult<Vec<String>> {
+        raised(self.client.projects())
+    }
+
+    fn namespaces(&self, project: &str) -> PyResult<Vec<String>> {
+        raised(self.client.namespaces(project))
+    }
+
+    fn __getitem__(&self, address: &str) -> PyResult<String> {
+        raised(self.client.get(address))?.ok_or_else(|| PyKeyError::new_err(address.to_string()))
+    }
+
+    fn __setitem__(&self, address: &str, value: &str) -> PyResult<()> {
+        raised(self.client.set(address, value, None))
+    }
+
+    fn __delitem__(&self, address: &str) -> PyResult<()> {
+        if raised(self.client.delete(address))? {
+            Ok(())
+        } else {
+            Err(PyKeyError::new_err(address.to_string()))
+        }
+    }
+
+    fn __contains__(&self, address: &str) -> PyResult<bool> {
+        raised(self.client.exists(address))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("prontodb.Client({:?})", self.path())
+    }
+}
+
+/// `prontodb.open(path, user=None, key_file=None, read_only=False)`.
+#[pyfunction]
+#[pyo3(signature = (path, user=None, key_file=None, read_only=false))]
+fn open(
+    path: &str,
+    user: Option<String>,
+    key_file: Option<String>,
+    read_only: bool,
+) -> PyResult<PyClient> {
+    PyClient::new(path, user, key_file, read_only)
+}
+
+/// The `prontodb` module maturin exports; embedders can register it with
+/// `pyo3::append_to_inittab!` before starting the interpreter.
+#[pymodule]
+pub fn prontodb(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClient>()?;
+    m.add_function(wrap_pyfunction!(open, m)?)?;
+    m.add("ProntoError", m.py().get_type_bound::<ProntoError>())?;
+    Ok(())
+}
+
+fn raised<T>(result: CrudResult<T>) -> PyResult<T> {
+    result.map_err(|error: CrudError| {
+        ProntoError::new_err(format!("{}: {}", ErrorCode::of(&error), error.source()))
+    })
+}
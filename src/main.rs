@@ -1,14 +1,24 @@
 // Core ProntoDB Application - NOT the admin CLI
-// The admin CLI is separate in src/bin/admin-cli.rs
+// The admin CLI is separate in src/bin/admin.rs
 
+use prontodb::lib::cli::app::{do_backup, do_install, do_restore, do_uninstall, pronto_dispatch};
 use rsb::prelude::*;
-//new base dispatch
 
 fn main() {
     // Core ProntoDB app bootstrap
     let args = bootstrap!();
     options!(&args);
 
+    // Lifecycle commands run ahead of the core table; RSB exits with their code
+    if pre_dispatch!(&args, {
+        "install" => do_install,
+        "uninstall" => do_uninstall,
+        "backup" => do_backup,
+        "restore" => do_restore
+    }) {
+        return;
+    }
+
     // Core application dispatch (separate from admin CLI)
-    std::process::exit(0); // Placeholder - implement core_dispatch later
+    std::process::exit(pronto_dispatch(args));
 }
@@ -0,0 +1,2293 @@
+use assert_cmd::Command;
+use prontodb::lib::adpt::sqlite::{Permission, SqliteConnectionConfig, SqliteKeystore};
+use tempfile::tempdir;
+
+fn prontodb(db: &std::path::Path, args: &[&str]) -> Command {
+    let mut cmd = Command::cargo_bin("prontodb").unwrap();
+    cmd.arg(format!("--database-path={}", db.display()));
+    cmd.args(args);
+    cmd
+}
+
+/// A read-write API token for all of `project`, as `serve` clients present.
+fn api_token(db: &std::path::Path, project: &str) -> String {
+    let store = SqliteKeystore::open(&SqliteConnectionConfig::new(db)).unwrap();
+    let (_, raw) = store.create_token(project, "*", Permission::Write).unwrap();
+    raw
+}
+
+#[test]
+fn scan_formats_escape_delimiters_and_newlines() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    prontodb(&db, &["set", "app.cfg.dsn", "a=b,c"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cfg.motd", "line1\nline2"])
+        .assert()
+        .success();
+
+    prontodb(&db, &["scan", "app.cfg", "--format=csv"])
+        .assert()
+        .success()
+        .stdout("key,value\ndsn,\"a=b,c\"\nmotd,\"line1\nline2\"\n");
+
+    prontodb(&db, &["scan", "app.cfg", "--format=tsv"])
+        .assert()
+        .success()
+        .stdout("key\tvalue\ndsn\ta=b,c\nmotd\tline1\\nline2\n");
+
+    prontodb(&db, &["scan", "app.cfg", "--format=xml"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn verbosity_flags_gate_chatter_and_diagnostics() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    prontodb(&db, &["set", "app.cfg.port", "8080"])
+        .assert()
+        .success();
+
+    prontodb(&db, &["copy", "app.cfg.*", "app.backup"])
+        .assert()
+        .success()
+        .stdout("Copied 1 keys from app.cfg.* to app.backup\n");
+    prontodb(&db, &["copy", "app.cfg.*", "app.quiet", "-q"])
+        .assert()
+        .success()
+        .stdout("");
+
+    let output = prontodb(&db, &["get", "cfg.port__dev", "-v"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("[verbose] database: "));
+    assert!(stderr.contains("address: cfg.port__dev -> default.cfg.port__dev (context: dev)"));
+
+    let output = prontodb(&db, &["get", "app.cfg.port", "--meta=acme", "-v"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("address: app.cfg.port -> acme.app.cfg.port (context: none)"));
+}
+
+#[test]
+fn keys_long_lists_metadata_columns() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    prontodb(&db, &["set", "app.cfg.port", "8080"])
+        .env("PRONTO_NOW", "1792000454")
+        .assert()
+        .success();
+
+    prontodb(&db, &["keys", "app.cfg", "--long", "--format=csv"])
+        .env("PRONTO_NOW", "1792000500")
+        .assert()
+        .success()
+        .stdout(
+            "key,ttl,size,created,updated\nport,-,4,2026-10-14T17:54:14Z,2026-10-14T17:54:14Z\n",
+        );
+}
+
+#[test]
+fn export_renders_toml_and_env() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    prontodb(&db, &["set", "app.cfg.motd__dev", "it's \"on\""])
+        .assert()
+        .success();
+
+    prontodb(&db, &["export", "app.cfg", "--format=toml"])
+        .assert()
+        .success()
+        .stdout(
+            "# prontodb export\n\n[[keys]]\nproject = \"app\"\nnamespace = \"cfg\"\n\
+             key = \"motd\"\ncontext = \"dev\"\nvalue = \"it's \\\"on\\\"\"\n",
+        );
+
+    prontodb(&db, &["export", "app", "--format=env"])
+        .assert()
+        .success()
+        .stdout("app.cfg.motd__dev='it'\\''s \"on\"'\n");
+
+    let out = temp.path().join("dump.json");
+    prontodb(&db, &["export", "--out", out.to_str().unwrap(), "-q"])
+        .assert()
+        .success()
+        .stdout("");
+    let dump = std::fs::read_to_string(&out).unwrap();
+    assert!(dump.contains("\"context\": \"dev\""));
+    assert!(dump.contains("\"ttl\": null"));
+}
+
+#[test]
+fn import_round_trips_export_and_honours_strategy() {
+    let temp = tempdir().unwrap();
+    let src = temp.path().join("src.sqlite");
+    let dst = temp.path().join("dst.sqlite");
+
+    prontodb(&src, &["set", "app.cfg.motd__dev", "it's\n\"on\""])
+        .assert()
+        .success();
+    prontodb(&dst, &["set", "app.cfg.motd__dev", "old"])
+        .assert()
+        .success();
+
+    for format in ["json", "toml", "env"] {
+        let file = temp.path().join(format!("dump.{}", format));
+        let file = file.to_str().unwrap();
+        prontodb(&src, &["export", "app", "--format", format, "--out", file])
+            .assert()
+            .success();
+
+        prontodb(
+            &dst,
+            &["import", file, "--format", format, "--strategy=fail"],
+        )
+        .assert()
+        .code(3);
+        prontodb(&dst, &["import", file, "--format", format])
+            .assert()
+            .success()
+            .stdout("Imported 1 keys (1 inserted, 0 skipped, 0 conflicted)\n");
+        prontodb(&dst, &["get", "app.cfg.motd__dev"])
+            .assert()
+            .success()
+            .stdout("it's\n\"on\"\n");
+        prontodb(&dst, &["set", "app.cfg.motd__dev", "old"])
+            .assert()
+            .success();
+    }
+}
+
+#[test]
+fn env_export_prints_prefixed_shell_safe_lines() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    prontodb(&db, &["set", "myapp.config.db-url", "postgres://x"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "myapp.config.motd", "it's $HOME"])
+        .assert()
+        .success();
+
+    prontodb(
+        &db,
+        &["env", "export", "myapp.config", "--prefix", "MYAPP_"],
+    )
+    .assert()
+    .success()
+    .stdout("MYAPP_DB_URL=postgres://x\nMYAPP_MOTD='it'\\''s $HOME'\n");
+
+    prontodb(&db, &["env", "export", "myapp.config", "--export"])
+        .assert()
+        .success()
+        .stdout("export DB_URL=postgres://x\nexport MOTD='it'\\''s $HOME'\n");
+}
+
+#[test]
+fn scan_sorts_by_updated_and_size() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    for (now, key, value) in [
+        ("1792000300", "a", "333"),
+        ("1792000100", "b", "1"),
+        ("1792000200", "c", "22"),
+    ] {
+        prontodb(&db, &["set", &format!("app.cfg.{}", key), value])
+            .env("PRONTO_NOW", now)
+            .assert()
+            .success();
+    }
+
+    prontodb(&db, &["scan", "app.cfg", "--sort", "updated"])
+        .assert()
+        .success()
+        .stdout("b=1\nc=22\na=333\n");
+    prontodb(&db, &["scan", "app.cfg", "--sort=size", "--desc"])
+        .assert()
+        .success()
+        .stdout("a=333\nc=22\nb=1\n");
+    prontodb(&db, &["scan", "app.cfg", "--desc"])
+        .assert()
+        .success()
+        .stdout("c=22\nb=1\na=333\n");
+    prontodb(&db, &["scan", "app.cfg", "--sort=age"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn db_registry_creates_renames_and_drops_databases() {
+    let temp = tempdir().unwrap();
+    let home = temp.path().join("home");
+    let pronto = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("prontodb").unwrap();
+        cmd.env("PRONTO_CURSOR_HOME", &home).args(args);
+        cmd
+    };
+    pronto(&["db", "create", "scratch"]).assert().success();
+    pronto(&["db", "create", "scratch"]).assert().failure();
+    pronto(&["--database=scratch", "set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    let scratch = home.join("scratch").join("scratch.sqlite3");
+    pronto(&["cursor", "set", "work", scratch.to_str().unwrap()])
+        .assert()
+        .success();
+    pronto(&["db", "list"])
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with("scratch\t"))
+        .stdout(predicates::str::contains(format!(
+            "\t1\t{}\n",
+            scratch.display()
+        )));
+
+    pronto(&["db", "rename", "scratch", "sandbox"])
+        .assert()
+        .success();
+    pronto(&["--cursor=work", "get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("localhost\n");
+    pronto(&["--database=sandbox", "get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("localhost\n");
+
+    pronto(&["db", "drop", "sandbox"]).assert().failure();
+    pronto(&["db", "drop", "sandbox", "--yes"])
+        .assert()
+        .success();
+    assert!(!home.join("sandbox").exists());
+    pronto(&["db", "list"]).assert().success().stdout("");
+}
+
+#[test]
+fn cursor_bundles_round_trip_between_homes() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    let (source, target) = (temp.path().join("a"), temp.path().join("b"));
+    let bundle = temp.path().join("cursors.json");
+    let bundle_arg = bundle.to_str().unwrap();
+
+    for (name, path, user) in [
+        ("staging", "/srv/staging.sqlite3", "default"),
+        ("prod", "/srv/prod.sqlite3", "default"),
+        ("scratch", "/tmp/scratch.sqlite3", "alice"),
+    ] {
+        prontodb(&db, &["cursor", "set", name, path, "--user", user])
+            .env("PRONTO_CURSOR_HOME", &source)
+            .assert()
+            .success();
+    }
+
+    prontodb(
+        &db,
+        &["cursor", "export", "--user=default", "--out", bundle_arg],
+    )
+    .env("PRONTO_CURSOR_HOME", &source)
+    .assert()
+    .success();
+    prontodb(&db, &["cursor", "import", bundle_arg])
+        .env("PRONTO_CURSOR_HOME", &target)
+        .assert()
+        .success();
+    prontodb(&db, &["cursor", "list"])
+        .env("PRONTO_CURSOR_HOME", &target)
+        .assert()
+        .success()
+        .stdout("prod\t/srv/prod.sqlite3\nstaging\t/srv/staging.sqlite3\n");
+    prontodb(&db, &["cursor", "list", "--user", "alice"])
+        .env("PRONTO_CURSOR_HOME", &target)
+        .assert()
+        .success()
+        .stdout("");
+
+    prontodb(&db, &["cursor", "import", bundle_arg])
+        .env("PRONTO_CURSOR_HOME", &target)
+        .assert()
+        .code(3);
+    prontodb(&db, &["cursor", "import", bundle_arg, "--force"])
+        .env("PRONTO_CURSOR_HOME", &target)
+        .assert()
+        .success();
+}
+
+#[test]
+fn cursor_defaults_fill_partial_addresses() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("work.sqlite");
+    let home = temp.path().join("cursors");
+    let db_arg = db.to_str().unwrap();
+    let cursor = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("prontodb").unwrap();
+        cmd.env("PRONTO_CURSOR_HOME", &home).args(args);
+        cmd
+    };
+
+    cursor(&[
+        "cursor",
+        "set",
+        "work",
+        db_arg,
+        "--project=app",
+        "--namespace=cfg",
+    ])
+    .assert()
+    .success();
+    cursor(&["--cursor=work", "set", "debug", "true"])
+        .assert()
+        .success();
+    cursor(&["--cursor=work", "set", "flags.beta", "on"])
+        .assert()
+        .success();
+
+    prontodb(&db, &["get", "app.cfg.debug"])
+        .assert()
+        .success()
+        .stdout("true\n");
+    prontodb(&db, &["get", "app.flags.beta"])
+        .assert()
+        .success()
+        .stdout("on\n");
+    cursor(&["--cursor=work", "get", "debug"])
+        .assert()
+        .success()
+        .stdout("true\n");
+    cursor(&["--cursor=missing", "get", "debug"])
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn cursor_show_prints_every_field() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    let home = temp.path().join("home");
+    let file = home.join("work/cursors/work.cursor");
+
+    prontodb(
+        &db,
+        &[
+            "cursor",
+            "set",
+            "work",
+            "/srv/work.sqlite3",
+            "--project=app",
+        ],
+    )
+    .env("PRONTO_CURSOR_HOME", &home)
+    .env("PRONTO_NOW", "1792000454")
+    .assert()
+    .success();
+
+    prontodb(&db, &["cursor", "show", "work"])
+        .env("PRONTO_CURSOR_HOME", &home)
+        .assert()
+        .success()
+        .stdout(format!(
+            "name:      work\n\
+             database:  /srv/work.sqlite3\n\
+             user:      default\n\
+             created:   2026-10-14T17:54:14Z\n\
+             project:   app\n\
+             namespace: -\n\
+             meta:      -\n\
+             file:      {}\n\
+             read-only: no\n\
+             legacy:    no\n",
+            file.display()
+        ));
+    prontodb(&db, &["--json", "cursor", "show", "work"])
+        .env("PRONTO_CURSOR_HOME", &home)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"legacy\":false"))
+        .stdout(predicates::str::contains("\"default_project\":\"app\""));
+    prontodb(&db, &["cursor", "show", "missing"])
+        .env("PRONTO_CURSOR_HOME", &home)
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn cursor_usage_is_tracked_and_stale_cursors_pruned() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    let home = temp.path().join("home");
+    let db_arg = db.to_str().unwrap();
+    let cursor = |now: &str, args: &[&str]| {
+        let mut cmd = Command::cargo_bin("prontodb").unwrap();
+        cmd.env("PRONTO_CURSOR_HOME", &home)
+            .env("PRONTO_NOW", now)
+            .args(args);
+        cmd
+    };
+
+    // Day 0: both created; day 50: only "hot" is used.
+    for name in ["hot", "cold"] {
+        cursor("1780000000", &["cursor", "set", name, db_arg])
+            .assert()
+            .success();
+    }
+    cursor("1784320000", &["--cursor=hot", "set", "k", "v"])
+        .assert()
+        .success();
+
+    cursor("1784320000", &["cursor", "list", "--sort=used"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "cold\t{db}\tnever\nhot\t{db}\t2026-07-17T20:26:40Z\n",
+            db = db.display()
+        ));
+
+    // Day 100: "cold" is 100 days idle, "hot" only 50.
+    cursor(
+        "1788640000",
+        &["cursor", "prune", "--older-than", "90d", "--dry-run"],
+    )
+    .assert()
+    .success()
+    .stdout("cold\n");
+    cursor("1788640000", &["cursor", "prune", "--older-than=90d"])
+        .assert()
+        .success()
+        .stdout("cold\n");
+    cursor("1788640000", &["cursor", "list"])
+        .assert()
+        .success()
+        .stdout(format!("hot\t{}\n", db.display()));
+    cursor("1788640000", &["cursor", "prune", "--older-than", "soon"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn read_only_mode_refuses_writes_but_serves_reads() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    let home = temp.path().join("home");
+    prontodb(&db, &["set", "app.cfg.mode", "live"])
+        .assert()
+        .success();
+
+    for args in [
+        &["--read-only", "set", "app.cfg.mode", "test"][..],
+        &["--read-only", "del", "app.cfg.mode"][..],
+        &["--read-only", "hook", "set", "app.cfg", "true"][..],
+        &["--read-only", "db", "create", "scratch"][..],
+        &["--read-only", "backup", "prune"][..],
+        &["--read-only", "cursor", "rm", "prod"][..],
+    ] {
+        prontodb(&db, args)
+            .assert()
+            .code(1)
+            .stderr(predicates::str::contains("read-only mode"));
+    }
+    prontodb(&db, &["--read-only", "get", "app.cfg.mode"])
+        .assert()
+        .success()
+        .stdout("live\n");
+    prontodb(&db, &["--read-only", "hook", "list"])
+        .assert()
+        .success();
+
+    // A read-only cursor behaves the same without the flag.
+    Command::cargo_bin("prontodb")
+        .unwrap()
+        .env("PRONTO_CURSOR_HOME", &home)
+        .args(["cursor", "set", "prod", db.to_str().unwrap(), "--read-only"])
+        .assert()
+        .success();
+    let with_cursor = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("prontodb").unwrap();
+        cmd.env("PRONTO_CURSOR_HOME", &home)
+            .arg("--cursor=prod")
+            .args(args);
+        cmd
+    };
+    with_cursor(&["mset", "app.cfg.mode=test"]).assert().code(1);
+    with_cursor(&["get", "app.cfg.mode"])
+        .assert()
+        .success()
+        .stdout("live\n");
+}
+
+#[test]
+fn system_does_not_bypass_namespace_acls() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    prontodb(&db, &["set", "app.secret.token", "abc"])
+        .assert()
+        .success();
+    let store = SqliteKeystore::open(&SqliteConnectionConfig::new(&db)).unwrap();
+    store
+        .grant_access("alice", "app", "secret", Permission::Read)
+        .unwrap();
+
+    prontodb(&db, &["get", "app.secret.token"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("no read access"));
+    // `--system` only opens the reserved namespaces; it never widens an ACL.
+    prontodb(&db, &["--system", "get", "app.secret.token"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("no read access"));
+    prontodb(
+        &db,
+        &["--system", "--user=mallory", "get", "app.secret.token"],
+    )
+    .assert()
+    .code(1)
+    .stderr(predicates::str::contains(
+        "user 'mallory' has no read access",
+    ));
+    prontodb(&db, &["--system", "get", "app.secret.token"])
+        .env("PRONTO_USER", "mallory")
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains(
+            "user 'mallory' has no read access",
+        ));
+    prontodb(&db, &["--system", "stream"])
+        .write_stdin("sec:user=mallory; ns=app.secret; token=xyz;")
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("no write access"));
+    // The database owner may act as a user the rules name.
+    prontodb(&db, &["--user=alice", "get", "app.secret.token"])
+        .assert()
+        .success()
+        .stdout("abc\n");
+}
+
+#[test]
+fn scan_masks_secret_values_unless_revealed() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    prontodb(&db, &["set", "app.cfg.db_pass", "hunter2"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cfg.port", "5432"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.keys.api", "k-123"])
+        .assert()
+        .success();
+    prontodb(&db, &["secret", "app.cfg.db_pass"])
+        .assert()
+        .success();
+    prontodb(&db, &["secret", "app.keys"]).assert().success();
+
+    prontodb(&db, &["scan", "app.cfg"])
+        .assert()
+        .success()
+        .stdout("db_pass=****\nport=5432\n");
+    prontodb(&db, &["scan", "app.*.*"])
+        .assert()
+        .success()
+        .stdout("app.cfg.db_pass=****\napp.cfg.port=5432\napp.keys.api=****\n");
+    prontodb(&db, &["scan", "app.cfg", "--ndjson"])
+        .assert()
+        .success()
+        .stdout(
+            "{\"key\":\"db_pass\",\"value\":\"****\",\"ttl\":null}\n\
+             {\"key\":\"port\",\"value\":\"5432\",\"ttl\":null}\n",
+        );
+    prontodb(&db, &["scan", "app.cfg", "--reveal"])
+        .assert()
+        .success()
+        .stdout("db_pass=hunter2\nport=5432\n");
+
+    prontodb(&db, &["secret", "app.keys", "--off"])
+        .assert()
+        .success();
+    prontodb(&db, &["scan", "app.keys"])
+        .assert()
+        .success()
+        .stdout("api=k-123\n");
+}
+
+#[test]
+fn admin_capability_toggles_persist_in_the_database() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    prontodb(&db, &["set", "app.cfg.mode", "live"])
+        .assert()
+        .success();
+
+    prontodb(&db, &["admin", "capability", "disable", "table", "delete"])
+        .assert()
+        .success()
+        .stdout("[capability] table delete disabled\n");
+    prontodb(&db, &["admin", "capability", "list"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "[capabilities] disabled\n  table delete\n",
+        ));
+    prontodb(&db, &["admin", "capability", "disable", "table", "bogus"])
+        .assert()
+        .code(1);
+    prontodb(
+        &db,
+        &[
+            "--read-only",
+            "admin",
+            "capability",
+            "enable",
+            "table",
+            "delete",
+        ],
+    )
+    .assert()
+    .code(1)
+    .stderr(predicates::str::contains("read-only mode"));
+
+    prontodb(&db, &["admin", "capability", "enable", "table", "delete"])
+        .assert()
+        .success();
+    let listed = prontodb(&db, &["admin", "capability", "list"])
+        .output()
+        .unwrap();
+    let listed = String::from_utf8(listed.stdout).unwrap();
+    assert!(listed.contains("table => ["), "{}", listed);
+    assert!(!listed.contains("disabled"), "{}", listed);
+}
+
+#[test]
+fn cursor_reset_needs_confirmation_outside_a_terminal() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    let home = temp.path().join("home");
+    let cursor = |args: &[&str]| {
+        let mut cmd = prontodb(&db, args);
+        cmd.env("PRONTO_CURSOR_HOME", &home);
+        cmd
+    };
+    let db_arg = db.to_str().unwrap();
+    for (name, user) in [("prod", "default"), ("dev", "default"), ("prod", "alice")] {
+        cursor(&["cursor", "set", name, db_arg, "--user", user])
+            .assert()
+            .success();
+    }
+
+    cursor(&["cursor", "reset"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("pass --yes"));
+    cursor(&["cursor", "reset", "--yes"])
+        .assert()
+        .success()
+        .stdout("Removed 2 cursors\n");
+    cursor(&["cursor", "list", "--user", "alice"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("prod"));
+
+    cursor(&["cursor", "reset", "--all", "--yes"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("pass --force"));
+    cursor(&["cursor", "reset", "--all", "--force"])
+        .assert()
+        .success()
+        .stdout("Removed 1 cursors\n");
+}
+
+#[test]
+fn stream_applies_tokens_in_one_transaction() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+
+    prontodb(&db, &["stream"])
+        .write_stdin("ns=zoo.animals; dog=fido;\ncat='tom; jerry'; zoo.birds:owl=\"hoot\";\n")
+        .assert()
+        .success()
+        .stdout("Stored 3 keys from the stream\n");
+    prontodb(&db, &["get", "zoo.animals.cat"])
+        .assert()
+        .success()
+        .stdout("tom; jerry\n");
+    prontodb(&db, &["get", "zoo.birds.owl"])
+        .assert()
+        .success()
+        .stdout("hoot\n");
+
+    prontodb(&db, &["stream"])
+        .write_stdin("ns=zoo.animals; dog=rex; meta:colour=red;")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("unknown directive meta:colour"));
+    prontodb(&db, &["stream"])
+        .write_stdin("ns=zoo.animals; dog=rex; bad.address.key=1;")
+        .assert()
+        .failure();
+    prontodb(&db, &["get", "zoo.animals.dog"])
+        .assert()
+        .success()
+        .stdout("fido\n");
+}
+
+#[test]
+fn load_stores_key_value_lines_in_one_transaction() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+
+    prontodb(&db, &["load", "-p", "app", "-n", "cfg"])
+        .write_stdin("host=localhost\n# comment\n\nmotd=a=b c\nother.flags.debug=1\n")
+        .assert()
+        .success()
+        .stdout("Loaded 3 keys\n");
+    prontodb(&db, &["get", "app.cfg.motd"])
+        .assert()
+        .success()
+        .stdout("a=b c\n");
+    prontodb(&db, &["get", "other.flags.debug"])
+        .assert()
+        .success()
+        .stdout("1\n");
+
+    prontodb(&db, &["load", "-n", "cfg"])
+        .write_stdin("host=remote\nnot a pair\n")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("line 2: expected key=value"));
+    prontodb(&db, &["get", "default.cfg.host"]).assert().code(2);
+}
+
+#[test]
+fn watch_prints_changes_after_the_first_poll() {
+    use std::io::Read;
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    prontodb(&db, &["set", "app.cfg.host", "a"])
+        .assert()
+        .success();
+
+    let mut watcher = std::process::Command::new(assert_cmd::cargo::cargo_bin("prontodb"))
+        .arg(format!("--database-path={}", db.display()))
+        .args(["watch", "app.cfg.*", "--interval", "1s", "--count", "2"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(500));
+    prontodb(&db, &["mset", "app.cfg.host=b", "app.cfg.port=1"])
+        .assert()
+        .success();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while watcher.try_wait().unwrap().is_none() {
+        if Instant::now() > deadline {
+            watcher.kill().unwrap();
+            panic!("watch did not exit after --count changes");
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    let mut stdout = String::new();
+    watcher
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut stdout)
+        .unwrap();
+    assert_eq!(stdout, "set app.cfg.host=b\nset app.cfg.port=1\n");
+}
+
+#[test]
+fn serve_answers_line_requests_on_a_unix_socket() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let socket = temp.path().join("pronto.sock");
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    let token = api_token(&db, "app");
+
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("prontodb"))
+        .arg(format!("--database-path={}", db.display()))
+        .args(["serve", "--socket", socket.to_str().unwrap()])
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let stream = loop {
+        match UnixStream::connect(&socket) {
+            Ok(stream) => break stream,
+            Err(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            Err(error) => {
+                server.kill().unwrap();
+                panic!("serve never listened: {}", error);
+            }
+        }
+    };
+
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut ask = |request: &str, lines: usize| {
+        writeln!(writer, "{}", request).unwrap();
+        (0..lines)
+            .map(|_| {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                line.trim_end_matches('\n').to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ask("PING", 1), ["PONG"]);
+    assert!(ask("GET app.cfg.host", 1)[0].starts_with("ERR PERMISSION_DENIED "));
+    assert!(ask("AUTH pdb_forged", 1)[0].starts_with("ERR PERMISSION_DENIED "));
+    assert_eq!(ask(&format!("AUTH {}", token), 1), ["OK"]);
+    assert_eq!(ask("GET app.cfg.host", 1), ["OK localhost"]);
+    assert_eq!(ask("SET app.cfg.motd two\\nlines", 1), ["OK"]);
+    assert_eq!(
+        ask("SCAN app.cfg", 3),
+        ["app.cfg.host=localhost", "app.cfg.motd=two\\nlines", "END"]
+    );
+    assert_eq!(ask("DEL app.cfg.host", 1), ["OK"]);
+    assert_eq!(ask("DEL app.cfg.host", 1), ["MISS"]);
+    assert!(ask("GET not.a.valid.address", 1)[0].starts_with("ERR INVALID_ADDRESS "));
+    assert!(ask("GET other.cfg.host", 1)[0].starts_with("ERR PERMISSION_DENIED "));
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    prontodb(&db, &["get", "app.cfg.motd"])
+        .assert()
+        .success()
+        .stdout("two\nlines\n");
+}
+
+#[test]
+fn serve_resp_speaks_a_redis_subset() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    prontodb(&db, &["create-cache", "app.cache", "60"])
+        .assert()
+        .success();
+    let token = api_token(&db, "app");
+    let addr = {
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().to_string()
+    };
+
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("prontodb"))
+        .arg(format!("--database-path={}", db.display()))
+        .args(["serve", "--resp", &addr])
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let stream = loop {
+        match TcpStream::connect(&addr) {
+            Ok(stream) => break stream,
+            Err(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            Err(error) => {
+                server.kill().unwrap();
+                panic!("serve --resp never listened: {}", error);
+            }
+        }
+    };
+
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut ask = |args: &[&str], reply_bytes: usize| {
+        let mut request = format!("*{}\r\n", args.len());
+        for arg in args {
+            request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+        writer.write_all(request.as_bytes()).unwrap();
+        let mut reply = vec![0; reply_bytes];
+        reader.read_exact(&mut reply).unwrap();
+        String::from_utf8(reply).unwrap()
+    };
+    assert_eq!(ask(&["PING"], 7), "+PONG\r\n");
+    assert_eq!(
+        ask(&["GET", "app.cfg.host"], 34),
+        "-NOAUTH Authentication required.\r\n"
+    );
+    assert_eq!(ask(&["AUTH", &token], 5), "+OK\r\n");
+    assert_eq!(ask(&["SET", "app.cfg.host", "localhost"], 5), "+OK\r\n");
+    assert_eq!(ask(&["GET", "app.cfg.host"], 15), "$9\r\nlocalhost\r\n");
+    assert_eq!(ask(&["GET", "app.cfg.port"], 5), "$-1\r\n");
+    assert_eq!(ask(&["TTL", "app.cfg.host"], 5), ":-1\r\n");
+    assert_eq!(
+        ask(&["SET", "app.cache.token", "abc", "EX", "30"], 5),
+        "+OK\r\n"
+    );
+    let ttl = ask(&["TTL", "app.cache.token"], 5);
+    assert!(ttl == ":30\r\n" || ttl == ":29\r\n", "{}", ttl);
+    assert_eq!(
+        ask(&["KEYS", "app.*.*"], 42),
+        "*2\r\n$15\r\napp.cache.token\r\n$12\r\napp.cfg.host\r\n"
+    );
+    assert_eq!(ask(&["DEL", "app.cfg.host", "app.cfg.port"], 4), ":1\r\n");
+    writer
+        .write_all(b"*2\r\n$6\r\nEXPIRE\r\n$12\r\napp.cfg.host\r\n")
+        .unwrap();
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert!(
+        line.starts_with("-ERR wrong number of arguments"),
+        "{}",
+        line
+    );
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[cfg(feature = "grpc")]
+mod grpc_proto {
+    tonic::include_proto!("prontodb.v1");
+}
+
+#[cfg(feature = "grpc")]
+#[test]
+fn serve_grpc_round_trips_and_maps_error_codes() {
+    use grpc_proto::keystore_client::KeystoreClient;
+    use grpc_proto::{DeleteRequest, GetRequest, ScanRequest, SetRequest};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use tonic::{Code, Request};
+
+    fn authed<T>(message: T, token: &str) -> Request<T> {
+        let mut request = Request::new(message);
+        request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        request
+    }
+    fn get(address: &str) -> GetRequest {
+        GetRequest {
+            address: address.to_string(),
+        }
+    }
+    fn set(address: &str, value: &str, ttl_secs: Option<u64>) -> SetRequest {
+        SetRequest {
+            address: address.to_string(),
+            value: value.to_string(),
+            ttl_secs,
+        }
+    }
+
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    prontodb(&db, &["set", "app.cfg.db_pass", "hunter2"])
+        .assert()
+        .success();
+    prontodb(&db, &["secret", "app.cfg.db_pass"])
+        .assert()
+        .success();
+    let token = api_token(&db, "app");
+    let free_addr = || {
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().to_string()
+    };
+    let (addr, read_only_addr) = (free_addr(), free_addr());
+    let spawn = |global: &[&str], addr: &str| {
+        std::process::Command::new(assert_cmd::cargo::cargo_bin("prontodb"))
+            .arg(format!("--database-path={}", db.display()))
+            .args(global)
+            .args(["serve", "--grpc", addr])
+            .spawn()
+            .unwrap()
+    };
+    let mut server = spawn(&[], &addr);
+    let mut read_only_server = spawn(&["--read-only"], &read_only_addr);
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let connect = |addr: String| async move {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            loop {
+                match KeystoreClient::connect(format!("http://{}", addr)).await {
+                    Ok(client) => return client,
+                    Err(error) if Instant::now() > deadline => {
+                        panic!("serve --grpc never listened: {}", error)
+                    }
+                    Err(_) => thread::sleep(Duration::from_millis(50)),
+                }
+            }
+        };
+        let mut client = connect(addr.clone()).await;
+
+        let anonymous = client.get(get("app.cfg.db_pass")).await.unwrap_err();
+        assert_eq!(anonymous.code(), Code::Unauthenticated);
+        let forged = client
+            .get(authed(get("app.cfg.db_pass"), "pdb_forged"))
+            .await
+            .unwrap_err();
+        assert_eq!(forged.code(), Code::Unauthenticated);
+
+        client
+            .set(authed(set("app.cfg.host", "localhost", None), &token))
+            .await
+            .unwrap();
+        let reply = client
+            .get(authed(get("app.cfg.host"), &token))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(reply.found);
+        assert_eq!(reply.value, "localhost");
+
+        let scope = ScanRequest {
+            scope: "app.cfg".to_string(),
+        };
+        let mut scan = client
+            .scan(authed(scope, &token))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut entries = Vec::new();
+        while let Some(entry) = scan.message().await.unwrap() {
+            entries.push((entry.address, entry.value));
+        }
+        assert_eq!(
+            entries,
+            [
+                ("app.cfg.db_pass".to_string(), "****".to_string()),
+                ("app.cfg.host".to_string(), "localhost".to_string()),
+            ]
+        );
+
+        let delete = DeleteRequest {
+            address: "app.cfg.host".to_string(),
+        };
+        let reply = client
+            .delete(authed(delete, &token))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(reply.deleted);
+        let reply = client
+            .get(authed(get("app.cfg.host"), &token))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!reply.found);
+
+        // The error kind picks the gRPC code; the message leads with the stable code.
+        let invalid = client
+            .get(authed(get("not.a.valid.address"), &token))
+            .await
+            .unwrap_err();
+        assert_eq!(invalid.code(), Code::InvalidArgument);
+        assert!(invalid.message().starts_with("INVALID_ADDRESS: "));
+        let not_ttl = client
+            .set(authed(set("app.cfg.tmp", "x", Some(60)), &token))
+            .await
+            .unwrap_err();
+        assert_eq!(not_ttl.code(), Code::InvalidArgument);
+        assert!(not_ttl.message().starts_with("NS_NOT_TTL: "));
+        let out_of_scope = client
+            .get(authed(get("other.cfg.host"), &token))
+            .await
+            .unwrap_err();
+        assert_eq!(out_of_scope.code(), Code::PermissionDenied);
+
+        // A read-only server answers reads and refuses writes.
+        let mut read_only = connect(read_only_addr.clone()).await;
+        let refused = read_only
+            .set(authed(set("app.cfg.host", "localhost", None), &token))
+            .await
+            .unwrap_err();
+        assert_eq!(refused.code(), Code::PermissionDenied);
+        let reply = read_only
+            .get(authed(get("app.cfg.db_pass"), &token))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(reply.value, "hunter2");
+    });
+    for server in [&mut server, &mut read_only_server] {
+        server.kill().unwrap();
+        server.wait().unwrap();
+    }
+}
+
+#[test]
+fn subscribe_prints_writes_published_by_serve() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::process::Stdio;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let socket = temp.path().join("pronto.sock");
+    let notify = temp.path().join("notify.sock");
+    let binary = assert_cmd::cargo::cargo_bin("prontodb");
+    let database = format!("--database-path={}", db.display());
+    let token = api_token(&db, "app");
+
+    let mut server = std::process::Command::new(&binary)
+        .arg(&database)
+        .args(["serve", "--socket", socket.to_str().unwrap()])
+        .args(["--notify", notify.to_str().unwrap()])
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let stream = loop {
+        match UnixStream::connect(&socket) {
+            Ok(stream) => break stream,
+            Err(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(50)),
+            Err(error) => {
+                server.kill().unwrap();
+                panic!("serve never listened: {}", error);
+            }
+        }
+    };
+
+    let mut subscriber = std::process::Command::new(&binary)
+        .arg(&database)
+        .args(["subscribe", "app.cfg", "--socket", notify.to_str().unwrap()])
+        .args(["--count", "2"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut events = BufReader::new(subscriber.stdout.take().unwrap()).lines();
+    assert_eq!(events.next().unwrap().unwrap(), "Subscribed to app.cfg");
+
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    let auth = format!("AUTH {}", token);
+    for request in [
+        auth.as_str(),
+        "SET app.cfg.host localhost",
+        "SET app.cfgx.host elsewhere",
+        "SET app.other.key 1",
+        "DEL app.cfg.host",
+    ] {
+        writeln!(writer, "{}", request).unwrap();
+        let mut reply = String::new();
+        reader.read_line(&mut reply).unwrap();
+        assert_eq!(reply, "OK\n");
+    }
+
+    let rest: Vec<String> = events.map(Result::unwrap).collect();
+    assert_eq!(rest, ["set app.cfg.host=localhost", "del app.cfg.host"]);
+    assert!(subscriber.wait().unwrap().success());
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn tail_prints_newest_keys_then_follows_new_ones() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    for (key, line) in [("001", "boot"), ("002", "ready"), ("003", "serving")] {
+        prontodb(&db, &["set", &format!("app.logs.{}", key), line])
+            .assert()
+            .success();
+    }
+
+    let mut tail = std::process::Command::new(assert_cmd::cargo::cargo_bin("prontodb"))
+        .arg(format!("--database-path={}", db.display()))
+        .args(["tail", "app.logs", "-n", "2", "--count", "1"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(tail.stdout.take().unwrap()).lines();
+    assert_eq!(lines.next().unwrap().unwrap(), "002=ready");
+    assert_eq!(lines.next().unwrap().unwrap(), "003=serving");
+
+    prontodb(&db, &["set", "app.logs.001", "rebooted"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.logs.004", "done"])
+        .assert()
+        .success();
+    let rest: Vec<String> = lines.map(Result::unwrap).collect();
+    assert_eq!(rest, ["004=done"]);
+    assert!(tail.wait().unwrap().success());
+}
+
+#[test]
+fn serve_ui_serves_the_page_and_json_routes() {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    let token = api_token(&db, "app");
+    let addr = {
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        probe.local_addr().unwrap().to_string()
+    };
+
+    let mut server = std::process::Command::new(assert_cmd::cargo::cargo_bin("prontodb"))
+        .arg(format!("--database-path={}", db.display()))
+        .args(["serve", "--ui", &addr])
+        .spawn()
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while TcpStream::connect(&addr).is_err() {
+        if Instant::now() > deadline {
+            server.kill().unwrap();
+            panic!("serve --ui never listened");
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let send = |method: &str, path: &str, host: &str, token: &str, body: &str| {
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            host,
+            token,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).unwrap();
+        reply
+    };
+    let request =
+        |method: &str, path: &str, host: &str, body: &str| send(method, path, host, &token, body);
+    assert!(send("GET", "/", &addr, "", "").contains("<title>ProntoDB</title>"));
+    let anonymous = send("GET", "/api/projects", &addr, "", "");
+    assert!(anonymous.starts_with("HTTP/1.1 401"), "{}", anonymous);
+    assert!(send("GET", "/api/projects", &addr, "pdb_forged", "").starts_with("HTTP/1.1 401"));
+    assert!(request("GET", "/api/projects", &addr, "").ends_with("[\"app\"]"));
+    assert!(request("PUT", "/api/key/app.cfg.motd?ttl=", &addr, "hi").starts_with("HTTP/1.1 400"));
+    assert!(request("PUT", "/api/key/app.cfg.motd", &addr, "hi there").starts_with("HTTP/1.1 204"));
+    let keys = request("GET", "/api/keys/app.cfg", &addr, "");
+    assert!(keys.starts_with("HTTP/1.1 200"), "{}", keys);
+    assert!(
+        keys.contains("{\"key\":\"motd\",\"value\":\"hi there\",\"ttl\":null}"),
+        "{}",
+        keys
+    );
+    assert!(request("DELETE", "/api/key/app.cfg.host", &addr, "").starts_with("HTTP/1.1 204"));
+    let missing = request("GET", "/api/key/app.cfg.host", &addr, "");
+    assert!(missing.starts_with("HTTP/1.1 404"));
+    assert!(missing.contains("\"code\":\"NOT_FOUND\""));
+    assert!(request("GET", "/api/projects", "evil.example", "").starts_with("HTTP/1.1 403"));
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn backup_archives_and_restores_the_database() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let dir = temp.path().join("archives");
+    let dir_arg = dir.to_str().unwrap();
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+
+    prontodb(&db, &["backup", "--output", dir_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Backed up"));
+    let archives: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(archives.len(), 1, "{:?}", archives);
+    assert!(archives[0].starts_with("prontodb_kv_") && archives[0].ends_with(".tar.gz"));
+    let archive = dir.join(&archives[0]);
+    prontodb(&db, &["backup", "--list", "--output", dir_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(archives[0].as_str()));
+
+    prontodb(&db, &["set", "app.cfg.host", "changed"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cfg.port", "8080"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--restore", archive.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("pass --yes"));
+    prontodb(
+        &db,
+        &["backup", "--restore", archive.to_str().unwrap(), "--yes"],
+    )
+    .assert()
+    .success();
+    prontodb(&db, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("localhost\n");
+    prontodb(&db, &["get", "app.cfg.port"]).assert().code(2);
+
+    std::fs::write(temp.path().join("junk.tar.gz"), "not an archive").unwrap();
+    prontodb(
+        &db,
+        &[
+            "backup",
+            "--restore",
+            temp.path().join("junk.tar.gz").to_str().unwrap(),
+            "--yes",
+        ],
+    )
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("not a prontodb backup"));
+
+    // `restore FILE` is `backup --restore FILE`, confirmation included.
+    prontodb(&db, &["set", "app.cfg.host", "again"])
+        .assert()
+        .success();
+    prontodb(&db, &["restore", archive.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("pass --yes"));
+    prontodb(&db, &["restore", archive.to_str().unwrap(), "--yes"])
+        .assert()
+        .success();
+    prontodb(&db, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("localhost\n");
+}
+
+#[test]
+fn install_and_uninstall_manage_the_binary_and_data() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let bin = temp.path().join("bin");
+    let home = temp.path().join("home");
+    let lifecycle = |args: &[&str]| {
+        let mut cmd = prontodb(&db, args);
+        cmd.env("PRONTO_CURSOR_HOME", &home)
+            .arg(format!("--target={}", bin.display()));
+        cmd
+    };
+
+    lifecycle(&["install"]).assert().success();
+    assert!(bin.join("prontodb").is_file());
+    assert!(home.is_dir());
+    lifecycle(&["install"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--force"));
+    lifecycle(&["install", "--force"]).assert().success();
+
+    lifecycle(&["uninstall"]).assert().success();
+    assert!(!bin.join("prontodb").exists());
+    assert!(home.is_dir());
+    lifecycle(&["uninstall", "--purge"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("pass --force"));
+    lifecycle(&["uninstall", "--purge", "--force"])
+        .assert()
+        .success();
+    assert!(!home.exists());
+}
+
+#[test]
+fn incremental_backup_layers_changes_over_the_full_backup() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let dir = temp.path().join("archives");
+    let dir_arg = dir.to_str().unwrap();
+    prontodb(&db, &["backup", "--incremental", "--output", dir_arg])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("take a full backup first"));
+    for (key, value) in [("app.cfg.host", "localhost"), ("app.cfg.port", "8080")] {
+        prontodb(&db, &["set", key, value]).assert().success();
+    }
+    prontodb(&db, &["set", "app.cfg.old", "moved"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--output", dir_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("full"));
+
+    prontodb(&db, &["set", "app.cfg.host", "changed"])
+        .assert()
+        .success();
+    prontodb(&db, &["del", "app.cfg.port"]).assert().success();
+    prontodb(&db, &["mv", "app.cfg.old", "app.cfg.new"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--incremental", "--output", dir_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("incremental"));
+    let listing = prontodb(&db, &["backup", "--list", "--output", dir_arg])
+        .output()
+        .unwrap();
+    let listing = String::from_utf8(listing.stdout).unwrap();
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", listing);
+    assert!(lines[0].ends_with("\tfull") && lines[1].ends_with("\tincremental"));
+    let incremental = lines[1].split('\t').next().unwrap();
+
+    prontodb(&db, &["set", "app.cfg.late", "after"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--restore", incremental, "--yes"])
+        .assert()
+        .success();
+    prontodb(&db, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("changed\n");
+    prontodb(&db, &["get", "app.cfg.new"])
+        .assert()
+        .success()
+        .stdout("moved\n");
+    for gone in ["app.cfg.port", "app.cfg.old", "app.cfg.late"] {
+        prontodb(&db, &["get", gone]).assert().code(2);
+    }
+}
+
+#[cfg(feature = "encryption-aes")]
+#[test]
+fn encrypted_backup_needs_the_key_file_to_restore() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let dir = temp.path().join("archives");
+    let dir_arg = dir.to_str().unwrap();
+    let key = temp.path().join("backup.key");
+    std::fs::write(&key, "correct horse battery staple").unwrap();
+    let key_arg = format!("--key-file={}", key.display());
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+
+    prontodb(&db, &["backup", "--encrypt", "--output", dir_arg])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("needs a key"));
+    prontodb(
+        &db,
+        &[key_arg.as_str(), "backup", "--encrypt", "--output", dir_arg],
+    )
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("encrypted"));
+    let archives: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(archives.len(), 1, "{:?}", archives);
+    assert!(archives[0].ends_with(".tar.gz.enc"));
+    let sealed = std::fs::read(dir.join(&archives[0])).unwrap();
+    assert!(!sealed.windows(9).any(|window| window == b"localhost"));
+    let archive = dir.join(&archives[0]);
+    let archive_arg = archive.to_str().unwrap();
+
+    prontodb(&db, &["set", "app.cfg.host", "changed"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--restore", archive_arg, "--yes"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("is encrypted"));
+    let wrong = temp.path().join("wrong.key");
+    std::fs::write(&wrong, "not the passphrase").unwrap();
+    prontodb(
+        &db,
+        &[
+            format!("--key-file={}", wrong.display()).as_str(),
+            "backup",
+            "--restore",
+            archive_arg,
+            "--yes",
+        ],
+    )
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("does not decrypt"));
+    prontodb(
+        &db,
+        &[
+            key_arg.as_str(),
+            "backup",
+            "--restore",
+            archive_arg,
+            "--yes",
+        ],
+    )
+    .assert()
+    .success();
+    prontodb(&db, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("localhost\n");
+}
+
+#[test]
+fn backup_verify_checks_archives_without_restoring() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let dir = temp.path().join("archives");
+    let dir_arg = dir.to_str().unwrap();
+    for (key, value) in [("app.cfg.host", "localhost"), ("app.cfg.port", "8080")] {
+        prontodb(&db, &["set", key, value]).assert().success();
+    }
+    prontodb(&db, &["backup", "--output", dir_arg])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cfg.user", "admin"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--incremental", "--output", dir_arg])
+        .assert()
+        .success();
+    let listing = prontodb(&db, &["backup", "--list", "--output", dir_arg])
+        .output()
+        .unwrap();
+    let listing = String::from_utf8(listing.stdout).unwrap();
+    let archives: Vec<&str> = listing
+        .lines()
+        .map(|line| line.split('\t').next().unwrap())
+        .collect();
+    assert_eq!(archives.len(), 2, "{}", listing);
+
+    prontodb(&db, &["set", "app.cfg.late", "after"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--verify", archives[0]])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("2 keys match the manifest"));
+    prontodb(&db, &["backup", "--verify", archives[1]])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("incremental, 2 archives"))
+        .stdout(predicates::str::contains("3 keys match the manifest"));
+    prontodb(&db, &["get", "app.cfg.late"])
+        .assert()
+        .success()
+        .stdout("after\n");
+
+    let whole = std::fs::read(archives[0]).unwrap();
+    let truncated = temp.path().join("truncated.tar.gz");
+    std::fs::write(&truncated, &whole[..whole.len() / 2]).unwrap();
+    prontodb(&db, &["backup", "--verify", truncated.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("not a prontodb backup"));
+}
+
+#[test]
+fn backup_prune_keeps_the_policy_and_incremental_bases() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let dir = temp.path().join("archives");
+    let dir_arg = dir.to_str().unwrap();
+    let day = |n: i64| (1_792_000_454 + n * 86_400).to_string();
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    for n in 0..5 {
+        prontodb(&db, &["backup", "--output", dir_arg])
+            .env("PRONTO_NOW", day(n))
+            .assert()
+            .success();
+    }
+    prontodb(&db, &["backup", "--incremental", "--output", dir_arg])
+        .env("PRONTO_NOW", day(4))
+        .assert()
+        .success();
+    let names = || {
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    };
+    let before = names();
+    assert_eq!(before.len(), 6, "{:?}", before);
+
+    prontodb(
+        &db,
+        &["backup", "prune", "--keep-daily", "2", "--dir", dir_arg],
+    )
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("pass --yes"));
+    prontodb(&db, &["backup", "prune", "--dir", dir_arg, "--yes"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("keep at least one"));
+    prontodb(
+        &db,
+        &[
+            "backup",
+            "prune",
+            "--keep-daily",
+            "2",
+            "--dir",
+            dir_arg,
+            "--yes",
+        ],
+    )
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("Pruned 3 archive(s)"))
+    .stdout(predicates::str::contains("kept 3"));
+    // Day 3, and day 4's incremental with the full backup it layers over.
+    assert_eq!(names(), before[3..].to_vec());
+}
+
+#[test]
+fn restore_dry_run_lists_changes_without_restoring() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let dir = temp.path().join("archives");
+    let dir_arg = dir.to_str().unwrap();
+    for (key, value) in [
+        ("app.cfg.host", "localhost"),
+        ("app.cfg.port", "8080"),
+        ("app.cfg.user", "admin"),
+    ] {
+        prontodb(&db, &["set", key, value]).assert().success();
+    }
+    prontodb(&db, &["backup", "--output", dir_arg])
+        .assert()
+        .success();
+    let archive = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+
+    prontodb(&db, &["set", "app.cfg.host", "changed"])
+        .assert()
+        .success();
+    prontodb(&db, &["del", "app.cfg.port"]).assert().success();
+    prontodb(&db, &["set", "app.flags.beta", "on"])
+        .assert()
+        .success();
+    prontodb(
+        &db,
+        &[
+            "backup",
+            "--restore",
+            archive.to_str().unwrap(),
+            "--dry-run",
+        ],
+    )
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(
+        "~ app.cfg.host\n+ app.cfg.port\n- app.flags.beta\n",
+    ))
+    .stdout(predicates::str::contains(
+        "Would add 1, overwrite 1 and remove 1 keys in 2 namespaces (1 unchanged)",
+    ));
+    prontodb(&db, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("changed\n");
+}
+
+#[test]
+fn backup_rejects_unknown_remote_targets() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--output", "ftp://example.com/backups"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("ftp:// is not a backup target"));
+    prontodb(
+        &db,
+        &["backup", "--list", "--output", "s3://bucket/backups"],
+    )
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("local directories only"));
+    assert!(!temp.path().join("backups").exists());
+}
+
+#[cfg(not(feature = "backup-s3"))]
+#[test]
+fn s3_backup_target_needs_its_feature() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    prontodb(&db, &["backup", "--output", "s3://bucket/backups"])
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("Backed up"))
+        .stderr(predicates::str::contains("backup-s3 feature"));
+    assert_eq!(
+        std::fs::read_dir(temp.path().join("backups"))
+            .unwrap()
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn backup_schedule_prints_the_next_run_in_utc() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    // Wednesday 2026-10-14T17:54:14Z.
+    for (expr, next) in [
+        ("0 3 * * *", "2026-10-15T03:00:00Z\n"),
+        ("*/15 * * * *", "2026-10-14T18:00:00Z\n"),
+        ("0 9 * * 1", "2026-10-19T09:00:00Z\n"),
+        ("0 0 13 * 5", "2026-10-16T00:00:00Z\n"),
+        ("30 4 1 1,7 *", "2027-01-01T04:30:00Z\n"),
+    ] {
+        prontodb(&db, &["backup", "--schedule", expr])
+            .env("PRONTO_NOW", "1792000454")
+            .assert()
+            .success()
+            .stdout(next);
+    }
+    prontodb(&db, &["backup", "--schedule", "0 0 30 2 *"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("never runs"));
+    prontodb(&db, &["backup", "--schedule", "61 * * * *"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("outside 0-59"));
+    prontodb(&db, &["backup", "--schedule", "0 3 * *"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("expected 5 fields"));
+}
+
+#[test]
+fn scoped_backup_merges_one_namespace_into_another_database() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let other = temp.path().join("other.sqlite");
+    let dir = temp.path().join("archives");
+    let dir_arg = dir.to_str().unwrap();
+    for (key, value) in [
+        ("myapp.config.host", "localhost"),
+        ("myapp.config.port", "8080"),
+        ("myapp.flags.beta", "on"),
+    ] {
+        prontodb(&db, &["set", key, value]).assert().success();
+    }
+    prontodb(
+        &db,
+        &["backup", "--scope", "myapp.config", "--output", dir_arg],
+    )
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("scoped"));
+    let archive = std::fs::read_dir(&dir)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let archive_arg = archive.to_str().unwrap();
+    assert!(
+        archive_arg.contains("prontodb_kv_myapp.config_"),
+        "{}",
+        archive_arg
+    );
+    prontodb(&db, &["backup", "--verify", archive_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("2 keys match the manifest"));
+    prontodb(&db, &["backup", "--restore", archive_arg, "--yes"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("backup --merge"));
+
+    for (key, value) in [
+        ("myapp.config.host", "db.internal"),
+        ("other.cfg.keep", "yes"),
+    ] {
+        prontodb(&other, &["set", key, value]).assert().success();
+    }
+    prontodb(
+        &other,
+        &["backup", "--merge", archive_arg, "--strategy", "skip"],
+    )
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("1 inserted, 1 skipped"));
+    for (key, value) in [
+        ("myapp.config.host", "db.internal\n"),
+        ("myapp.config.port", "8080\n"),
+        ("other.cfg.keep", "yes\n"),
+    ] {
+        prontodb(&other, &["get", key])
+            .assert()
+            .success()
+            .stdout(value);
+    }
+    prontodb(&other, &["get", "myapp.flags.beta"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn admin_snapshot_copies_the_live_database() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let copy = temp.path().join("copy.sqlite");
+    let copy_arg = copy.to_str().unwrap();
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    prontodb(
+        &db,
+        &["--read-only", "admin", "snapshot", "--out", copy_arg],
+    )
+    .assert()
+    .success()
+    .stdout(predicates::str::contains(copy_arg));
+    prontodb(&db, &["set", "app.cfg.host", "changed"])
+        .assert()
+        .success();
+    prontodb(&copy, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("localhost\n");
+    prontodb(&db, &["admin", "snapshot", "--out", copy_arg])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("already exists"));
+}
+
+#[test]
+fn admin_merge_imports_another_database_and_reports_conflicts() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let old = temp.path().join("old.sqlite");
+    let old_arg = old.to_str().unwrap();
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    prontodb(&old, &["set", "app.cfg.host", "legacy"])
+        .assert()
+        .success();
+    prontodb(&old, &["set", "app.cfg.port", "8080"])
+        .assert()
+        .success();
+    prontodb(&db, &["admin", "merge", "--from", old_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("! app.cfg.host (kept)"))
+        .stdout(predicates::str::contains(
+            "1 added, 0 overwritten, 0 unchanged, 1 conflicts",
+        ));
+    prontodb(&db, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("localhost\n");
+    prontodb(
+        &db,
+        &["admin", "merge", "--from", old_arg, "--strategy", "fail"],
+    )
+    .assert()
+    .code(3);
+    prontodb(
+        &db,
+        &["admin", "merge", "--from", old_arg, "--prefix", "old"],
+    )
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("2 added"));
+    prontodb(&db, &["get", "old_app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("legacy\n");
+}
+
+#[test]
+fn changes_lists_oplog_rows_after_a_sequence_number() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    prontodb(&db, &["changes"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("admin oplog enable"));
+    prontodb(&db, &["admin", "oplog", "enable"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .env("PRONTO_NOW", "1700000000")
+        .assert()
+        .success();
+    prontodb(&db, &["del", "app.cfg.host"])
+        .env("PRONTO_NOW", "1700000060")
+        .assert()
+        .success();
+    prontodb(&db, &["changes"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "1\t2023-11-14T22:13:20Z\tset\tapp.cfg.host\t{:x}\t-\n\
+         2\t2023-11-14T22:14:20Z\tdel\tapp.cfg.host\t-\t-\n",
+            md5::compute("localhost")
+        ));
+    prontodb(&db, &["changes", "--since", "1"])
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with("2\t"));
+    prontodb(&db, &["changes", "--since", "2023-11-14T22:14:00Z"])
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with("2\t"));
+}
+
+#[test]
+fn scan_databases_labels_rows_by_database() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("kv.sqlite");
+    let staging = temp.path().join("staging.sqlite");
+    let databases = format!("main,{}", staging.to_str().unwrap());
+    prontodb(&db, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cfg.port", "8080"])
+        .assert()
+        .success();
+    prontodb(&staging, &["set", "app.cfg.host", "staging.local"])
+        .assert()
+        .success();
+    prontodb(&staging, &["set", "app.other.key", "ignored"])
+        .assert()
+        .success();
+    prontodb(&db, &["scan", "app.cfg", "--databases", &databases])
+        .assert()
+        .success()
+        .stdout(format!(
+            "main:host=localhost\n{0}:host=staging.local\nmain:port=8080\n",
+            staging.to_str().unwrap()
+        ));
+    prontodb(
+        &db,
+        &["scan", "app.cfg", "--databases", "main,missing.sqlite"],
+    )
+    .assert()
+    .failure()
+    .stderr(predicates::str::contains("no database at"));
+    prontodb(
+        &db,
+        &["scan", "app.cfg", "--databases", "main", "--where", ".a=1"],
+    )
+    .assert()
+    .failure();
+}
+
+#[test]
+fn sync_push_and_pull_copy_changes_since_the_last_run() {
+    let temp = tempdir().unwrap();
+    let laptop = temp.path().join("laptop.sqlite");
+    let desk = temp.path().join("desk.sqlite");
+    let desk_arg = desk.to_str().unwrap();
+    prontodb(&laptop, &["set", "app.cfg.host", "localhost"])
+        .assert()
+        .success();
+    prontodb(&laptop, &["sync", "push", "--to", desk_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Pushed 1 keys to"));
+    prontodb(&desk, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("localhost\n");
+
+    prontodb(&desk, &["set", "app.cfg.port", "8080"])
+        .assert()
+        .success();
+    prontodb(&laptop, &["sync", "pull", "--from", desk_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Pulled"));
+    prontodb(&laptop, &["get", "app.cfg.port"])
+        .assert()
+        .success()
+        .stdout("8080\n");
+
+    prontodb(&laptop, &["sync", "pull", "--from", "missing.sqlite"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("no database at"));
+}
+
+#[test]
+fn sync_merge_resolves_conflicts_and_takes_one_sided_edits() {
+    let temp = tempdir().unwrap();
+    let ours = temp.path().join("ours.sqlite");
+    let theirs = temp.path().join("theirs.sqlite");
+    let theirs_arg = theirs.to_str().unwrap();
+    let at = |db: &std::path::Path, secs: i64, args: &[&str]| {
+        let mut cmd = prontodb(db, args);
+        cmd.env("PRONTO_NOW", (1_792_000_454 + secs).to_string());
+        cmd
+    };
+    at(&ours, 0, &["set", "app.cfg.host", "ours-1"])
+        .assert()
+        .success();
+    at(&theirs, 0, &["set", "app.cfg.host", "theirs-1"])
+        .assert()
+        .success();
+    at(&theirs, 0, &["set", "app.cfg.port", "8080"])
+        .assert()
+        .success();
+
+    at(
+        &ours,
+        10,
+        &["sync", "merge", theirs_arg, "--strategy", "theirs"],
+    )
+    .assert()
+    .success()
+    .stdout(predicates::str::contains("! app.cfg.host kept theirs\n"))
+    .stdout(predicates::str::contains(
+        "2 keys in, 0 keys out, 1 conflicts",
+    ));
+    at(&ours, 10, &["get", "app.cfg.port"])
+        .assert()
+        .success()
+        .stdout("8080\n");
+
+    at(&ours, 20, &["set", "app.cfg.host", "ours-2"])
+        .assert()
+        .success();
+    at(&ours, 30, &["sync", "merge", theirs_arg])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "0 keys in, 1 keys out, 0 conflicts",
+        ));
+    at(&theirs, 30, &["get", "app.cfg.host"])
+        .assert()
+        .success()
+        .stdout("ours-2\n");
+}
+
+#[test]
+fn diff_lists_drift_between_two_databases() {
+    let temp = tempdir().unwrap();
+    let staging = temp.path().join("staging.sqlite");
+    let prod = temp.path().join("prod.sqlite");
+    for (key, value) in [
+        ("app.cfg.host", "staging.local"),
+        ("app.cfg.debug", "true"),
+        ("other.cfg.x", "1"),
+    ] {
+        prontodb(&staging, &["set", key, value]).assert().success();
+    }
+    for (key, value) in [("app.cfg.host", "prod.local"), ("app.cfg.port", "443")] {
+        prontodb(&prod, &["set", key, value]).assert().success();
+    }
+    let (from, to) = (staging.to_str().unwrap(), prod.to_str().unwrap());
+    prontodb(&staging, &["diff", from, to, "app.cfg"])
+        .assert()
+        .success()
+        .stdout("- app.cfg.debug\n~ app.cfg.host\n+ app.cfg.port\n1 added, 1 removed, 1 changed (0 unchanged)\n");
+    prontodb(&staging, &["diff", from, to, "app", "--values"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "~ app.cfg.host: staging.local -> prod.local\n+ app.cfg.port = 443\n",
+        ));
+}
+
+#[test]
+fn admin_sweep_reclaims_expired_cache_rows() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    prontodb(&db, &["create-cache", "app.cache", "60"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cache.token", "abc"])
+        .env("PRONTO_NOW", "1700000000")
+        .assert()
+        .success();
+
+    prontodb(&db, &["--read-only", "admin", "sweep"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("admin sweep"));
+    prontodb(&db, &["admin", "sweep", "--batch-size", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--batch-size"));
+    prontodb(&db, &["admin", "sweep", "--batch-size", "10"])
+        .env("PRONTO_NOW", "1700000120")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("[sweep] app.cache: 1 expired"))
+        .stdout(predicates::str::contains("[sweep] reclaimed 1 rows"));
+}
+
+#[test]
+fn admin_caches_lists_ttl_namespaces() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    prontodb(&db, &["admin", "caches"])
+        .assert()
+        .success()
+        .stdout("(no cache namespaces)\n");
+    prontodb(&db, &["create-cache", "app.cache", "60"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cache.token", "abc"])
+        .env("PRONTO_NOW", "1700000000")
+        .assert()
+        .success();
+    prontodb(&db, &["--read-only", "admin", "caches"])
+        .env("PRONTO_NOW", "1700000015")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("NAMESPACE"))
+        .stdout(predicates::str::contains("app.cache"))
+        .stdout(predicates::str::contains("in 45s"));
+}
+
+#[test]
+fn admin_purge_expired_limits_itself_to_a_scope() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+
+    for scope in ["app.cache", "web.cache"] {
+        prontodb(&db, &["create-cache", scope, "60"])
+            .assert()
+            .success();
+        prontodb(&db, &["set", &format!("{}.token", scope), "abc"])
+            .env("PRONTO_NOW", "1700000000")
+            .assert()
+            .success();
+    }
+
+    prontodb(&db, &["--read-only", "admin", "purge-expired", "app"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("admin purge-expired"));
+    prontodb(&db, &["admin", "purge-expired", "app.cache"])
+        .env("PRONTO_NOW", "1700000120")
+        .assert()
+        .success()
+        .stdout("[purge] app.cache: 1 expired\n[purge] deleted 1 expired rows\n");
+    prontodb(&db, &["admin", "purge-expired"])
+        .env("PRONTO_NOW", "1700000120")
+        .assert()
+        .success()
+        .stdout("[purge] web.cache: 1 expired\n[purge] deleted 1 expired rows\n");
+}
+
+#[test]
+fn expiry_hooks_read_removed_keys_and_are_waited_for() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    let seen = temp.path().join("seen.txt");
+
+    prontodb(&db, &["create-cache", "app.cache", "60"])
+        .assert()
+        .success();
+    for key in ["a", "b"] {
+        prontodb(&db, &["set", &format!("app.cache.{}", key), "1"])
+            .env("PRONTO_NOW", "1700000000")
+            .assert()
+            .success();
+    }
+    let hook = format!("cat > '{}'; exit 0", seen.display());
+    prontodb(&db, &["hook", "set", "app.cache", &hook])
+        .assert()
+        .success();
+
+    prontodb(&db, &["admin", "purge-expired", "app"])
+        .env("PRONTO_NOW", "1700000120")
+        .assert()
+        .success()
+        .stderr("");
+    assert_eq!(std::fs::read_to_string(&seen).unwrap(), "a\nb\n");
+
+    // A hook that ignores stdin is not reported as failed.
+    prontodb(&db, &["hook", "set", "app.cache", "exit 0"])
+        .assert()
+        .success();
+    prontodb(&db, &["set", "app.cache.c", "1"])
+        .env("PRONTO_NOW", "1700000000")
+        .assert()
+        .success();
+    prontodb(&db, &["admin", "sweep"])
+        .env("PRONTO_NOW", "1700000120")
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn del_glob_asks_before_deleting_every_match() {
+    let temp = tempdir().unwrap();
+    let db = temp.path().join("cli.sqlite");
+    for address in ["app.cache.a", "app.cache.b", "app.cfg.port"] {
+        prontodb(&db, &["set", address, "1"]).assert().success();
+    }
+
+    prontodb(&db, &["del", "app.cache.*"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("pass --yes"));
+    prontodb(&db, &["del", "app.cache.*", "--dry-run"])
+        .assert()
+        .success()
+        .stdout("app.cache.a\napp.cache.b\n");
+    prontodb(&db, &["del", "app.cache.*", "--yes"])
+        .assert()
+        .success();
+    prontodb(&db, &["count", "app.cache"])
+        .assert()
+        .success()
+        .stdout("0\n");
+    prontodb(&db, &["get", "app.cfg.port"])
+        .assert()
+        .success()
+        .stdout("1\n");
+}
@@ -0,0 +1,2310 @@
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Duration;
+
+use prontodb::lib::addr::{Address, AddressDefaults, AddressLayout, KeyPattern, NamespaceScope};
+use prontodb::lib::adpt::sqlite::{
+    AuditQuery, CacheSettings, FixedClock, ImportStrategy, IndexKind, OplogSince, Permission,
+    RetentionPolicy, SetCondition, SqliteConnectionConfig, SqliteKeystore, ValidationMode,
+    ValueType, API_TOKEN_PREFIX,
+};
+use prontodb::lib::adpt::{KvBackend, MemoryKeystore};
+use prontodb::lib::api;
+use prontodb::lib::core::crud::CrudErrorKind;
+use prontodb::lib::cursor::{
+    resolve_working_directory_cursor, CursorData, CursorManager, UserDefaults,
+};
+use tempfile::{tempdir, TempDir};
+
+fn temp_config() -> (TempDir, SqliteConnectionConfig) {
+    let temp = tempdir().unwrap();
+    let config = SqliteConnectionConfig::new(temp.path().join("kv.sqlite"));
+    (temp, config)
+}
+
+#[test]
+fn address_parse_fills_defaults_and_context() {
+    let addr = Address::parse("debug").unwrap();
+    assert_eq!(addr.project, "default");
+    assert_eq!(addr.namespace, "default");
+    assert_eq!(addr.key, "debug");
+
+    let addr = Address::parse("app.config.port__prod").unwrap();
+    assert_eq!(addr.project, "app");
+    assert_eq!(addr.namespace, "config");
+    assert_eq!(addr.key, "port");
+    assert_eq!(addr.context.as_deref(), Some("prod"));
+    assert_eq!(addr.to_string(), "app.config.port__prod");
+
+    assert!(Address::parse("a.b.c.d").is_err());
+    assert!(Address::parse("a..c").is_err());
+}
+
+#[test]
+fn address_parse_unquotes_keys_holding_the_delimiter() {
+    let addr = Address::parse(r#"app.config."my.dotted.key""#).unwrap();
+    assert_eq!(addr.namespace, "config");
+    assert_eq!(addr.key, "my.dotted.key");
+    assert_eq!(addr.context, None);
+
+    let addr = Address::parse(r"app.config.my\.dotted\.key__prod").unwrap();
+    assert_eq!(addr.key, "my.dotted.key");
+    assert_eq!(addr.context.as_deref(), Some("prod"));
+
+    // Quoted or escaped, `__` and `"` belong to the key.
+    let addr = Address::parse(r#"app.config."a__b""#).unwrap();
+    assert_eq!((addr.key.as_str(), addr.context), ("a__b", None));
+    assert_eq!(Address::parse(r#"say\"hi"#).unwrap().key, "say\"hi");
+
+    assert!(Address::parse(r#"app.config."open"#).is_err());
+    assert!(Address::parse(r"app.config.key\").is_err());
+    assert!(Address::parse(r#"app.config."""#).is_err());
+
+    let (_temp, config) = temp_config();
+    api::set_value(&config, r#"app.config."my.dotted.key""#, "v").unwrap();
+    assert_eq!(
+        api::get_value(&config, r"app.config.my\.dotted\.key")
+            .unwrap()
+            .as_deref(),
+        Some("v")
+    );
+    let keys: Vec<String> = api::list_entries(&config, "app.config")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.address.key)
+        .collect();
+    assert_eq!(keys, vec!["my.dotted.key"]);
+}
+
+#[test]
+fn set_get_delete_roundtrip() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.config.debug", "true").unwrap();
+    assert_eq!(
+        api::get_value(&config, "app.config.debug")
+            .unwrap()
+            .as_deref(),
+        Some("true")
+    );
+
+    api::set_value(&config, "app.config.debug", "false").unwrap();
+    assert_eq!(
+        api::get_value(&config, "app.config.debug")
+            .unwrap()
+            .as_deref(),
+        Some("false")
+    );
+
+    assert!(api::delete_value(&config, "app.config.debug").unwrap());
+    assert!(!api::delete_value(&config, "app.config.debug").unwrap());
+    assert_eq!(api::get_value(&config, "app.config.debug").unwrap(), None);
+}
+
+#[test]
+fn context_suffix_is_a_distinct_key() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.db.host", "localhost").unwrap();
+    api::set_value(&config, "app.db.host__prod", "db.internal").unwrap();
+
+    assert_eq!(
+        api::get_value(&config, "app.db.host").unwrap().as_deref(),
+        Some("localhost")
+    );
+    assert_eq!(
+        api::get_value(&config, "app.db.host__prod")
+            .unwrap()
+            .as_deref(),
+        Some("db.internal")
+    );
+}
+
+#[test]
+fn contexts_are_listed_per_key() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.db.host", "localhost").unwrap();
+    api::set_value(&config, "app.db.host__staging", "db.staging").unwrap();
+    api::set_value(&config, "app.db.host__prod", "db.internal").unwrap();
+    api::set_value(&config, "app.db.port__dev", "5433").unwrap();
+
+    assert_eq!(
+        api::list_contexts(&config, "app.db.host").unwrap(),
+        vec!["prod".to_string(), "staging".to_string()]
+    );
+    assert_eq!(
+        api::list_contexts(&config, "app.db.host__prod").unwrap(),
+        vec!["prod".to_string(), "staging".to_string()]
+    );
+    assert!(api::list_contexts(&config, "app.db.user")
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn strict_validation_refuses_unknown_namespaces() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.config.debug", "true").unwrap();
+    api::set_validation_mode(&config, ValidationMode::Strict).unwrap();
+    assert_eq!(
+        api::validation_mode(&config).unwrap(),
+        ValidationMode::Strict
+    );
+
+    api::set_value(&config, "app.config.port", "8080").unwrap();
+    let err = api::set_value(&config, "app.confg.port", "8080").unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::NsUnknown);
+    let err = api::set_values(&config, &[("app.new.a".into(), "1".into())]).unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::NsUnknown);
+    assert_eq!(
+        api::list_namespaces(&config, "app").unwrap(),
+        vec!["config"]
+    );
+
+    let creating = config.clone().with_namespace_creation(true);
+    api::set_value(&creating, "app.features.beta", "true").unwrap();
+    api::set_value(&config, "app.features.gamma", "false").unwrap();
+
+    let lax = config
+        .clone()
+        .with_validation_mode(Some(ValidationMode::Lax));
+    api::set_value(&lax, "other.ns.key", "v").unwrap();
+}
+
+#[test]
+fn reserved_namespaces_need_system_access() {
+    let (_temp, config) = temp_config();
+    let system = config.clone().with_system_access(true);
+    api::set_value(&system, "sys.jobs.last", "42").unwrap();
+    api::set_value(&system, "pipe.cache.abc", "piped").unwrap();
+
+    for address in ["sys.jobs.last", "sys.other.key", "pipe.cache.abc"] {
+        let err = api::set_value(&config, address, "x").unwrap_err();
+        assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    }
+    let err = api::delete_value(&config, "sys.jobs.last").unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    let err = api::delete_matching(&config, "*.*.last").unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+
+    assert_eq!(
+        api::get_value(&config, "sys.jobs.last").unwrap().as_deref(),
+        Some("42")
+    );
+    api::set_value(&config, "pipe.other.key", "ok").unwrap();
+    assert!(api::delete_value(&system, "sys.jobs.last").unwrap());
+}
+
+#[test]
+fn meta_context_places_addresses_under_another_project() {
+    let (_temp, config) = temp_config();
+    let ops = config.clone().with_meta_context(Some("ops"));
+    api::set_value(&ops, "myapp.config.debug", "true").unwrap();
+    api::set_value(&config, "myapp.config.debug", "false").unwrap();
+
+    assert_eq!(
+        api::get_value(&ops, "myapp.config.debug")
+            .unwrap()
+            .as_deref(),
+        Some("true")
+    );
+    assert_eq!(
+        api::get_value(&config, r#""ops.myapp".config.debug"#)
+            .unwrap()
+            .as_deref(),
+        Some("true")
+    );
+    let entries = api::list_entries(&ops, "myapp.config").unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].address.project, "ops.myapp");
+    assert_eq!(api::keys_matching(&ops, "myapp.*.debug").unwrap().len(), 1);
+
+    assert!(api::delete_value(&ops, "myapp.config.debug").unwrap());
+    assert_eq!(
+        api::get_value(&config, "myapp.config.debug")
+            .unwrap()
+            .as_deref(),
+        Some("false")
+    );
+
+    let bad = config.with_meta_context(Some("a.b"));
+    let err = api::get_value(&bad, "myapp.config.debug").unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::InvalidAddress);
+}
+
+#[test]
+fn read_only_configs_refuse_writes_through_api_and_sessions() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.mode", "live").unwrap();
+    let read_only = config.clone().with_read_only(true);
+
+    let denied = api::set_value(&read_only, "app.cfg.mode", "test").unwrap_err();
+    assert_eq!(denied.kind, CrudErrorKind::PermissionDenied);
+    let denied = api::delete_matching(&read_only, "app.cfg.*").unwrap_err();
+    assert_eq!(denied.kind, CrudErrorKind::PermissionDenied);
+
+    let session = api::Session::open(&read_only).unwrap();
+    let denied = session.set("app.cfg.mode", "test", None).unwrap_err();
+    assert_eq!(denied.kind, CrudErrorKind::PermissionDenied);
+    let denied = session.delete("app.cfg.mode").unwrap_err();
+    assert_eq!(denied.kind, CrudErrorKind::PermissionDenied);
+    assert_eq!(
+        session.get("app.cfg.mode").unwrap().as_deref(),
+        Some("live")
+    );
+}
+
+#[test]
+fn batch_set_and_get_preserve_order() {
+    let (_temp, config) = temp_config();
+
+    let entries = vec![
+        ("app.config.a".to_string(), "1".to_string()),
+        ("app.config.b".to_string(), "2".to_string()),
+        ("app.config.c".to_string(), "x=y".to_string()),
+    ];
+    assert_eq!(api::set_values(&config, &entries).unwrap(), 3);
+
+    let keys = vec![
+        "app.config.c".to_string(),
+        "app.config.missing".to_string(),
+        "app.config.a".to_string(),
+    ];
+    let values = api::get_values(&config, &keys).unwrap();
+    assert_eq!(
+        values,
+        vec![Some("x=y".to_string()), None, Some("1".to_string())]
+    );
+}
+
+#[test]
+fn batch_set_rejects_invalid_address_without_writing() {
+    let (_temp, config) = temp_config();
+
+    let entries = vec![
+        ("app.config.a".to_string(), "1".to_string()),
+        ("too.many.parts.here".to_string(), "2".to_string()),
+    ];
+    let error = api::set_values(&config, &entries).expect_err("invalid address fails");
+    assert_eq!(error.kind, CrudErrorKind::InvalidInput);
+    assert_eq!(api::get_value(&config, "app.config.a").unwrap(), None);
+}
+
+#[test]
+fn increment_creates_and_adjusts_counters() {
+    let (_temp, config) = temp_config();
+
+    assert_eq!(
+        api::increment_value(&config, "app.stats.hits", 1).unwrap(),
+        1
+    );
+    assert_eq!(
+        api::increment_value(&config, "app.stats.hits", 5).unwrap(),
+        6
+    );
+    assert_eq!(
+        api::increment_value(&config, "app.stats.hits", -10).unwrap(),
+        -4
+    );
+    assert_eq!(
+        api::get_value(&config, "app.stats.hits")
+            .unwrap()
+            .as_deref(),
+        Some("-4")
+    );
+}
+
+#[test]
+fn increment_rejects_non_integer_values() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.stats.label", "hello").unwrap();
+    let error = api::increment_value(&config, "app.stats.label", 1).expect_err("not numeric");
+    assert_eq!(error.kind, CrudErrorKind::InvalidInput);
+    assert_eq!(
+        api::get_value(&config, "app.stats.label")
+            .unwrap()
+            .as_deref(),
+        Some("hello")
+    );
+}
+
+#[test]
+fn append_creates_then_concatenates() {
+    let (_temp, config) = temp_config();
+
+    api::append_value(&config, "app.logs.run", "one;").unwrap();
+    api::append_value(&config, "app.logs.run", "two;").unwrap();
+    assert_eq!(
+        api::get_value(&config, "app.logs.run").unwrap().as_deref(),
+        Some("one;two;")
+    );
+}
+
+#[test]
+fn conditional_set_enforces_preconditions() {
+    let (_temp, config) = temp_config();
+
+    api::set_value_if(&config, "app.lock.owner", "alice", &SetCondition::IfAbsent).unwrap();
+    let error = api::set_value_if(&config, "app.lock.owner", "bob", &SetCondition::IfAbsent)
+        .expect_err("key already present");
+    assert_eq!(error.kind, CrudErrorKind::Conflict);
+
+    let swap = SetCondition::IfValue("alice".to_string());
+    api::set_value_if(&config, "app.lock.owner", "bob", &swap).unwrap();
+    let error = api::set_value_if(&config, "app.lock.owner", "carol", &swap)
+        .expect_err("value changed underneath");
+    assert_eq!(error.kind, CrudErrorKind::Conflict);
+    assert_eq!(
+        api::get_value(&config, "app.lock.owner")
+            .unwrap()
+            .as_deref(),
+        Some("bob")
+    );
+}
+
+#[test]
+fn exists_reports_presence() {
+    let (_temp, config) = temp_config();
+
+    assert!(!api::value_exists(&config, "app.flags.ready").unwrap());
+    api::set_value(&config, "app.flags.ready", "").unwrap();
+    assert!(api::value_exists(&config, "app.flags.ready").unwrap());
+}
+
+#[test]
+fn move_renames_and_keeps_context() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.old.host__prod", "db.internal").unwrap();
+    api::move_value(&config, "app.old.host__prod", "app.new.host", false).unwrap();
+
+    assert_eq!(api::get_value(&config, "app.old.host__prod").unwrap(), None);
+    assert_eq!(
+        api::get_value(&config, "app.new.host__prod")
+            .unwrap()
+            .as_deref(),
+        Some("db.internal")
+    );
+
+    let error = api::move_value(&config, "app.old.host", "app.new.other", false)
+        .expect_err("missing source");
+    assert_eq!(error.kind, CrudErrorKind::NotFound);
+}
+
+#[test]
+fn move_refuses_to_clobber_without_overwrite() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.cfg.a", "1").unwrap();
+    api::set_value(&config, "app.cfg.b", "2").unwrap();
+
+    let error = api::move_value(&config, "app.cfg.a", "app.cfg.b", false).expect_err("conflict");
+    assert_eq!(error.kind, CrudErrorKind::Conflict);
+
+    api::move_value(&config, "app.cfg.a", "app.cfg.b", true).unwrap();
+    assert_eq!(
+        api::get_value(&config, "app.cfg.b").unwrap().as_deref(),
+        Some("1")
+    );
+}
+
+#[test]
+fn namespace_scope_parses_wildcards_and_prefixes() {
+    let scope = NamespaceScope::parse("app.config.*").unwrap();
+    assert_eq!(scope, NamespaceScope::new("app", "config", None));
+
+    let scope = NamespaceScope::parse("app.config.").unwrap();
+    assert_eq!(scope.prefix, None);
+
+    let scope = NamespaceScope::parse("app.config.db_").unwrap();
+    assert_eq!(scope.prefix.as_deref(), Some("db_"));
+
+    assert!(NamespaceScope::parse("app").is_err());
+}
+
+#[test]
+fn copy_namespace_clones_all_keys() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.staging.host", "stage.local").unwrap();
+    api::set_value(&config, "app.staging.port__blue", "8080").unwrap();
+    api::set_value(&config, "app.other.host", "ignored").unwrap();
+
+    let copied = api::copy_namespace(&config, "app.staging.*", "app.prod", false).unwrap();
+    assert_eq!(copied, 2);
+    assert_eq!(
+        api::get_value(&config, "app.prod.host").unwrap().as_deref(),
+        Some("stage.local")
+    );
+    assert_eq!(
+        api::get_value(&config, "app.prod.port__blue")
+            .unwrap()
+            .as_deref(),
+        Some("8080")
+    );
+    assert_eq!(
+        api::get_value(&config, "app.staging.host")
+            .unwrap()
+            .as_deref(),
+        Some("stage.local")
+    );
+}
+
+#[test]
+fn count_matches_namespace_and_prefix() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.config.db_host", "localhost").unwrap();
+    api::set_value(&config, "app.config.db_port", "5432").unwrap();
+    api::set_value(&config, "app.config.debug", "true").unwrap();
+    api::set_value(&config, "app.other.db_host", "ignored").unwrap();
+
+    assert_eq!(api::count_keys(&config, "app.config").unwrap(), 3);
+    assert_eq!(api::count_keys(&config, "app.config.db_*").unwrap(), 2);
+    assert_eq!(api::count_keys(&config, "app.empty").unwrap(), 0);
+}
+
+#[test]
+fn persist_clears_ttl_and_keeps_value() {
+    let (_temp, config) = temp_config();
+
+    let store = SqliteKeystore::open(&config).unwrap();
+    let addr = Address::parse("app.cache.token").unwrap();
+    store.set(&addr, "abc", Some(3600)).unwrap();
+
+    assert!(api::persist_value(&config, "app.cache.token").unwrap());
+    let expires_at: Option<i64> = store
+        .connection()
+        .query_row("SELECT expires_at FROM kv WHERE key = 'token'", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(expires_at, None);
+    assert_eq!(
+        api::get_value(&config, "app.cache.token")
+            .unwrap()
+            .as_deref(),
+        Some("abc")
+    );
+
+    assert!(!api::persist_value(&config, "app.cache.missing").unwrap());
+}
+
+#[test]
+fn sweep_removes_only_expired_rows() {
+    let (_temp, config) = temp_config();
+
+    let store = SqliteKeystore::open(&config).unwrap();
+    for key in ["a", "b", "c"] {
+        let addr = Address::parse(&format!("app.cache.{}", key)).unwrap();
+        store.set(&addr, "stale", Some(0)).unwrap();
+    }
+    store
+        .set(
+            &Address::parse("app.cache.fresh").unwrap(),
+            "ok",
+            Some(3600),
+        )
+        .unwrap();
+    store
+        .set(&Address::parse("app.config.keep").unwrap(), "ok", None)
+        .unwrap();
+
+    let reports = store.sweep_expired(2).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(
+        (reports[0].project.as_str(), reports[0].namespace.as_str()),
+        ("app", "cache")
+    );
+    assert_eq!(reports[0].removed, 3);
+    let mut keys = reports[0].keys.clone();
+    keys.sort();
+    assert_eq!(keys, vec!["a", "b", "c"]);
+
+    let remaining: i64 = store
+        .connection()
+        .query_row("SELECT COUNT(*) FROM kv", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(remaining, 2);
+    assert!(store.sweep_expired(2).unwrap().is_empty());
+}
+
+fn expires_at(store: &SqliteKeystore, key: &str) -> Option<i64> {
+    store
+        .connection()
+        .query_row("SELECT expires_at FROM kv WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .unwrap()
+}
+
+#[test]
+fn sliding_cache_renews_ttl_on_read() {
+    let (_temp, config) = temp_config();
+
+    api::create_cache(
+        &config,
+        "app.sessions",
+        &CacheSettings::new(3600).with_sliding(true),
+    )
+    .unwrap();
+    api::set_value(&config, "app.sessions.alice", "token").unwrap();
+
+    let store = SqliteKeystore::open(&config).unwrap();
+    store
+        .connection()
+        .execute("UPDATE kv SET expires_at = expires_at - 1800", [])
+        .unwrap();
+    let before = expires_at(&store, "alice").unwrap();
+
+    assert_eq!(
+        api::get_value(&config, "app.sessions.alice")
+            .unwrap()
+            .as_deref(),
+        Some("token")
+    );
+    assert!(expires_at(&store, "alice").unwrap() >= before + 1800);
+
+    assert!(api::create_cache(&config, "app.sessions.prefix", &CacheSettings::new(60)).is_err());
+}
+
+#[test]
+fn per_key_ttl_requires_opt_in() {
+    let (_temp, config) = temp_config();
+
+    let error = api::set_value_with_ttl(&config, "app.config.token", "abc", 60)
+        .expect_err("strict by default");
+    assert_eq!(error.kind, CrudErrorKind::InvalidInput);
+    assert_eq!(api::get_value(&config, "app.config.token").unwrap(), None);
+
+    api::allow_key_ttl(&config, "app.config", true).unwrap();
+    api::set_value_with_ttl(&config, "app.config.token", "abc", 60).unwrap();
+    let store = SqliteKeystore::open(&config).unwrap();
+    assert!(expires_at(&store, "token").is_some());
+
+    api::create_cache(&config, "app.cache", &CacheSettings::new(300)).unwrap();
+    api::set_value_with_ttl(&config, "app.cache.item", "x", 10).unwrap();
+}
+
+#[test]
+fn expiry_hooks_are_registered_per_namespace() {
+    let (_temp, config) = temp_config();
+
+    api::set_expiry_hook(&config, "app.cache", "logger -t prontodb").unwrap();
+    api::set_expiry_hook(&config, "app.cache", "touch /tmp/invalidate").unwrap();
+    let hooks = api::expiry_hooks(&config).unwrap();
+    assert_eq!(hooks.len(), 1);
+    assert_eq!(hooks[0].command, "touch /tmp/invalidate");
+
+    assert!(api::set_expiry_hook(&config, "app.cache", "  ").is_err());
+    assert!(api::remove_expiry_hook(&config, "app.cache").unwrap());
+    assert!(!api::remove_expiry_hook(&config, "app.cache").unwrap());
+}
+
+#[test]
+fn caches_lists_ttl_namespaces_with_counts() {
+    let (_temp, config) = temp_config();
+
+    api::create_cache(
+        &config,
+        "app.sessions",
+        &CacheSettings::new(600).with_sliding(true),
+    )
+    .unwrap();
+    api::create_cache(&config, "app.empty", &CacheSettings::new(60)).unwrap();
+    api::allow_key_ttl(&config, "app.config", true).unwrap();
+    api::set_value(&config, "app.sessions.a", "1").unwrap();
+    api::set_value(&config, "app.sessions.b", "2").unwrap();
+
+    let caches = SqliteKeystore::open(&config).unwrap().caches().unwrap();
+    assert_eq!(caches.len(), 2);
+    assert_eq!(caches[0].namespace, "empty");
+    assert_eq!(caches[0].keys, 0);
+    assert_eq!(caches[0].next_expiry, None);
+    assert_eq!(caches[1].namespace, "sessions");
+    assert_eq!(caches[1].keys, 2);
+    assert!(caches[1].settings.sliding);
+    assert!(caches[1].next_expiry.is_some());
+}
+
+#[test]
+fn injected_clock_drives_expiry() {
+    let (_temp, config) = temp_config();
+    let addr = Address::parse("app.cache.token").unwrap();
+
+    let at = |now: i64| {
+        SqliteKeystore::open(&config)
+            .unwrap()
+            .with_clock(Arc::new(FixedClock(now)))
+    };
+    at(1_000).set(&addr, "abc", Some(60)).unwrap();
+
+    assert_eq!(at(1_059).get(&addr).unwrap().as_deref(), Some("abc"));
+    assert_eq!(at(1_060).get(&addr).unwrap(), None);
+    assert!(!at(1_060).exists(&addr).unwrap());
+
+    let reports = at(1_060).sweep_expired(10).unwrap();
+    assert_eq!(reports[0].keys, vec!["token"]);
+}
+
+#[test]
+fn max_ttl_rejects_or_clamps_long_ttls() {
+    let (_temp, config) = temp_config();
+
+    api::create_cache(
+        &config,
+        "app.strict",
+        &CacheSettings::new(60).with_max_ttl(120, false),
+    )
+    .unwrap();
+    let error = api::set_value_with_ttl(&config, "app.strict.k", "v", 500).expect_err("too long");
+    assert_eq!(error.kind, CrudErrorKind::InvalidInput);
+    api::set_value_with_ttl(&config, "app.strict.k", "v", 120).unwrap();
+
+    api::create_cache(
+        &config,
+        "app.loose",
+        &CacheSettings::new(60).with_max_ttl(120, true),
+    )
+    .unwrap();
+    api::set_value_with_ttl(&config, "app.loose.k", "v", 500).unwrap();
+    let store = SqliteKeystore::open(&config).unwrap();
+    let stored: i64 = store
+        .connection()
+        .query_row(
+            "SELECT expires_at - updated_at FROM kv WHERE namespace = 'loose'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(stored, 120);
+
+    assert!(api::create_cache(
+        &config,
+        "app.bad",
+        &CacheSettings::new(60).with_max_ttl(30, false)
+    )
+    .is_err());
+}
+
+#[test]
+fn purge_expired_honours_scope() {
+    let (_temp, config) = temp_config();
+
+    let store = SqliteKeystore::open(&config).unwrap();
+    for path in ["app.cache.a", "app.sessions.b", "other.cache.c"] {
+        store
+            .set(&Address::parse(path).unwrap(), "stale", Some(0))
+            .unwrap();
+    }
+
+    let reports = store
+        .purge_expired(Some("app"), Some("cache"), 100)
+        .unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].keys, vec!["a"]);
+
+    let reports = store.purge_expired(Some("app"), None, 100).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].namespace, "sessions");
+
+    let reports = store.purge_expired(None, None, 100).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].project, "other");
+}
+
+#[test]
+fn include_expired_reads_unswept_rows() {
+    let (_temp, config) = temp_config();
+
+    SqliteKeystore::open(&config)
+        .unwrap()
+        .set(&Address::parse("app.cache.old").unwrap(), "stale", Some(0))
+        .unwrap();
+
+    assert_eq!(api::get_value(&config, "app.cache.old").unwrap(), None);
+    assert_eq!(
+        api::get_value_including_expired(&config, "app.cache.old")
+            .unwrap()
+            .as_deref(),
+        Some("stale")
+    );
+}
+
+#[test]
+fn listing_reports_entries_projects_and_namespaces() {
+    let (_temp, config) = temp_config();
+
+    api::set_value(&config, "app.config.port__blue", "8080").unwrap();
+    api::set_value(&config, "app.config.host", "localhost").unwrap();
+    api::set_value(&config, "app.secrets.token", "abc").unwrap();
+    api::set_value(&config, "tools.config.editor", "vim").unwrap();
+
+    let entries = api::list_entries(&config, "app.config").unwrap();
+    let keys: Vec<String> = entries.iter().map(|e| e.address.key_path()).collect();
+    assert_eq!(keys, vec!["host", "port__blue"]);
+    assert_eq!(entries[1].value, "8080");
+    assert_eq!(entries[1].expires_at, None);
+
+    assert_eq!(api::list_projects(&config).unwrap(), vec!["app", "tools"]);
+    assert_eq!(
+        api::list_namespaces(&config, "app").unwrap(),
+        vec!["config", "secrets"]
+    );
+
+    let entry = api::get_entry(&config, "app.config.host", false)
+        .unwrap()
+        .expect("entry");
+    assert_eq!(entry.value, "localhost");
+    assert_eq!(entry.ttl_remaining(entry.updated_at), None);
+}
+
+#[test]
+fn scan_entries_streams_and_stops_early() {
+    let (_temp, config) = temp_config();
+
+    for key in ["a", "b", "c", "d"] {
+        api::set_value(&config, &format!("app.big.{}", key), key).unwrap();
+    }
+
+    let mut seen = Vec::new();
+    api::scan_entries(&config, "app.big", |entry| {
+        seen.push(entry.value);
+        if seen.len() == 2 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+    .unwrap();
+    assert_eq!(seen, vec!["a", "b"]);
+}
+
+#[test]
+fn api_errors_carry_stable_codes() {
+    let (_temp, config) = temp_config();
+    let code = |error| api::ErrorCode::of(&error).as_str();
+
+    let error = api::set_value(&config, "a.b.c.d", "v").expect_err("bad address");
+    assert_eq!(code(error), "INVALID_ADDRESS");
+
+    let error = api::set_value_with_ttl(&config, "app.plain.k", "v", 30).expect_err("not ttl");
+    assert_eq!(code(error), "NS_NOT_TTL");
+
+    api::create_cache(
+        &config,
+        "app.capped",
+        &CacheSettings::new(60).with_max_ttl(120, false),
+    )
+    .unwrap();
+    let error = api::set_value_with_ttl(&config, "app.capped.k", "v", 500).expect_err("too long");
+    assert_eq!(code(error), "TTL_EXCEEDS_MAX");
+
+    let error =
+        api::move_value(&config, "app.plain.gone", "app.plain.k", false).expect_err("missing");
+    assert_eq!(code(error), "NOT_FOUND");
+}
+
+#[test]
+fn export_entries_filters_by_project_and_namespace() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.port", "8080").unwrap();
+    api::set_value(&config, "app.cfg.host__dev", "localhost").unwrap();
+    api::set_value(&config, "app.flags.beta", "on").unwrap();
+    api::set_value(&config, "other.cfg.port", "9090").unwrap();
+
+    let paths = |scope| -> Vec<String> {
+        api::export_entries(&config, scope)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.address.to_string())
+            .collect()
+    };
+    assert_eq!(paths(None).len(), 4);
+    assert_eq!(
+        paths(Some("app")),
+        vec!["app.cfg.host__dev", "app.cfg.port", "app.flags.beta"]
+    );
+    assert_eq!(paths(Some("app.flags")), vec!["app.flags.beta"]);
+    assert!(api::export_entries(&config, Some("app.cfg.po")).is_err());
+}
+
+#[test]
+fn import_values_applies_merge_strategy() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.port", "8080").unwrap();
+    let rows = vec![
+        ("app.cfg.port".to_string(), "9090".to_string(), None),
+        ("app.cfg.host".to_string(), "db".to_string(), Some(60)),
+    ];
+
+    let report = api::import_values(&config, &rows, ImportStrategy::Fail).unwrap();
+    assert_eq!((report.inserted, report.conflicted), (0, 1));
+    assert_eq!(api::get_value(&config, "app.cfg.host").unwrap(), None);
+
+    let report = api::import_values(&config, &rows, ImportStrategy::Skip).unwrap();
+    assert_eq!((report.inserted, report.skipped), (1, 1));
+    assert_eq!(
+        api::get_value(&config, "app.cfg.port").unwrap().as_deref(),
+        Some("8080")
+    );
+    let store = SqliteKeystore::open(&config).unwrap();
+    assert!(expires_at(&store, "host").is_some());
+
+    let report = api::import_values(&config, &rows, ImportStrategy::Overwrite).unwrap();
+    assert_eq!(report.inserted, 2);
+    assert_eq!(
+        api::get_value(&config, "app.cfg.port").unwrap().as_deref(),
+        Some("9090")
+    );
+}
+
+#[test]
+fn glob_scopes_match_keys_across_namespaces() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.db_host", "db").unwrap();
+    api::set_value(&config, "app.cfg.db_port", "5432").unwrap();
+    api::set_value(&config, "app.cfg.cache", "on").unwrap();
+    api::set_value(&config, "app.search.enabled", "yes").unwrap();
+    api::set_value(&config, "app.billing.enabled", "no").unwrap();
+
+    let paths = |scope| -> Vec<String> {
+        api::list_entries(&config, scope)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.address.to_string())
+            .collect()
+    };
+    assert_eq!(paths("app.cfg.db_?ort"), vec!["app.cfg.db_port"]);
+    assert_eq!(
+        paths("app.*.enabled"),
+        vec!["app.billing.enabled", "app.search.enabled"]
+    );
+    assert_eq!(paths("app.cfg.db_*").len(), 2);
+
+    assert!(KeyPattern::is_pattern("app.*.enabled"));
+    assert!(!KeyPattern::is_pattern("app.cfg.db_*"));
+    assert!(KeyPattern::parse("app").is_err());
+}
+
+#[test]
+fn regex_filters_keys_and_rejects_bad_patterns() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.db_host", "db").unwrap();
+    api::set_value(&config, "app.cfg.db_port__dev", "5432").unwrap();
+    api::set_value(&config, "app.cfg.cache", "on").unwrap();
+
+    let keys: Vec<String> = api::list_entries_matching(&config, "app.cfg", r"^db_\w+__dev$")
+        .unwrap()
+        .iter()
+        .map(|entry| entry.address.key_path())
+        .collect();
+    assert_eq!(keys, vec!["db_port__dev"]);
+
+    let error = api::list_entries_matching(&config, "app.cfg", "(").expect_err("bad regex");
+    assert_eq!(api::ErrorCode::of(&error).as_str(), "INVALID_PATTERN");
+}
+
+#[test]
+fn grep_values_streams_substring_and_regex_matches() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.dsn", "postgres://db:5432").unwrap();
+    api::set_value(&config, "app.cfg.cache", "redis://cache:6379").unwrap();
+    api::set_value(&config, "other.cfg.dsn", "postgres://replica:5432").unwrap();
+
+    let grep = |scope, pattern, regex| -> Vec<String> {
+        let mut found = Vec::new();
+        api::grep_values(&config, scope, pattern, regex, |entry| {
+            found.push(entry.address.to_string());
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        found
+    };
+    assert_eq!(
+        grep(None, "postgres", false),
+        vec!["app.cfg.dsn", "other.cfg.dsn"]
+    );
+    assert_eq!(
+        grep(Some("app"), r":\d{4}$", true),
+        vec!["app.cfg.cache", "app.cfg.dsn"]
+    );
+    assert!(grep(Some("app.cfg"), "mysql", false).is_empty());
+}
+
+#[test]
+fn full_text_index_ranks_values_in_opted_in_namespaces() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "notes.rust.tip", "borrow checker tip").unwrap();
+    api::set_value(&config, "notes.other.skip", "borrow borrow borrow").unwrap();
+
+    assert!(api::search_values(&config, "borrow", None, 10).is_err());
+
+    let mut store = SqliteKeystore::open(&config).unwrap();
+    assert!(store
+        .create_index("notes", "rust", &IndexKind::FullText)
+        .unwrap());
+    assert!(!store
+        .create_index("notes", "rust", &IndexKind::FullText)
+        .unwrap());
+    api::set_value(
+        &config,
+        "notes.rust.intro",
+        "borrow the borrow checker, borrow often",
+    )
+    .unwrap();
+
+    let hits = api::search_values(&config, "borrow", Some("notes"), 10).unwrap();
+    let found: Vec<String> = hits
+        .iter()
+        .map(|hit| hit.entry.address.to_string())
+        .collect();
+    assert_eq!(found, vec!["notes.rust.intro", "notes.rust.tip"]);
+    assert!(hits[0].snippet.contains("[borrow]"));
+
+    api::delete_value(&config, "notes.rust.intro").unwrap();
+    assert_eq!(
+        api::search_values(&config, "often", None, 10)
+            .unwrap()
+            .len(),
+        0
+    );
+    assert!(store
+        .drop_index("notes", "rust", &IndexKind::FullText)
+        .unwrap());
+    assert!(api::search_values(&config, "checker", None, 10)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn json_path_index_backs_where_filters() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.jobs.a", r#"{"status":"active","tries":2}"#).unwrap();
+    api::set_value(&config, "app.jobs.b", r#"{"status":"done","tries":1}"#).unwrap();
+    api::set_value(&config, "app.jobs.c", "status=active").unwrap();
+    api::set_value(&config, "app.other.d", r#"{"status":"active"}"#).unwrap();
+
+    let keys = |filter: &str| -> Vec<String> {
+        api::list_entries_where(&config, "app.jobs", filter)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.address.key_path())
+            .collect()
+    };
+    assert_eq!(keys(".status=active"), vec!["a"]);
+
+    let mut store = SqliteKeystore::open(&config).unwrap();
+    let kind = IndexKind::JsonPath(".status".to_string());
+    assert!(store.create_index("app", "jobs", &kind).unwrap());
+    assert!(!store
+        .create_index("app", "jobs", &IndexKind::JsonPath("status".to_string()))
+        .unwrap());
+    assert_eq!(store.indexes().unwrap()[0].kind.to_string(), "json .status");
+
+    assert_eq!(keys(".status=active"), vec!["a"]);
+    assert_eq!(keys(".tries=1"), vec!["b"]);
+    assert!(api::list_entries_where(&config, "app.jobs", "status").is_err());
+    assert!(api::list_entries_where(&config, "app.jobs", ".st'atus=x").is_err());
+    assert!(store.drop_index("app", "jobs", &kind).unwrap());
+    assert_eq!(keys(".status=done"), vec!["b"]);
+}
+
+#[test]
+fn key_ranges_select_half_open_windows() {
+    let (_temp, config) = temp_config();
+    for key in ["2024-12-31", "2025-01-01", "2025-01-31", "2025-02-01"] {
+        api::set_value(&config, &format!("app.logs.{}", key), "entry").unwrap();
+    }
+
+    let keys = |from: Option<&str>, to: Option<&str>| -> Vec<String> {
+        api::list_entries_in_range(&config, "app.logs", from, to)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.address.key_path())
+            .collect()
+    };
+    assert_eq!(
+        keys(Some("2025-01"), Some("2025-02")),
+        vec!["2025-01-01", "2025-01-31"]
+    );
+    assert_eq!(
+        keys(Some("2025-01-31"), None),
+        vec!["2025-01-31", "2025-02-01"]
+    );
+    assert_eq!(keys(None, Some("2025")), vec!["2024-12-31"]);
+}
+
+#[test]
+fn json_paths_are_set_read_and_removed_in_place() {
+    let (_temp, config) = temp_config();
+    let addr = "app.config.settings";
+
+    api::json_set_value(&config, addr, ".db.port", "5432").unwrap();
+    api::json_set_value(&config, addr, ".db.host", "localhost").unwrap();
+    api::json_set_value(&config, addr, ".debug", "true").unwrap();
+    assert_eq!(
+        api::get_value(&config, addr).unwrap().as_deref(),
+        Some(r#"{"db":{"port":5432,"host":"localhost"},"debug":true}"#)
+    );
+    assert_eq!(
+        api::json_get_value(&config, addr, ".db.host")
+            .unwrap()
+            .as_deref(),
+        Some("localhost")
+    );
+    assert_eq!(
+        api::json_get_value(&config, addr, ".db")
+            .unwrap()
+            .as_deref(),
+        Some(r#"{"port":5432,"host":"localhost"}"#)
+    );
+    assert_eq!(
+        api::json_get_value(&config, addr, ".debug")
+            .unwrap()
+            .as_deref(),
+        Some("true")
+    );
+    assert_eq!(
+        api::json_get_value(&config, addr, ".missing").unwrap(),
+        None
+    );
+
+    assert!(api::json_delete_value(&config, addr, ".db.port").unwrap());
+    assert!(!api::json_delete_value(&config, addr, ".db.port").unwrap());
+    assert_eq!(
+        api::get_value(&config, addr).unwrap().as_deref(),
+        Some(r#"{"db":{"host":"localhost"},"debug":true}"#)
+    );
+
+    api::set_value(&config, "app.config.plain", "not json").unwrap();
+    assert!(api::json_set_value(&config, "app.config.plain", ".a", "1").is_err());
+    assert!(api::json_get_value(&config, addr, "..").is_err());
+}
+
+#[test]
+fn typed_values_are_validated_and_guard_increments() {
+    let (_temp, config) = temp_config();
+
+    api::set_typed_value(&config, "app.cfg.port", "8080", ValueType::Int, None).unwrap();
+    api::set_typed_value(&config, "app.cfg.debug", "true", ValueType::Bool, None).unwrap();
+    let error = api::set_typed_value(&config, "app.cfg.workers", "many", ValueType::Int, None)
+        .expect_err("not an int");
+    assert_eq!(error.kind, CrudErrorKind::InvalidInput);
+    assert!(api::get_value(&config, "app.cfg.workers")
+        .unwrap()
+        .is_none());
+
+    let entry = api::get_entry(&config, "app.cfg.port", false)
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.value_type, Some(ValueType::Int));
+    assert_eq!(
+        api::increment_value(&config, "app.cfg.port", 1).unwrap(),
+        8081
+    );
+    assert_eq!(
+        api::get_entry(&config, "app.cfg.port", false)
+            .unwrap()
+            .unwrap()
+            .value_type,
+        Some(ValueType::Int)
+    );
+    let error = api::increment_value(&config, "app.cfg.debug", 1).expect_err("typed bool");
+    assert_eq!(error.kind, CrudErrorKind::InvalidInput);
+
+    api::set_value(&config, "app.cfg.debug", "1").unwrap();
+    let entry = api::get_entry(&config, "app.cfg.debug", false)
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.value_type, None);
+    assert_eq!(
+        api::increment_value(&config, "app.cfg.debug", 1).unwrap(),
+        2
+    );
+}
+
+#[test]
+fn cursor_rename_moves_scoped_and_legacy_files() {
+    let temp = tempdir().unwrap();
+    let manager = CursorManager::with_home(temp.path());
+    let work = CursorData::new("/srv/work.sqlite3", "alice").with_project(Some("app".into()));
+    let path = manager.set("work", &work).unwrap();
+    assert_eq!(path, temp.path().join("work/cursors/work.alice.cursor"));
+
+    manager.rename("work", "office", "alice", false).unwrap();
+    assert!(!path.exists());
+    let renamed = manager.get("office", "alice").unwrap().unwrap();
+    assert_eq!(renamed.default_project.as_deref(), Some("app"));
+    assert!(manager.get("office", "default").unwrap().is_none());
+
+    // Legacy flat files are migrated into their database directory on rename.
+    let legacy = temp.path().join("cursors/old.cursor");
+    std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+    let data = CursorData::new("/srv/work.sqlite3", "default");
+    std::fs::write(
+        &legacy,
+        hub::data_ext::serde_json::to_string(&data).unwrap(),
+    )
+    .unwrap();
+    let moved = manager.rename("old", "fresh", "default", false).unwrap();
+    assert_eq!(moved, temp.path().join("work/cursors/fresh.cursor"));
+    assert!(!legacy.exists());
+
+    manager.set("taken", &data).unwrap();
+    let err = manager
+        .rename("fresh", "taken", "default", false)
+        .unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::Conflict);
+    manager.rename("fresh", "taken", "default", true).unwrap();
+    assert_eq!(manager.list("default").unwrap().len(), 1);
+
+    let err = manager
+        .rename("missing", "other", "default", false)
+        .unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::NotFound);
+    let err = manager
+        .rename("taken", "bad.name", "default", false)
+        .unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::InvalidInput);
+}
+
+#[test]
+fn cursor_copy_replicates_definitions_between_users() {
+    let temp = tempdir().unwrap();
+    let manager = CursorManager::with_home(temp.path());
+    let vetted = CursorData::new("/srv/prod.sqlite3", "alice")
+        .with_project(Some("app".into()))
+        .with_meta_context(Some("ops".into()));
+    manager.set("prod", &vetted).unwrap();
+
+    let path = manager.copy("prod", "alice", "bob", false).unwrap();
+    assert_eq!(path, temp.path().join("prod/cursors/prod.bob.cursor"));
+    let copy = manager.get("prod", "bob").unwrap().unwrap();
+    assert_eq!(copy.user, "bob");
+    assert_eq!(copy.database_path, vetted.database_path);
+    assert_eq!(copy.default_project.as_deref(), Some("app"));
+    assert_eq!(copy.meta_context.as_deref(), Some("ops"));
+
+    let err = manager.copy("prod", "alice", "bob", false).unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::Conflict);
+    manager.copy("prod", "alice", "bob", true).unwrap();
+    let err = manager.copy("staging", "alice", "bob", false).unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::NotFound);
+}
+
+#[test]
+fn cursor_check_reports_broken_databases_and_files() {
+    let (temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.debug", "true").unwrap();
+    let manager = CursorManager::with_home(temp.path().join("home"));
+
+    manager
+        .set("good", &CursorData::new(&config.database_path, "default"))
+        .unwrap();
+    manager
+        .set(
+            "gone",
+            &CursorData::new(temp.path().join("gone.sqlite"), "default"),
+        )
+        .unwrap();
+    let not_db = temp.path().join("notes.txt");
+    std::fs::write(&not_db, "plain text, not sqlite").unwrap();
+    manager
+        .set("text", &CursorData::new(&not_db, "default"))
+        .unwrap();
+    let bad_meta =
+        CursorData::new(&config.database_path, "bob").with_meta_context(Some("a.b".into()));
+    manager.set("meta", &bad_meta).unwrap();
+    let legacy = temp.path().join("home/cursors/old.cursor");
+    std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+    std::fs::write(&legacy, "{not json").unwrap();
+
+    let reports = manager.check(None);
+    let broken: Vec<&str> = reports
+        .iter()
+        .filter(|report| !report.is_ok())
+        .map(|report| report.name.as_str())
+        .collect();
+    assert_eq!(reports.len(), 5);
+    assert_eq!(broken.len(), 4);
+    for name in ["gone", "text", "meta", "old"] {
+        assert!(broken.contains(&name), "{} should be broken", name);
+    }
+    let old = reports.iter().find(|report| report.name == "old").unwrap();
+    assert!(old.legacy);
+
+    let good = manager.check(Some("good"));
+    assert_eq!(good.len(), 1);
+    assert!(good[0].is_ok());
+}
+
+#[test]
+fn workdir_cursor_is_discovered_from_nested_directories() {
+    let temp = tempdir().unwrap();
+    let repo = temp.path().join("repo");
+    let nested = repo.join("src/deep");
+    std::fs::create_dir_all(&nested).unwrap();
+    assert!(
+        resolve_working_directory_cursor(&nested, "default", "default")
+            .unwrap()
+            .is_none()
+    );
+
+    std::fs::write(
+        repo.join(".prontodb"),
+        r#"{"path": "db/dev.sqlite3", "project": "app", "namespace": "config",
+            "cursors": {"prod": {"path": "/srv/prod.sqlite3", "namespace": "live"}},
+            "users": {"alice": {"prod": "/home/alice/prod.sqlite3"}}}"#,
+    )
+    .unwrap();
+
+    let local = resolve_working_directory_cursor(&nested, "default", "default")
+        .unwrap()
+        .unwrap();
+    assert_eq!(local.database_path, repo.join("db/dev.sqlite3"));
+    assert_eq!(local.default_project.as_deref(), Some("app"));
+    assert_eq!(local.default_namespace.as_deref(), Some("config"));
+
+    let prod = resolve_working_directory_cursor(&nested, "prod", "bob")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        prod.database_path,
+        std::path::PathBuf::from("/srv/prod.sqlite3")
+    );
+    assert_eq!(prod.default_project.as_deref(), Some("app"));
+    assert_eq!(prod.default_namespace.as_deref(), Some("live"));
+
+    let alice = resolve_working_directory_cursor(&nested, "prod", "alice")
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        alice.database_path,
+        std::path::PathBuf::from("/home/alice/prod.sqlite3")
+    );
+    assert!(
+        resolve_working_directory_cursor(&nested, "staging", "default")
+            .unwrap()
+            .is_none()
+    );
+
+    // The nearest file wins, even with the simple bare-path format.
+    std::fs::write(repo.join("src/.prontodb"), "local.sqlite3\n").unwrap();
+    let inner = resolve_working_directory_cursor(&nested, "default", "default")
+        .unwrap()
+        .unwrap();
+    assert_eq!(inner.database_path, repo.join("src/local.sqlite3"));
+    assert!(resolve_working_directory_cursor(&nested, "prod", "default")
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn concurrent_cursor_writes_never_leave_partial_files() {
+    let temp = tempdir().unwrap();
+    let home = temp.path().to_path_buf();
+    CursorManager::with_home(&home)
+        .set("shared", &CursorData::new("/srv/a.sqlite3", "default"))
+        .unwrap();
+
+    let writers: Vec<_> = (0..4)
+        .map(|writer| {
+            let manager = CursorManager::with_home(&home);
+            std::thread::spawn(move || {
+                for round in 0..50 {
+                    let project = format!("p{}_{}", writer, round);
+                    let data =
+                        CursorData::new("/srv/a.sqlite3", "default").with_project(Some(project));
+                    manager.set("shared", &data).unwrap();
+                }
+            })
+        })
+        .collect();
+    let reader = CursorManager::with_home(&home);
+    for _ in 0..200 {
+        assert!(reader.get("shared", "default").unwrap().is_some());
+    }
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let files: Vec<_> = std::fs::read_dir(home.join("a/cursors"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(files, vec![std::ffi::OsString::from("shared.cursor")]);
+}
+
+#[test]
+fn namespace_acls_restrict_reads_and_writes() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.secret.token", "abc").unwrap();
+    api::set_value(&config, "app.public.motd", "hi").unwrap();
+
+    let store = SqliteKeystore::open(&config).unwrap();
+    store
+        .grant_access("alice", "app", "secret", Permission::Write)
+        .unwrap();
+    store
+        .grant_access("bob", "app", "secret", Permission::Read)
+        .unwrap();
+
+    let alice = config.clone().with_user(Some("alice"));
+    let bob = config.clone().with_user(Some("bob"));
+    api::set_value(&alice, "app.secret.token", "xyz").unwrap();
+    assert_eq!(
+        api::get_value(&bob, "app.secret.token").unwrap().as_deref(),
+        Some("xyz")
+    );
+    let err = api::set_value(&bob, "app.secret.token", "nope").unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    let err = api::get_value(&config, "app.secret.token").unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    let err = api::list_entries(&config, "app.secret").unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+
+    // Unrestricted namespaces stay open; globs silently skip hidden rows.
+    api::set_value(&bob, "app.public.motd", "hello").unwrap();
+    let visible: Vec<String> = api::list_entries(&config, "app.*.*")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.address.to_string())
+        .collect();
+    assert_eq!(visible, vec!["app.public.motd".to_string()]);
+    assert_eq!(api::list_entries(&bob, "app.*.*").unwrap().len(), 2);
+
+    // `project.*` rules restrict every namespace of the project.
+    store
+        .grant_access("alice", "ops", "*", Permission::Write)
+        .unwrap();
+    api::set_value(&alice, "ops.deploy.lock", "1").unwrap();
+    let err = api::delete_value(&bob, "ops.deploy.lock").unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    let err =
+        api::copy_value(&bob, "app.public.motd", "ops.deploy.motd", false, false).unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+
+    assert!(store.revoke_access("bob", "app", "secret").unwrap());
+    assert_eq!(store.access_rules().unwrap().len(), 2);
+    let err = api::get_value(&bob, "app.secret.token").unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+}
+
+#[test]
+fn namespace_acls_cover_settings_and_listings() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.secret.token", "abc").unwrap();
+    api::set_value(&config, "app.public.motd", "hi").unwrap();
+    api::set_value(&config, "ops.deploy.lock", "1").unwrap();
+
+    let store = SqliteKeystore::open(&config).unwrap();
+    store
+        .grant_access("alice", "app", "secret", Permission::Write)
+        .unwrap();
+    store
+        .grant_access("alice", "ops", "*", Permission::Write)
+        .unwrap();
+    store
+        .grant_access("bob", "app", "secret", Permission::Read)
+        .unwrap();
+    let bob = config.clone().with_user(Some("bob"));
+
+    // Changing how a namespace behaves needs write access, like writing its keys.
+    let err = api::create_cache(&bob, "app.secret", &CacheSettings::new(60)).unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    let err = api::allow_key_ttl(&bob, "app.secret", true).unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    let err = api::mark_secret(&bob, "app.secret.token", false).unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    let alice = config.clone().with_user(Some("alice"));
+    api::allow_key_ttl(&alice, "app.secret", true).unwrap();
+
+    // Listings leave out what the user may not read.
+    assert_eq!(
+        api::list_projects(&config).unwrap(),
+        vec!["app".to_string()]
+    );
+    assert_eq!(
+        api::list_namespaces(&config, "app").unwrap(),
+        vec!["public".to_string()]
+    );
+    assert_eq!(
+        api::list_projects(&alice).unwrap(),
+        vec!["app".to_string(), "ops".to_string()]
+    );
+    let session = api::Session::open(&bob).unwrap();
+    assert_eq!(session.projects().unwrap(), vec!["app".to_string()]);
+    assert_eq!(
+        session.namespaces("app").unwrap(),
+        vec!["public".to_string(), "secret".to_string()]
+    );
+}
+
+#[test]
+fn audit_log_records_sets_deletes_and_copies() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.before", "untracked").unwrap();
+    let mut store = SqliteKeystore::open(&config).unwrap();
+    assert!(!store.audit_enabled());
+    assert!(store.enable_audit().unwrap());
+    assert!(!store.enable_audit().unwrap());
+
+    let alice = config.clone().with_user(Some("alice"));
+    api::set_value(&alice, "app.cfg.mode", "live").unwrap();
+    api::set_value(&alice, "app.cfg.mode", "test").unwrap();
+    api::copy_value(&config, "app.cfg.mode", "app.bak.mode", false, false).unwrap();
+    assert!(api::delete_value(&alice, "app.cfg.mode").unwrap());
+
+    let hash = |value: &str| Some(format!("{:x}", md5::compute(value)));
+    let log = store.audit_tail(10).unwrap();
+    let actions: Vec<String> = log
+        .iter()
+        .map(|entry| {
+            let user = entry.user.as_deref().unwrap_or("-");
+            format!("{} {} {}", user, entry.command, entry.address)
+        })
+        .collect();
+    assert_eq!(
+        actions,
+        vec![
+            "alice set app.cfg.mode",
+            "alice set app.cfg.mode",
+            "- copy app.bak.mode",
+            "alice del app.cfg.mode",
+        ]
+    );
+    let hashes: Vec<_> = log
+        .into_iter()
+        .map(|entry| (entry.old_hash, entry.new_hash))
+        .collect();
+    assert_eq!(
+        hashes,
+        vec![
+            (None, hash("live")),
+            (hash("live"), hash("test")),
+            (None, hash("test")),
+            (hash("test"), None),
+        ]
+    );
+
+    let found = store
+        .audit_search(&AuditQuery {
+            user: Some("alice".into()),
+            command: Some("set".into()),
+            address_prefix: Some("app.cfg".into()),
+            limit: 1,
+        })
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].new_hash, hash("test"));
+
+    // The log is append-only even through a raw connection.
+    assert!(store
+        .connection()
+        .execute("DELETE FROM sys_audit", [])
+        .is_err());
+    assert_eq!(store.audit_tail(10).unwrap().len(), 4);
+}
+
+#[test]
+fn oplog_records_every_write_in_order() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.before", "untracked").unwrap();
+    assert_eq!(
+        api::changes_since(&config, OplogSince::Seq(0), 10).unwrap(),
+        None
+    );
+    assert!(api::enable_oplog(&config).unwrap());
+    assert!(!api::enable_oplog(&config).unwrap());
+
+    let alice = config.clone().with_user(Some("alice"));
+    let clock = Arc::new(FixedClock(1_000));
+    let store = SqliteKeystore::open(&alice).unwrap().with_clock(clock);
+    let mode = Address::parse("app.cfg.mode__prod").unwrap();
+    store.set(&mode, "live", None).unwrap();
+    assert!(store.expire(&mode, 60).unwrap());
+    assert!(store.delete(&mode).unwrap());
+    drop(store);
+    api::set_value(&config, "app.cfg.port", "8080").unwrap();
+
+    let hash = |value: &str| Some(format!("{:x}", md5::compute(value)));
+    let changes = api::changes_since(&config, OplogSince::Seq(0), 10)
+        .unwrap()
+        .unwrap();
+    let ops: Vec<_> = changes
+        .iter()
+        .map(|change| {
+            let user = change.user.as_deref().unwrap_or("-");
+            (
+                change.seq,
+                change.op.as_str(),
+                change.address.as_str(),
+                user,
+            )
+        })
+        .collect();
+    assert_eq!(
+        ops,
+        vec![
+            (1, "set", "app.cfg.mode__prod", "alice"),
+            (2, "set", "app.cfg.mode__prod", "alice"),
+            (3, "del", "app.cfg.mode__prod", "alice"),
+            (4, "set", "app.cfg.port", "-"),
+        ]
+    );
+    assert_eq!(changes[0].value_hash, hash("live"));
+    assert_eq!(changes[2].value_hash, None);
+    assert_eq!(changes[0].at, 1_000);
+
+    let after = api::changes_since(&config, OplogSince::Seq(2), 10)
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.len(), 2);
+    let recent = api::changes_since(&config, OplogSince::Time(1_001), 10)
+        .unwrap()
+        .unwrap();
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].address, "app.cfg.port");
+}
+
+#[cfg(feature = "encryption-aes")]
+#[test]
+fn encrypted_namespace_roundtrips_only_with_its_key_file() {
+    let (temp, config) = temp_config();
+    let key_file = temp.path().join("pronto.key");
+    std::fs::write(&key_file, "correct horse\n").unwrap();
+    let mut store = SqliteKeystore::open(&config).unwrap();
+    store
+        .create_namespace("app", "vault", Some(b"correct horse"))
+        .unwrap();
+    assert_eq!(store.encrypted_namespaces().unwrap().len(), 1);
+
+    let keyed = config.clone().with_key_file(Some(&key_file));
+    api::set_value(&keyed, "app.vault.token", "s3cr3t").unwrap();
+    assert_eq!(
+        api::get_value(&keyed, "app.vault.token")
+            .unwrap()
+            .as_deref(),
+        Some("s3cr3t")
+    );
+    let stored = store
+        .get(&Address::parse("app.vault.token").unwrap())
+        .unwrap()
+        .unwrap();
+    assert!(stored.starts_with("enc:v1:"), "{}", stored);
+
+    store
+        .connection()
+        .execute(
+            "INSERT INTO kv (project, namespace, key, context, value, created_at, updated_at)
+             SELECT project, namespace, 'stolen', context, value, created_at, updated_at
+             FROM kv WHERE key = 'token'",
+            [],
+        )
+        .unwrap();
+    let copied = api::get_value(&keyed, "app.vault.stolen").unwrap_err();
+    assert_eq!(copied.kind, CrudErrorKind::Internal);
+    api::move_value(&keyed, "app.vault.token", "app.vault.moved", false).unwrap();
+    assert_eq!(
+        api::get_value(&keyed, "app.vault.moved")
+            .unwrap()
+            .as_deref(),
+        Some("s3cr3t")
+    );
+    api::copy_value(&keyed, "app.vault.moved", "app.vault.token", false, false).unwrap();
+
+    let missing = api::get_value(&config, "app.vault.token").unwrap_err();
+    assert_eq!(missing.kind, CrudErrorKind::InvalidInput);
+    assert_eq!(api::ErrorCode::of(&missing).as_str(), "KEY_REQUIRED");
+
+    std::fs::write(&key_file, "battery staple").unwrap();
+    let wrong = api::get_value(&keyed, "app.vault.token").unwrap_err();
+    assert_eq!(wrong.kind, CrudErrorKind::PermissionDenied);
+
+    let conflict = store
+        .create_namespace("app", "vault", Some(b"correct horse"))
+        .unwrap_err();
+    assert_eq!(conflict.kind, CrudErrorKind::Conflict);
+}
+
+#[cfg(not(feature = "encryption-aes"))]
+#[test]
+fn encrypted_namespace_needs_the_encryption_feature() {
+    let (_temp, config) = temp_config();
+    let mut store = SqliteKeystore::open(&config).unwrap();
+    store.create_namespace("app", "plain", None).unwrap();
+    let err = store
+        .create_namespace("app", "vault", Some(b"secret"))
+        .unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::InvalidInput);
+    assert!(store.encrypted_namespaces().unwrap().is_empty());
+}
+
+#[test]
+fn api_tokens_authenticate_and_limit_access_to_their_scope() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.mode", "live").unwrap();
+    api::set_value(&config, "app.other.mode", "hidden").unwrap();
+    let store = SqliteKeystore::open(&config).unwrap();
+    let (token, raw) = store.create_token("app", "cfg", Permission::Read).unwrap();
+    assert!(raw.starts_with(API_TOKEN_PREFIX));
+    let stored: String = store
+        .connection()
+        .query_row("SELECT token_hash FROM sys_tokens", [], |row| row.get(0))
+        .unwrap();
+    assert_ne!(stored, raw);
+
+    let remote = config.clone().with_token(Some(raw.as_str()));
+    assert_eq!(
+        api::get_value(&remote, "app.cfg.mode").unwrap().as_deref(),
+        Some("live")
+    );
+    let denied = api::set_value(&remote, "app.cfg.mode", "test").unwrap_err();
+    assert_eq!(denied.kind, CrudErrorKind::PermissionDenied);
+    let denied = api::get_value(&remote, "app.other.mode").unwrap_err();
+    assert_eq!(denied.kind, CrudErrorKind::PermissionDenied);
+
+    let forged = config.clone().with_token(Some("pdb_forged"));
+    let denied = api::get_value(&forged, "app.cfg.mode").unwrap_err();
+    assert_eq!(denied.kind, CrudErrorKind::PermissionDenied);
+
+    assert_eq!(store.tokens().unwrap(), vec![token.clone()]);
+    assert!(store.revoke_token(token.id).unwrap());
+    let revoked = api::get_value(&remote, "app.cfg.mode").unwrap_err();
+    assert_eq!(revoked.kind, CrudErrorKind::PermissionDenied);
+}
+
+#[test]
+fn retention_policy_trims_old_and_excess_keys() {
+    let (_temp, config) = temp_config();
+    let at = |now: i64| {
+        SqliteKeystore::open(&config)
+            .unwrap()
+            .with_clock(Arc::new(FixedClock(now)))
+    };
+    for (key, written) in [("a", 100), ("b", 200), ("c", 300), ("d", 400)] {
+        let addr = Address::parse(&format!("app.logs.{}", key)).unwrap();
+        at(written).set(&addr, "line", None).unwrap();
+    }
+    at(400)
+        .set(&Address::parse("app.cfg.keep").unwrap(), "x", None)
+        .unwrap();
+
+    let policy = RetentionPolicy {
+        max_age_secs: Some(300),
+        max_keys: Some(1),
+    };
+    api::set_retention(&config, "app.logs", &policy).unwrap();
+    assert_eq!(
+        api::retention_policies(&config).unwrap(),
+        vec![("app".to_string(), "logs".to_string(), policy)]
+    );
+
+    // At 450 only `a` is older than 300s; of b, c and d only the newest stays.
+    let reports = at(450).enforce_retention().unwrap();
+    assert_eq!(reports.len(), 1);
+    let mut trimmed = reports[0].keys.clone();
+    trimmed.sort();
+    assert_eq!(trimmed, vec!["a", "b", "c"]);
+    let left: Vec<String> = at(450)
+        .list(&NamespaceScope::parse("app.logs").unwrap())
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.address.key)
+        .collect();
+    assert_eq!(left, vec!["d"]);
+    assert!(api::value_exists(&config, "app.cfg.keep").unwrap());
+
+    // A retention policy deletes keys, so it needs write access like `del`.
+    at(450)
+        .grant_access("alice", "app", "logs", Permission::Read)
+        .unwrap();
+    let alice = config.clone().with_user(Some("alice"));
+    let denied = api::set_retention(&alice, "app.logs", &RetentionPolicy::default()).unwrap_err();
+    assert_eq!(denied.kind, CrudErrorKind::PermissionDenied);
+    assert_eq!(api::retention_policies(&config).unwrap().len(), 1);
+    assert!(at(450).revoke_access("alice", "app", "logs").unwrap());
+
+    api::set_retention(&config, "app.logs", &RetentionPolicy::default()).unwrap();
+    assert!(api::retention_policies(&config).unwrap().is_empty());
+}
+
+#[test]
+fn pronto_client_embeds_the_store_without_the_cli() {
+    let temp = tempdir().unwrap();
+    let path = temp.path().join("embedded.sqlite3");
+    let client = prontodb::ProntoClient::open(&path).unwrap();
+    assert_eq!(client.path(), path.as_path());
+
+    client.set("app.config.port", "8080", None).unwrap();
+    client.set("app.config.host", "localhost", None).unwrap();
+    assert_eq!(
+        client.get("app.config.port").unwrap().as_deref(),
+        Some("8080")
+    );
+    assert!(client.exists("app.config.host").unwrap());
+    let keys: Vec<String> = client
+        .entries("app.config")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.address.key)
+        .collect();
+    assert_eq!(keys, vec!["host", "port"]);
+    assert_eq!(client.projects().unwrap(), vec!["app"]);
+
+    // Errors come back as values; a TTL outside a cache namespace is refused.
+    let err = client.set("app.config.tmp", "x", Some(60)).unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::NsNotTtl);
+    assert!(matches!(
+        api::ProntoError::from(err),
+        api::ProntoError::NamespaceNotTtl(_)
+    ));
+
+    assert!(client.delete("app.config.port").unwrap());
+    assert!(!client.delete("app.config.port").unwrap());
+    assert_eq!(client.get("app.config.port").unwrap(), None);
+}
+
+#[test]
+fn error_codes_name_missing_cursors_and_busy_storage() {
+    let temp = tempdir().unwrap();
+    let manager = CursorManager::with_home(temp.path().join("home"));
+    let missing = manager
+        .rename("ghost", "other", "default", false)
+        .expect_err("no cursor");
+    assert_eq!(api::ErrorCode::of(&missing), api::ErrorCode::CursorMissing);
+    assert_eq!(api::ErrorCode::of(&missing).as_str(), "CURSOR_MISSING");
+    let typed = api::ProntoError::from(&missing);
+    assert!(matches!(typed, api::ProntoError::CursorMissing(_)));
+    assert_eq!(typed.code(), api::ErrorCode::CursorMissing);
+
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cfg.mode", "live").unwrap();
+    let holder = rusqlite::Connection::open(&config.database_path).unwrap();
+    holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+    let busy = api::set_value(&config, "app.cfg.mode", "test").expect_err("locked");
+    assert_eq!(api::ErrorCode::of(&busy), api::ErrorCode::StorageBusy);
+    assert!(matches!(
+        api::ProntoError::from(busy),
+        api::ProntoError::StorageBusy(_)
+    ));
+    holder.execute_batch("ROLLBACK").unwrap();
+    api::set_value(&config, "app.cfg.mode", "test").unwrap();
+}
+
+#[test]
+fn pronto_client_round_trips_serde_values_as_json() {
+    use std::collections::BTreeMap;
+
+    let temp = tempdir().unwrap();
+    let client = prontodb::ProntoClient::open(temp.path().join("json.sqlite3")).unwrap();
+    let ports: BTreeMap<String, u16> = [("http".to_string(), 80), ("https".to_string(), 443)]
+        .into_iter()
+        .collect();
+    client.set_json("app.config.ports", &ports, None).unwrap();
+    assert_eq!(
+        client.get("app.config.ports").unwrap().as_deref(),
+        Some(r#"{"http":80,"https":443}"#)
+    );
+    assert_eq!(
+        client
+            .get_json::<BTreeMap<String, u16>>("app.config.ports")
+            .unwrap(),
+        Some(ports)
+    );
+    assert_eq!(
+        client.get_json::<Vec<String>>("app.config.none").unwrap(),
+        None
+    );
+
+    client.set("app.config.mode", "not json", None).unwrap();
+    let err = client.get_json::<u16>("app.config.mode").unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::InvalidValue);
+    assert!(err.to_string().contains("app.config.mode"), "{}", err);
+}
+
+#[test]
+fn storage_options_pick_the_database_like_the_cli() {
+    let temp = tempdir().unwrap();
+    let home = temp.path().join("home");
+    let manager = CursorManager::with_home(&home);
+    let staging = temp.path().join("staging.sqlite3");
+    manager
+        .set(
+            "staging",
+            &CursorData::new(&staging, "default").with_project(Some("app".into())),
+        )
+        .unwrap();
+    let base = api::StorageOptions::new().cursor_home(&home);
+
+    let config = base.clone().cursor("staging").resolve().unwrap();
+    assert_eq!(config.database_path, staging);
+    assert_eq!(config.address_defaults.project, "app");
+
+    // An explicit path, then a registry name, win over the cursor.
+    let config = base
+        .clone()
+        .cursor("staging")
+        .database("reports")
+        .resolve()
+        .unwrap();
+    assert_eq!(config.database_path, manager.database_path("reports"));
+    let config = base
+        .clone()
+        .cursor("staging")
+        .database("reports")
+        .path(temp.path().join("x.sqlite3"))
+        .busy_timeout(Some(Duration::from_millis(250)))
+        .resolve()
+        .unwrap();
+    assert_eq!(config.database_path, temp.path().join("x.sqlite3"));
+    assert_eq!(config.busy_timeout, Some(Duration::from_millis(250)));
+
+    let missing = base.clone().cursor("ghost").resolve().unwrap_err();
+    assert_eq!(api::ErrorCode::of(&missing), api::ErrorCode::CursorMissing);
+
+    let existing_only = base.clone().cursor("staging").create_if_missing(false);
+    let err = prontodb::ProntoClient::with_options(&existing_only)
+        .err()
+        .expect("no database file yet");
+    assert_eq!(err.kind, CrudErrorKind::NotFound);
+    assert!(!staging.exists());
+    let client = prontodb::ProntoClient::with_options(&base.cursor("staging")).unwrap();
+    client.set("cfg.mode", "live", None).unwrap();
+    assert_eq!(
+        prontodb::ProntoClient::with_options(&existing_only)
+            .unwrap()
+            .get("app.cfg.mode")
+            .unwrap()
+            .as_deref(),
+        Some("live")
+    );
+}
+
+#[test]
+fn reused_connections_return_to_the_pool_and_see_new_logs() {
+    let (_temp, config) = temp_config();
+    let pooled = config.clone().with_connection_reuse(true);
+    api::set_value(&pooled, "app.cfg.a", "1").unwrap();
+    api::set_value(&pooled, "app.cfg.b", "2").unwrap();
+    assert_eq!(
+        api::get_value(&pooled, "app.cfg.a").unwrap().as_deref(),
+        Some("1")
+    );
+
+    // A separate connection turns the oplog on; the idle pooled one picks it up.
+    assert!(api::enable_oplog(&config).unwrap());
+    api::set_value(&pooled, "app.cfg.c", "3").unwrap();
+    let changes = api::changes_since(&pooled, OplogSince::Seq(0), 10)
+        .unwrap()
+        .unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].address, "app.cfg.c");
+
+    assert_eq!(
+        api::close_idle_connections(Some(config.database_path.as_path())),
+        1
+    );
+    assert_eq!(
+        api::close_idle_connections(Some(config.database_path.as_path())),
+        0
+    );
+}
+
+#[test]
+fn pooled_connections_recheck_tokens_and_respect_create_if_missing() {
+    let (_temp, config) = temp_config();
+    let pooled = config.clone().with_connection_reuse(true);
+    api::set_value(&pooled, "app.cfg.mode", "live").unwrap();
+    let store = SqliteKeystore::open(&config).unwrap();
+    let (token, raw) = store.create_token("app", "cfg", Permission::Read).unwrap();
+
+    // Token sessions share the pool; a reused connection re-authenticates.
+    let remote = pooled.clone().with_token(Some(raw.as_str()));
+    let session = api::Session::open(&remote).unwrap();
+    assert_eq!(
+        session.get("app.cfg.mode").unwrap().as_deref(),
+        Some("live")
+    );
+    drop(session);
+    assert!(store.revoke_token(token.id).unwrap());
+    let revoked = api::get_value(&remote, "app.cfg.mode").unwrap_err();
+    assert_eq!(revoked.kind, CrudErrorKind::PermissionDenied);
+
+    // A caller that must not create the database never borrows a connection
+    // opened by one that may, so a deleted file stays missing.
+    assert_eq!(
+        api::get_value(&pooled, "app.cfg.mode").unwrap().as_deref(),
+        Some("live")
+    );
+    drop(store);
+    std::fs::remove_file(&config.database_path).unwrap();
+    let existing = pooled.clone().with_create_if_missing(false);
+    let missing = api::get_value(&existing, "app.cfg.mode").unwrap_err();
+    assert_eq!(missing.kind, CrudErrorKind::NotFound);
+    api::close_idle_connections(Some(config.database_path.as_path()));
+}
+
+fn exercise_backend<B: KvBackend>(store: &B) {
+    let port = Address::parse("app.cfg.port").unwrap();
+    let host = Address::parse("app.cfg.host__dev").unwrap();
+    store.set(&port, "8080", None).unwrap();
+    store.set(&host, "localhost", Some(60)).unwrap();
+    store
+        .set(&Address::parse("app.other.x").unwrap(), "1", None)
+        .unwrap();
+    assert_eq!(store.get(&port).unwrap().as_deref(), Some("8080"));
+    assert!(store.exists(&host).unwrap());
+    let keys: Vec<String> = store
+        .list(&NamespaceScope::parse("app.cfg").unwrap())
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.address.to_string())
+        .collect();
+    assert_eq!(keys, vec!["app.cfg.host__dev", "app.cfg.port"]);
+    assert!(store.delete(&port).unwrap());
+    assert!(!store.delete(&port).unwrap());
+    assert_eq!(store.get(&port).unwrap(), None);
+}
+
+#[test]
+fn memory_and_sqlite_backends_agree() {
+    exercise_backend(&MemoryKeystore::new());
+
+    let (_temp, config) = temp_config();
+    api::create_cache(&config, "app.cfg", &CacheSettings::new(300)).unwrap();
+    exercise_backend(&SqliteKeystore::open(&config).unwrap());
+
+    // Expiry follows the clock, as in SQLite.
+    let memory = MemoryKeystore::new().with_clock(Arc::new(FixedClock(1_000)));
+    let addr = Address::parse("app.cache.token").unwrap();
+    memory.set(&addr, "t", Some(30)).unwrap();
+    assert_eq!(memory.entries()[0].expires_at, Some(1_030));
+    let memory = memory.with_clock(Arc::new(FixedClock(1_030)));
+    assert!(!memory.exists(&addr).unwrap());
+    assert!(memory.entries().is_empty());
+}
+
+#[test]
+fn backend_client_parses_addresses_for_any_backend() {
+    let client = api::BackendClient::new(MemoryKeystore::new())
+        .with_defaults(AddressDefaults::new(Some("app"), Some("cfg")));
+    client.set("port", "8080", None).unwrap();
+    client
+        .set("app.cfg.host__dev", "localhost", Some(60))
+        .unwrap();
+    assert_eq!(client.get("app.cfg.port").unwrap().as_deref(), Some("8080"));
+    assert!(client.exists("host__dev").unwrap());
+    let keys: Vec<String> = client
+        .entries("app.cfg")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.address.to_string())
+        .collect();
+    assert_eq!(keys, vec!["app.cfg.host__dev", "app.cfg.port"]);
+    assert!(client.delete("port").unwrap());
+    assert_eq!(client.backend().entries().len(), 1);
+
+    // Bad input fails with the same codes as the SQLite-backed API.
+    let err = client.get("a..b").unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::InvalidAddress);
+    let err = client.entries("app").unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::InvalidScope);
+}
+
+#[test]
+fn scan_iter_pages_through_a_namespace_lazily() {
+    let (_temp, config) = temp_config();
+    for index in 0..25 {
+        api::set_value(&config, &format!("app.logs.k{:02}", index), "v").unwrap();
+    }
+    api::set_value(&config, "app.logs.k05__eu", "ctx").unwrap();
+    api::set_value(&config, "app.other.k00", "elsewhere").unwrap();
+
+    let streamed: Vec<String> = api::scan_iter(&config, "app.logs")
+        .unwrap()
+        .batch_size(4)
+        .map(|entry| entry.unwrap().address.to_string())
+        .collect();
+    let listed: Vec<String> = api::list_entries(&config, "app.logs")
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.address.to_string())
+        .collect();
+    assert_eq!(streamed.len(), 26);
+    assert_eq!(streamed, listed);
+
+    let client = prontodb::ProntoClient::with_config(&config).unwrap();
+    let first: Vec<String> = client
+        .scan_iter("app.logs.k1")
+        .unwrap()
+        .take(3)
+        .map(|entry| entry.unwrap().address.key)
+        .collect();
+    assert_eq!(first, vec!["k10", "k11", "k12"]);
+
+    let err = api::scan_iter(&config, "*.logs")
+        .err()
+        .expect("patterns are refused");
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::InvalidScope);
+}
+
+#[test]
+fn dotted_keys_layout_reads_long_addresses_per_database() {
+    let (_temp, config) = temp_config();
+    let err = api::set_value(&config, "app.routes.api.v2", "x").unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::InvalidAddress);
+    assert_eq!(api::address_layout(&config).unwrap(), AddressLayout::Fixed);
+
+    api::set_address_layout(&config, AddressLayout::DottedKeys).unwrap();
+    api::set_value(&config, "app.routes.api.v2", "10.0.0.7").unwrap();
+    api::set_value(&config, "app.routes.api.v2__eu", "10.0.0.8").unwrap();
+    let entry = api::get_entry(&config, "app.routes.api.v2")
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.address.namespace, "routes");
+    assert_eq!(entry.address.key, "api.v2");
+    assert_eq!(entry.address.to_string(), "app.routes.api.v2");
+    assert_eq!(
+        api::get_value(&config, "app.routes.api.v2__eu")
+            .unwrap()
+            .as_deref(),
+        Some("10.0.0.8")
+    );
+    assert_eq!(
+        api::list_entries(&config, "app.routes.api.").unwrap().len(),
+        2
+    );
+
+    // Sessions read the layout once; an explicit one in the config wins.
+    let client = prontodb::ProntoClient::with_config(&config).unwrap();
+    assert_eq!(
+        client.get("app.routes.api.v2").unwrap().as_deref(),
+        Some("10.0.0.7")
+    );
+    let fixed = config
+        .clone()
+        .with_address_layout(Some(AddressLayout::Fixed));
+    assert!(api::get_value(&fixed, "app.routes.api.v2").is_err());
+
+    // Back under the fixed layout the same key is reached by quoting it.
+    api::set_address_layout(&config, AddressLayout::Fixed).unwrap();
+    assert_eq!(
+        api::get_value(&config, r#"app.routes."api.v2""#)
+            .unwrap()
+            .as_deref(),
+        Some("10.0.0.7")
+    );
+}
+
+#[test]
+fn resolve_address_applies_defaults_without_touching_the_database() {
+    let (_temp, config) = temp_config();
+    let config = config.with_address_defaults(AddressDefaults::new(Some("app"), Some("config")));
+
+    let addr = api::resolve_address(&config, "debug__prod").unwrap();
+    assert_eq!(addr.to_string(), "app.config.debug__prod");
+    assert_eq!(addr.context.as_deref(), Some("prod"));
+    let addr = api::resolve_address(&config, "cache.token").unwrap();
+    assert_eq!(addr.to_string(), "app.cache.token");
+    assert!(!config.database_path().exists());
+
+    let err = api::resolve_address(&config, "app.routes.api.v2").unwrap_err();
+    assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::InvalidAddress);
+    let dotted = config.with_address_layout(Some(AddressLayout::DottedKeys));
+    let addr = api::resolve_address(&dotted, "app.routes.api.v2").unwrap();
+    assert_eq!(
+        (addr.namespace.as_str(), addr.key.as_str()),
+        ("routes", "api.v2")
+    );
+}
+
+#[test]
+fn user_defaults_fill_what_the_cursor_leaves_unset() {
+    let temp = tempdir().unwrap();
+    let home = temp.path().join("home");
+    let manager = CursorManager::with_home(&home);
+    assert!(manager.user_defaults("alice").unwrap().is_empty());
+
+    let saved = UserDefaults {
+        project: Some("app".into()),
+        namespace: Some("config".into()),
+    };
+    manager.set_user_defaults("alice", &saved).unwrap();
+    assert_eq!(manager.user_defaults("alice").unwrap(), saved);
+    assert!(manager.user_defaults("bob").unwrap().is_empty());
+
+    let path = temp.path().join("kv.sqlite3");
+    let options = api::StorageOptions::new()
+        .cursor_home(&home)
+        .path(&path)
+        .user(Some("alice"));
+    let config = options.resolve().unwrap();
+    api::set_value(&config, "debug", "true").unwrap();
+    assert_eq!(
+        api::get_value(&config, "app.config.debug")
+            .unwrap()
+            .as_deref(),
+        Some("true")
+    );
+
+    // A cursor's own project wins; its unset namespace still comes from the user.
+    manager
+        .set(
+            "work",
+            &CursorData::new(&path, "alice").with_project(Some("svc".into())),
+        )
+        .unwrap();
+    let config = api::StorageOptions::new()
+        .cursor_home(&home)
+        .cursor("work")
+        .user(Some("alice"))
+        .resolve()
+        .unwrap();
+    assert_eq!(config.address_defaults.project, "svc");
+    assert_eq!(config.address_defaults.namespace, "config");
+
+    manager
+        .set_user_defaults("alice", &UserDefaults::default())
+        .unwrap();
+    assert!(!manager.user_defaults_path("alice").exists());
+    assert!(manager.user_defaults("../x").is_err());
+}
+
+#[test]
+fn delete_matching_removes_every_glob_match_at_once() {
+    let (_temp, config) = temp_config();
+    api::set_value(&config, "app.cache.a", "1").unwrap();
+    api::set_value(&config, "app.cache.b", "2").unwrap();
+    api::set_value(&config, "app.cache.b__eu", "3").unwrap();
+    api::set_value(&config, "app.config.a", "keep").unwrap();
+
+    let matched: Vec<String> = api::keys_matching(&config, "app.cache.*")
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        matched,
+        vec!["app.cache.a", "app.cache.b", "app.cache.b__eu"]
+    );
+    assert_eq!(api::delete_matching(&config, "app.cache.*").unwrap(), 3);
+    assert_eq!(api::count_keys(&config, "app.cache").unwrap(), 0);
+    assert_eq!(api::count_keys(&config, "app.config").unwrap(), 1);
+    assert_eq!(api::delete_matching(&config, "app.cache.*").unwrap(), 0);
+
+    // A namespace the user may not write refuses the whole pattern.
+    api::set_value(&config, "app.cache.c", "4").unwrap();
+    let store = SqliteKeystore::open(&config).unwrap();
+    store
+        .grant_access("alice", "app", "config", Permission::Read)
+        .unwrap();
+    let alice = config.clone().with_user(Some("alice"));
+    let err = api::delete_matching(&alice, "app.c*.*").unwrap_err();
+    assert_eq!(err.kind, CrudErrorKind::PermissionDenied);
+    assert_eq!(api::count_keys(&config, "app.cache").unwrap(), 1);
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_client_runs_calls_off_the_executor() {
+    let temp = tempdir().unwrap();
+    let path = temp.path().join("async.sqlite3");
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let client = prontodb::AsyncProntoClient::open(&path).await.unwrap();
+        client.set("app.cfg.port", "8080", None).await.unwrap();
+
+        // Clones share the connection, so tasks see each other's writes.
+        let other = client.clone();
+        let read = tokio::spawn(async move { other.get("app.cfg.port").await })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(read.as_deref(), Some("8080"));
+
+        let err = client.set("app.cfg.tmp", "x", Some(60)).await.unwrap_err();
+        assert_eq!(api::ErrorCode::of(&err), api::ErrorCode::NsNotTtl);
+        assert!(client.delete("app.cfg.port").await.unwrap());
+        assert_eq!(client.entries("app.cfg").await.unwrap(), Vec::new());
+    });
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn ffi_reads_and_writes_through_the_c_abi() {
+    use prontodb::lib::ffi::{
+        pronto_close, pronto_delete, pronto_free, pronto_get, pronto_last_error, pronto_open,
+        pronto_set,
+    };
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    let temp = tempdir().unwrap();
+    let path = CString::new(temp.path().join("ffi.sqlite3").to_str().unwrap()).unwrap();
+    let address = CString::new("app.cfg.port").unwrap();
+    let value = CString::new("8080").unwrap();
+    unsafe {
+        let handle = pronto_open(path.as_ptr());
+        assert!(!handle.is_null());
+        assert_eq!(pronto_set(handle, address.as_ptr(), value.as_ptr(), 0), 0);
+
+        let mut out = ptr::null_mut();
+        assert_eq!(pronto_get(handle, address.as_ptr(), &mut out), 0);
+        assert_eq!(CStr::from_ptr(out).to_str().unwrap(), "8080");
+        pronto_free(out);
+
+        // A TTL outside a cache namespace fails with exit code 1 and a message.
+        assert_eq!(pronto_set(handle, address.as_ptr(), value.as_ptr(), 60), 1);
+        let message = CStr::from_ptr(pronto_last_error(handle)).to_str().unwrap();
+        assert!(message.contains("TTL"), "{}", message);
+
+        assert_eq!(pronto_delete(handle, address.as_ptr()), 0);
+        assert_eq!(pronto_get(handle, address.as_ptr(), &mut out), 2);
+        assert!(out.is_null());
+        pronto_close(handle);
+    }
+}
+
+#[cfg(feature = "python")]
+#[test]
+fn python_module_maps_failures_to_pronto_error() {
+    use prontodb::lib::python::prontodb as pronto_module;
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    let temp = tempdir().unwrap();
+    let path = temp.path().join("py.sqlite3");
+    pyo3::append_to_inittab!(pronto_module);
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let locals = PyDict::new_bound(py);
+        locals.set_item("path", path.to_str().unwrap()).unwrap();
+        py.run_bound(
+            r#"
+import prontodb
+db = prontodb.open(path)
+db["app.cfg.port"] = "8080"
+assert db.get("app.cfg.port") == "8080"
+assert db.get("app.cfg.host", "localhost") == "localhost"
+assert db.keys("app.cfg") == ["app.cfg.port"]
+try:
+    db.set("app.cfg.tmp", "x", ttl=60)
+    raise AssertionError("a TTL outside a cache namespace was accepted")
+except prontodb.ProntoError as error:
+    assert str(error).startswith("NS_NOT_TTL: "), str(error)
+try:
+    db["app.cfg.host"]
+    raise AssertionError("a missing key did not raise")
+except KeyError:
+    pass
+"#,
+            None,
+            Some(&locals),
+        )
+        .unwrap();
+    });
+}
@@ -2,7 +2,8 @@ use std::fs;
 use std::io::Write;
 
 use prontodb::lib::adpt::sqlite::{
-    SqliteBaseAdapter, SqliteConnectionConfig, SqliteRecordAdapter, SqliteTableAdapter,
+    set_capability_enabled, SqliteBaseAdapter, SqliteConnectionConfig, SqliteRecordAdapter,
+    SqliteTableAdapter,
 };
 use prontodb::lib::core::crud::{
     CrudContext, CrudDomain, CrudErrorKind, CrudObjectKind, CrudResource, CrudStatus, CrudVerb,
@@ -175,3 +176,37 @@ fn insert_sample_row(path: &str) {
     )
     .unwrap();
 }
+
+#[test]
+fn disabled_capability_is_refused_and_hidden() {
+    let temp = tempdir().unwrap();
+    let db_path = temp.path().join("base_toggle.sqlite");
+    let config = SqliteConnectionConfig::new(&db_path);
+    let adapter = SqliteBaseAdapter::new(config.clone());
+    adapter
+        .dispatch(
+            CrudVerb::Create,
+            ctx_for_base(db_path.to_str().unwrap(), CrudVerb::Create),
+        )
+        .unwrap();
+
+    set_capability_enabled(&config, &CrudObjectKind::Base, CrudVerb::Read, false).unwrap();
+    let error = adapter
+        .dispatch(
+            CrudVerb::Read,
+            ctx_for_base(db_path.to_str().unwrap(), CrudVerb::Read),
+        )
+        .unwrap_err();
+    assert_eq!(error.kind, CrudErrorKind::PermissionDenied);
+    assert!(!adapter
+        .capabilities()
+        .allows(&CrudObjectKind::Base, CrudVerb::Read));
+
+    set_capability_enabled(&config, &CrudObjectKind::Base, CrudVerb::Read, true).unwrap();
+    adapter
+        .dispatch(
+            CrudVerb::Read,
+            ctx_for_base(db_path.to_str().unwrap(), CrudVerb::Read),
+        )
+        .expect("read is enabled again");
+}